@@ -13,14 +13,14 @@ use openraft::error::RPCError;
 use openraft::error::RemoteError;
 use openraft::raft::AddLearnerResponse;
 use openraft::raft::ClientWriteResponse;
-use openraft::NodeId;
+use openraft::Node;
+use crate::NodeId;
 use openraft::RaftMetrics;
 use reqwest::Client;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::network::node_manager::NodeManager;
 use crate::ExampleRequest;
 use crate::ExampleResponse;
 
@@ -28,25 +28,21 @@ use crate::ExampleResponse;
 pub struct Empty {}
 
 pub struct ExampleClient {
-    /// The leader node to send request to.
+    /// The leader node to send request to, and its address.
     ///
     /// All traffic should be sent to the leader in a cluster.
-    pub leader_id: Arc<Mutex<NodeId>>,
-
-    /// Raft itself does not store node addresses.
-    /// Thus when a `ForwardToLeader` error is returned, the client need to find the node address by itself.
-    pub node_manager: Arc<dyn NodeManager>,
+    /// When a `ForwardToLeader` error is returned, it is updated with the leader's node id and address in the error.
+    pub leader: Arc<Mutex<(NodeId, String)>>,
 
     pub inner: Client,
 }
 
 impl ExampleClient {
-    /// Create a client with a leader node id and a node manager to get node address by node id.
-    pub fn new(leader_id: NodeId, node_manager: Arc<dyn NodeManager>) -> Self {
+    /// Create a client with a leader node id and its address.
+    pub fn new(leader_id: NodeId, leader_addr: String) -> Self {
         Self {
-            leader_id: Arc::new(Mutex::new(leader_id)),
+            leader: Arc::new(Mutex::new((leader_id, leader_addr))),
             inner: reqwest::Client::new(),
-            node_manager,
         }
     }
 
@@ -69,8 +65,7 @@ impl ExampleClient {
     ///
     /// This method may return stale value because it does not force to read on a legal leader.
     pub async fn read(&self, req: &String) -> Result<String, RPCError<Infallible>> {
-        let target = self.get_leader_id();
-        self.send_rpc(target, "read", Some(req)).await
+        self.send_rpc("read", Some(req)).await
     }
 
     // --- Cluster management API
@@ -78,18 +73,16 @@ impl ExampleClient {
     /// Initialize a cluster of only the node that receives this request.
     ///
     /// This is the first step to initialize a cluster.
-    /// With a initialized cluster, new node can be added with [`write`].
-    /// Then setup replication with [`add_learner`].
+    /// With a initialized cluster, new node can be added as learner with [`add_learner`].
     /// Then make the new node a member with [`change_membership`].
     pub async fn init(&self) -> Result<(), RPCError<InitializeError>> {
-        let target = self.get_leader_id();
-        self.send_rpc(target, "init", Some(&Empty {})).await
+        self.send_rpc("init", Some(&Empty {})).await
     }
 
-    /// Add a node as learner.
+    /// Add a node as learner, with its node id and address.
     ///
-    /// The node to add has to exist, i.e., being added with `write(ExampleRequest::AddNode{})`
-    pub async fn add_learner(&self, req: &NodeId) -> Result<AddLearnerResponse, RPCError<AddLearnerError>> {
+    /// The address is stored in the membership config, thus every node is able to send RPC to it.
+    pub async fn add_learner(&self, req: &(NodeId, String)) -> Result<AddLearnerResponse, RPCError<AddLearnerError>> {
         self.send_rpc_to_leader("add-learner", Some(req)).await
     }
 
@@ -106,46 +99,31 @@ impl ExampleClient {
 
     /// Get the metrics about the cluster.
     pub async fn metrics(&self) -> Result<RaftMetrics, RPCError<Infallible>> {
-        let target = self.get_leader_id();
-        self.send_rpc(target, "metrics", None::<&()>).await
+        self.send_rpc("metrics", None::<&()>).await
     }
 
-    /// List all known nodes.
-    ///
-    /// A known node does not have to be a member or learner.
-    /// It is just a node that the cluster can use as learner or a member.
-    pub async fn list_nodes(&self) -> Result<BTreeMap<NodeId, String>, RPCError<Infallible>> {
-        let target = self.get_leader_id();
-        self.send_rpc(target, "list-nodes", None::<&()>).await
+    /// List all members and learners, along with their node info.
+    pub async fn list_nodes(&self) -> Result<BTreeMap<NodeId, Node>, RPCError<Infallible>> {
+        self.send_rpc("list-nodes", None::<&()>).await
     }
 
     // --- Internal methods
 
-    /// Get the last known leader node id by this client.
-    fn get_leader_id(&self) -> NodeId {
-        let t = self.leader_id.lock().unwrap();
-        *t
-    }
-
-    /// Send RPC to specified node.
+    /// Send RPC to the last known leader.
     ///
     /// It sends out a POST request if `req` is Some. Otherwise a GET request.
     /// The remote endpoint must respond a reply in form of `Result<T, E>`.
     /// An `Err` happened on remote will be wrapped in an [`RPCError::RemoteError`].
-    async fn send_rpc<Req, Resp, Err>(
-        &self,
-        target: NodeId,
-        uri: &str,
-        req: Option<&Req>,
-    ) -> Result<Resp, RPCError<Err>>
+    async fn send_rpc<Req, Resp, Err>(&self, uri: &str, req: Option<&Req>) -> Result<Resp, RPCError<Err>>
     where
         Req: Serialize + 'static,
         Resp: DeserializeOwned,
         Err: std::error::Error + DeserializeOwned,
     {
-        let addr = self.node_manager.get_node_address(target).await?;
-
-        let url = format!("http://{}/{}", addr, uri);
+        let (target, url) = {
+            let t = self.leader.lock().unwrap();
+            (t.0, format!("http://{}/{}", t.1, uri))
+        };
 
         let resp = if let Some(r) = req {
             self.inner.post(url).json(r)
@@ -175,9 +153,7 @@ impl ExampleClient {
         let mut n_retry = 3;
 
         loop {
-            let target = self.get_leader_id();
-
-            let res: Result<Resp, RPCError<Err>> = self.send_rpc(target, uri, req).await;
+            let res: Result<Resp, RPCError<Err>> = self.send_rpc(uri, req).await;
 
             let rpc_err = match res {
                 Ok(x) => return Ok(x),
@@ -189,12 +165,13 @@ impl ExampleClient {
 
                 if let Ok(ForwardToLeader {
                     leader_id: Some(leader_id),
+                    leader_node: Some(leader_node),
                 }) = forward_err_res
                 {
                     // Update target to the new leader.
                     {
-                        let mut t = self.leader_id.lock().unwrap();
-                        *t = leader_id;
+                        let mut t = self.leader.lock().unwrap();
+                        *t = (leader_id, leader_node.addr);
                     }
 
                     n_retry -= 1;
@@ -6,7 +6,6 @@ use actix_web::web::Data;
 use actix_web::App;
 use actix_web::HttpServer;
 use openraft::Config;
-use openraft::NodeId;
 use openraft::Raft;
 
 use crate::app::ExampleApp;
@@ -23,6 +22,9 @@ pub mod client;
 pub mod network;
 pub mod store;
 
+/// The type of node id used in this example.
+pub type NodeId = u64;
+
 pub type ExampleRaft = Raft<ExampleRequest, ExampleResponse, ExampleNetwork, ExampleStore>;
 
 pub async fn start_example_raft_node(node_id: NodeId, http_addr: String) -> std::io::Result<()> {
@@ -34,7 +36,9 @@ pub async fn start_example_raft_node(node_id: NodeId, http_addr: String) -> std:
 
     // Create the network layer that will connect and communicate the raft instances and
     // will be used in conjunction with the store created above.
-    let network = Arc::new(ExampleNetwork { store: store.clone() });
+    let network = Arc::new(ExampleNetwork {
+        client: reqwest::Client::new(),
+    });
 
     // Create a local raft instance.
     let raft = Raft::new(node_id, config.clone(), network, store.clone());
@@ -43,6 +47,7 @@ pub async fn start_example_raft_node(node_id: NodeId, http_addr: String) -> std:
     // be later used on the actix-web services.
     let app = Data::new(ExampleApp {
         id: node_id,
+        addr: http_addr.clone(),
         raft,
         store,
         config,
@@ -59,15 +64,19 @@ pub async fn start_example_raft_node(node_id: NodeId, http_addr: String) -> std:
             .service(raft::append)
             .service(raft::snapshot)
             .service(raft::vote)
+            .service(raft::timeout_now)
+            .service(raft::read_index)
             // admin API
             .service(management::init)
             .service(management::add_learner)
             .service(management::change_membership)
+            .service(management::transfer_leader)
             .service(management::metrics)
             .service(management::list_nodes)
             // application API
             .service(api::write)
             .service(api::read)
+            .service(api::consistent_read)
     });
 
     let x = server.bind(http_addr)?;
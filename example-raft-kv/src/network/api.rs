@@ -2,6 +2,7 @@ use actix_web::post;
 use actix_web::web;
 use actix_web::web::Data;
 use actix_web::Responder;
+use openraft::error::ClientReadError;
 use openraft::error::Infallible;
 use openraft::raft::ClientWriteRequest;
 use openraft::raft::EntryPayload;
@@ -18,6 +19,7 @@ use crate::store::ExampleRequest;
  *
  *  - `POST - /write` saves a value in a key and sync the nodes.
  *  - `POST - /read` attempt to find a value from a given key.
+ *  - `POST - /consistent_read` find a value from a given key with linearizability, on any node.
  */
 #[post("/write")]
 pub async fn write(app: Data<ExampleApp>, req: Json<ExampleRequest>) -> actix_web::Result<impl Responder> {
@@ -35,3 +37,18 @@ pub async fn read(app: Data<ExampleApp>, req: Json<String>) -> actix_web::Result
     let res: Result<String, Infallible> = Ok(value.unwrap_or_default());
     Ok(Json(res))
 }
+
+#[post("/consistent_read")]
+pub async fn consistent_read(app: Data<ExampleApp>, req: Json<String>) -> actix_web::Result<impl Responder> {
+    let ret = app.raft.client_read_index().await;
+
+    let res: Result<String, ClientReadError> = match ret {
+        Ok(_read_log_id) => {
+            let state_machine = app.store.state_machine.read().await;
+            let value = state_machine.data.get(&req.0).cloned();
+            Ok(value.unwrap_or_default())
+        }
+        Err(e) => Err(e),
+    };
+    Ok(Json(res))
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 
 use actix_web::get;
@@ -6,7 +7,8 @@ use actix_web::web;
 use actix_web::web::Data;
 use actix_web::Responder;
 use openraft::error::Infallible;
-use openraft::NodeId;
+use openraft::Node;
+use crate::NodeId;
 use openraft::RaftMetrics;
 use web::Json;
 
@@ -14,14 +16,17 @@ use crate::app::ExampleApp;
 
 // --- Cluster management
 
-/// Add a node as **Learner**.
+/// Add a node as **Learner**, with its node id and address.
 ///
 /// A Learner receives log replication from the leader but does not vote.
 /// This should be done before adding a node as a member into the cluster
 /// (by calling `change-membership`)
+///
+/// The address is stored in the membership config, thus every node is able to find it by the node id.
 #[post("/add-learner")]
-pub async fn add_learner(app: Data<ExampleApp>, req: Json<NodeId>) -> actix_web::Result<impl Responder> {
-    let res = app.raft.add_learner(req.0, true).await;
+pub async fn add_learner(app: Data<ExampleApp>, req: Json<(NodeId, String)>) -> actix_web::Result<impl Responder> {
+    let (node_id, addr) = req.0;
+    let res = app.raft.add_learner(node_id, Some(Node::new(addr)), true).await;
     Ok(Json(res))
 }
 
@@ -31,15 +36,22 @@ pub async fn change_membership(
     app: Data<ExampleApp>,
     req: Json<BTreeSet<NodeId>>,
 ) -> actix_web::Result<impl Responder> {
-    let res = app.raft.change_membership(req.0, true).await;
+    let res = app.raft.change_membership(req.0, true, false).await;
+    Ok(Json(res))
+}
+
+/// Transfer leadership to another voter, e.g., before taking the current leader down for maintenance.
+#[post("/transfer-leader")]
+pub async fn transfer_leader(app: Data<ExampleApp>, req: Json<NodeId>) -> actix_web::Result<impl Responder> {
+    let res = app.raft.transfer_leader(req.0).await;
     Ok(Json(res))
 }
 
 /// Initialize a single-node cluster.
 #[post("/init")]
 pub async fn init(app: Data<ExampleApp>) -> actix_web::Result<impl Responder> {
-    let mut nodes = BTreeSet::new();
-    nodes.insert(app.id);
+    let mut nodes = BTreeMap::new();
+    nodes.insert(app.id, Node::new(app.addr.clone()));
     let res = app.raft.initialize(nodes).await;
     Ok(Json(res))
 }
@@ -53,12 +65,12 @@ pub async fn metrics(app: Data<ExampleApp>) -> actix_web::Result<impl Responder>
     Ok(Json(res))
 }
 
-/// List known nodes of the cluster.
+/// List members and learners of the cluster, along with their node info.
 #[get("/list-nodes")]
 pub async fn list_nodes(app: Data<ExampleApp>) -> actix_web::Result<impl Responder> {
     let nodes = {
-        let state_machine = app.store.state_machine.read().await;
-        state_machine.nodes.clone()
+        let m = app.raft.metrics().borrow().clone();
+        m.membership_config.membership.nodes().clone()
     };
 
     let res: Result<_, Infallible> = Ok(nodes);
@@ -1,5 +1,4 @@
 pub mod api;
 pub mod management;
-pub mod node_manager;
 pub mod raft;
 pub mod raft_network_impl;
@@ -4,6 +4,8 @@ use actix_web::web::Data;
 use actix_web::Responder;
 use openraft::raft::AppendEntriesRequest;
 use openraft::raft::InstallSnapshotRequest;
+use openraft::raft::ReadIndexRequest;
+use openraft::raft::TimeoutNowRequest;
 use openraft::raft::VoteRequest;
 use web::Json;
 
@@ -32,3 +34,15 @@ pub async fn snapshot(app: Data<ExampleApp>, req: Json<InstallSnapshotRequest>)
     let res = app.raft.install_snapshot(req.0).await;
     Ok(Json(res))
 }
+
+#[post("/raft-timeout-now")]
+pub async fn timeout_now(app: Data<ExampleApp>, req: Json<TimeoutNowRequest>) -> actix_web::Result<impl Responder> {
+    let res = app.raft.timeout_now(req.0).await;
+    Ok(Json(res))
+}
+
+#[post("/raft-read-index")]
+pub async fn read_index(app: Data<ExampleApp>, req: Json<ReadIndexRequest>) -> actix_web::Result<impl Responder> {
+    let res = app.raft.read_index(req.0).await;
+    Ok(Json(res))
+}
@@ -1,46 +1,69 @@
-use std::sync::Arc;
-
+use anyerror::AnyError;
 use async_trait::async_trait;
 use openraft::error::AppendEntriesError;
+use openraft::error::ClientReadError;
 use openraft::error::InstallSnapshotError;
 use openraft::error::NetworkError;
+use openraft::error::NodeNotFound;
 use openraft::error::RPCError;
 use openraft::error::RemoteError;
+use openraft::error::TimeoutNowError;
 use openraft::error::VoteError;
 use openraft::raft::AppendEntriesRequest;
 use openraft::raft::AppendEntriesResponse;
 use openraft::raft::InstallSnapshotRequest;
 use openraft::raft::InstallSnapshotResponse;
+use openraft::raft::ReadIndexRequest;
+use openraft::raft::ReadIndexResponse;
+use openraft::raft::TimeoutNowRequest;
+use openraft::raft::TimeoutNowResponse;
 use openraft::raft::VoteRequest;
 use openraft::raft::VoteResponse;
-use openraft::NodeId;
+use openraft::Node;
+use crate::NodeId;
 use openraft::RaftNetwork;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::store::ExampleRequest;
-use crate::ExampleStore;
 
 pub struct ExampleNetwork {
-    pub store: Arc<ExampleStore>,
+    /// A client shared by all RPCs, to reuse connections.
+    pub client: reqwest::Client,
 }
 
 impl ExampleNetwork {
-    pub async fn send_rpc<Req, Resp, Err>(&self, target: NodeId, uri: &str, req: Req) -> Result<Resp, RPCError<Err>>
+    pub async fn send_rpc<Req, Resp, Err>(
+        &self,
+        target: NodeId,
+        target_node: Option<&Node>,
+        uri: &str,
+        req: Req,
+    ) -> Result<Resp, RPCError<Err>>
     where
         Req: Serialize,
         Err: std::error::Error + DeserializeOwned,
         Resp: DeserializeOwned,
     {
-        let addr = {
-            let state_machine = self.store.state_machine.read().await;
-            state_machine.nodes.get(&target).unwrap().clone()
+        // The address is stored in the membership config, along with the node id.
+        let addr = match target_node {
+            Some(node) => &node.addr,
+            None => {
+                return Err(RPCError::NodeNotFound(NodeNotFound {
+                    node_id: target,
+                    source: AnyError::error("node address is not provided"),
+                }));
+            }
         };
 
         let url = format!("http://{}/{}", addr, uri);
-        let client = reqwest::Client::new();
-
-        let resp = client.post(url).json(&req).send().await.map_err(|e| RPCError::Network(NetworkError::new(&e)))?;
+        let resp = self
+            .client
+            .post(url)
+            .json(&req)
+            .send()
+            .await
+            .map_err(|e| RPCError::Network(NetworkError::new(&e)))?;
 
         let res: Result<Resp, Err> = resp.json().await.map_err(|e| RPCError::Network(NetworkError::new(&e)))?;
 
@@ -53,20 +76,45 @@ impl RaftNetwork<ExampleRequest> for ExampleNetwork {
     async fn send_append_entries(
         &self,
         target: NodeId,
+        target_node: Option<&Node>,
         req: AppendEntriesRequest<ExampleRequest>,
     ) -> Result<AppendEntriesResponse, RPCError<AppendEntriesError>> {
-        self.send_rpc(target, "raft-append", req).await
+        self.send_rpc(target, target_node, "raft-append", req).await
     }
 
     async fn send_install_snapshot(
         &self,
         target: NodeId,
+        target_node: Option<&Node>,
         req: InstallSnapshotRequest,
     ) -> Result<InstallSnapshotResponse, RPCError<InstallSnapshotError>> {
-        self.send_rpc(target, "raft-snapshot", req).await
+        self.send_rpc(target, target_node, "raft-snapshot", req).await
+    }
+
+    async fn send_vote(
+        &self,
+        target: NodeId,
+        target_node: Option<&Node>,
+        req: VoteRequest,
+    ) -> Result<VoteResponse, RPCError<VoteError>> {
+        self.send_rpc(target, target_node, "raft-vote", req).await
     }
 
-    async fn send_vote(&self, target: NodeId, req: VoteRequest) -> Result<VoteResponse, RPCError<VoteError>> {
-        self.send_rpc(target, "raft-vote", req).await
+    async fn send_timeout_now(
+        &self,
+        target: NodeId,
+        target_node: Option<&Node>,
+        req: TimeoutNowRequest,
+    ) -> Result<TimeoutNowResponse, RPCError<TimeoutNowError>> {
+        self.send_rpc(target, target_node, "raft-timeout-now", req).await
+    }
+
+    async fn send_read_index(
+        &self,
+        target: NodeId,
+        target_node: Option<&Node>,
+        req: ReadIndexRequest,
+    ) -> Result<ReadIndexResponse, RPCError<ClientReadError>> {
+        self.send_rpc(target, target_node, "raft-read-index", req).await
     }
 }
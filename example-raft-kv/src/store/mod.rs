@@ -17,7 +17,6 @@ use openraft::EffectiveMembership;
 use openraft::ErrorSubject;
 use openraft::ErrorVerb;
 use openraft::LogId;
-use openraft::NodeId;
 use openraft::RaftStorage;
 use openraft::SnapshotMeta;
 use openraft::StateMachineChanges;
@@ -31,13 +30,11 @@ use tokio::sync::RwLock;
 /**
  * Here you will set the types of request that will interact with the raft nodes.
  * For example the `Set` will be used to write data (key and value) to the raft database.
- * The `AddNode` will append a new node to the current existing shared list of nodes.
  * You will want to add any request that can write data in all nodes here.
  */
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum ExampleRequest {
     Set { key: String, value: String },
-    AddNode { id: NodeId, addr: String },
 }
 
 // Inform to raft that `ExampleRequest` is an application data to be used by raft.
@@ -78,9 +75,6 @@ pub struct ExampleStateMachine {
 
     pub last_membership: Option<EffectiveMembership>,
 
-    /// Node addresses in this cluster.
-    pub nodes: BTreeMap<NodeId, String>,
-
     /// Application data.
     pub data: BTreeMap<String, String>,
 }
@@ -217,12 +211,6 @@ impl RaftStorage<ExampleRequest, ExampleResponse> for ExampleStore {
                             value: Some(value.clone()),
                         })
                     }
-                    ExampleRequest::AddNode { id, addr } => {
-                        sm.nodes.insert(*id, addr.clone());
-                        res.push(ExampleResponse {
-                            value: Some(format!("cluster: {:?}", sm.nodes)),
-                        })
-                    }
                 },
                 EntryPayload::Membership(ref mem) => {
                     sm.last_membership = Some(EffectiveMembership {
@@ -236,6 +224,21 @@ impl RaftStorage<ExampleRequest, ExampleResponse> for ExampleStore {
         Ok(res)
     }
 
+    #[tracing::instrument(level = "trace", skip(self, membership))]
+    async fn skip_apply_upto(
+        &self,
+        last_applied: &LogId,
+        membership: Option<&EffectiveMembership>,
+    ) -> Result<(), StorageError> {
+        let mut sm = self.state_machine.write().await;
+
+        sm.last_applied_log = Some(*last_applied);
+        if let Some(m) = membership {
+            sm.last_membership = Some(m.clone());
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     async fn build_snapshot(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
         let (data, last_applied_log);
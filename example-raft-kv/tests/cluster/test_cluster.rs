@@ -1,38 +1,21 @@
-use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
-use anyerror::AnyError;
 use example_raft_key_value::client::ExampleClient;
 use example_raft_key_value::start_example_raft_node;
 use example_raft_key_value::store::ExampleRequest;
 use maplit::btreeset;
-use openraft::error::NodeNotFound;
 use tokio::runtime::Runtime;
 
 /// Setup a cluster of 3 nodes.
 /// Write to it and read from it.
 #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
 async fn test_cluster() -> anyhow::Result<()> {
-    // --- The client itself does not store addresses for all nodes, but just node id.
-    //     Thus we need a supporting component to provide mapping from node id to node address.
-    //     This is only used by the client. A raft node in this example stores node addresses in its store.
-
-    let get_addr = |node_id| {
-        let addr = match node_id {
-            1 => "127.0.0.1:21001".to_string(),
-            2 => "127.0.0.1:21002".to_string(),
-            3 => "127.0.0.1:21003".to_string(),
-            _ => {
-                return Err(NodeNotFound {
-                    node_id,
-                    source: AnyError::error("node not found"),
-                });
-            }
-        };
-        Ok(addr)
-    };
-    let get_addr = Arc::new(get_addr);
+    // --- Node addresses are stored in the membership config, along with node ids.
+    //     A client only needs to know the address of the node it connects to.
+    //     When a request is sent to a non-leader, the leader's address is returned in a `ForwardToLeader` error.
+
+    let get_addr = |node_id| format!("127.0.0.1:2100{}", node_id);
 
     // --- Start 3 raft node in 3 threads.
 
@@ -54,61 +37,38 @@ async fn test_cluster() -> anyhow::Result<()> {
         println!("x: {:?}", x);
     });
 
+    // --- Wait for the servers to start listening.
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
     // --- Create a client to the first node, as a control handle to the cluster.
 
-    let client = ExampleClient::new(1, get_addr.clone());
+    let client = ExampleClient::new(1, get_addr(1));
 
     // --- 1. Initialize the target node as a cluster of only one node.
     //        After init(), the single node cluster will be fully functional.
+    //        The address of the node is stored in the initial membership config.
 
     client.init().await?;
 
     let m = client.metrics().await?;
     println!("metrics after init: {:?}", m);
 
-    // --- 2. Before adding more members to the cluster, first we need to let the cluster know the address of every node
-    //        to add.
-    //        This is done with a regular raft protocol: replicate a log that contains node info and then apply the log
-    //        to state machine.
-    //        Then `RaftNetwork` will be able to read node address from its store.
-
-    let x = client
-        .write(&ExampleRequest::AddNode {
-            id: 1,
-            addr: "127.0.0.1:21001".to_string(),
-        })
-        .await?;
-    println!("add node 1 res: {:?}", x);
-
-    let x = client
-        .write(&ExampleRequest::AddNode {
-            id: 2,
-            addr: "127.0.0.1:21002".to_string(),
-        })
-        .await?;
-    println!("add node 2 res: {:?}", x);
-
-    let x = client
-        .write(&ExampleRequest::AddNode {
-            id: 3,
-            addr: "127.0.0.1:21003".to_string(),
-        })
-        .await?;
-    println!("add node 3 res: {:?}", x);
-
-    let x = client.list_nodes().await?;
-    println!("list-nodes: {:?}", x);
-
-    // --- 3. Add node 2 and 3 to the cluster as `Learner`, to let them start to receive log replication from the
+    // --- 2. Add node 2 and 3 to the cluster as `Learner`, to let them start to receive log replication from the
     //        leader.
+    //        The addresses are stored in the membership config, thus `RaftNetwork` is able to find them.
 
-    let x = client.add_learner(&2).await?;
+    let x = client.add_learner(&(2, get_addr(2))).await?;
     println!("add-learner 2: {:?}", x);
 
-    let x = client.add_learner(&3).await?;
+    let x = client.add_learner(&(3, get_addr(3))).await?;
     println!("add-learner 3: {:?}", x);
 
-    // --- 4. Turn the two learners to members. A member node can vote or elect itself as leader.
+    let x = client.list_nodes().await?;
+    println!("list-nodes: {:?}", x);
+    assert_eq!(vec![1, 2, 3], x.keys().cloned().collect::<Vec<_>>());
+
+    // --- 3. Turn the two learners to members. A member node can vote or elect itself as leader.
 
     let x = client.change_membership(&btreeset! {1,2,3}).await?;
     println!("change-membership to 1,2,3: {:?}", x);
@@ -150,11 +110,11 @@ async fn test_cluster() -> anyhow::Result<()> {
     let x = client.read(&("foo".to_string())).await?;
     println!("read `foo` on node 1: {:?}", x);
 
-    let client2 = ExampleClient::new(2, get_addr.clone());
+    let client2 = ExampleClient::new(2, get_addr(2));
     let x = client2.read(&("foo".to_string())).await?;
     println!("read `foo` on node 2: {:?}", x);
 
-    let client3 = ExampleClient::new(3, get_addr.clone());
+    let client3 = ExampleClient::new(3, get_addr(3));
     let x = client3.read(&("foo".to_string())).await?;
     println!("read `foo` on node 3: {:?}", x);
 
@@ -175,11 +135,11 @@ async fn test_cluster() -> anyhow::Result<()> {
     let x = client.read(&("foo".to_string())).await?;
     println!("read `foo` on node 1: {:?}", x);
 
-    let client2 = ExampleClient::new(2, get_addr.clone());
+    let client2 = ExampleClient::new(2, get_addr(2));
     let x = client2.read(&("foo".to_string())).await?;
     println!("read `foo` on node 2: {:?}", x);
 
-    let client3 = ExampleClient::new(3, get_addr.clone());
+    let client3 = ExampleClient::new(3, get_addr(3));
     let x = client3.read(&("foo".to_string())).await?;
     println!("read `foo` on node 3: {:?}", x);
 
@@ -23,6 +23,9 @@ use openraft::EffectiveMembership;
 use openraft::ErrorSubject;
 use openraft::ErrorVerb;
 use openraft::LogId;
+use openraft::Node;
+use openraft::NodeId;
+use openraft::NodeInfo;
 use openraft::RaftStorage;
 use openraft::RaftStorageDebug;
 use openraft::SnapshotMeta;
@@ -62,8 +65,8 @@ impl AppDataResponse for ClientResponse {}
 
 /// The application snapshot type which the `MemStore` works with.
 #[derive(Debug)]
-pub struct MemStoreSnapshot {
-    pub meta: SnapshotMeta,
+pub struct MemStoreSnapshot<NID: NodeId = u64> {
+    pub meta: SnapshotMeta<NID>,
 
     /// The data of the state machine at the time of this snapshot.
     pub data: Vec<u8>,
@@ -71,10 +74,11 @@ pub struct MemStoreSnapshot {
 
 /// The state machine of the `MemStore`.
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
-pub struct MemStoreStateMachine {
-    pub last_applied_log: Option<LogId>,
+#[serde(bound = "")]
+pub struct MemStoreStateMachine<NID: NodeId = u64, NI: NodeInfo = Node> {
+    pub last_applied_log: Option<LogId<NID>>,
 
-    pub last_membership: Option<EffectiveMembership>,
+    pub last_membership: Option<EffectiveMembership<NID, NI>>,
 
     /// A mapping of client IDs to their state info.
     pub client_serial_responses: HashMap<String, (u64, Option<String>)>,
@@ -83,25 +87,28 @@ pub struct MemStoreStateMachine {
 }
 
 /// An in-memory storage system implementing the `RaftStorage` trait.
-pub struct MemStore {
-    last_purged_log_id: RwLock<Option<LogId>>,
+///
+/// It works with any type of node id, e.g., `MemStore<String>`, and `u64` by default, and with any type of node info,
+/// which is `Node` by default.
+pub struct MemStore<NID: NodeId = u64, NI: NodeInfo = Node> {
+    last_purged_log_id: RwLock<Option<LogId<NID>>>,
 
     /// The Raft log.
-    log: RwLock<BTreeMap<u64, Entry<ClientRequest>>>,
+    log: RwLock<BTreeMap<u64, Entry<ClientRequest, NID, NI>>>,
 
     /// The Raft state machine.
-    sm: RwLock<MemStoreStateMachine>,
+    sm: RwLock<MemStoreStateMachine<NID, NI>>,
 
     /// The current hard state.
-    vote: RwLock<Option<Vote>>,
+    vote: RwLock<Option<Vote<NID>>>,
 
     snapshot_idx: Arc<Mutex<u64>>,
 
     /// The current snapshot.
-    current_snapshot: RwLock<Option<MemStoreSnapshot>>,
+    current_snapshot: RwLock<Option<MemStoreSnapshot<NID>>>,
 }
 
-impl MemStore {
+impl<NID: NodeId, NI: NodeInfo> MemStore<NID, NI> {
     /// Create a new `MemStore` instance.
     pub async fn new() -> Self {
         let log = RwLock::new(BTreeMap::new());
@@ -120,34 +127,34 @@ impl MemStore {
 }
 
 #[async_trait]
-impl RaftStorageDebug<MemStoreStateMachine> for MemStore {
+impl<NID: NodeId, NI: NodeInfo> RaftStorageDebug<MemStoreStateMachine<NID, NI>> for MemStore<NID, NI> {
     /// Get a handle to the state machine for testing purposes.
-    async fn get_state_machine(&self) -> MemStoreStateMachine {
+    async fn get_state_machine(&self) -> MemStoreStateMachine<NID, NI> {
         self.sm.write().await.clone()
     }
 }
 
 #[async_trait]
-impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
+impl<NID: NodeId, NI: NodeInfo> RaftStorage<ClientRequest, ClientResponse, NID, NI> for MemStore<NID, NI> {
     type SnapshotData = Cursor<Vec<u8>>;
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn save_vote(&self, vote: &Vote) -> Result<(), StorageError> {
+    async fn save_vote(&self, vote: &Vote<NID>) -> Result<(), StorageError<NID>> {
         tracing::debug!(?vote, "save_vote");
         let mut h = self.vote.write().await;
 
-        *h = Some(*vote);
+        *h = Some(vote.clone());
         Ok(())
     }
 
-    async fn read_vote(&self) -> Result<Option<Vote>, StorageError> {
-        Ok(*self.vote.read().await)
+    async fn read_vote(&self) -> Result<Option<Vote<NID>>, StorageError<NID>> {
+        Ok(self.vote.read().await.clone())
     }
 
     async fn try_get_log_entries<RB: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RB,
-    ) -> Result<Vec<Entry<ClientRequest>>, StorageError> {
+    ) -> Result<Vec<Entry<ClientRequest, NID, NI>>, StorageError<NID>> {
         let res = {
             let log = self.log.read().await;
             log.range(range.clone()).map(|(_, val)| val.clone()).collect::<Vec<_>>()
@@ -156,11 +163,11 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         Ok(res)
     }
 
-    async fn get_log_state(&self) -> Result<LogState, StorageError> {
+    async fn get_log_state(&self) -> Result<LogState<NID>, StorageError<NID>> {
         let log = self.log.read().await;
-        let last = log.iter().rev().next().map(|(_, ent)| ent.log_id);
+        let last = log.iter().rev().next().map(|(_, ent)| ent.log_id.clone());
 
-        let last_deleted = *self.last_purged_log_id.read().await;
+        let last_deleted = self.last_purged_log_id.read().await.clone();
 
         let last = match last {
             None => last_deleted,
@@ -173,13 +180,15 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         })
     }
 
-    async fn last_applied_state(&self) -> Result<(Option<LogId>, Option<EffectiveMembership>), StorageError> {
+    async fn last_applied_state(
+        &self,
+    ) -> Result<(Option<LogId<NID>>, Option<EffectiveMembership<NID, NI>>), StorageError<NID>> {
         let sm = self.sm.read().await;
-        Ok((sm.last_applied_log, sm.last_membership.clone()))
+        Ok((sm.last_applied_log.clone(), sm.last_membership.clone()))
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn delete_conflict_logs_since(&self, log_id: LogId) -> Result<(), StorageError> {
+    async fn delete_conflict_logs_since(&self, log_id: LogId<NID>) -> Result<(), StorageError<NID>> {
         tracing::debug!("delete_log: [{:?}, +oo)", log_id);
 
         {
@@ -195,13 +204,13 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn purge_logs_upto(&self, log_id: LogId) -> Result<(), StorageError> {
+    async fn purge_logs_upto(&self, log_id: LogId<NID>) -> Result<(), StorageError<NID>> {
         tracing::debug!("delete_log: [{:?}, +oo)", log_id);
 
         {
             let mut ld = self.last_purged_log_id.write().await;
-            assert!(*ld <= Some(log_id));
-            *ld = Some(log_id);
+            assert!(*ld <= Some(log_id.clone()));
+            *ld = Some(log_id.clone());
         }
 
         {
@@ -217,7 +226,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "trace", skip(self, entries))]
-    async fn append_to_log(&self, entries: &[&Entry<ClientRequest>]) -> Result<(), StorageError> {
+    async fn append_to_log(&self, entries: &[&Entry<ClientRequest, NID, NI>]) -> Result<(), StorageError<NID>> {
         let mut log = self.log.write().await;
         for entry in entries {
             log.insert(entry.log_id.index, (*entry).clone());
@@ -228,8 +237,8 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     #[tracing::instrument(level = "trace", skip(self, entries))]
     async fn apply_to_state_machine(
         &self,
-        entries: &[&Entry<ClientRequest>],
-    ) -> Result<Vec<ClientResponse>, StorageError> {
+        entries: &[&Entry<ClientRequest, NID, NI>],
+    ) -> Result<Vec<ClientResponse>, StorageError<NID>> {
         let mut res = Vec::with_capacity(entries.len());
 
         let mut sm = self.sm.write().await;
@@ -237,7 +246,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         for entry in entries {
             tracing::debug!(%entry.log_id, "replicate to sm");
 
-            sm.last_applied_log = Some(entry.log_id);
+            sm.last_applied_log = Some(entry.log_id.clone());
 
             match entry.payload {
                 EntryPayload::Blank => res.push(ClientResponse(None)),
@@ -254,7 +263,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
                 }
                 EntryPayload::Membership(ref mem) => {
                     sm.last_membership = Some(EffectiveMembership {
-                        log_id: entry.log_id,
+                        log_id: entry.log_id.clone(),
                         membership: mem.clone(),
                     });
                     res.push(ClientResponse(None))
@@ -264,8 +273,23 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         Ok(res)
     }
 
+    #[tracing::instrument(level = "trace", skip(self, membership))]
+    async fn skip_apply_upto(
+        &self,
+        last_applied: &LogId<NID>,
+        membership: Option<&EffectiveMembership<NID, NI>>,
+    ) -> Result<(), StorageError<NID>> {
+        let mut sm = self.sm.write().await;
+
+        sm.last_applied_log = Some(last_applied.clone());
+        if let Some(m) = membership {
+            sm.last_membership = Some(m.clone());
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn build_snapshot(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
+    async fn build_snapshot(&self) -> Result<Snapshot<Self::SnapshotData, NID>, StorageError<NID>> {
         let (data, last_applied_log);
 
         {
@@ -274,7 +298,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
             data = serde_json::to_vec(&*sm)
                 .map_err(|e| StorageIOError::new(ErrorSubject::StateMachine, ErrorVerb::Read, AnyError::new(&e)))?;
 
-            last_applied_log = sm.last_applied_log;
+            last_applied_log = sm.last_applied_log.clone();
         }
 
         let last_applied_log = match last_applied_log {
@@ -321,16 +345,16 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError> {
+    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError<NID>> {
         Ok(Box::new(Cursor::new(Vec::new())))
     }
 
     #[tracing::instrument(level = "trace", skip(self, snapshot))]
     async fn install_snapshot(
         &self,
-        meta: &SnapshotMeta,
+        meta: &SnapshotMeta<NID>,
         snapshot: Box<Self::SnapshotData>,
-    ) -> Result<StateMachineChanges, StorageError> {
+    ) -> Result<StateMachineChanges<NID>, StorageError<NID>> {
         tracing::info!(
             { snapshot_size = snapshot.get_ref().len() },
             "decoding snapshot for installation"
@@ -350,7 +374,7 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
 
         // Update the state machine.
         {
-            let new_sm: MemStoreStateMachine = serde_json::from_slice(&new_snapshot.data).map_err(|e| {
+            let new_sm: MemStoreStateMachine<NID, NI> = serde_json::from_slice(&new_snapshot.data).map_err(|e| {
                 StorageIOError::new(
                     ErrorSubject::Snapshot(new_snapshot.meta.clone()),
                     ErrorVerb::Read,
@@ -365,13 +389,13 @@ impl RaftStorage<ClientRequest, ClientResponse> for MemStore {
         let mut current_snapshot = self.current_snapshot.write().await;
         *current_snapshot = Some(new_snapshot);
         Ok(StateMachineChanges {
-            last_applied: meta.last_log_id,
+            last_applied: meta.last_log_id.clone(),
             is_snapshot: true,
         })
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError> {
+    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData, NID>>, StorageError<NID>> {
         match &*self.current_snapshot.read().await {
             Some(snapshot) => {
                 let data = snapshot.data.clone();
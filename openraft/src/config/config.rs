@@ -1,5 +1,7 @@
 //! Raft runtime configuration.
 
+use std::time::Duration;
+
 use clap::Parser;
 use rand::thread_rng;
 use rand::Rng;
@@ -7,18 +9,113 @@ use serde::Deserialize;
 use serde::Serialize;
 
 use crate::config::error::ConfigError;
+use crate::config::SnapshotCompression;
 
 /// Log compaction and snapshot policy.
 ///
 /// This governs when periodic snapshots will be taken, and also governs the conditions which
 /// would cause a leader to send an `InstallSnapshot` RPC to a follower based on replication lag.
 ///
-/// Additional policies may become available in the future.
+/// For a replication target, only the lag in logs and bytes is checked, i.e., the logs committed but not yet
+/// replicated to it: `IntervalSinceLast` never makes a leader send a snapshot to a target.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum SnapshotPolicy {
     /// A snapshot will be generated once the log has grown the specified number of logs since
     /// the last snapshot.
     LogsSinceLast(u64),
+
+    /// A snapshot will be generated once the logs applied since the last snapshot have grown to about the specified
+    /// number of bytes, measured by the bincode encoding of the logs.
+    BytesSinceLast(u64),
+
+    /// A snapshot will be generated once the specified number of milliseconds has elapsed since the last snapshot,
+    /// if any log is applied since then.
+    IntervalSinceLast(u64),
+
+    /// A snapshot will be generated once any of the policies is satisfied.
+    Any(Vec<SnapshotPolicy>),
+
+    /// A snapshot will be generated once all of the policies are satisfied.
+    All(Vec<SnapshotPolicy>),
+
+    /// No snapshot is generated by policy.
+    ///
+    /// A snapshot is only built by `Raft::trigger_snapshot()`, or when a follower needs logs that are purged.
+    Never,
+}
+
+/// The progress since the last snapshot, or the lag of a replication target, to check a `SnapshotPolicy` against.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct SnapshotProgress {
+    pub(crate) logs: u64,
+    pub(crate) bytes: u64,
+    pub(crate) elapsed: Duration,
+}
+
+impl SnapshotPolicy {
+    /// Check if the policy is satisfied with the given progress.
+    pub(crate) fn is_satisfied(&self, progress: &SnapshotProgress) -> bool {
+        match self {
+            SnapshotPolicy::LogsSinceLast(n_logs) => progress.logs >= *n_logs,
+            SnapshotPolicy::BytesSinceLast(n_bytes) => progress.bytes >= *n_bytes,
+            SnapshotPolicy::IntervalSinceLast(ms) => {
+                progress.logs > 0 && progress.elapsed >= Duration::from_millis(*ms)
+            }
+            SnapshotPolicy::Any(policies) => policies.iter().any(|p| p.is_satisfied(progress)),
+            SnapshotPolicy::All(policies) => !policies.is_empty() && policies.iter().all(|p| p.is_satisfied(progress)),
+            SnapshotPolicy::Never => false,
+        }
+    }
+
+    /// Check if the lag of a replication target satisfies the policy, in which case a snapshot is sent instead of logs.
+    ///
+    /// `IntervalSinceLast` is ignored: an idle target that is up to date must not be sent a snapshot just because
+    /// time has elapsed.
+    pub(crate) fn is_lag_satisfied(&self, logs: u64, bytes: u64) -> bool {
+        match self {
+            SnapshotPolicy::LogsSinceLast(n_logs) => logs >= *n_logs,
+            SnapshotPolicy::BytesSinceLast(n_bytes) => bytes >= *n_bytes,
+            SnapshotPolicy::IntervalSinceLast(_) | SnapshotPolicy::Never => false,
+            SnapshotPolicy::Any(policies) => policies.iter().any(|p| p.is_lag_satisfied(logs, bytes)),
+            SnapshotPolicy::All(policies) => {
+                let lag_policies = policies.iter().filter(|p| p.has_lag()).collect::<Vec<_>>();
+                !lag_policies.is_empty() && lag_policies.iter().all(|p| p.is_lag_satisfied(logs, bytes))
+            }
+        }
+    }
+
+    /// The smallest number of logs in a `LogsSinceLast` of this policy, if there is one.
+    pub(crate) fn logs_threshold(&self) -> Option<u64> {
+        match self {
+            SnapshotPolicy::LogsSinceLast(n_logs) => Some(*n_logs),
+            SnapshotPolicy::Any(policies) | SnapshotPolicy::All(policies) => {
+                policies.iter().filter_map(|p| p.logs_threshold()).min()
+            }
+            _ => None,
+        }
+    }
+
+    /// Check if this policy has an `IntervalSinceLast`, which has to be checked periodically.
+    pub(crate) fn has_interval(&self) -> bool {
+        self.contains(&|p| matches!(p, SnapshotPolicy::IntervalSinceLast(_)))
+    }
+
+    /// Check if this policy has a `BytesSinceLast`, which requires the size of logs.
+    pub(crate) fn has_bytes(&self) -> bool {
+        self.contains(&|p| matches!(p, SnapshotPolicy::BytesSinceLast(_)))
+    }
+
+    /// Check if this policy has a `LogsSinceLast` or `BytesSinceLast`, which checks the lag of a replication target.
+    fn has_lag(&self) -> bool {
+        self.contains(&|p| matches!(p, SnapshotPolicy::LogsSinceLast(_) | SnapshotPolicy::BytesSinceLast(_)))
+    }
+
+    fn contains(&self, f: &impl Fn(&SnapshotPolicy) -> bool) -> bool {
+        match self {
+            SnapshotPolicy::Any(policies) | SnapshotPolicy::All(policies) => policies.iter().any(|p| p.contains(f)),
+            _ => f(self),
+        }
+    }
 }
 
 /// Parse number with unit such as 5.3 KB
@@ -31,27 +128,87 @@ fn parse_bytes_with_unit(src: &str) -> Result<u64, ConfigError> {
     Ok(res.get_bytes() as u64)
 }
 
-fn parse_snapshot_policy(src: &str) -> Result<SnapshotPolicy, ConfigError> {
-    let elts = src.split(':').collect::<Vec<_>>();
-    if elts.len() != 2 {
-        return Err(ConfigError::InvalidSnapshotPolicy {
-            syntax: "since_last:<num>".to_string(),
-            invalid: src.to_string(),
-        });
+const SNAPSHOT_POLICY_SYNTAX: &str =
+    "since_last:<num> | bytes_since_last:<size> | interval:<ms> | never | any(<policy>,...) | all(<policy>,...)";
+
+pub(super) fn parse_snapshot_policy(src: &str) -> Result<SnapshotPolicy, ConfigError> {
+    let invalid = || ConfigError::InvalidSnapshotPolicy {
+        syntax: SNAPSHOT_POLICY_SYNTAX.to_string(),
+        invalid: src.to_string(),
+    };
+
+    let src = src.trim();
+
+    if src == "never" {
+        return Ok(SnapshotPolicy::Never);
+    }
+
+    if let Some(inner) = src.strip_prefix("any(") {
+        return Ok(SnapshotPolicy::Any(parse_snapshot_policy_list(src, inner)?));
     }
 
-    if elts[0] != "since_last" {
-        return Err(ConfigError::InvalidSnapshotPolicy {
-            syntax: "since_last:<num>".to_string(),
+    if let Some(inner) = src.strip_prefix("all(") {
+        return Ok(SnapshotPolicy::All(parse_snapshot_policy_list(src, inner)?));
+    }
+
+    let (kind, value) = src.split_once(':').ok_or_else(invalid)?;
+
+    let parse_u64 = |v: &str| {
+        v.parse::<u64>().map_err(|e| ConfigError::InvalidNumber {
             invalid: src.to_string(),
-        });
+            reason: e.to_string(),
+        })
+    };
+
+    match kind {
+        "since_last" => Ok(SnapshotPolicy::LogsSinceLast(parse_u64(value)?)),
+        "bytes_since_last" => Ok(SnapshotPolicy::BytesSinceLast(parse_bytes_with_unit(value)?)),
+        "interval" => Ok(SnapshotPolicy::IntervalSinceLast(parse_u64(value)?)),
+        _ => Err(invalid()),
     }
+}
 
-    let n_logs = elts[1].parse::<u64>().map_err(|e| ConfigError::InvalidNumber {
+/// Parse the comma separated policies in `any(...)` or `all(...)`, `inner` is `src` without the leading `any(`.
+fn parse_snapshot_policy_list(src: &str, inner: &str) -> Result<Vec<SnapshotPolicy>, ConfigError> {
+    let invalid = || ConfigError::InvalidSnapshotPolicy {
+        syntax: SNAPSHOT_POLICY_SYNTAX.to_string(),
         invalid: src.to_string(),
-        reason: e.to_string(),
-    })?;
-    Ok(SnapshotPolicy::LogsSinceLast(n_logs))
+    };
+
+    let inner = inner.strip_suffix(')').ok_or_else(invalid)?;
+
+    let policies = split_top_level(inner).into_iter().map(parse_snapshot_policy).collect::<Result<Vec<_>, _>>()?;
+
+    if policies.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(policies)
+}
+
+/// Split `src` by the commas that are not enclosed in parentheses.
+fn split_top_level(src: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in src.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&src[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if !src[start..].trim().is_empty() || !parts.is_empty() {
+        parts.push(&src[start..]);
+    }
+
+    parts
 }
 
 /// The runtime configuration for a Raft node.
@@ -96,6 +253,12 @@ pub struct Config {
     #[clap(long, env = "RAFT_HEARTBEAT_INTERVAL", default_value = "50")]
     pub heartbeat_interval: u64,
 
+    /// The timeout for sending an AppendEntries RPC with logs, in millisecond
+    ///
+    /// Heartbeats are sent separately and always time out after `heartbeat_interval`.
+    #[clap(long, env = "RAFT_APPEND_ENTRIES_TIMEOUT", default_value = "200")]
+    pub append_entries_timeout: u64,
+
     /// The timeout for sending a snapshot segment, in millisecond
     #[clap(long, env = "RAFT_INSTALL_SNAPSHOT_TIMEOUT", default_value = "200")]
     pub install_snapshot_timeout: u64,
@@ -107,6 +270,34 @@ pub struct Config {
     #[clap(long, env = "RAFT_MAX_PAYLOAD_ENTRIES", default_value = "300")]
     pub max_payload_entries: u64,
 
+    /// The maximum size in bytes of the entries per payload allowed to be transmitted during replication, `0` for no
+    /// limit
+    ///
+    /// The size of an entry is measured by its bincode encoding. An entry larger than this limit is sent alone.
+    #[clap(long, env = "RAFT_MAX_PAYLOAD_BYTES", default_value = "0", parse(try_from_str=parse_bytes_with_unit))]
+    pub max_payload_bytes: u64,
+
+    /// The maximum number of AppendEntries RPCs a leader sends to one target without waiting for their responses
+    ///
+    /// With a value greater than 1, replication to a target with a matching log is pipelined,
+    /// so that a high latency link is not limited to one payload per round trip.
+    /// Replication falls back to one RPC at a time after a conflict or an error.
+    #[clap(long, env = "RAFT_MAX_IN_FLIGHT_APPEND_ENTRIES", default_value = "1")]
+    pub max_in_flight_append_entries: u64,
+
+    /// The delay in milliseconds before retrying a replication RPC to a target after the first transport error
+    ///
+    /// The delay doubles with every consecutive failure, up to `replication_backoff_max`, and is randomized to between
+    /// half of it and all of it. `0` retries at once.
+    #[clap(long, env = "RAFT_REPLICATION_BACKOFF_MIN", default_value = "10")]
+    pub replication_backoff_min: u64,
+
+    /// The maximum delay in milliseconds before retrying a replication RPC to a failing target
+    ///
+    /// Heartbeats back off too, but not beyond this, so that a target that comes back is found in time.
+    #[clap(long, env = "RAFT_REPLICATION_BACKOFF_MAX", default_value = "500")]
+    pub replication_backoff_max: u64,
+
     /// The distance behind in log replication a follower must fall before it is considered lagging
     ///
     /// Once a replication stream transition into line-rate state, the target node will be considered safe to join a
@@ -115,6 +306,9 @@ pub struct Config {
     pub replication_lag_threshold: u64,
 
     /// The snapshot policy to use for a Raft node.
+    ///
+    /// Syntax: `since_last:<num>`, `bytes_since_last:<size>`, `interval:<ms>`, `never`,
+    /// or a combination such as `any(since_last:5000,interval:60000)` or `all(...)`.
     #[clap(
         long,
         env = "RAFT_SNAPSHOT_POLICY",
@@ -124,12 +318,56 @@ pub struct Config {
     pub snapshot_policy: SnapshotPolicy,
 
     /// The maximum snapshot chunk size allowed when transmitting snapshots (in bytes)
+    ///
+    /// A compressed chunk received is rejected if it decompresses to more than this.
     #[clap(long, env = "RAFT_SNAPSHOT_MAX_CHUNK_SIZE", default_value = "3MiB", parse(try_from_str=parse_bytes_with_unit))]
     pub snapshot_max_chunk_size: u64,
 
+    /// The compression of snapshot chunks sent to followers: `none`, `lz4` or `zstd`
+    ///
+    /// `lz4` and `zstd` require the cargo feature of the same name.
+    #[clap(long, env = "RAFT_SNAPSHOT_COMPRESSION", default_value = "none")]
+    pub snapshot_compression: SnapshotCompression,
+
+    /// The maximum rate in bytes per second to send a snapshot to a follower, `0` for no limit
+    ///
+    /// It limits the average rate of the bytes sent over the network, after compression, so that streaming a snapshot
+    /// does not saturate the link used by client traffic.
+    #[clap(long, env = "RAFT_SNAPSHOT_MAX_BYTES_PER_SEC", default_value = "0", parse(try_from_str=parse_bytes_with_unit))]
+    pub snapshot_max_bytes_per_sec: u64,
+
     /// The maximum number of applied logs to keep before purging
     #[clap(long, env = "RAFT_MAX_APPLIED_LOG_TO_KEEP", default_value = "1000")]
     pub max_applied_log_to_keep: u64,
+
+    /// Whether to run a pre-vote round before starting an election (§9.6 of the thesis)
+    ///
+    /// A candidate first asks the voters whether it could win, without raising its term.
+    /// A node that rejoins after a partition then does not disrupt a healthy leader.
+    #[clap(long, env = "RAFT_ENABLE_PRE_VOTE")]
+    pub enable_pre_vote: bool,
+
+    /// Whether the leader serves `client_read` with a lease, instead of a heartbeat round per read
+    ///
+    /// A follower does not grant a vote within `election_timeout_min` since it heard from the leader.
+    /// Thus the leader is still the leader for a while since a quorum acknowledged it.
+    /// This relies on bounded clock drift, and a restarted follower forgets it has acknowledged a leader.
+    #[clap(long, env = "RAFT_ENABLE_LEASE_READ")]
+    pub enable_lease_read: bool,
+
+    /// The duration of a leader lease in milliseconds, since a quorum acknowledged the leader
+    ///
+    /// It has to be shorter than `election_timeout_min`, leaving a margin for clock drift.
+    /// Otherwise lease read is disabled.
+    #[clap(long, env = "RAFT_LEASE_READ_TIMEOUT", default_value = "100")]
+    pub lease_read_timeout: u64,
+
+    /// Whether the leader steps down if a quorum has not responded to it within `election_timeout_max`
+    ///
+    /// A leader partitioned away from a quorum then stops accepting requests that it can not serve,
+    /// instead of staying leader until it hears from a new one.
+    #[clap(long, env = "RAFT_ENABLE_CHECK_QUORUM")]
+    pub enable_check_quorum: bool,
 }
 
 impl Default for Config {
@@ -144,6 +382,19 @@ impl Config {
         thread_rng().gen_range(self.election_timeout_min..self.election_timeout_max)
     }
 
+    /// Get the duration of a leader lease, or `None` if lease read is disabled or not safe with this config.
+    pub fn lease_duration(&self) -> Option<Duration> {
+        if !self.enable_lease_read {
+            return None;
+        }
+
+        if self.lease_read_timeout >= self.election_timeout_min {
+            return None;
+        }
+
+        Some(Duration::from_millis(self.lease_read_timeout))
+    }
+
     pub fn build(args: &[&str]) -> Result<Config, ConfigError> {
         let config = <Self as Parser>::parse_from(args);
         config.validate()
@@ -169,6 +420,23 @@ impl Config {
             return Err(ConfigError::MaxPayloadIs0);
         }
 
+        if self.max_in_flight_append_entries == 0 {
+            return Err(ConfigError::MaxInFlightIs0);
+        }
+
+        if self.replication_backoff_min > self.replication_backoff_max {
+            return Err(ConfigError::ReplicationBackoff {
+                min: self.replication_backoff_min,
+                max: self.replication_backoff_max,
+            });
+        }
+
+        if !self.snapshot_compression.is_enabled() {
+            return Err(ConfigError::SnapshotCompressionNotEnabled {
+                compression: self.snapshot_compression,
+            });
+        }
+
         Ok(self)
     }
 }
@@ -1,5 +1,10 @@
+use std::time::Duration;
+
+use crate::config::config::parse_snapshot_policy;
 use crate::config::error::ConfigError;
+use crate::config::SnapshotProgress;
 use crate::Config;
+use crate::SnapshotCompression;
 use crate::SnapshotPolicy;
 
 #[test]
@@ -10,11 +15,22 @@ fn test_config_defaults() {
     assert!(cfg.election_timeout_max <= 300);
 
     assert_eq!(50, cfg.heartbeat_interval);
+    assert_eq!(200, cfg.append_entries_timeout);
     assert_eq!(300, cfg.max_payload_entries);
+    assert_eq!(0, cfg.max_payload_bytes);
+    assert_eq!(1, cfg.max_in_flight_append_entries);
+    assert_eq!(10, cfg.replication_backoff_min);
+    assert_eq!(500, cfg.replication_backoff_max);
     assert_eq!(1000, cfg.replication_lag_threshold);
 
     assert_eq!(3 * 1024 * 1024, cfg.snapshot_max_chunk_size);
     assert_eq!(SnapshotPolicy::LogsSinceLast(5000), cfg.snapshot_policy);
+    assert!(!cfg.enable_pre_vote);
+    assert!(!cfg.enable_lease_read);
+    assert_eq!(100, cfg.lease_read_timeout);
+    assert!(!cfg.enable_check_quorum);
+    assert_eq!(SnapshotCompression::None, cfg.snapshot_compression);
+    assert_eq!(0, cfg.snapshot_max_bytes_per_sec);
 }
 
 #[test]
@@ -44,6 +60,32 @@ fn test_invalid_election_timeout_config_produces_expected_error() {
     });
 }
 
+#[test]
+fn test_invalid_max_in_flight_append_entries() {
+    let config = Config {
+        max_in_flight_append_entries: 0,
+        ..Default::default()
+    };
+
+    let res = config.validate();
+    assert_eq!(Err(ConfigError::MaxInFlightIs0), res.map(|_| ()));
+}
+
+#[test]
+fn test_invalid_replication_backoff() {
+    let config = Config {
+        replication_backoff_min: 100,
+        replication_backoff_max: 50,
+        ..Default::default()
+    };
+
+    let res = config.validate();
+    assert_eq!(
+        Err(ConfigError::ReplicationBackoff { min: 100, max: 50 }),
+        res.map(|_| ())
+    );
+}
+
 #[test]
 fn test_build() -> anyhow::Result<()> {
     let config = Config::build(&[
@@ -52,24 +94,274 @@ fn test_build() -> anyhow::Result<()> {
         "--election-timeout-min=10",
         "--election-timeout-max=20",
         "--heartbeat-interval=5",
+        "--append-entries-timeout=150",
         "--install-snapshot-timeout=200",
         "--max-payload-entries=201",
+        "--max-payload-bytes=2KiB",
+        "--max-in-flight-append-entries=9",
+        "--replication-backoff-min=20",
+        "--replication-backoff-max=2000",
         "--replication-lag-threshold=202",
-        "--snapshot-policy=since_last:203",
+        "--snapshot-policy=any(since_last:203,interval:1000)",
         "--snapshot-max-chunk-size=204",
         "--max-applied-log-to-keep=205",
+        "--enable-pre-vote",
+        "--enable-lease-read",
+        "--lease-read-timeout=8",
+        "--enable-check-quorum",
+        "--snapshot-compression=none",
+        "--snapshot-max-bytes-per-sec=1MiB",
     ])?;
 
     assert_eq!("bar", config.cluster_name);
     assert_eq!(10, config.election_timeout_min);
     assert_eq!(20, config.election_timeout_max);
     assert_eq!(5, config.heartbeat_interval);
+    assert_eq!(150, config.append_entries_timeout);
     assert_eq!(200, config.install_snapshot_timeout);
     assert_eq!(201, config.max_payload_entries);
+    assert_eq!(2048, config.max_payload_bytes);
+    assert_eq!(9, config.max_in_flight_append_entries);
+    assert_eq!(20, config.replication_backoff_min);
+    assert_eq!(2000, config.replication_backoff_max);
     assert_eq!(202, config.replication_lag_threshold);
-    assert_eq!(SnapshotPolicy::LogsSinceLast(203), config.snapshot_policy);
+    assert_eq!(
+        SnapshotPolicy::Any(vec![
+            SnapshotPolicy::LogsSinceLast(203),
+            SnapshotPolicy::IntervalSinceLast(1000)
+        ]),
+        config.snapshot_policy
+    );
     assert_eq!(204, config.snapshot_max_chunk_size);
     assert_eq!(205, config.max_applied_log_to_keep);
+    assert!(config.enable_pre_vote);
+    assert!(config.enable_lease_read);
+    assert_eq!(8, config.lease_read_timeout);
+    assert!(config.enable_check_quorum);
+    assert_eq!(SnapshotCompression::None, config.snapshot_compression);
+    assert_eq!(1024 * 1024, config.snapshot_max_bytes_per_sec);
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_compression() -> anyhow::Result<()> {
+    assert_eq!(SnapshotCompression::Lz4, "lz4".parse()?);
+    assert_eq!(SnapshotCompression::Zstd, "zstd".parse()?);
+    assert_eq!(
+        Err(ConfigError::InvalidSnapshotCompression {
+            invalid: "gzip".to_string()
+        }),
+        "gzip".parse::<SnapshotCompression>()
+    );
+
+    let res = Config::build(&["foo", "--snapshot-compression=lz4"]);
+    if cfg!(feature = "lz4") {
+        assert_eq!(SnapshotCompression::Lz4, res?.snapshot_compression);
+    } else {
+        assert_eq!(
+            Err(ConfigError::SnapshotCompressionNotEnabled {
+                compression: SnapshotCompression::Lz4
+            }),
+            res.map(|_| ())
+        );
+    }
 
     Ok(())
 }
+
+#[cfg(feature = "lz4")]
+#[test]
+fn test_decompress_lz4_corrupted_chunk() -> anyhow::Result<()> {
+    let c = SnapshotCompression::Lz4;
+    let data = vec![7u8; 1024];
+    let compressed = c.compress(&data).map_err(anyhow::Error::msg)?;
+
+    assert_eq!(Ok(data), c.decompress(&compressed, 1024));
+    assert!(
+        c.decompress(&compressed, 1023).is_err(),
+        "decompressed size exceeds the limit"
+    );
+
+    // The size prefix is corrupted to claim 4 GiB.
+    let mut corrupted = compressed;
+    corrupted[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(
+        Err(format!("decompressed size {} exceeds the limit {}", u32::MAX, 1024)),
+        c.decompress(&corrupted, 1024)
+    );
+
+    assert!(c.decompress(&[1, 2], 1024).is_err(), "too short to contain the size");
+
+    Ok(())
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_decompress_zstd_corrupted_chunk() -> anyhow::Result<()> {
+    let c = SnapshotCompression::Zstd;
+    let data = vec![7u8; 1024];
+    let compressed = c.compress(&data).map_err(anyhow::Error::msg)?;
+
+    assert_eq!(Ok(data), c.decompress(&compressed, 1024));
+    assert!(
+        c.decompress(&compressed, 1023).is_err(),
+        "decompressed size exceeds the limit"
+    );
+
+    // A small chunk that decompresses to 64 MiB.
+    let bomb = c.compress(&vec![0u8; 64 * 1024 * 1024]).map_err(anyhow::Error::msg)?;
+    assert!(bomb.len() < 1024 * 1024);
+    assert!(c.decompress(&bomb, 1024 * 1024).is_err());
+
+    // Garbage is not a zstd frame.
+    assert!(c.decompress(&[1, 2, 3, 4, 5, 6, 7, 8], 1024).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_lease_duration() {
+    let config = Config::default();
+    assert_eq!(None, config.lease_duration(), "disabled by default");
+
+    let config = Config {
+        enable_lease_read: true,
+        election_timeout_min: 150,
+        lease_read_timeout: 100,
+        ..Default::default()
+    };
+    assert_eq!(Some(Duration::from_millis(100)), config.lease_duration());
+
+    let config = Config {
+        enable_lease_read: true,
+        election_timeout_min: 150,
+        lease_read_timeout: 150,
+        ..Default::default()
+    };
+    assert_eq!(
+        None,
+        config.lease_duration(),
+        "lease must be shorter than election_timeout_min"
+    );
+}
+
+#[test]
+fn test_parse_snapshot_policy() -> anyhow::Result<()> {
+    assert_eq!(SnapshotPolicy::LogsSinceLast(5), parse_snapshot_policy("since_last:5")?);
+    assert_eq!(
+        SnapshotPolicy::BytesSinceLast(2 * 1024 * 1024),
+        parse_snapshot_policy("bytes_since_last:2MiB")?
+    );
+    assert_eq!(
+        SnapshotPolicy::IntervalSinceLast(100),
+        parse_snapshot_policy("interval:100")?
+    );
+    assert_eq!(SnapshotPolicy::Never, parse_snapshot_policy("never")?);
+
+    assert_eq!(
+        SnapshotPolicy::All(vec![
+            SnapshotPolicy::IntervalSinceLast(100),
+            SnapshotPolicy::Any(vec![
+                SnapshotPolicy::LogsSinceLast(5),
+                SnapshotPolicy::BytesSinceLast(1000)
+            ]),
+        ]),
+        parse_snapshot_policy("all(interval:100, any(since_last:5,bytes_since_last:1000))")?
+    );
+
+    for invalid in [
+        "since_last",
+        "foo:5",
+        "any()",
+        "any(since_last:5",
+        "all(since_last:5,)",
+        "never:5",
+    ] {
+        let res = parse_snapshot_policy(invalid);
+        assert!(
+            matches!(res, Err(ConfigError::InvalidSnapshotPolicy { .. })),
+            "invalid: {}, got: {:?}",
+            invalid,
+            res
+        );
+    }
+
+    let res = parse_snapshot_policy("since_last:x");
+    assert!(matches!(res, Err(ConfigError::InvalidNumber { .. })), "got: {:?}", res);
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_policy_is_satisfied() {
+    let progress = SnapshotProgress {
+        logs: 10,
+        bytes: 1000,
+        elapsed: Duration::from_millis(100),
+    };
+
+    assert!(SnapshotPolicy::LogsSinceLast(10).is_satisfied(&progress));
+    assert!(!SnapshotPolicy::LogsSinceLast(11).is_satisfied(&progress));
+    assert!(SnapshotPolicy::BytesSinceLast(1000).is_satisfied(&progress));
+    assert!(!SnapshotPolicy::BytesSinceLast(1001).is_satisfied(&progress));
+    assert!(SnapshotPolicy::IntervalSinceLast(100).is_satisfied(&progress));
+    assert!(!SnapshotPolicy::IntervalSinceLast(101).is_satisfied(&progress));
+    assert!(!SnapshotPolicy::Never.is_satisfied(&progress));
+
+    assert!(
+        !SnapshotPolicy::IntervalSinceLast(100).is_satisfied(&SnapshotProgress {
+            logs: 0,
+            ..progress.clone()
+        }),
+        "no snapshot without new logs"
+    );
+
+    let logs_or_bytes = SnapshotPolicy::Any(vec![
+        SnapshotPolicy::LogsSinceLast(11),
+        SnapshotPolicy::BytesSinceLast(1000),
+    ]);
+    assert!(logs_or_bytes.is_satisfied(&progress));
+
+    let logs_and_bytes = SnapshotPolicy::All(vec![
+        SnapshotPolicy::LogsSinceLast(11),
+        SnapshotPolicy::BytesSinceLast(1000),
+    ]);
+    assert!(!logs_and_bytes.is_satisfied(&progress));
+
+    assert!(!SnapshotPolicy::All(vec![]).is_satisfied(&progress));
+
+    assert_eq!(Some(11), logs_and_bytes.logs_threshold());
+    assert!(logs_and_bytes.has_bytes());
+    assert!(!logs_and_bytes.has_interval());
+}
+
+#[test]
+fn test_snapshot_policy_is_lag_satisfied() {
+    assert!(SnapshotPolicy::LogsSinceLast(10).is_lag_satisfied(10, 0));
+    assert!(!SnapshotPolicy::LogsSinceLast(10).is_lag_satisfied(9, 0));
+    assert!(SnapshotPolicy::BytesSinceLast(1000).is_lag_satisfied(1, 1000));
+    assert!(!SnapshotPolicy::BytesSinceLast(1000).is_lag_satisfied(1, 999));
+    assert!(!SnapshotPolicy::Never.is_lag_satisfied(u64::MAX, u64::MAX));
+
+    assert!(
+        !SnapshotPolicy::IntervalSinceLast(0).is_lag_satisfied(1, 100),
+        "an interval never sends a snapshot to a replication target"
+    );
+
+    let logs_or_interval = SnapshotPolicy::Any(vec![
+        SnapshotPolicy::LogsSinceLast(10),
+        SnapshotPolicy::IntervalSinceLast(0),
+    ]);
+    assert!(!logs_or_interval.is_lag_satisfied(1, 100));
+    assert!(logs_or_interval.is_lag_satisfied(10, 100));
+
+    let logs_and_interval = SnapshotPolicy::All(vec![
+        SnapshotPolicy::LogsSinceLast(10),
+        SnapshotPolicy::IntervalSinceLast(1_000_000),
+    ]);
+    assert!(!logs_and_interval.is_lag_satisfied(9, 100));
+    assert!(logs_and_interval.is_lag_satisfied(10, 100));
+
+    assert!(!SnapshotPolicy::All(vec![SnapshotPolicy::IntervalSinceLast(0)]).is_lag_satisfied(10, 100));
+}
@@ -1,3 +1,5 @@
+use crate::config::SnapshotCompression;
+
 /// Error variants related to configuration.
 #[derive(Debug, thiserror::Error, PartialEq)]
 #[non_exhaustive]
@@ -8,15 +10,27 @@ pub enum ConfigError {
     #[error("max_payload_entries must be > 0")]
     MaxPayloadIs0,
 
+    #[error("max_in_flight_append_entries must be > 0")]
+    MaxInFlightIs0,
+
     #[error("election_timeout_min({election_timeout_min}) must be > heartbeat_interval({heartbeat_interval})")]
     ElectionTimeoutLTHeartBeat {
         election_timeout_min: u64,
         heartbeat_interval: u64,
     },
 
+    #[error("replication backoff: min({min}) must be <= max({max})")]
+    ReplicationBackoff { min: u64, max: u64 },
+
     #[error("snapshot policy string is invalid: '{invalid:?}' expect: '{syntax}'")]
     InvalidSnapshotPolicy { invalid: String, syntax: String },
 
     #[error("{reason} when parsing {invalid:?}")]
     InvalidNumber { invalid: String, reason: String },
+
+    #[error("snapshot compression is invalid: '{invalid}' expect: 'none | lz4 | zstd'")]
+    InvalidSnapshotCompression { invalid: String },
+
+    #[error("snapshot compression {compression} requires the cargo feature '{compression}'")]
+    SnapshotCompressionNotEnabled { compression: SnapshotCompression },
 }
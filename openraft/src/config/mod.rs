@@ -1,10 +1,13 @@
 #[allow(clippy::module_inception)]
 mod config;
 mod error;
+mod snapshot_compression;
 
 #[cfg(test)]
 mod config_test;
 
 pub use config::Config;
 pub use config::SnapshotPolicy;
+pub(crate) use config::SnapshotProgress;
 pub use error::ConfigError;
+pub use snapshot_compression::SnapshotCompression;
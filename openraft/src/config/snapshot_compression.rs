@@ -0,0 +1,112 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::error::ConfigError;
+
+/// The compression applied to every chunk of a snapshot sent to a follower.
+///
+/// A codec is only available if the cargo feature of the same name is enabled: `lz4` or `zstd`.
+/// A follower that can not decompress a chunk rejects it, and the leader sends the rest of the snapshot uncompressed.
+/// A chunk that decompresses to more than `Config::snapshot_max_chunk_size` of the follower is rejected too.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SnapshotCompression {
+    /// Snapshot chunks are sent as they are.
+    None,
+
+    /// LZ4, fast with a moderate compression ratio.
+    Lz4,
+
+    /// Zstandard, with a better compression ratio at the cost of more CPU.
+    Zstd,
+}
+
+// `#[derive(Default)]` on an enum requires rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for SnapshotCompression {
+    fn default() -> Self {
+        SnapshotCompression::None
+    }
+}
+
+impl SnapshotCompression {
+    /// Returns true if this codec is compiled in.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            SnapshotCompression::None => true,
+            SnapshotCompression::Lz4 => cfg!(feature = "lz4"),
+            SnapshotCompression::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    pub(crate) fn compress(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            SnapshotCompression::None => Ok(data.to_vec()),
+
+            #[cfg(feature = "lz4")]
+            SnapshotCompression::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+
+            #[cfg(feature = "zstd")]
+            SnapshotCompression::Zstd => zstd::bulk::compress(data, 0).map_err(|e| e.to_string()),
+
+            #[allow(unreachable_patterns)]
+            _ => Err(format!("{} is not enabled", self)),
+        }
+    }
+
+    /// Decompress a snapshot chunk, which must not decompress to more than `max_size` bytes.
+    ///
+    /// The size is checked before the output is allocated, thus a corrupted chunk does not exhaust the memory.
+    pub(crate) fn decompress(&self, data: &[u8], max_size: u64) -> Result<Vec<u8>, String> {
+        match self {
+            SnapshotCompression::None => Ok(data.to_vec()),
+
+            #[cfg(feature = "lz4")]
+            SnapshotCompression::Lz4 => {
+                // `compress_prepend_size()` prepends the decompressed size as a little-endian u32.
+                if data.len() < 4 {
+                    return Err("lz4 chunk is too short to contain the size".to_string());
+                }
+                let (size, data) = data.split_at(4);
+                let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as u64;
+                if size > max_size {
+                    return Err(format!("decompressed size {} exceeds the limit {}", size, max_size));
+                }
+
+                lz4_flex::decompress(data, size as usize).map_err(|e| e.to_string())
+            }
+
+            // The output buffer is limited to `max_size`, decompressing to more than that is an error.
+            #[cfg(feature = "zstd")]
+            SnapshotCompression::Zstd => zstd::bulk::decompress(data, max_size as usize).map_err(|e| e.to_string()),
+
+            #[allow(unreachable_patterns)]
+            _ => Err(format!("{} is not enabled", self)),
+        }
+    }
+}
+
+impl fmt::Display for SnapshotCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotCompression::None => write!(f, "none"),
+            SnapshotCompression::Lz4 => write!(f, "lz4"),
+            SnapshotCompression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl FromStr for SnapshotCompression {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "none" => Ok(SnapshotCompression::None),
+            "lz4" => Ok(SnapshotCompression::Lz4),
+            "zstd" => Ok(SnapshotCompression::Zstd),
+            _ => Err(ConfigError::InvalidSnapshotCompression { invalid: s.to_string() }),
+        }
+    }
+}
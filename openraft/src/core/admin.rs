@@ -1,6 +1,8 @@
-use std::collections::BTreeSet;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
+use maplit::btreemap;
+
 use crate::core::client::ClientRequestEntry;
 use crate::core::LeaderState;
 use crate::core::LearnerState;
@@ -13,27 +15,40 @@ use crate::error::InProgress;
 use crate::error::InitializeError;
 use crate::error::LearnerIsLagging;
 use crate::error::LearnerNotFound;
+use crate::membership::split_nodes;
 use crate::raft::AddLearnerResponse;
 use crate::raft::ClientWriteResponse;
 use crate::raft::EntryPayload;
 use crate::raft::RaftRespTx;
 use crate::raft_types::LogIdOptionExt;
+use crate::replication::RaftEvent;
 use crate::AppData;
 use crate::AppDataResponse;
+use crate::ChangeMembers;
 use crate::LogId;
 use crate::Membership;
 use crate::NodeId;
+use crate::NodeInfo;
 use crate::RaftNetwork;
 use crate::RaftStorage;
 use crate::StorageError;
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LearnerState<'a, D, R, N, S> {
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > LearnerState<'a, D, R, N, S, NID, NI>
+{
     /// Handle the admin `init_with_config` command.
     #[tracing::instrument(level = "debug", skip(self))]
     pub(super) async fn handle_init_with_config(
         &mut self,
-        mut members: BTreeSet<NodeId>,
-    ) -> Result<(), InitializeError> {
+        members: BTreeMap<NID, Option<NI>>,
+    ) -> Result<(), InitializeError<NID>> {
         // TODO(xp): simplify this condition
 
         if self.core.last_log_id.is_some() || self.core.vote.term != 0 {
@@ -43,12 +58,14 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             return Err(InitializeError::NotAllowed);
         }
 
+        let (mut members, nodes) = split_nodes(members);
+
         // Ensure given config contains this nodes ID as well.
         if !members.contains(&self.core.id) {
-            members.insert(self.core.id);
+            members.insert(self.core.id.clone());
         }
 
-        let membership = Membership::new_single(members);
+        let membership = Membership::new_single(members).with_nodes(nodes);
 
         let payload = EntryPayload::Membership(membership.clone());
         let _ent = self.core.append_payload_to_log(payload).await?;
@@ -59,10 +76,19 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > LeaderState<'a, D, R, N, S, NID, NI>
+{
     // add node into learner,return true if the node is already a member or learner
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn add_learner_into_membership(&mut self, target: &NodeId) -> bool {
+    async fn add_learner_into_membership(&mut self, target: &NID, node: Option<NI>) -> bool {
         tracing::debug!(
             "add_learner_into_membership target node {} into learner {:?}",
             target,
@@ -75,10 +101,12 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 "target node {} is already a member or learner,cannot add as learner",
                 target
             );
+            self.update_node_info(target, node).await;
             return true;
         }
 
-        let new_config = curr.add_learner(target);
+        let nodes = node.map(|n| (target.clone(), n)).into_iter().collect();
+        let new_config = curr.add_learner(target).with_nodes(nodes);
 
         tracing::debug!(?new_config, "new_config");
 
@@ -87,13 +115,28 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         false
     }
 
+    /// Update the node info of a member or learner, if a different one is provided.
+    async fn update_node_info(&mut self, target: &NID, node: Option<NI>) {
+        let curr = &self.core.effective_membership.membership;
+        let node = match node {
+            Some(n) if curr.contains(target) && curr.get_node(target) != Some(&n) => n,
+            _ => return,
+        };
+
+        tracing::debug!("update the node info of {} to: {}", target, node);
+
+        let new_config = curr.clone().with_nodes(btreemap! {target.clone() => node});
+        let _ = self.append_membership_log(new_config, None).await;
+    }
+
     /// Add a new node to the cluster as a learner, bringing it up-to-speed, and then responding
     /// on the given channel.
     #[tracing::instrument(level = "debug", skip(self, tx))]
     pub(super) async fn add_learner(
         &mut self,
-        target: NodeId,
-        tx: RaftRespTx<AddLearnerResponse, AddLearnerError>,
+        target: NID,
+        node: Option<NI>,
+        tx: RaftRespTx<AddLearnerResponse<NID>, AddLearnerError<NID, NI>>,
         blocking: bool,
     ) {
         tracing::debug!("add target node {} as learner {:?}", target, self.nodes.keys());
@@ -103,28 +146,30 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         if target == self.core.id {
             tracing::debug!("target node is this node");
             let _ = tx.send(Ok(AddLearnerResponse {
-                matched: self.core.last_log_id,
+                matched: self.core.last_log_id.clone(),
             }));
             return;
         }
 
         if let Some(t) = self.nodes.get(&target) {
             tracing::debug!("target node is already a cluster member or is being synced");
-            let _ = tx.send(Ok(AddLearnerResponse { matched: t.matched }));
+            let matched = t.matched.clone();
+            self.update_node_info(&target, node).await;
+            let _ = tx.send(Ok(AddLearnerResponse { matched }));
             return;
         }
 
-        let exist = self.add_learner_into_membership(&target).await;
+        let exist = self.add_learner_into_membership(&target, node).await;
         if exist {
             return;
         }
 
         if blocking {
-            let state = self.spawn_replication_stream(target, Some(tx));
-            self.nodes.insert(target, state);
+            let state = self.spawn_replication_stream(target.clone(), Some(tx));
+            self.nodes.insert(target.clone(), state);
         } else {
-            let state = self.spawn_replication_stream(target, None);
-            self.nodes.insert(target, state);
+            let state = self.spawn_replication_stream(target.clone(), None);
+            self.nodes.insert(target.clone(), state);
 
             // non-blocking mode, do not know about the replication stat.
             let _ = tx.send(Ok(AddLearnerResponse { matched: None }));
@@ -140,33 +185,34 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     #[tracing::instrument(level = "debug", skip(self, tx))]
     pub(super) async fn change_membership(
         &mut self,
-        members: BTreeSet<NodeId>,
+        changes: ChangeMembers<NID, NI>,
         blocking: bool,
         turn_to_learner: bool,
-        tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
-    ) -> Result<(), StorageError> {
-        // Ensure cluster will have at least one node.
-        if members.is_empty() {
-            let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
-                ChangeMembershipError::EmptyMembership(EmptyMembership {}),
-            )));
-            return Ok(());
-        }
-
+        tx: RaftRespTx<ClientWriteResponse<R, NID, NI>, ClientWriteError<NID, NI>>,
+    ) -> Result<(), StorageError<NID>> {
         // The last membership config is not committed yet.
         // Can not process the next one.
-        if self.core.committed < Some(self.core.effective_membership.log_id) {
+        if self.core.committed < Some(self.core.effective_membership.log_id.clone()) {
             let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
                 ChangeMembershipError::InProgress(InProgress {
-                    membership_log_id: self.core.effective_membership.log_id,
+                    membership_log_id: self.core.effective_membership.log_id.clone(),
                 }),
             )));
             return Ok(());
         }
 
         let curr = self.core.effective_membership.membership.clone();
-        let new_members = members.difference(curr.all_members());
-        let mut new_config = curr.next_safe(members.clone(), turn_to_learner);
+        let mut new_config = changes.apply_to(&curr, turn_to_learner);
+
+        // Ensure cluster will have at least one node.
+        if new_config.get_configs().last().map(|c| c.is_empty()).unwrap_or(true) {
+            let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
+                ChangeMembershipError::EmptyMembership(EmptyMembership {}),
+            )));
+            return Ok(());
+        }
+
+        let new_members = new_config.all_members().difference(curr.all_members()).cloned().collect::<Vec<_>>();
 
         tracing::debug!(?new_config, "new_config");
 
@@ -181,7 +227,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
         // TODO(xp): 111 test adding a node that is not learner.
         // TODO(xp): 111 test adding a node that is lagging.
-        for new_node in new_members {
+        for new_node in new_members.iter() {
             match self.nodes.get(new_node) {
                 Some(node) => {
                     if node.is_line_rate(&self.core.last_log_id, &self.core.config) {
@@ -194,8 +240,8 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                         // Node has repl stream, but is not yet ready to join.
                         let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
                             ChangeMembershipError::LearnerIsLagging(LearnerIsLagging {
-                                node_id: *new_node,
-                                matched: node.matched,
+                                node_id: new_node.clone(),
+                                matched: node.matched.clone(),
                                 distance: self.core.last_log_id.next_index().saturating_sub(node.matched.next_index()),
                             }),
                         )));
@@ -206,26 +252,55 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 // Node does not yet have a repl stream, spawn one.
                 None => {
                     let _ = tx.send(Err(ClientWriteError::ChangeMembershipError(
-                        ChangeMembershipError::LearnerNotFound(LearnerNotFound { node_id: *new_node }),
+                        ChangeMembershipError::LearnerNotFound(LearnerNotFound {
+                            node_id: new_node.clone(),
+                        }),
                     )));
                     return Ok(());
                 }
             }
         }
 
+        // Learners added by this change need a replication stream.
+        let new_learners = new_config
+            .all_learners()
+            .iter()
+            .filter(|id| **id != self.core.id && !self.nodes.contains_key(*id))
+            .cloned()
+            .collect::<Vec<_>>();
+
         self.append_membership_log(new_config, Some(tx)).await?;
+
+        for target in new_learners {
+            let state = self.spawn_replication_stream(target.clone(), None);
+            self.nodes.insert(target, state);
+        }
+        self.leader_report_metrics();
+
         Ok(())
     }
 
-    #[tracing::instrument(level = "debug", skip(self, resp_tx), fields(id=self.core.id))]
+    #[tracing::instrument(level = "debug", skip(self, resp_tx), fields(id=%self.core.id))]
     pub async fn append_membership_log(
         &mut self,
-        mem: Membership,
-        resp_tx: Option<RaftRespTx<ClientWriteResponse<R>, ClientWriteError>>,
-    ) -> Result<(), StorageError> {
+        mem: Membership<NID, NI>,
+        resp_tx: Option<RaftRespTx<ClientWriteResponse<R, NID, NI>, ClientWriteError<NID, NI>>>,
+    ) -> Result<(), StorageError<NID>> {
         let payload = EntryPayload::Membership(mem.clone());
+        let prev = self.core.effective_membership.membership.clone();
         let entry = self.core.append_payload_to_log(payload).await?;
 
+        // Let replication streams use the updated node info, e.g., a new address, at once.
+        for (target, node) in self.nodes.iter() {
+            let updated = mem.get_node(target);
+            if prev.get_node(target) != updated {
+                let _ = node.repl_stream.repl_tx.send((
+                    RaftEvent::UpdateNode { node: updated.cloned() },
+                    tracing::debug_span!("CH"),
+                ));
+            }
+        }
+
         self.leader_report_metrics();
 
         let cr_entry = ClientRequestEntry {
@@ -242,14 +317,14 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     ///
     /// This is ony called by leader.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub(super) fn handle_uniform_consensus_committed(&mut self, log_id: &LogId) {
+    pub(super) fn handle_uniform_consensus_committed(&mut self, log_id: &LogId<NID>) {
         let index = log_id.index;
 
         // Step down if needed.
         if !self.core.effective_membership.membership.is_member(&self.core.id) {
             tracing::debug!("raft node is stepping down");
 
-            // TODO(xp): transfer leadership
+            self.transfer_on_removal();
             self.core.set_target_state(State::Learner);
             return;
         }
@@ -284,8 +359,8 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     ///
     /// Return true if removed.
     #[tracing::instrument(level = "trace", skip(self))]
-    pub fn try_remove_replication(&mut self, target: u64) -> bool {
-        tracing::debug!(target, "try_remove_replication");
+    pub fn try_remove_replication(&mut self, target: NID) -> bool {
+        tracing::debug!(%target, "try_remove_replication");
 
         {
             let n = self.nodes.get(&target);
@@ -1,31 +1,41 @@
-use crate::core::apply_to_state_machine;
 use crate::core::RaftCore;
 use crate::core::State;
 use crate::error::AppendEntriesError;
 use crate::raft::AppendEntriesRequest;
 use crate::raft::AppendEntriesResponse;
+use crate::raft::ConflictHint;
 use crate::raft::Entry;
 use crate::raft::EntryPayload;
-use crate::raft_types::LogIdOptionExt;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::EffectiveMembership;
 use crate::LogId;
+use crate::LogIdOptionExt;
 use crate::MessageSummary;
+use crate::NodeId;
+use crate::NodeInfo;
 use crate::RaftNetwork;
 use crate::RaftStorage;
 use crate::StorageError;
 use crate::Update;
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+impl<
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > RaftCore<D, R, N, S, NID, NI>
+{
     /// An RPC invoked by the leader to replicate log entries (§5.3); also used as heartbeat (§5.2).
     ///
     /// See `receiver implementation: AppendEntries RPC` in raft-essentials.md in this repo.
     #[tracing::instrument(level = "debug", skip(self, req))]
     pub(super) async fn handle_append_entries_request(
         &mut self,
-        req: AppendEntriesRequest<D>,
-    ) -> Result<AppendEntriesResponse, AppendEntriesError> {
+        req: AppendEntriesRequest<D, NID, NI>,
+    ) -> Result<AppendEntriesResponse<NID>, AppendEntriesError<NID>> {
         tracing::debug!(last_log_id=?self.last_log_id, ?self.last_applied, msg=%req.summary(), "handle_append_entries_request");
 
         let msg_entries = req.entries.as_slice();
@@ -35,9 +45,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             tracing::debug!(?self.vote, %req.vote, "AppendEntries RPC term is less than current term");
 
             return Ok(AppendEntriesResponse {
-                vote: self.vote,
+                vote: self.vote.clone(),
                 success: false,
                 conflict: false,
+                conflict_hint: None,
             });
         }
 
@@ -54,12 +65,17 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             }
 
             self.report_metrics(Update::AsIs);
+        } else if self.target_state.is_candidate() {
+            // A candidate in a pre-vote round has not raised its vote yet,
+            // thus the leader it hears from may have the same vote.
+            self.set_target_state(State::Follower);
         }
 
         // Caveat: [commit-index must not advance the last known consistent log](https://datafuselabs.github.io/openraft/replication.html#caveat-commit-index-must-not-advance-the-last-known-consistent-log)
 
         // TODO(xp): cleanup commit index at sender side.
-        let valid_commit_index = msg_entries.last().map(|x| Some(x.log_id)).unwrap_or_else(|| req.prev_log_id);
+        let valid_commit_index =
+            msg_entries.last().map(|x| Some(x.log_id.clone())).unwrap_or_else(|| req.prev_log_id.clone());
         let valid_committed = std::cmp::min(req.leader_commit, valid_commit_index);
 
         tracing::debug!("begin log consistency check");
@@ -75,7 +91,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn delete_conflict_logs_since(&mut self, start: LogId) -> Result<(), StorageError> {
+    async fn delete_conflict_logs_since(&mut self, start: LogId<NID>) -> Result<(), StorageError<NID>> {
         self.storage.delete_conflict_logs_since(start).await?;
 
         self.last_log_id = self.storage.get_log_state().await?.last_log_id;
@@ -88,7 +104,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         //           When a node starts in a single-node mode, it does not append an initial log
         //           but instead depends on storage.get_membership() to return a default one.
         //           It would be better a node always append an initial log entry.
-        let membership = membership.unwrap_or_else(|| EffectiveMembership::new_initial(self.id));
+        let membership = membership.unwrap_or_else(|| EffectiveMembership::new_initial(self.id.clone()));
 
         self.update_membership(membership);
 
@@ -137,7 +153,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// If log 5 is committed by R1, and log 3 is not removed, R5 in future could become a new leader and overrides log
     /// 5 on R3.
     #[tracing::instrument(level="trace", skip(self, msg_entries), fields(msg_entries=%msg_entries.summary()))]
-    async fn find_and_delete_conflict_logs(&mut self, msg_entries: &[Entry<D>]) -> Result<(), StorageError> {
+    async fn find_and_delete_conflict_logs(
+        &mut self,
+        msg_entries: &[Entry<D, NID, NI>],
+    ) -> Result<(), StorageError<NID>> {
         // all msg_entries are inconsistent logs
 
         tracing::debug!(msg_entries=%msg_entries.summary(), "try to delete_inconsistent_log");
@@ -147,7 +166,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             return Ok(());
         }
 
-        if let Some(last_log_id) = self.last_log_id {
+        if let Some(last_log_id) = &self.last_log_id {
             if msg_entries[0].log_id.index > last_log_id.index {
                 return Ok(());
             }
@@ -161,7 +180,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             msg_entries.summary()
         );
 
-        self.delete_conflict_logs_since(msg_entries[0].log_id).await?;
+        self.delete_conflict_logs_since(msg_entries[0].log_id.clone()).await?;
 
         Ok(())
     }
@@ -171,11 +190,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     #[tracing::instrument(level="trace", skip(self, entries), fields(entries=%entries.summary()))]
     async fn append_apply_log_entries(
         &mut self,
-        prev_log_id: Option<LogId>,
-        entries: &[Entry<D>],
-        committed: Option<LogId>,
-    ) -> Result<AppendEntriesResponse, StorageError> {
-        let mismatched = self.does_log_id_match(prev_log_id).await?;
+        prev_log_id: Option<LogId<NID>>,
+        entries: &[Entry<D, NID, NI>],
+        committed: Option<LogId<NID>>,
+    ) -> Result<AppendEntriesResponse<NID>, StorageError<NID>> {
+        let mismatched = self.does_log_id_match(prev_log_id.clone()).await?;
 
         tracing::debug!(
             "check prev_log_id {:?} match: committed: {:?}, mismatched: {:?}",
@@ -185,8 +204,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         );
 
         if let Some(mismatched_log_id) = mismatched {
+            // Build the hint before the inconsistent logs are removed.
+            let conflict_hint = self.build_conflict_hint(&mismatched_log_id).await?;
+
             // prev_log_id mismatches, the logs [prev_log_id.index, +oo) are all inconsistent and should be removed
-            if let Some(last_log_id) = self.last_log_id {
+            if let Some(last_log_id) = &self.last_log_id {
                 if mismatched_log_id.index <= last_log_id.index {
                     tracing::debug!(%mismatched_log_id, "delete inconsistent log since prev_log_id");
                     self.delete_conflict_logs_since(mismatched_log_id).await?;
@@ -194,9 +216,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             }
 
             return Ok(AppendEntriesResponse {
-                vote: self.vote,
+                vote: self.vote.clone(),
                 success: false,
                 conflict: true,
+                conflict_hint: Some(conflict_hint),
             });
         }
 
@@ -221,17 +244,50 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // This is guaranteed by caller.
         self.committed = committed;
 
-        self.replicate_to_state_machine_if_needed().await?;
+        self.apply_committed(vec![]);
 
         self.report_metrics(Update::AsIs);
 
         Ok(AppendEntriesResponse {
-            vote: self.vote,
+            vote: self.vote.clone(),
             success: true,
             conflict: false,
+            conflict_hint: None,
         })
     }
 
+    /// Build a hint for the leader about where the local log diverges from the mismatching `prev_log_id`.
+    ///
+    /// If there is a local log at `prev_log_id.index`, the logs proposed by the same leader are contiguous since the
+    /// leader ids in a log are monotonic. The first of them is found with a binary search, among the logs that are not
+    /// committed, because committed logs always match.
+    async fn build_conflict_hint(&self, prev_log_id: &LogId<NID>) -> Result<ConflictHint<NID>, StorageError<NID>> {
+        if self.last_log_id.index() < Some(prev_log_id.index) {
+            return Ok(ConflictHint::LastLogId(self.last_log_id.clone()));
+        }
+
+        let leader_id = match self.storage.try_get_log_entry(prev_log_id.index).await? {
+            Some(ent) => ent.log_id.leader_id,
+            None => return Ok(ConflictHint::ConflictTermStart(prev_log_id.index)),
+        };
+
+        let mut lo = self.committed.next_index();
+        let mut hi = prev_log_id.index;
+
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let ent = self.storage.try_get_log_entry(mid).await?;
+
+            if ent.map(|x| x.log_id.leader_id) == Some(leader_id.clone()) {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+
+        Ok(ConflictHint::ConflictTermStart(lo))
+    }
+
     /// Returns number of entries that match local storage by comparing log_id,
     /// and the the unmatched entries.
     ///
@@ -239,14 +295,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// Filter them out.
     pub async fn skip_matching_entries<'s, 'e>(
         &'s self,
-        entries: &'e [Entry<D>],
-    ) -> Result<(usize, &'e [Entry<D>]), StorageError> {
+        entries: &'e [Entry<D, NID, NI>],
+    ) -> Result<(usize, &'e [Entry<D, NID, NI>]), StorageError<NID>> {
         let l = entries.len();
 
         for i in 0..l {
-            let log_id = entries[i].log_id;
+            let log_id = &entries[i].log_id;
 
-            if Some(log_id) <= self.committed {
+            if Some(log_id.clone()) <= self.committed {
                 continue;
             }
 
@@ -256,7 +312,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             let log = self.storage.try_get_log_entry(index).await?;
 
             if let Some(local) = log {
-                if local.log_id == log_id {
+                if &local.log_id == log_id {
                     continue;
                 }
             }
@@ -271,12 +327,15 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     ///
     /// This way to check if the entries in append-entries request is consecutive with local logs.
     /// Raft only accept consecutive logs to be appended.
-    pub async fn does_log_id_match(&self, remote_log_id: Option<LogId>) -> Result<Option<LogId>, StorageError> {
-        let log_id = match remote_log_id {
+    pub async fn does_log_id_match(
+        &self,
+        remote_log_id: Option<LogId<NID>>,
+    ) -> Result<Option<LogId<NID>>, StorageError<NID>> {
+        let log_id = match &remote_log_id {
             None => {
                 return Ok(None);
             }
-            Some(x) => x,
+            Some(x) => x.clone(),
         };
 
         // Committed entries are always safe and are consistent to a valid leader.
@@ -289,7 +348,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         let log = self.storage.try_get_log_entry(index).await?;
         tracing::debug!(
             "check log id matching: local: {:?} remote: {}",
-            log.as_ref().map(|x| x.log_id),
+            log.as_ref().map(|x| x.log_id.clone()),
             log_id
         );
 
@@ -307,7 +366,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// Configuration changes are also detected and applied here. See `configuration changes`
     /// in the raft-essentials.md in this repo.
     #[tracing::instrument(level = "trace", skip(self, entries), fields(entries=%entries.summary()))]
-    async fn append_log_entries(&mut self, entries: &[Entry<D>]) -> Result<(), StorageError> {
+    async fn append_log_entries(&mut self, entries: &[Entry<D, NID, NI>]) -> Result<(), StorageError<NID>> {
         if entries.is_empty() {
             return Ok(());
         }
@@ -317,7 +376,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             .iter()
             .filter_map(|ent| match &ent.payload {
                 EntryPayload::Membership(conf) => Some(EffectiveMembership {
-                    log_id: ent.log_id,
+                    log_id: ent.log_id.clone(),
                     membership: conf.clone(),
                 }),
                 _ => None,
@@ -337,47 +396,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         let entry_refs = entries.iter().collect::<Vec<_>>();
         self.storage.append_to_log(&entry_refs).await?;
         if let Some(entry) = entries.last() {
-            self.last_log_id = Some(entry.log_id);
-        }
-        Ok(())
-    }
-
-    /// Replicate any outstanding entries to the state machine for which it is safe to do so.
-    ///
-    /// Very importantly, this routine must not block the main control loop main task, else it
-    /// may cause the Raft leader to timeout the requests to this node.
-    #[tracing::instrument(level = "trace", skip(self))]
-    async fn replicate_to_state_machine_if_needed(&mut self) -> Result<(), StorageError> {
-        tracing::debug!(?self.last_applied, "replicate_to_sm_if_needed");
-
-        // If we don't have any new entries to replicate, then do nothing.
-        if self.committed <= self.last_applied {
-            tracing::debug!(
-                "committed({:?}) <= last_applied({:?}), return",
-                self.committed,
-                self.last_applied
-            );
-            return Ok(());
+            self.last_log_id = Some(entry.log_id.clone());
         }
-
-        // Drain entries from the beginning of the cache up to commit index.
-
-        let entries = self.storage.get_log_entries(self.last_applied.next_index()..self.committed.next_index()).await?;
-
-        let last_log_id = entries.last().map(|x| x.log_id).unwrap();
-
-        tracing::debug!("entries: {}", entries.as_slice().summary());
-        tracing::debug!(?last_log_id);
-
-        let entries_refs: Vec<_> = entries.iter().collect();
-
-        apply_to_state_machine(self.storage.clone(), &entries_refs, self.config.max_applied_log_to_keep).await?;
-
-        self.last_applied = Some(last_log_id);
-
-        self.report_metrics(Update::AsIs);
-        self.trigger_log_compaction_if_needed(false);
-
         Ok(())
     }
 }
@@ -0,0 +1,266 @@
+//! Apply committed logs to the state machine in a task separated from `RaftCore`,
+//! thus a slow state machine does not delay heartbeats, votes or replication.
+
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+use crate::config::Config;
+use crate::core::apply_to_state_machine;
+use crate::core::client::send_response;
+use crate::core::client::ClientRequestEntry;
+use crate::core::RaftCore;
+use crate::error::PurgeLogError;
+use crate::raft::RaftRespTx;
+use crate::raft_types::LogIdOptionExt;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::LogId;
+use crate::MessageSummary;
+use crate::NodeId;
+use crate::NodeInfo;
+use crate::RaftNetwork;
+use crate::RaftStorage;
+use crate::StorageError;
+use crate::Update;
+
+/// A command sent by `RaftCore` to the apply task.
+pub(super) enum ApplyCommand<D: AppData, R: AppDataResponse, NID: NodeId, NI: NodeInfo> {
+    /// Apply the logs in the range `(since, upto]`.
+    Apply {
+        since: Option<LogId<NID>>,
+        upto: LogId<NID>,
+
+        /// The client requests of the logs to apply, which are responded to once applied.
+        requests: Vec<ClientRequestEntry<D, R, NID, NI>>,
+    },
+
+    /// Purge logs upto index `upto`, inclusive.
+    ///
+    /// Purging is done by the apply task, in order with applying logs, which also purges the applied logs. Thus two
+    /// purges never run concurrently.
+    Purge {
+        upto: u64,
+
+        /// The caller of `Raft::purge_log()`, which is responded to once the logs are purged.
+        tx: RaftRespTx<(), PurgeLogError<NID>>,
+    },
+
+    /// Respond once every command sent before it is done.
+    Flush { tx: oneshot::Sender<()> },
+}
+
+/// The result of an `ApplyCommand::Apply`, sent back to `RaftCore`.
+pub(super) struct Applied<D: AppData, R: AppDataResponse, NID: NodeId, NI: NodeInfo> {
+    /// The last log id applied.
+    pub(super) last_applied: LogId<NID>,
+
+    /// The size in bytes of the applied logs, if the snapshot policy requires it.
+    pub(super) bytes: u64,
+
+    /// The client requests of the applied logs, along with the responses from the state machine.
+    pub(super) responses: Vec<(ClientRequestEntry<D, R, NID, NI>, R)>,
+}
+
+/// The task applying committed logs to the state machine.
+///
+/// Commands are handled one by one in the order they are sent, thus logs are applied in order.
+pub(super) struct ApplyCore<D: AppData, R: AppDataResponse, S: RaftStorage<D, R, NID, NI>, NID: NodeId, NI: NodeInfo> {
+    /// The `RaftStorage` interface.
+    storage: Arc<S>,
+
+    /// The Raft's runtime config.
+    config: Arc<Config>,
+
+    /// A channel for receiving commands from `RaftCore`.
+    rx_apply: mpsc::UnboundedReceiver<ApplyCommand<D, R, NID, NI>>,
+
+    /// A channel for sending results back to `RaftCore`.
+    tx_applied: mpsc::UnboundedSender<Result<Applied<D, R, NID, NI>, StorageError<NID>>>,
+}
+
+impl<D: AppData, R: AppDataResponse, S: RaftStorage<D, R, NID, NI>, NID: NodeId, NI: NodeInfo>
+    ApplyCore<D, R, S, NID, NI>
+{
+    /// Spawn the apply task. It quits when `RaftCore` drops the sending end of `rx_apply`.
+    pub(super) fn spawn(
+        storage: Arc<S>,
+        config: Arc<Config>,
+        rx_apply: mpsc::UnboundedReceiver<ApplyCommand<D, R, NID, NI>>,
+        tx_applied: mpsc::UnboundedSender<Result<Applied<D, R, NID, NI>, StorageError<NID>>>,
+    ) -> JoinHandle<()> {
+        let this = Self {
+            storage,
+            config,
+            rx_apply,
+            tx_applied,
+        };
+
+        tokio::spawn(this.main().instrument(tracing::trace_span!("apply").or_current()))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn main(mut self) {
+        while let Some(cmd) = self.rx_apply.recv().await {
+            match cmd {
+                ApplyCommand::Apply { since, upto, requests } => {
+                    let res = self.apply(since, upto, requests).await;
+                    let is_err = res.is_err();
+
+                    let _ = self.tx_applied.send(res);
+
+                    if is_err {
+                        // RaftCore quits on a storage error.
+                        return;
+                    }
+                }
+                ApplyCommand::Purge { upto, tx } => {
+                    let res = self.purge(upto).await;
+
+                    let _ = tx.send(res.clone().map_err(PurgeLogError::from));
+
+                    if let Err(err) = res {
+                        let _ = self.tx_applied.send(Err(err));
+                        return;
+                    }
+                }
+                ApplyCommand::Flush { tx } => {
+                    let _ = tx.send(());
+                }
+            }
+        }
+
+        tracing::debug!("RaftCore is closed, quit apply task");
+    }
+
+    /// Apply the logs in `(since, upto]` and collect the responses to the client requests.
+    #[tracing::instrument(level = "debug", skip(self, requests))]
+    async fn apply(
+        &self,
+        since: Option<LogId<NID>>,
+        upto: LogId<NID>,
+        requests: Vec<ClientRequestEntry<D, R, NID, NI>>,
+    ) -> Result<Applied<D, R, NID, NI>, StorageError<NID>> {
+        let start = since.next_index();
+        let entries = self.storage.get_log_entries(start..upto.index + 1).await?;
+
+        tracing::debug!("entries: {}", entries.as_slice().summary());
+
+        let bytes = if self.config.snapshot_policy.has_bytes() {
+            entries.iter().map(|ent| bincode::serialized_size(ent).unwrap_or_default()).sum()
+        } else {
+            0
+        };
+
+        let entry_refs: Vec<_> = entries.iter().collect();
+        let results =
+            apply_to_state_machine(self.storage.clone(), &entry_refs, self.config.max_applied_log_to_keep).await?;
+
+        let mut results = results.into_iter().map(Some).collect::<Vec<_>>();
+
+        let responses = requests
+            .into_iter()
+            .map(|req| {
+                let i = (req.entry.log_id.index - start) as usize;
+                let resp = results[i].take().unwrap();
+                (req, resp)
+            })
+            .collect();
+
+        Ok(Applied {
+            last_applied: upto,
+            bytes,
+            responses,
+        })
+    }
+
+    /// Purge logs upto index `upto`, inclusive, if they are not yet purged.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn purge(&self, upto: u64) -> Result<(), StorageError<NID>> {
+        let st = self.storage.get_log_state().await?;
+        if st.last_purged_log_id.index() >= Some(upto) {
+            tracing::debug!(?st.last_purged_log_id, upto, "logs are already purged");
+            return Ok(());
+        }
+
+        let log_id = self.storage.get_log_id(upto).await?;
+        self.storage.purge_logs_upto(log_id).await
+    }
+}
+
+impl<
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > RaftCore<D, R, N, S, NID, NI>
+{
+    /// Send the committed logs that have not yet been sent to the apply task, along with the client requests of them.
+    ///
+    /// A witness applies logs too, but its logs carry no application data: only the last applied log id and the
+    /// membership configs are stored in its state machine. The applied logs are then purged as on any other node.
+    #[tracing::instrument(level = "trace", skip(self, requests), fields(requests = requests.len()))]
+    pub(super) fn apply_committed(&mut self, requests: Vec<ClientRequestEntry<D, R, NID, NI>>) {
+        let upto = match &self.committed {
+            Some(committed) if self.committed > self.apply_requested => committed.clone(),
+            _ => {
+                debug_assert!(requests.is_empty(), "client requests are applied only once");
+                return;
+            }
+        };
+
+        tracing::debug!(since=?self.apply_requested, %upto, "send committed logs to apply");
+
+        let _ = self.tx_apply.send(ApplyCommand::Apply {
+            since: self.apply_requested.clone(),
+            upto: upto.clone(),
+            requests,
+        });
+
+        self.apply_requested = Some(upto);
+    }
+
+    /// Handle the result of applying logs from the apply task: update `last_applied` and respond to client requests.
+    #[tracing::instrument(level = "trace", skip(self, res))]
+    pub(super) fn handle_applied(
+        &mut self,
+        res: Result<Applied<D, R, NID, NI>, StorageError<NID>>,
+    ) -> Result<(), StorageError<NID>> {
+        let applied = res?;
+
+        self.last_applied = Some(applied.last_applied);
+        self.applied_bytes_since_snapshot += applied.bytes;
+        self.report_metrics(Update::AsIs);
+
+        for (req, resp) in applied.responses {
+            send_response(&req.entry, resp, req.tx);
+        }
+
+        self.trigger_log_compaction_if_needed(false);
+
+        Ok(())
+    }
+
+    /// Wait for the apply task to apply every log sent to it, and handle the results.
+    ///
+    /// All changes to the state machine must be serialized, e.g., a snapshot must not be installed while the apply
+    /// task is applying logs.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub(super) async fn flush_apply(&mut self) -> Result<(), StorageError<NID>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.tx_apply.send(ApplyCommand::Flush { tx });
+        let _ = rx.await;
+
+        // Results are sent before the apply task handles the next command.
+        while let Ok(res) = self.rx_applied.try_recv() {
+            self.handle_applied(res)?;
+        }
+
+        Ok(())
+    }
+}
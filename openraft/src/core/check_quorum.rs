@@ -0,0 +1,61 @@
+use maplit::btreeset;
+use tokio::time::Duration;
+use tokio::time::Instant;
+
+use crate::core::LeaderState;
+use crate::core::State;
+use crate::membership::quorum::QuorumSet;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::NodeId;
+use crate::NodeInfo;
+use crate::RaftNetwork;
+use crate::RaftStorage;
+
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > LeaderState<'a, D, R, N, S, NID, NI>
+{
+    /// Step down to follower if a quorum has not responded to this leader within an election timeout.
+    ///
+    /// Without this check, a leader partitioned away from the majority keeps accepting client requests, which can
+    /// never be committed, until it receives a greater vote.
+    ///
+    /// A node that has not responded since this leader was elected is regarded as responded at the election.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) fn check_quorum(&mut self) {
+        let now = Instant::now();
+        self.next_quorum_check = now + Duration::from_millis(self.core.config.heartbeat_interval);
+
+        let timeout = Duration::from_millis(self.core.config.election_timeout_max);
+        let mut responded = btreeset! {self.core.id.clone()};
+
+        for (target, node) in self.nodes.iter() {
+            let acked_at = node.acked_at.map_or(self.elected_at, |t| std::cmp::max(t, self.elected_at));
+            if acked_at + timeout > now {
+                responded.insert(target.clone());
+            }
+        }
+
+        if self.core.quorum_set().is_replication_quorum(&responded) {
+            return;
+        }
+
+        tracing::warn!(
+            id = display(&self.core.id),
+            ?responded,
+            "no quorum responded within {:?}, step down to follower",
+            timeout
+        );
+
+        self.core.quorum_lost += 1;
+        self.core.set_target_state(State::Follower);
+        self.leader_report_metrics();
+    }
+}
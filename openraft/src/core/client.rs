@@ -1,59 +1,192 @@
 use std::sync::Arc;
 
-use futures::future::TryFutureExt;
 use futures::stream::FuturesUnordered;
 use futures::stream::StreamExt;
 use maplit::btreeset;
 use tokio::time::timeout;
 use tokio::time::Duration;
+use tokio::time::Instant;
 use tracing::Instrument;
 
-use crate::core::apply_to_state_machine;
 use crate::core::LeaderState;
-use crate::core::State;
 use crate::error::ClientReadError;
 use crate::error::ClientWriteError;
 use crate::error::QuorumNotEnough;
-use crate::error::RPCError;
-use crate::error::Timeout;
+use crate::membership::quorum::Joint;
+use crate::membership::quorum::QuorumSet;
 use crate::raft::AppendEntriesRequest;
 use crate::raft::ClientWriteRequest;
 use crate::raft::ClientWriteResponse;
 use crate::raft::Entry;
 use crate::raft::EntryPayload;
 use crate::raft::RaftRespTx;
+use crate::raft::ReadIndexResponse;
 use crate::replication::RaftEvent;
 use crate::AppData;
 use crate::AppDataResponse;
+use crate::LogId;
+use crate::Membership;
 use crate::MessageSummary;
-use crate::RPCTypes;
+use crate::NodeId;
+use crate::NodeInfo;
 use crate::RaftNetwork;
 use crate::RaftStorage;
 use crate::StorageError;
+use crate::Vote;
+
+/// The response channel of a client write request.
+pub(super) type ClientWriteTx<R, NID, NI> = RaftRespTx<ClientWriteResponse<R, NID, NI>, ClientWriteError<NID, NI>>;
 
 /// A wrapper around a ClientRequest which has been transformed into an Entry, along with its response channel.
-pub(super) struct ClientRequestEntry<D: AppData, R: AppDataResponse> {
+pub(super) struct ClientRequestEntry<D: AppData, R: AppDataResponse, NID: NodeId, NI: NodeInfo> {
     /// The Arc'd entry of the ClientRequest.
     ///
     /// This value is Arc'd so that it may be sent across thread boundaries for replication
     /// without having to clone the data payload itself.
-    pub entry: Arc<Entry<D>>,
+    pub entry: Arc<Entry<D, NID, NI>>,
 
     /// The response channel for the request.
-    pub tx: Option<RaftRespTx<ClientWriteResponse<R>, ClientWriteError>>,
+    pub tx: Option<ClientWriteTx<R, NID, NI>>,
 }
 
-impl<D: AppData, R: AppDataResponse> MessageSummary for ClientRequestEntry<D, R> {
+impl<D: AppData, R: AppDataResponse, NID: NodeId, NI: NodeInfo> MessageSummary for ClientRequestEntry<D, R, NID, NI> {
     fn summary(&self) -> String {
         format!("entry:{}", self.entry.summary())
     }
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+/// A read request waiting for the leader to confirm its leadership.
+pub(super) enum PendingRead<NID: NodeId, NI: NodeInfo> {
+    /// From `Raft::client_read`.
+    Read {
+        tx: RaftRespTx<(), ClientReadError<NID, NI>>,
+    },
+
+    /// From `Raft::client_read_index` on the leader.
+    ReadIndex {
+        read_log_id: Option<LogId<NID>>,
+        tx: RaftRespTx<Option<LogId<NID>>, ClientReadError<NID, NI>>,
+    },
+
+    /// A ReadIndex RPC from a follower or learner.
+    ReadIndexRpc {
+        read_log_id: Option<LogId<NID>>,
+        tx: RaftRespTx<ReadIndexResponse<NID>, ClientReadError<NID, NI>>,
+    },
+}
+
+impl<NID: NodeId, NI: NodeInfo> PendingRead<NID, NI> {
+    /// Respond to the caller with the result of confirming the leadership.
+    pub(super) fn respond(self, res: Result<(), ClientReadError<NID, NI>>) {
+        match self {
+            PendingRead::Read { tx } => {
+                let _ = tx.send(res);
+            }
+            PendingRead::ReadIndex { read_log_id, tx } => {
+                let _ = tx.send(res.map(|_| read_log_id));
+            }
+            PendingRead::ReadIndexRpc { read_log_id, tx } => {
+                let _ = tx.send(res.map(|_| ReadIndexResponse { read_log_id }));
+            }
+        }
+    }
+}
+
+/// The result of a round to confirm the leadership.
+pub(super) struct ReadRoundResult<NID: NodeId> {
+    pub(super) res: Result<(), QuorumNotEnough<NID>>,
+
+    /// A node and the vote greater than the leader's it responded with.
+    pub(super) higher_vote: Option<(NID, Vote<NID>)>,
+}
+
+/// Send a heartbeat to every voter, until a quorum confirms the leadership of `vote` or all of them respond.
+async fn confirm_leadership<D: AppData, N: RaftNetwork<D, NID, NI>, NID: NodeId, NI: NodeInfo>(
+    my_id: NID,
+    vote: Vote<NID>,
+    membership: Membership<NID, NI>,
+    quorum_set: Joint<Box<dyn QuorumSet<NID>>>,
+    rpcs: Vec<(NID, AppendEntriesRequest<D, NID, NI>)>,
+    network: Arc<N>,
+    ttl: Duration,
+) -> ReadRoundResult<NID> {
+    let mut pending = FuturesUnordered::new();
+
+    for (target, rpc) in rpcs {
+        let network = network.clone();
+        let target_node = membership.get_node(&target).cloned();
+        pending.push(async move {
+            let res = timeout(
+                ttl,
+                network.send_append_entries(target.clone(), target_node.as_ref(), rpc),
+            )
+            .await;
+            (target, res)
+        });
+    }
+
+    let mut granted = btreeset! {my_id};
+    let mut higher_vote = None;
+
+    // Handle responses as they return.
+    while let Some((target, res)) = pending.next().await {
+        let resp = match res {
+            Ok(Ok(resp)) => resp,
+            Ok(Err(err)) => {
+                tracing::error!(%target, error=%err, "error while confirming leadership for read request");
+                continue;
+            }
+            Err(_timeout) => {
+                tracing::error!(%target, ?ttl, "timeout while confirming leadership for read request");
+                continue;
+            }
+        };
+
+        // A node that has seen a greater vote does not confirm this leader.
+        if resp.vote > vote {
+            if higher_vote.as_ref().map(|(_, v)| &resp.vote > v).unwrap_or(true) {
+                higher_vote = Some((target, resp.vote));
+            }
+            continue;
+        }
+
+        granted.insert(target);
+
+        if quorum_set.is_replication_quorum(&granted) {
+            return ReadRoundResult {
+                res: Ok(()),
+                higher_vote,
+            };
+        }
+    }
+
+    // If we've hit this location, then we've failed to gather needed confirmations due to
+    // request failures.
+
+    ReadRoundResult {
+        res: Err(QuorumNotEnough {
+            cluster: membership.summary(),
+            got: granted,
+        }),
+        higher_vote,
+    }
+}
+
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > LeaderState<'a, D, R, N, S, NID, NI>
+{
     /// Commit the initial entry which new leaders are obligated to create when first coming to power, per §8.
     #[tracing::instrument(level = "trace", skip(self))]
-    pub(super) async fn commit_initial_leader_entry(&mut self) -> Result<(), StorageError> {
+    pub(super) async fn commit_initial_leader_entry(&mut self) -> Result<(), StorageError<NID>> {
         let entry = self.core.append_payload_to_log(EntryPayload::Blank).await?;
+        self.blank_log_id = Some(entry.log_id.clone());
 
         self.leader_report_metrics();
 
@@ -67,138 +200,170 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         Ok(())
     }
 
-    /// Handle client read requests.
-    ///
-    /// Spawn requests to all members of the cluster, include members being added in joint
-    /// consensus. Each request will have a timeout, and we respond once we have a majority
-    /// agreement from each config group. Most of the time, we will have a single uniform
-    /// config group.
+    /// Handle client read requests, including read index requests from a local client or from a follower.
     ///
     /// From the spec (§8):
     /// Second, a leader must check whether it has been deposed before processing a read-only
     /// request (its information may be stale if a more recent leader has been elected). Raft
     /// handles this by having the leader exchange heartbeat messages with a majority of the
     /// cluster before responding to read-only requests.
-    #[tracing::instrument(level = "trace", skip(self, tx))]
-    pub(super) async fn handle_client_read_request(&mut self, tx: RaftRespTx<(), ClientReadError>) {
-        // Setup sentinel values to track when we've received majority confirmation of leadership.
-
-        let mem = &self.core.effective_membership.membership;
-        let mut granted = btreeset! {self.core.id};
+    ///
+    /// If lease read is enabled, the heartbeat round is skipped while the leader lease is valid (§6.4.1 of the
+    /// thesis).
+    ///
+    /// The heartbeat round runs in a background task, thus the leader keeps handling other messages meanwhile.
+    /// Reads received while a round is in progress are confirmed together by the next round.
+    #[tracing::instrument(level = "trace", skip(self, read))]
+    pub(super) fn handle_client_read_request(&mut self, read: PendingRead<NID, NI>) {
+        if self.core.quorum_set().is_replication_quorum(&btreeset! {self.core.id.clone()}) {
+            read.respond(Ok(()));
+            return;
+        }
 
-        if mem.is_majority(&granted) {
-            let _ = tx.send(Ok(()));
+        if self.has_valid_lease() {
+            tracing::debug!("leader lease is valid, no need to confirm leadership");
+            read.respond(Ok(()));
             return;
         }
 
-        // Spawn parallel requests, all with the standard timeout for heartbeats.
-        let mut pending = FuturesUnordered::new();
-        let membership = &self.core.effective_membership.membership;
+        self.queued_reads.push(read);
+        self.try_start_read_round();
+    }
 
-        for (target, node) in self.nodes.iter() {
-            if !membership.is_member(target) {
-                continue;
+    /// Start a round to confirm the leadership for all queued reads, if there is no round in progress.
+    ///
+    /// A heartbeat sent before a read is received does not prove the leadership at the time of the read.
+    /// Thus reads received during a round have to wait for the next one.
+    fn try_start_read_round(&mut self) {
+        if self.confirming_reads.is_some() || self.queued_reads.is_empty() {
+            return;
+        }
+
+        let reads = std::mem::take(&mut self.queued_reads);
+        tracing::debug!(reads = reads.len(), "start a round to confirm leadership");
+        self.confirming_reads = Some(reads);
+
+        let membership = self.core.effective_membership.membership.clone();
+        let quorum_set = self.core.quorum_set();
+
+        let rpcs = self
+            .nodes
+            .iter()
+            .filter(|(target, _)| membership.is_member(target))
+            .map(|(target, node)| {
+                let rpc = AppendEntriesRequest {
+                    vote: self.core.vote.clone(),
+                    prev_log_id: node.matched.clone(),
+                    entries: vec![],
+                    leader_commit: self.core.committed.clone(),
+                };
+                (target.clone(), rpc)
+            })
+            .collect::<Vec<_>>();
+
+        let my_id = self.core.id.clone();
+        let vote = self.core.vote.clone();
+        let network = self.core.network.clone();
+        let ttl = Duration::from_millis(self.core.config.heartbeat_interval);
+        let tx = self.tx_read_round.clone();
+
+        let _ = tokio::spawn(
+            async move {
+                let res = confirm_leadership(my_id, vote, membership, quorum_set, rpcs, network, ttl).await;
+                let _ = tx.send(res);
             }
+            .instrument(tracing::debug_span!("confirm_leadership")),
+        );
+    }
 
-            let rpc = AppendEntriesRequest {
-                vote: self.core.vote,
-                prev_log_id: node.matched,
-                entries: vec![],
-                leader_commit: self.core.committed,
-            };
+    /// Respond to the reads confirmed by a finished round, then start the next round for queued reads.
+    #[tracing::instrument(level = "debug", skip(self, result))]
+    pub(super) async fn handle_read_round_result(
+        &mut self,
+        result: ReadRoundResult<NID>,
+    ) -> Result<(), StorageError<NID>> {
+        let reads = self.confirming_reads.take().unwrap_or_default();
 
-            let my_id = self.core.id;
-            let target = *target;
-            let network = self.core.network.clone();
-
-            let ttl = Duration::from_millis(self.core.config.heartbeat_interval);
-
-            let task = tokio::spawn(
-                async move {
-                    let outer_res = timeout(ttl, network.send_append_entries(target, rpc)).await;
-                    match outer_res {
-                        Ok(append_res) => match append_res {
-                            Ok(x) => Ok((target, x)),
-                            Err(err) => Err((target, err)),
-                        },
-                        Err(_timeout) => {
-                            let timeout_err = Timeout {
-                                action: RPCTypes::AppendEntries,
-                                id: my_id,
-                                target,
-                                timeout: ttl,
-                            };
-
-                            Err((target, RPCError::Timeout(timeout_err)))
-                        }
-                    }
-                }
-                // TODO(xp): add target to span
-                .instrument(tracing::debug_span!("SPAWN_append_entries")),
-            )
-            .map_err(move |err| (target, err));
+        let res = result.res.map_err(ClientReadError::from);
+        tracing::debug!(reads = reads.len(), ?res, "leadership confirmation round finished");
 
-            pending.push(task);
+        for read in reads {
+            read.respond(res.clone());
         }
 
-        // Handle responses as they return.
-        while let Some(res) = pending.next().await {
-            let (target, data) = match res {
-                Ok(Ok(res)) => res,
-                Ok(Err((target, err))) => {
-                    tracing::error!(target, error=%err, "timeout while confirming leadership for read request");
-                    continue;
-                }
-                Err((target, err)) => {
-                    tracing::error!(target, "{}", err);
-                    continue;
-                }
-            };
+        if let Some((target, vote)) = result.higher_vote {
+            self.handle_revert_to_follower(target, vote).await?;
+        }
 
-            // If we receive a response with a greater term, then revert to follower and abort this request.
-            if data.vote > self.core.vote {
-                self.core.vote = data.vote;
-                // TODO(xp): deal with storage error
-                self.core.save_vote().await.unwrap();
-                // TODO(xp): if receives error about a higher term, it should stop at once?
-                self.core.set_target_state(State::Follower);
-            }
+        if self.core.target_state.is_leader() {
+            self.try_start_read_round();
+        }
 
-            granted.insert(target);
+        Ok(())
+    }
 
-            let mem = &self.core.effective_membership.membership;
-            if mem.is_majority(&granted) {
-                let _ = tx.send(Ok(()));
-                return;
-            }
+    /// Respond to all pending reads when this node is no longer a leader.
+    pub(super) fn reject_pending_reads(&mut self) {
+        let mut reads = self.confirming_reads.take().unwrap_or_default();
+        reads.append(&mut self.queued_reads);
+
+        for read in reads {
+            let err = self.core.forward_to(self.core.current_leader());
+            read.respond(Err(err.into()));
+        }
+    }
+
+    /// Return true if a quorum has acknowledged this leader within the lease duration.
+    ///
+    /// A leader lease is never valid if lease read is disabled, or after a `TimeoutNow` is sent.
+    fn has_valid_lease(&self) -> bool {
+        let lease = match self.core.config.lease_duration() {
+            Some(x) => x,
+            None => return false,
+        };
+
+        if self.lease_revoked {
+            return false;
         }
 
-        // If we've hit this location, then we've failed to gather needed confirmations due to
-        // request failures.
+        let now = Instant::now();
+        let mut granted = btreeset! {self.core.id.clone()};
 
-        let _ = tx.send(Err(QuorumNotEnough {
-            cluster: self.core.effective_membership.membership.summary(),
-            got: granted,
+        for (target, node) in self.nodes.iter() {
+            if let Some(acked_at) = node.acked_at {
+                if acked_at + lease > now {
+                    granted.insert(target.clone());
+                }
+            }
         }
-        .into()));
+
+        self.core.quorum_set().is_replication_quorum(&granted)
     }
 
     /// Handle client write requests.
-    #[tracing::instrument(level = "trace", skip(self, tx), fields(rpc=%rpc.summary()))]
-    pub(super) async fn handle_client_write_request(
+    ///
+    /// All of the entries are appended to the log with one `RaftStorage::append_to_log` call and are replicated
+    /// together. Every request still receives its own response once its entry is applied.
+    #[tracing::instrument(level = "trace", skip(self, reqs), fields(reqs = reqs.len()))]
+    pub(super) async fn handle_client_write_requests(
         &mut self,
-        rpc: ClientWriteRequest<D>,
-        tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
-    ) -> Result<(), StorageError> {
-        let entry = self.core.append_payload_to_log(rpc.payload).await?;
-        let entry = ClientRequestEntry {
-            entry: Arc::new(entry),
-            tx: Some(tx),
-        };
+        reqs: Vec<(ClientWriteRequest<D, NID, NI>, ClientWriteTx<R, NID, NI>)>,
+    ) -> Result<(), StorageError<NID>> {
+        let (payloads, txs): (Vec<_>, Vec<_>) = reqs.into_iter().map(|(rpc, tx)| (rpc.payload, tx)).unzip();
+
+        let entries = self.core.append_payloads_to_log(payloads).await?;
+        let entries = entries
+            .into_iter()
+            .zip(txs.into_iter())
+            .map(|(entry, tx)| ClientRequestEntry {
+                entry: Arc::new(entry),
+                tx: Some(tx),
+            })
+            .collect::<Vec<_>>();
 
         self.leader_report_metrics();
 
-        self.replicate_client_request(entry).await?;
+        self.replicate_client_requests(entries).await?;
         Ok(())
     }
 
@@ -208,30 +373,47 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// merely beings the process. Once the request is committed to the cluster, its response will
     /// be generated asynchronously.
     #[tracing::instrument(level = "debug", skip(self, req), fields(req=%req.summary()))]
-    pub(super) async fn replicate_client_request(&mut self, req: ClientRequestEntry<D, R>) -> Result<(), StorageError> {
+    pub(super) async fn replicate_client_request(
+        &mut self,
+        req: ClientRequestEntry<D, R, NID, NI>,
+    ) -> Result<(), StorageError<NID>> {
+        self.replicate_client_requests(vec![req]).await
+    }
+
+    /// Begin the process of replicating the given client requests, which are consecutive entries in the log.
+    ///
+    /// Replication streams are notified once for all of them.
+    #[tracing::instrument(level = "debug", skip(self, reqs), fields(reqs = reqs.len()))]
+    pub(super) async fn replicate_client_requests(
+        &mut self,
+        reqs: Vec<ClientRequestEntry<D, R, NID, NI>>,
+    ) -> Result<(), StorageError<NID>> {
         // Replicate the request if there are other cluster members. The client response will be
         // returned elsewhere after the entry has been committed to the cluster.
 
-        let log_id = req.entry.log_id;
-        let quorum_granted = self.core.effective_membership.membership.is_majority(&btreeset! {self.core.id});
+        let log_id = match reqs.last() {
+            Some(req) => req.entry.log_id.clone(),
+            None => return Ok(()),
+        };
+        let quorum_granted = self.core.quorum_set().is_replication_quorum(&btreeset! {self.core.id.clone()});
 
         if quorum_granted {
-            assert!(self.core.committed < Some(log_id));
+            assert!(self.core.committed < Some(log_id.clone()));
 
-            self.core.committed = Some(log_id);
+            self.core.committed = Some(log_id.clone());
             tracing::debug!(?self.core.committed, "update committed, no need to replicate");
 
             self.leader_report_metrics();
-            self.client_request_post_commit(req).await?;
+            self.client_requests_post_commit(reqs);
         } else {
-            self.awaiting_committed.push(req);
+            self.awaiting_committed.extend(reqs);
         }
 
         for node in self.nodes.values() {
             let _ = node.repl_stream.repl_tx.send((
                 RaftEvent::Replicate {
-                    appended: log_id,
-                    committed: self.core.committed,
+                    appended: log_id.clone(),
+                    committed: self.core.committed.clone(),
                 },
                 tracing::debug_span!("CH"),
             ));
@@ -240,55 +422,19 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         Ok(())
     }
 
-    /// Handle the post-commit logic for a client request.
-    #[tracing::instrument(level = "debug", skip(self, req))]
-    pub(super) async fn client_request_post_commit(
-        &mut self,
-        req: ClientRequestEntry<D, R>,
-    ) -> Result<(), StorageError> {
-        let entry = &req.entry;
-
-        let apply_res = self.apply_entry_to_state_machine(entry).await?;
-
-        self.send_response(entry, apply_res, req.tx).await;
-
-        // Trigger log compaction if needed.
-        self.core.trigger_log_compaction_if_needed(false);
-        Ok(())
-    }
-
-    #[tracing::instrument(level = "debug", skip(self, entry, resp, tx), fields(entry=%entry.summary()))]
-    pub(super) async fn send_response(
-        &mut self,
-        entry: &Entry<D>,
-        resp: R,
-        tx: Option<RaftRespTx<ClientWriteResponse<R>, ClientWriteError>>,
-    ) {
-        let tx = match tx {
-            None => return,
-            Some(x) => x,
-        };
-
-        let membership = if let EntryPayload::Membership(ref c) = entry.payload {
-            Some(c.clone())
-        } else {
-            None
-        };
-
-        let res = Ok(ClientWriteResponse {
-            log_id: entry.log_id,
-            data: resp,
-            membership,
-        });
+    /// Handle the post-commit logic for client requests.
+    ///
+    /// The committed logs are applied to the state machine by the apply task, which then responds to the requests.
+    #[tracing::instrument(level = "debug", skip(self, reqs), fields(reqs = reqs.len()))]
+    pub(super) fn client_requests_post_commit(&mut self, reqs: Vec<ClientRequestEntry<D, R, NID, NI>>) {
+        for req in reqs.iter() {
+            self.handle_special_log(&req.entry);
+        }
 
-        let send_res = tx.send(res);
-        tracing::debug!(
-            "send client response through tx, send_res is error: {}",
-            send_res.is_err()
-        );
+        self.core.apply_committed(reqs);
     }
 
-    pub fn handle_special_log(&mut self, entry: &Entry<D>) {
+    pub fn handle_special_log(&mut self, entry: &Entry<D, NID, NI>) {
         match &entry.payload {
             EntryPayload::Membership(ref m) => {
                 if m.is_in_joint_consensus() {
@@ -301,58 +447,35 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             EntryPayload::Normal(_) => {}
         }
     }
+}
 
-    /// Apply the given log entry to the state machine.
-    #[tracing::instrument(level = "debug", skip(self, entry))]
-    pub(super) async fn apply_entry_to_state_machine(&mut self, entry: &Entry<D>) -> Result<R, StorageError> {
-        self.handle_special_log(entry);
-
-        // First, we just ensure that we apply any outstanding up to, but not including, the index
-        // of the given entry. We need to be able to return the data response from applying this
-        // entry to the state machine.
-        //
-        // Note that this would only ever happen if a node had unapplied logs from before becoming leader.
-
-        let log_id = &entry.log_id;
-        let index = log_id.index;
-
-        let expected_next_index = match self.core.last_applied {
-            None => 0,
-            Some(log_id) => log_id.index + 1,
-        };
-
-        if index != expected_next_index {
-            let entries = self.core.storage.get_log_entries(expected_next_index..index).await?;
-
-            if let Some(entry) = entries.last() {
-                self.core.last_applied = Some(entry.log_id);
-            }
-
-            let data_entries: Vec<_> = entries.iter().collect();
-            if !data_entries.is_empty() {
-                apply_to_state_machine(
-                    self.core.storage.clone(),
-                    &data_entries,
-                    self.core.config.max_applied_log_to_keep,
-                )
-                .await?;
-            }
-        }
-
-        // Apply this entry to the state machine and return its data response.
-        let apply_res = apply_to_state_machine(
-            self.core.storage.clone(),
-            &[entry],
-            self.core.config.max_applied_log_to_keep,
-        )
-        .await?;
-
-        // TODO(xp): deal with partial apply.
-        self.core.last_applied = Some(*log_id);
-        self.leader_report_metrics();
-
-        // TODO(xp) merge this function to replication_to_state_machine?
-
-        Ok(apply_res.into_iter().next().unwrap())
-    }
+/// Send the response of an applied client request through its response channel.
+#[tracing::instrument(level = "debug", skip(entry, resp, tx), fields(entry=%entry.summary()))]
+pub(super) fn send_response<D: AppData, R: AppDataResponse, NID: NodeId, NI: NodeInfo>(
+    entry: &Entry<D, NID, NI>,
+    resp: R,
+    tx: Option<ClientWriteTx<R, NID, NI>>,
+) {
+    let tx = match tx {
+        None => return,
+        Some(x) => x,
+    };
+
+    let membership = if let EntryPayload::Membership(ref c) = entry.payload {
+        Some(c.clone())
+    } else {
+        None
+    };
+
+    let res = Ok(ClientWriteResponse {
+        log_id: entry.log_id.clone(),
+        data: resp,
+        membership,
+    });
+
+    let send_res = tx.send(res);
+    tracing::debug!(
+        "send client response through tx, send_res is error: {}",
+        send_res.is_err()
+    );
 }
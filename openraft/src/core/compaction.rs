@@ -0,0 +1,67 @@
+use crate::core::apply::ApplyCommand;
+use crate::core::RaftCore;
+use crate::core::SnapshotState;
+use crate::error::NotApplied;
+use crate::error::PurgeLogError;
+use crate::error::TriggerSnapshotError;
+use crate::raft::RaftRespTx;
+use crate::raft_types::LogIdOptionExt;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::NodeId;
+use crate::NodeInfo;
+use crate::RaftNetwork;
+use crate::RaftStorage;
+use crate::SnapshotMeta;
+
+impl<
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > RaftCore<D, R, N, S, NID, NI>
+{
+    /// Build a snapshot on demand. The response is sent when the snapshot is built.
+    ///
+    /// If a snapshot is already being built, the response is sent when it is done.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) fn handle_trigger_snapshot(&mut self, tx: RaftRespTx<SnapshotMeta<NID>, TriggerSnapshotError<NID>>) {
+        match &self.snapshot_state {
+            Some(SnapshotState::Streaming { .. }) => {
+                let _ = tx.send(Err(TriggerSnapshotError::InstallingSnapshot));
+                return;
+            }
+            Some(SnapshotState::Snapshotting { .. }) => {}
+            None => {
+                self.trigger_log_compaction_if_needed(true);
+
+                if self.snapshot_state.is_none() {
+                    let _ = tx.send(Err(TriggerSnapshotError::NothingApplied));
+                    return;
+                }
+            }
+        }
+
+        self.snapshot_waiters.push(tx);
+    }
+
+    /// Purge logs upto index `upto`, inclusive. Only applied logs can be purged.
+    ///
+    /// The logs are purged by the apply task, which also purges logs after applying them, thus the two never race.
+    /// The response is sent once the logs are purged.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) fn handle_purge_log(&mut self, upto: u64, tx: RaftRespTx<(), PurgeLogError<NID>>) {
+        if Some(upto) > self.last_applied.index() {
+            let _ = tx.send(Err(NotApplied {
+                upto,
+                last_applied: self.last_applied.clone(),
+            }
+            .into()));
+            return;
+        }
+
+        let _ = self.tx_apply.send(ApplyCommand::Purge { upto, tx });
+    }
+}
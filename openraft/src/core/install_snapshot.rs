@@ -1,14 +1,15 @@
-use std::io::SeekFrom;
-
 use anyerror::AnyError;
-use tokio::io::AsyncSeekExt;
 use tokio::io::AsyncWriteExt;
+use tokio::time::Instant;
 
 use crate::core::purge_applied_logs;
+use crate::core::EffectiveMembership;
 use crate::core::RaftCore;
 use crate::core::SnapshotState;
 use crate::core::State;
 use crate::error::InstallSnapshotError;
+use crate::error::SnapshotChecksumMismatch;
+use crate::error::SnapshotDecompressError;
 use crate::error::SnapshotMismatch;
 use crate::raft::InstallSnapshotRequest;
 use crate::raft::InstallSnapshotResponse;
@@ -17,14 +18,25 @@ use crate::AppDataResponse;
 use crate::ErrorSubject;
 use crate::ErrorVerb;
 use crate::MessageSummary;
+use crate::NodeId;
+use crate::NodeInfo;
 use crate::RaftNetwork;
 use crate::RaftStorage;
+use crate::SnapshotCompression;
 use crate::SnapshotSegmentId;
 use crate::StorageError;
 use crate::StorageIOError;
 use crate::Update;
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+impl<
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > RaftCore<D, R, N, S, NID, NI>
+{
     /// Invoked by leader to send chunks of a snapshot to a follower (§7).
     ///
     /// Leaders always send chunks in order. It is important to note that, according to the Raft spec,
@@ -33,19 +45,21 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     #[tracing::instrument(level = "debug", skip(self, req), fields(req=%req.summary()))]
     pub(super) async fn handle_install_snapshot_request(
         &mut self,
-        req: InstallSnapshotRequest,
-    ) -> Result<InstallSnapshotResponse, InstallSnapshotError> {
+        req: InstallSnapshotRequest<NID, NI>,
+    ) -> Result<InstallSnapshotResponse<NID>, InstallSnapshotError<NID>> {
         if req.vote < self.vote {
             tracing::debug!(?self.vote, %req.vote, "InstallSnapshot RPC term is less than current term");
 
-            return Ok(InstallSnapshotResponse { vote: self.vote });
+            return Ok(InstallSnapshotResponse {
+                vote: self.vote.clone(),
+            });
         }
 
         // Update election timeout.
         self.update_next_election_timeout(true);
 
         if req.vote > self.vote {
-            self.vote = req.vote;
+            self.vote = req.vote.clone();
             self.save_vote().await?;
 
             // If not follower, become follower.
@@ -54,10 +68,22 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             }
 
             self.report_metrics(Update::AsIs);
+        } else if self.target_state.is_candidate() {
+            // A candidate in a pre-vote round has not raised its vote yet,
+            // thus the leader it hears from may have the same vote.
+            self.set_target_state(State::Follower);
+        }
+
+        if let Some(membership) = req.witness_membership.clone() {
+            return self.install_witness_snapshot(req, membership).await;
         }
 
+        let req = decompress_chunk(req, self.config.snapshot_max_chunk_size)?;
+
         // Compare current snapshot state with received RPC and handle as needed.
         // - Init a new state if it is empty or building a snapshot locally.
+        // - Matched id continues the stream, as long as the offset is where the received data ends. Otherwise the
+        //   `SnapshotMismatch` error tells the leader where to resume.
         // - Mismatched id with offset=0 indicates a new stream has been sent, the old one should be dropped and start
         //   to receive the new snapshot,
         // - Mismatched id with offset greater than 0 is an out of order message that should be rejected.
@@ -69,9 +95,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 handle.abort(); // Abort the current compaction in favor of installation from leader.
                 return self.begin_installing_snapshot(req).await;
             }
-            Some(SnapshotState::Streaming { snapshot, id, offset }) => {
+            Some(SnapshotState::Streaming {
+                snapshot,
+                id,
+                offset,
+                checksum,
+            }) => {
                 if req.meta.snapshot_id == id {
-                    return self.continue_installing_snapshot(req, offset, snapshot).await;
+                    return self.continue_installing_snapshot(req, offset, snapshot, checksum).await;
                 }
 
                 if req.offset == 0 {
@@ -90,11 +121,57 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
     }
 
+    /// A witness does not install the snapshot data: it records the last log included in the snapshot as applied,
+    /// purges the logs included in the snapshot, and uses the membership config of the leader if it is newer.
+    #[tracing::instrument(level = "debug", skip(self, req), fields(req=%req.summary()))]
+    async fn install_witness_snapshot(
+        &mut self,
+        req: InstallSnapshotRequest<NID, NI>,
+        membership: EffectiveMembership<NID, NI>,
+    ) -> Result<InstallSnapshotResponse<NID>, InstallSnapshotError<NID>> {
+        let last_log_id = req.meta.last_log_id;
+
+        if self.snapshot_last_log_id < Some(last_log_id.clone()) {
+            // All changes to the state machine must be serialized, see `finalize_snapshot_installation`.
+            self.flush_apply().await?;
+
+            if self.last_applied < Some(last_log_id.clone()) {
+                // The membership config of the leader may be newer than the snapshot. It is then applied later,
+                // along with the log that contains it.
+                let m = Some(&membership).filter(|m| m.log_id <= last_log_id);
+                self.storage.skip_apply_upto(&last_log_id, m).await?;
+
+                self.last_applied = Some(last_log_id.clone());
+                self.apply_requested = self.last_applied.clone();
+            }
+
+            purge_applied_logs(self.storage.clone(), &last_log_id, self.config.max_applied_log_to_keep).await?;
+
+            if self.last_log_id < Some(last_log_id.clone()) {
+                self.last_log_id = Some(last_log_id.clone());
+            }
+            if self.committed < Some(last_log_id.clone()) {
+                self.committed = Some(last_log_id.clone());
+            }
+            self.snapshot_last_log_id = Some(last_log_id);
+        }
+
+        if membership.log_id > self.effective_membership.log_id {
+            self.update_membership(membership);
+        }
+
+        self.report_metrics(Update::AsIs);
+
+        Ok(InstallSnapshotResponse {
+            vote: self.vote.clone(),
+        })
+    }
+
     #[tracing::instrument(level = "debug", skip(self, req), fields(req=%req.summary()))]
     async fn begin_installing_snapshot(
         &mut self,
-        req: InstallSnapshotRequest,
-    ) -> Result<InstallSnapshotResponse, InstallSnapshotError> {
+        req: InstallSnapshotRequest<NID, NI>,
+    ) -> Result<InstallSnapshotResponse<NID>, InstallSnapshotError<NID>> {
         let id = req.meta.snapshot_id.clone();
 
         if req.offset > 0 {
@@ -118,10 +195,16 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             ),
         })?;
 
+        let mut checksum = crc32fast::Hasher::new();
+        checksum.update(&req.data);
+
         // If this was a small snapshot, and it is already done, then finish up.
         if req.done {
+            verify_checksum(&req, checksum)?;
             self.finalize_snapshot_installation(req, snapshot).await?;
-            return Ok(InstallSnapshotResponse { vote: self.vote });
+            return Ok(InstallSnapshotResponse {
+                vote: self.vote.clone(),
+            });
         }
 
         // Else, retain snapshot components for later segments & respond.
@@ -129,49 +212,67 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             offset: req.data.len() as u64,
             id,
             snapshot,
+            checksum,
         });
-        Ok(InstallSnapshotResponse { vote: self.vote })
+        Ok(InstallSnapshotResponse {
+            vote: self.vote.clone(),
+        })
     }
 
-    #[tracing::instrument(level = "debug", skip(self, req, snapshot), fields(req=%req.summary()))]
+    #[tracing::instrument(level = "debug", skip(self, req, snapshot, checksum), fields(req=%req.summary()))]
     async fn continue_installing_snapshot(
         &mut self,
-        req: InstallSnapshotRequest,
+        req: InstallSnapshotRequest<NID, NI>,
         mut offset: u64,
         mut snapshot: Box<S::SnapshotData>,
-    ) -> Result<InstallSnapshotResponse, InstallSnapshotError> {
+        mut checksum: crc32fast::Hasher,
+    ) -> Result<InstallSnapshotResponse<NID>, InstallSnapshotError<NID>> {
         let id = req.meta.snapshot_id.clone();
 
-        // Always seek to the target offset if not an exact match.
+        // Data is only appended, so that the checksum can be calculated along the way. A lost or repeated chunk is
+        // rejected with the offset the leader should resume from.
         if req.offset != offset {
-            if let Err(err) = snapshot.as_mut().seek(SeekFrom::Start(req.offset)).await {
-                self.snapshot_state = Some(SnapshotState::Streaming { offset, id, snapshot });
-                return Err(StorageError::from_io_error(
-                    ErrorSubject::Snapshot(req.meta.clone()),
-                    ErrorVerb::Seek,
-                    err,
-                )
-                .into());
-            }
-            offset = req.offset;
+            let err = SnapshotMismatch {
+                expect: SnapshotSegmentId { id: id.clone(), offset },
+                got: SnapshotSegmentId {
+                    id: id.clone(),
+                    offset: req.offset,
+                },
+            };
+            self.snapshot_state = Some(SnapshotState::Streaming {
+                offset,
+                id,
+                snapshot,
+                checksum,
+            });
+            return Err(err.into());
         }
 
         // Write the next segment & update offset.
+        // A partially written segment can not be resumed, the snapshot is dropped and the leader starts over.
         if let Err(err) = snapshot.as_mut().write_all(&req.data).await {
-            self.snapshot_state = Some(SnapshotState::Streaming { offset, id, snapshot });
             return Err(
                 StorageError::from_io_error(ErrorSubject::Snapshot(req.meta.clone()), ErrorVerb::Write, err).into(),
             );
         }
+        checksum.update(&req.data);
         offset += req.data.len() as u64;
 
         // If the snapshot stream is done, then finalize.
         if req.done {
+            verify_checksum(&req, checksum)?;
             self.finalize_snapshot_installation(req, snapshot).await?;
         } else {
-            self.snapshot_state = Some(SnapshotState::Streaming { offset, id, snapshot });
+            self.snapshot_state = Some(SnapshotState::Streaming {
+                offset,
+                id,
+                snapshot,
+                checksum,
+            });
         }
-        Ok(InstallSnapshotResponse { vote: self.vote })
+        Ok(InstallSnapshotResponse {
+            vote: self.vote.clone(),
+        })
     }
 
     /// Finalize the installation of a new snapshot.
@@ -180,9 +281,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     #[tracing::instrument(level = "debug", skip(self, req, snapshot), fields(req=%req.summary()))]
     async fn finalize_snapshot_installation(
         &mut self,
-        req: InstallSnapshotRequest,
+        req: InstallSnapshotRequest<NID, NI>,
         mut snapshot: Box<S::SnapshotData>,
-    ) -> Result<(), StorageError> {
+    ) -> Result<(), StorageError<NID>> {
         snapshot.as_mut().shutdown().await.map_err(|e| StorageError::IO {
             source: StorageIOError::new(
                 ErrorSubject::Snapshot(req.meta.clone()),
@@ -204,6 +305,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // task:      apply 2------------------------'
         // --------------------------------------------------------------------> time
         // ```
+        //
+        // Thus wait for the apply task to finish applying logs before installing the snapshot.
+
+        self.flush_apply().await?;
 
         // TODO(xp): do not install if self.last_applied >= snapshot.meta.last_applied
 
@@ -222,12 +327,13 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         // snapshot is installed
         self.last_applied = Some(last_applied);
+        self.apply_requested = self.last_applied.clone();
 
         if self.committed < self.last_applied {
-            self.committed = self.last_applied;
+            self.committed = self.last_applied.clone();
         }
         if self.last_log_id < self.last_applied {
-            self.last_log_id = self.last_applied;
+            self.last_log_id = self.last_applied.clone();
         }
 
         // There could be unknown membership in the snapshot.
@@ -240,9 +346,64 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         self.update_membership(membership);
 
-        self.snapshot_last_log_id = self.last_applied;
+        self.snapshot_last_log_id = self.last_applied.clone();
+        self.applied_bytes_since_snapshot = 0;
+        self.last_snapshot_at = Instant::now();
         self.report_metrics(Update::AsIs);
 
         Ok(())
     }
 }
+
+/// Replace the data of a compressed snapshot chunk with the decompressed data, which must not exceed `max_size` bytes.
+fn decompress_chunk<NID: NodeId, NI: NodeInfo>(
+    mut req: InstallSnapshotRequest<NID, NI>,
+    max_size: u64,
+) -> Result<InstallSnapshotRequest<NID, NI>, SnapshotDecompressError> {
+    if req.compression == SnapshotCompression::None {
+        return Ok(req);
+    }
+
+    req.data = req.compression.decompress(&req.data, max_size).map_err(|reason| SnapshotDecompressError {
+        segment: SnapshotSegmentId {
+            id: req.meta.snapshot_id.clone(),
+            offset: req.offset,
+        },
+        compression: req.compression,
+        reason,
+    })?;
+    req.compression = SnapshotCompression::None;
+
+    Ok(req)
+}
+
+/// Verify the checksum of the received snapshot data against the one sent along with the last chunk, if there is one.
+///
+/// The snapshot is dropped by the caller if it is corrupted, and the leader has to send it again from the beginning.
+fn verify_checksum<NID: NodeId, NI: NodeInfo>(
+    req: &InstallSnapshotRequest<NID, NI>,
+    checksum: crc32fast::Hasher,
+) -> Result<(), SnapshotChecksumMismatch> {
+    let expect = match req.checksum {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+
+    let got = checksum.finalize();
+    if got != expect {
+        tracing::warn!(
+            snapshot_id = display(&req.meta.snapshot_id),
+            expect,
+            got,
+            "snapshot is corrupted"
+        );
+
+        return Err(SnapshotChecksumMismatch {
+            snapshot_id: req.meta.snapshot_id.clone(),
+            expect,
+            got,
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,254 @@
+use tokio::time::timeout;
+use tokio::time::Duration;
+use tokio::time::Instant;
+use tracing_futures::Instrument;
+
+use crate::core::LeaderState;
+use crate::core::LeaderTransfer;
+use crate::error::ForwardToLeader;
+use crate::error::IsWitness;
+use crate::error::NotVoter;
+use crate::error::TransferLeaderError;
+use crate::error::TransferLeaderInProgress;
+use crate::error::TransferLeaderTimeout;
+use crate::raft::RaftRespTx;
+use crate::raft::TimeoutNowRequest;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::NodeId;
+use crate::NodeInfo;
+use crate::RaftNetwork;
+use crate::RaftStorage;
+
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > LeaderState<'a, D, R, N, S, NID, NI>
+{
+    /// Handle the admin `transfer_leader` command.
+    ///
+    /// The leader stops accepting writes, waits for `target` to catch up with its last log, then sends it a
+    /// `TimeoutNow` RPC to make it start an election at once (§3.10 of the thesis).
+    /// `tx` is responded with the deadline of the transfer once `TimeoutNow` is sent, thus the caller waits for
+    /// `target` to become leader no longer than the transfer lasts. The transfer is aborted if `target` has not become
+    /// leader within an `election_timeout_max`.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) fn transfer_leader(&mut self, target: NID, tx: RaftRespTx<Instant, TransferLeaderError<NID, NI>>) {
+        if target == self.core.id {
+            let _ = tx.send(Ok(Instant::now()));
+            return;
+        }
+
+        if !self.core.effective_membership.membership.is_member(&target) {
+            let _ = tx.send(Err(TransferLeaderError::NotVoter(NotVoter { node_id: target })));
+            return;
+        }
+
+        if self.core.effective_membership.membership.is_witness(&target) {
+            let _ = tx.send(Err(TransferLeaderError::IsWitness(IsWitness { node_id: target })));
+            return;
+        }
+
+        if let Some(transfer) = &self.leader_transfer {
+            let _ = tx.send(Err(TransferLeaderError::InProgress(TransferLeaderInProgress {
+                target: transfer.target.clone(),
+            })));
+            return;
+        }
+
+        tracing::info!(%target, "start transferring leadership");
+
+        self.leader_transfer = Some(LeaderTransfer {
+            target,
+            deadline: Instant::now() + Duration::from_millis(self.core.config.election_timeout_max),
+            timeout_now_sent: false,
+            tx: Some(tx),
+        });
+
+        self.try_send_timeout_now();
+    }
+
+    /// Send `TimeoutNow` to the transfer target if it has caught up with the last log of this leader.
+    ///
+    /// It does nothing if there is no transfer in progress or `TimeoutNow` has already been sent.
+    pub(super) fn try_send_timeout_now(&mut self) {
+        let transfer = match &mut self.leader_transfer {
+            Some(t) if !t.timeout_now_sent => t,
+            _ => return,
+        };
+
+        let target = transfer.target.clone();
+        let matched = self.nodes.get(&target).map(|s| &s.matched);
+
+        if matched != Some(&self.core.last_log_id) {
+            tracing::debug!(%target, ?matched, ?self.core.last_log_id, "transfer target is lagging");
+            return;
+        }
+
+        let rpc = TimeoutNowRequest {
+            vote: self.core.vote.clone(),
+        };
+        let target_node = self.core.effective_membership.membership.get_node(&target).cloned();
+        let network = self.core.network.clone();
+        let ttl = Duration::from_millis(self.core.config.heartbeat_interval);
+
+        let span = tracing::debug_span!("send_timeout_now", target = %target);
+        let _ = tokio::spawn(
+            async move {
+                let res = timeout(ttl, network.send_timeout_now(target.clone(), target_node.as_ref(), rpc)).await;
+                match res {
+                    Ok(Ok(resp)) => {
+                        tracing::debug!(%target, %resp.vote, "TimeoutNow sent");
+                    }
+                    Ok(Err(err)) => {
+                        tracing::warn!(%target, error=%err, "error while sending TimeoutNow");
+                    }
+                    Err(_) => {
+                        tracing::warn!(%target, "timeout while sending TimeoutNow");
+                    }
+                }
+            }
+            .instrument(span),
+        );
+
+        self.lease_revoked = true;
+        transfer.timeout_now_sent = true;
+
+        if let Some(tx) = transfer.tx.take() {
+            let _ = tx.send(Ok(transfer.deadline));
+        }
+    }
+
+    /// Transfer the leadership to the voter with the highest priority, if it is higher than this leader's and the
+    /// voter has caught up.
+    ///
+    /// Nothing is done while a transfer is in progress, or the last membership config is not committed.
+    /// It is called when a replication makes progress and every `heartbeat_interval`, thus a failed transfer is
+    /// retried after `election_timeout_max` even on an idle cluster.
+    pub(super) fn try_transfer_to_higher_priority(&mut self) {
+        if self.leader_transfer.is_some() {
+            return;
+        }
+
+        let membership = &self.core.effective_membership.membership;
+
+        if membership.is_in_joint_consensus()
+            || self.core.committed < Some(self.core.effective_membership.log_id.clone())
+        {
+            return;
+        }
+
+        let retry_interval = Duration::from_millis(self.core.config.election_timeout_max);
+        if self.priority_transfer_at.map(|t| t.elapsed() < retry_interval).unwrap_or(false) {
+            return;
+        }
+
+        let priority = membership.get_priority(&self.core.id);
+
+        let target = membership
+            .all_members()
+            .iter()
+            .filter(|id| !membership.is_witness(id))
+            .filter(|id| membership.get_priority(id) > priority)
+            .max_by_key(|id| membership.get_priority(id))
+            .cloned();
+
+        let target = match target {
+            Some(t) => t,
+            None => return,
+        };
+
+        let matched = self.nodes.get(&target).map(|s| &s.matched);
+        if matched != Some(&self.core.last_log_id) {
+            return;
+        }
+
+        tracing::info!(%target, "transfer leadership to the voter with a higher priority");
+
+        let now = Instant::now();
+        self.priority_transfer_at = Some(now);
+        self.leader_transfer = Some(LeaderTransfer {
+            target,
+            deadline: now + retry_interval,
+            timeout_now_sent: false,
+            tx: None,
+        });
+
+        self.try_send_timeout_now();
+    }
+
+    /// Hand the leadership over to a voter that has caught up, when this leader is removed from the cluster.
+    ///
+    /// The voter with the highest priority among those that have caught up is sent a `TimeoutNow`, so that the
+    /// cluster does not wait for an election timeout to elect a new leader. If there is no such voter, or a transfer
+    /// is already in progress, the voters elect a new leader as usual.
+    pub(super) fn transfer_on_removal(&mut self) {
+        if self.leader_transfer.is_some() {
+            return;
+        }
+
+        let membership = &self.core.effective_membership.membership;
+
+        let target = membership
+            .all_members()
+            .iter()
+            .filter(|id| !membership.is_witness(id))
+            .filter(|id| self.nodes.get(id).map(|s| &s.matched) == Some(&self.core.last_log_id))
+            .max_by_key(|id| membership.get_priority(id))
+            .cloned();
+
+        let target = match target {
+            Some(t) => t,
+            None => return,
+        };
+
+        tracing::info!(%target, "removed from the cluster, transfer leadership");
+
+        self.leader_transfer = Some(LeaderTransfer {
+            target,
+            deadline: Instant::now() + Duration::from_millis(self.core.config.election_timeout_max),
+            timeout_now_sent: false,
+            tx: None,
+        });
+
+        self.try_send_timeout_now();
+    }
+
+    /// Give up the leadership transfer in progress and resume accepting writes.
+    pub(super) fn abort_leader_transfer(&mut self) {
+        if let Some(transfer) = self.leader_transfer.take() {
+            tracing::warn!(%transfer.target, "leadership transfer timed out, abort");
+
+            if let Some(tx) = transfer.tx {
+                let _ = tx.send(Err(TransferLeaderError::Timeout(TransferLeaderTimeout {
+                    target: transfer.target,
+                    timeout: Duration::from_millis(self.core.config.election_timeout_max),
+                })));
+            }
+        }
+    }
+
+    /// Respond to the pending transfer request when this node is no longer a leader.
+    pub(super) fn finish_leader_transfer(&mut self) {
+        if let Some(transfer) = self.leader_transfer.take() {
+            if let Some(tx) = transfer.tx {
+                self.core.reject_with_forward_to_leader(tx);
+            }
+        }
+    }
+
+    /// Reject a write request while a leadership transfer is in progress.
+    ///
+    /// The caller is forwarded to the transfer target, which is about to become the leader.
+    pub(super) fn reject_during_leader_transfer<T, E>(&self, tx: RaftRespTx<T, E>)
+    where E: From<ForwardToLeader<NID, NI>> {
+        let err = self.core.forward_to(self.leader_transfer.as_ref().map(|t| t.target.clone()));
+
+        let _ = tx.send(Err(err.into()));
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::BTreeSet;
+
+use crate::core::LeaderState;
+use crate::error::LearnerNotFound;
+use crate::error::PromoteLearnerError;
+use crate::raft::RaftRespTx;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::ChangeMembers;
+use crate::NodeId;
+use crate::NodeInfo;
+use crate::RaftNetwork;
+use crate::RaftStorage;
+use crate::StorageError;
+
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > LeaderState<'a, D, R, N, S, NID, NI>
+{
+    /// Handle the admin `promote_learner_when_ready` and `cancel_learner_promotion` commands.
+    #[tracing::instrument(level = "debug", skip(self, tx))]
+    pub(super) async fn promote_learner(
+        &mut self,
+        target: NID,
+        promote: bool,
+        tx: RaftRespTx<(), PromoteLearnerError<NID, NI>>,
+    ) -> Result<(), StorageError<NID>> {
+        let membership = &self.core.effective_membership.membership;
+
+        if !promote {
+            self.promoting.remove(&target);
+        } else if membership.is_learner(&target) && !membership.is_member(&target) {
+            tracing::info!(%target, "learner will be promoted once it catches up");
+            self.promoting.insert(target);
+        } else if !membership.is_member(&target) {
+            let _ = tx.send(Err(PromoteLearnerError::LearnerNotFound(LearnerNotFound {
+                node_id: target,
+            })));
+            return Ok(());
+        }
+
+        let _ = tx.send(Ok(()));
+
+        self.try_promote_learners().await
+    }
+
+    /// Propose a membership config to promote the marked learners that have caught up.
+    ///
+    /// A promotion is a two-step change: the joint config is proposed once a marked learner becomes line-rate, and
+    /// the uniform config is proposed once the joint one is committed.
+    /// A joint config left by a previous leader is changed to the uniform config the same way.
+    /// Nothing is proposed while a leadership transfer is in progress or the last membership config is not committed.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(super) async fn try_promote_learners(&mut self) -> Result<(), StorageError<NID>> {
+        let curr = self.core.effective_membership.membership.clone();
+
+        // Marks of nodes that are no longer learners, e.g., promoted by `change_membership`, are obsolete.
+        self.promoting.retain(|id| curr.is_learner(id) && !curr.is_member(id));
+        self.report_promoting();
+
+        if self.leader_transfer.is_some() {
+            return Ok(());
+        }
+
+        if self.core.committed < Some(self.core.effective_membership.log_id.clone()) {
+            return Ok(());
+        }
+
+        if curr.is_in_joint_consensus() {
+            // Only finish the joint config proposed for a promotion or left by a previous leader. One proposed by
+            // `change_membership()` on this leader is finished by its caller.
+            if self.joint_to_finish.as_ref() == Some(&self.core.effective_membership.log_id) {
+                self.joint_to_finish = None;
+
+                let goal = curr.get_configs().last().cloned().unwrap_or_default();
+                tracing::info!(?goal, "finish joint config: change to uniform config");
+
+                self.append_membership_log(curr.next_safe(goal, false), None).await?;
+            }
+            return Ok(());
+        }
+
+        let ready = self
+            .promoting
+            .iter()
+            .filter(|id| {
+                self.nodes
+                    .get(*id)
+                    .map(|s| s.is_line_rate(&self.core.last_log_id, &self.core.config))
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect::<BTreeSet<_>>();
+
+        if ready.is_empty() {
+            return Ok(());
+        }
+
+        tracing::info!(?ready, "promotion: learners caught up, change to joint config");
+
+        let mut new_config =
+            ChangeMembers::AddVoters(ready.iter().map(|id| (id.clone(), None)).collect()).apply_to(&curr, false);
+        for id in ready.iter() {
+            self.promoting.remove(id);
+            new_config.remove_learner(id);
+        }
+        self.report_promoting();
+
+        self.append_membership_log(new_config, None).await?;
+        self.joint_to_finish = Some(self.core.effective_membership.log_id.clone());
+
+        Ok(())
+    }
+
+    /// Update `LeaderMetrics::promoting` if the marked learners changed.
+    fn report_promoting(&mut self) {
+        if self.leader_metrics.promoting != self.promoting {
+            self.leader_metrics.promoting = self.promoting.clone();
+            self.leader_report_metrics();
+        }
+    }
+}
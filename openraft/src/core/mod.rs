@@ -2,11 +2,18 @@
 
 mod admin;
 mod append_entries;
+mod apply;
+mod check_quorum;
 mod client;
+mod compaction;
 mod install_snapshot;
+mod leader_transfer;
+mod learner_promotion;
+mod read_index;
 pub(crate) mod replication;
 #[cfg(test)]
 mod replication_state_test;
+mod timeout_now;
 mod vote;
 
 use std::collections::BTreeMap;
@@ -26,21 +33,33 @@ use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tokio::sync::watch;
 use tokio::task::JoinHandle;
+use tokio::time::interval;
 use tokio::time::sleep_until;
 use tokio::time::Duration;
 use tokio::time::Instant;
+use tokio::time::Interval;
 use tracing::trace_span;
 use tracing::Instrument;
 use tracing::Span;
 
 use crate::config::Config;
-use crate::config::SnapshotPolicy;
+use crate::config::SnapshotProgress;
+use crate::core::apply::Applied;
+use crate::core::apply::ApplyCommand;
+use crate::core::apply::ApplyCore;
 use crate::core::client::ClientRequestEntry;
+use crate::core::client::PendingRead;
+use crate::core::client::ReadRoundResult;
 use crate::error::AddLearnerError;
 use crate::error::ExtractFatal;
 use crate::error::Fatal;
 use crate::error::ForwardToLeader;
 use crate::error::InitializeError;
+use crate::error::TransferLeaderError;
+use crate::error::TriggerSnapshotError;
+use crate::membership::quorum::Joint;
+use crate::membership::quorum::QuorumSet;
+use crate::membership::quorum::QuorumSystem;
 use crate::metrics::LeaderMetrics;
 use crate::metrics::RaftMetrics;
 use crate::raft::AddLearnerResponse;
@@ -48,6 +67,7 @@ use crate::raft::Entry;
 use crate::raft::EntryPayload;
 use crate::raft::RaftMsg;
 use crate::raft::RaftRespTx;
+use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::raft_types::LogIdOptionExt;
 use crate::replication::ReplicaEvent;
@@ -59,9 +79,12 @@ use crate::LeaderId;
 use crate::LogId;
 use crate::Membership;
 use crate::MessageSummary;
+use crate::Node;
 use crate::NodeId;
+use crate::NodeInfo;
 use crate::RaftNetwork;
 use crate::RaftStorage;
+use crate::SnapshotMeta;
 use crate::StorageError;
 use crate::Update;
 
@@ -73,15 +96,16 @@ use crate::Update;
 ///
 /// An active config is just the last seen config in raft spec.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct EffectiveMembership {
+#[serde(bound = "")]
+pub struct EffectiveMembership<NID: NodeId = u64, NI: NodeInfo = Node> {
     /// The id of the log that applies this membership config
-    pub log_id: LogId,
+    pub log_id: LogId<NID>,
 
-    pub membership: Membership,
+    pub membership: Membership<NID, NI>,
 }
 
-impl EffectiveMembership {
-    pub fn new_initial(node_id: u64) -> Self {
+impl<NID: NodeId, NI: NodeInfo> EffectiveMembership<NID, NI> {
+    pub fn new_initial(node_id: NID) -> Self {
         EffectiveMembership {
             // TODO(xp): avoid using Vote::default()
             log_id: LogId::new(LeaderId::default(), 0),
@@ -90,22 +114,32 @@ impl EffectiveMembership {
     }
 }
 
-impl MessageSummary for EffectiveMembership {
+impl<NID: NodeId, NI: NodeInfo> MessageSummary for EffectiveMembership<NID, NI> {
     fn summary(&self) -> String {
         format!("{{log_id:{} membership:{}}}", self.log_id, self.membership.summary())
     }
 }
 
 /// The core type implementing the Raft protocol.
-pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
+pub struct RaftCore<
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID, NI>,
+    S: RaftStorage<D, R, NID, NI>,
+    NID: NodeId = u64,
+    NI: NodeInfo = Node,
+> {
     /// This node's ID.
-    id: NodeId,
+    id: NID,
 
     /// This node's runtime config.
     config: Arc<Config>,
 
+    /// Builds the quorum set of the effective membership, to count votes and to calculate the committed log id.
+    quorum_system: Arc<dyn QuorumSystem<NID, NI>>,
+
     /// The cluster's current membership configuration.
-    effective_membership: EffectiveMembership,
+    effective_membership: EffectiveMembership<NID, NI>,
 
     /// The `RaftNetwork` implementation.
     network: Arc<N>,
@@ -121,16 +155,19 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     /// Committed means:
     /// - a log that is replicated to a quorum of the cluster and it is of the term of the leader.
     /// - A quorum could be a joint quorum.
-    committed: Option<LogId>,
+    committed: Option<LogId<NID>>,
 
     /// The log id of the highest log entry which has been applied to the local state machine.
-    last_applied: Option<LogId>,
+    last_applied: Option<LogId<NID>>,
+
+    /// The log id of the highest log entry which has been sent to the apply task.
+    apply_requested: Option<LogId<NID>>,
 
     /// The vote state of this node.
-    vote: Vote,
+    vote: Vote<NID>,
 
     /// The last entry to be appended to the log.
-    last_log_id: Option<LogId>,
+    last_log_id: Option<LogId<NID>>,
 
     /// The node's current snapshot state.
     snapshot_state: Option<SnapshotState<S::SnapshotData>>,
@@ -138,7 +175,22 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     /// The log id upto which the current snapshot includes, inclusive, if a snapshot exists.
     ///
     /// This is primarily used in making a determination on when a compaction job needs to be triggered.
-    snapshot_last_log_id: Option<LogId>,
+    snapshot_last_log_id: Option<LogId<NID>>,
+
+    /// The size in bytes of logs applied since the last snapshot, for `SnapshotPolicy::BytesSinceLast`.
+    ///
+    /// It is approximate: logs applied while a snapshot is being built are not counted.
+    applied_bytes_since_snapshot: u64,
+
+    /// When the last snapshot is built or installed, or when this node started.
+    last_snapshot_at: Instant,
+
+    /// Ticks to check a snapshot policy with `SnapshotPolicy::IntervalSinceLast`, which is not triggered by applying
+    /// logs.
+    snapshot_check_interval: Option<Interval>,
+
+    /// The `trigger_snapshot` requests waiting for the snapshot being built.
+    snapshot_waiters: Vec<RaftRespTx<SnapshotMeta<NID>, TriggerSnapshotError<NID>>>,
 
     /// The last time a heartbeat was received.
     last_heartbeat: Option<Instant>,
@@ -146,35 +198,67 @@ pub struct RaftCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftSt
     /// The duration until the next election timeout.
     next_election_timeout: Option<Instant>,
 
-    tx_compaction: mpsc::Sender<SnapshotUpdate>,
-    rx_compaction: mpsc::Receiver<SnapshotUpdate>,
+    /// Set when the leader transferred its leadership to this node by sending a `TimeoutNow` RPC.
+    ///
+    /// The next election is then campaigned with `VoteRequest::leader_transfer` set.
+    timeout_now: bool,
+
+    /// The number of times this node stepped down from leader because a quorum did not respond to it.
+    quorum_lost: u64,
+
+    tx_compaction: mpsc::Sender<SnapshotUpdate<NID>>,
+    rx_compaction: mpsc::Receiver<SnapshotUpdate<NID>>,
 
-    rx_api: mpsc::UnboundedReceiver<(RaftMsg<D, R>, Span)>,
+    tx_apply: mpsc::UnboundedSender<ApplyCommand<D, R, NID, NI>>,
+    rx_applied: mpsc::UnboundedReceiver<Result<Applied<D, R, NID, NI>, StorageError<NID>>>,
 
-    tx_metrics: watch::Sender<RaftMetrics>,
+    rx_api: mpsc::UnboundedReceiver<(RaftMsg<D, R, NID, NI>, Span)>,
+
+    tx_metrics: watch::Sender<RaftMetrics<NID, NI>>,
 
     rx_shutdown: oneshot::Receiver<()>,
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+impl<
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > RaftCore<D, R, N, S, NID, NI>
+{
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn spawn(
-        id: NodeId,
+        id: NID,
         config: Arc<Config>,
+        quorum_system: Arc<dyn QuorumSystem<NID, NI>>,
         network: Arc<N>,
         storage: Arc<S>,
-        rx_api: mpsc::UnboundedReceiver<(RaftMsg<D, R>, Span)>,
-        tx_metrics: watch::Sender<RaftMetrics>,
+        rx_api: mpsc::UnboundedReceiver<(RaftMsg<D, R, NID, NI>, Span)>,
+        tx_metrics: watch::Sender<RaftMetrics<NID, NI>>,
         rx_shutdown: oneshot::Receiver<()>,
-    ) -> JoinHandle<Result<(), Fatal>> {
+    ) -> JoinHandle<Result<(), Fatal<NID>>> {
         //
 
         // TODO(xp): remove this.
-        let membership = Membership::new_initial(id); // This is updated from storage in the main loop.
+        let membership = Membership::new_initial(id.clone()); // This is updated from storage in the main loop.
         let (tx_compaction, rx_compaction) = mpsc::channel(1);
 
+        let snapshot_check_interval = if config.snapshot_policy.has_interval() {
+            Some(interval(Duration::from_millis(config.heartbeat_interval)))
+        } else {
+            None
+        };
+
+        let (tx_apply, rx_apply) = mpsc::unbounded_channel();
+        let (tx_applied, rx_applied) = mpsc::unbounded_channel();
+        let _apply_handle = ApplyCore::spawn(storage.clone(), config.clone(), rx_apply, tx_applied);
+
         let this = Self {
             id,
             config,
+            quorum_system,
             effective_membership: EffectiveMembership {
                 log_id: LogId::default(),
                 membership,
@@ -184,16 +268,26 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             target_state: State::Follower,
             committed: None,
             last_applied: None,
+            apply_requested: None,
             vote: Vote::default(),
             last_log_id: None,
             snapshot_state: None,
             snapshot_last_log_id: None,
+            applied_bytes_since_snapshot: 0,
+            last_snapshot_at: Instant::now(),
+            snapshot_check_interval,
+            snapshot_waiters: vec![],
             last_heartbeat: None,
             next_election_timeout: None,
+            timeout_now: false,
+            quorum_lost: 0,
 
             tx_compaction,
             rx_compaction,
 
+            tx_apply,
+            rx_applied,
+
             rx_api,
 
             tx_metrics,
@@ -204,8 +298,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     }
 
     /// The main loop of the Raft protocol.
-    #[tracing::instrument(level="trace", skip(self), fields(id=self.id, cluster=%self.config.cluster_name))]
-    async fn main(mut self) -> Result<(), Fatal> {
+    #[tracing::instrument(level="trace", skip(self), fields(id=%self.id, cluster=%self.config.cluster_name))]
+    async fn main(mut self) -> Result<(), Fatal<NID>> {
         let res = self.do_main().await;
         match res {
             Ok(_) => Ok(()),
@@ -221,8 +315,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
     }
 
-    #[tracing::instrument(level="trace", skip(self), fields(id=self.id, cluster=%self.config.cluster_name))]
-    async fn do_main(&mut self) -> Result<(), Fatal> {
+    #[tracing::instrument(level="trace", skip(self), fields(id=%self.id, cluster=%self.config.cluster_name))]
+    async fn do_main(&mut self) -> Result<(), Fatal<NID>> {
         tracing::debug!("raft node is initializing");
 
         let state = self.storage.get_initial_state().await?;
@@ -232,8 +326,10 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         self.last_log_id = state.last_log_id;
         self.vote = state.vote;
-        self.effective_membership = state.last_membership.unwrap_or_else(|| EffectiveMembership::new_initial(self.id));
-        self.last_applied = state.last_applied;
+        self.effective_membership =
+            state.last_membership.unwrap_or_else(|| EffectiveMembership::new_initial(self.id.clone()));
+        self.last_applied = state.last_applied.clone();
+        self.apply_requested = state.last_applied;
 
         // NOTE: The commit index must be determined by a leader after
         // successfully committing a new log to the cluster.
@@ -289,6 +385,11 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
             }
         };
 
+        // A witness never campaigns, even if it is the only member.
+        if self.effective_membership.membership.is_witness(&self.id) {
+            self.target_state = State::Witness;
+        }
+
         if self.target_state == State::Follower {
             // Here we use a 30 second overhead on the initial next_election_timeout. This is because we need
             // to ensure that restarted nodes don't disrupt a stable cluster by timing out and driving up their
@@ -309,7 +410,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 State::Leader => LeaderState::new(self).run().await?,
                 State::Candidate => CandidateState::new(self).run().await?,
                 State::Follower => FollowerState::new(self).run().await?,
-                State::Learner => LearnerState::new(self).run().await?,
+                State::Learner | State::Witness => LearnerState::new(self).run().await?,
                 State::Shutdown => {
                     tracing::info!("node has shutdown");
                     return Ok(());
@@ -320,7 +421,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Report a metrics payload on the current state of the Raft node.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn report_metrics(&mut self, leader_metrics: Update<Option<&LeaderMetrics>>) {
+    fn report_metrics(&mut self, leader_metrics: Update<Option<&LeaderMetrics<NID>>>) {
         let leader_metrics = match leader_metrics {
             Update::Update(v) => v.cloned(),
             Update::AsIs => self.tx_metrics.borrow().leader_metrics.clone(),
@@ -329,15 +430,16 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         let m = RaftMetrics {
             running_state: Ok(()),
 
-            id: self.id,
+            id: self.id.clone(),
             state: self.target_state,
             current_term: self.vote.term,
-            last_log_index: self.last_log_id.map(|id| id.index),
-            last_applied: self.last_applied,
+            last_log_index: self.last_log_id.clone().map(|id| id.index),
+            last_applied: self.last_applied.clone(),
             current_leader: self.current_leader(),
             membership_config: self.effective_membership.clone(),
-            snapshot: self.snapshot_last_log_id,
+            snapshot: self.snapshot_last_log_id.clone(),
             leader_metrics,
+            quorum_lost: self.quorum_lost,
         };
 
         {
@@ -352,26 +454,53 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         let res = self.tx_metrics.send(m);
 
         if let Err(err) = res {
-            tracing::error!(error=%err, id=self.id, "error reporting metrics");
+            tracing::error!(error=%err, id=%self.id, "error reporting metrics");
         }
     }
 
     /// Save the Raft node's current hard state to disk.
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn save_vote(&mut self) -> Result<(), StorageError> {
+    async fn save_vote(&mut self) -> Result<(), StorageError<NID>> {
         self.storage.save_vote(&self.vote).await
     }
 
     /// Update core's target state, ensuring all invariants are upheld.
-    #[tracing::instrument(level = "trace", skip(self), fields(id=self.id))]
+    #[tracing::instrument(level = "trace", skip(self), fields(id=%self.id))]
     fn set_target_state(&mut self, target_state: State) {
-        tracing::debug!(id = self.id, ?target_state, "set_target_state");
+        tracing::debug!(id = %self.id, ?target_state, "set_target_state");
 
-        if target_state == State::Follower && !self.effective_membership.membership.is_member(&self.id) {
-            self.target_state = State::Learner;
-        } else {
-            self.target_state = target_state;
-        }
+        let membership = &self.effective_membership.membership;
+
+        self.target_state = match target_state {
+            State::Shutdown => State::Shutdown,
+
+            // A witness only follows the leader: it never campaigns or leads.
+            _ if membership.is_witness(&self.id) => State::Witness,
+
+            State::Follower if !membership.is_member(&self.id) => State::Learner,
+            _ => target_state,
+        };
+    }
+
+    /// Generate a new random election timeout, delayed if there are voters with a higher election priority.
+    ///
+    /// The timeout is delayed by `election_timeout_max - election_timeout_min` for every distinct priority of the
+    /// voters that is higher than this node's, so that a voter with a higher priority always times out first.
+    fn new_election_timeout(&self) -> Duration {
+        let membership = &self.effective_membership.membership;
+        let priority = membership.get_priority(&self.id);
+
+        let higher = membership
+            .all_members()
+            .iter()
+            .filter(|id| !membership.is_witness(id))
+            .map(|id| membership.get_priority(id))
+            .filter(|p| *p > priority)
+            .collect::<BTreeSet<_>>();
+
+        let delay = higher.len() as u64 * (self.config.election_timeout_max - self.config.election_timeout_min);
+
+        Duration::from_millis(self.config.new_rand_election_timeout() + delay)
     }
 
     /// Get the next election timeout, generating a new value if not set.
@@ -380,7 +509,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         match self.next_election_timeout {
             Some(inst) => inst,
             None => {
-                let t = Duration::from_millis(self.config.new_rand_election_timeout());
+                let t = self.new_election_timeout();
                 tracing::debug!("create election timeout after: {:?}", t);
                 let inst = Instant::now() + t;
                 self.next_election_timeout = Some(inst);
@@ -396,7 +525,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     fn update_next_election_timeout(&mut self, heartbeat: bool) {
         let now = Instant::now();
 
-        let t = Duration::from_millis(self.config.new_rand_election_timeout());
+        let t = self.new_election_timeout();
         tracing::debug!("update election timeout after: {:?}", t);
 
         self.next_election_timeout = Some(now + t);
@@ -407,7 +536,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Update the node's current membership config & save hard state.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn update_membership(&mut self, cfg: EffectiveMembership) {
+    fn update_membership(&mut self, cfg: EffectiveMembership<NID, NI>) {
         // If the given config does not contain this node's ID, it means one of the following:
         //
         // - the node is currently a learner and is replicating an old config to which it has
@@ -416,7 +545,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         // transition to the learner state as a signal for when it is safe to shutdown a node
         // being removed.
         self.effective_membership = cfg;
-        if self.effective_membership.membership.is_member(&self.id) {
+        if self.effective_membership.membership.is_witness(&self.id) {
+            self.set_target_state(State::Witness);
+        } else if self.effective_membership.membership.is_member(&self.id) {
             if self.target_state == State::Learner {
                 // The node is a Learner and the new config has it configured as a normal member.
                 // Transition to follower.
@@ -429,10 +560,23 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Update the system's snapshot state based on the given data.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn update_snapshot_state(&mut self, update: SnapshotUpdate) {
-        if let SnapshotUpdate::SnapshotComplete(log_id) = update {
-            self.snapshot_last_log_id = Some(log_id);
-            self.report_metrics(Update::AsIs);
+    fn update_snapshot_state(&mut self, update: SnapshotUpdate<NID>) {
+        match update {
+            SnapshotUpdate::SnapshotComplete(meta) => {
+                self.snapshot_last_log_id = Some(meta.last_log_id.clone());
+                self.applied_bytes_since_snapshot = 0;
+                self.last_snapshot_at = Instant::now();
+                self.report_metrics(Update::AsIs);
+
+                for tx in self.snapshot_waiters.drain(..) {
+                    let _ = tx.send(Ok(meta.clone()));
+                }
+            }
+            SnapshotUpdate::SnapshotFailed => {
+                for tx in self.snapshot_waiters.drain(..) {
+                    let _ = tx.send(Err(TriggerSnapshotError::BuildFailed));
+                }
+            }
         }
         // If snapshot state is anything other than streaming, then drop it.
         if let Some(state @ SnapshotState::Streaming { .. }) = self.snapshot_state.take() {
@@ -447,13 +591,15 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         if self.snapshot_state.is_some() {
             return;
         }
-        let SnapshotPolicy::LogsSinceLast(threshold) = &self.config.snapshot_policy;
-
-        let last_applied = match self.last_applied {
+        // A witness has no application data to build a snapshot of.
+        if self.target_state.is_witness() {
+            return;
+        }
+        let last_applied = match &self.last_applied {
             None => {
                 return;
             }
-            Some(x) => x,
+            Some(x) => x.clone(),
         };
 
         // Check to ensure we have actual entries for compaction.
@@ -462,8 +608,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         }
 
         if !force {
-            // If we are below the threshold, then there is nothing to do.
-            if self.last_applied.next_index() - self.snapshot_last_log_id.next_index() < *threshold {
+            let progress = SnapshotProgress {
+                logs: self.last_applied.next_index() - self.snapshot_last_log_id.next_index(),
+                bytes: self.applied_bytes_since_snapshot,
+                elapsed: self.last_snapshot_at.elapsed(),
+            };
+
+            // If the policy is not satisfied, then there is nothing to do.
+            if !self.config.snapshot_policy.is_satisfied(&progress) {
                 return;
             }
         }
@@ -485,8 +637,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 match res {
                     Ok(res) => match res {
                         Ok(snapshot) => {
-                            let _ = tx_compaction.try_send(SnapshotUpdate::SnapshotComplete(snapshot.meta.last_log_id));
-                            let _ = chan_tx.send(snapshot.meta.last_log_id.index); // This will always succeed.
+                            let last_log_index = snapshot.meta.last_log_id.index;
+                            let _ = tx_compaction.try_send(SnapshotUpdate::SnapshotComplete(snapshot.meta));
+                            let _ = chan_tx.send(last_log_index); // This will always succeed.
                         }
                         Err(err) => {
                             tracing::error!({error=%err}, "error while generating snapshot");
@@ -504,48 +657,82 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
     /// Reject an init config request due to the Raft node being in a state which prohibits the request.
     #[tracing::instrument(level = "trace", skip(self, tx))]
-    fn reject_init_with_config(&self, tx: oneshot::Sender<Result<(), InitializeError>>) {
+    fn reject_init_with_config(&self, tx: oneshot::Sender<Result<(), InitializeError<NID>>>) {
         let _ = tx.send(Err(InitializeError::NotAllowed));
     }
 
     /// Reject a request due to the Raft node being in a state which prohibits the request.
     #[tracing::instrument(level = "trace", skip(self, tx))]
     fn reject_with_forward_to_leader<T, E>(&self, tx: RaftRespTx<T, E>)
-    where E: From<ForwardToLeader> {
-        let err = ForwardToLeader {
-            leader_id: self.current_leader(),
-        };
+    where E: From<ForwardToLeader<NID, NI>> {
+        let err = self.forward_to(self.current_leader());
 
         let _ = tx.send(Err(err.into()));
     }
 
+    /// Returns the quorum set of the effective membership.
+    pub(super) fn quorum_set(&self) -> Joint<Box<dyn QuorumSet<NID>>> {
+        self.effective_membership.membership.quorum_set(&*self.quorum_system)
+    }
+
+    /// Build a `ForwardToLeader` error with the info of the leader found in the effective membership.
+    pub(super) fn forward_to(&self, leader_id: Option<NID>) -> ForwardToLeader<NID, NI> {
+        let leader_node = leader_id.as_ref().and_then(|id| self.effective_membership.membership.get_node(id).cloned());
+        ForwardToLeader { leader_id, leader_node }
+    }
+
     #[tracing::instrument(level = "debug", skip(self, payload))]
-    pub(super) async fn append_payload_to_log(&mut self, payload: EntryPayload<D>) -> Result<Entry<D>, StorageError> {
-        let log_id = LogId::new(self.vote.leader_id(), self.last_log_id.next_index());
+    pub(super) async fn append_payload_to_log(
+        &mut self,
+        payload: EntryPayload<D, NID, NI>,
+    ) -> Result<Entry<D, NID, NI>, StorageError<NID>> {
+        let mut entries = self.append_payloads_to_log(vec![payload]).await?;
+        Ok(entries.pop().unwrap())
+    }
 
-        let entry = Entry { log_id, payload };
-        self.storage.append_to_log(&[&entry]).await?;
+    /// Append several payloads to the log with a single call to `RaftStorage::append_to_log`.
+    #[tracing::instrument(level = "debug", skip(self, payloads), fields(payloads = payloads.len()))]
+    pub(super) async fn append_payloads_to_log(
+        &mut self,
+        payloads: Vec<EntryPayload<D, NID, NI>>,
+    ) -> Result<Vec<Entry<D, NID, NI>>, StorageError<NID>> {
+        let leader_id = self.vote.leader_id();
+        let mut next_index = self.last_log_id.next_index();
+
+        let entries = payloads
+            .into_iter()
+            .map(|payload| {
+                let log_id = LogId::new(leader_id.clone(), next_index);
+                next_index += 1;
+                Entry { log_id, payload }
+            })
+            .collect::<Vec<_>>();
 
-        tracing::debug!("append log: {}", entry.summary());
-        self.last_log_id = Some(log_id);
+        let entry_refs = entries.iter().collect::<Vec<_>>();
+        self.storage.append_to_log(&entry_refs).await?;
 
-        if let EntryPayload::Membership(mem) = &entry.payload {
-            self.effective_membership = EffectiveMembership {
-                log_id: entry.log_id,
-                membership: mem.clone(),
-            };
+        for entry in entries.iter() {
+            tracing::debug!("append log: {}", entry.summary());
+            self.last_log_id = Some(entry.log_id.clone());
+
+            if let EntryPayload::Membership(mem) = &entry.payload {
+                self.effective_membership = EffectiveMembership {
+                    log_id: entry.log_id.clone(),
+                    membership: mem.clone(),
+                };
+            }
         }
 
-        Ok(entry)
+        Ok(entries)
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
-    pub fn current_leader(&self) -> Option<NodeId> {
+    pub fn current_leader(&self) -> Option<NID> {
         if !self.vote.committed {
             return None;
         }
 
-        let id = self.vote.node_id;
+        let id = self.vote.node_id.clone();
 
         if id == self.id {
             if self.target_state == State::Leader {
@@ -560,19 +747,19 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 }
 
 #[tracing::instrument(level = "trace", skip(sto), fields(entries=%entries.summary()))]
-async fn apply_to_state_machine<D, R, S>(
+async fn apply_to_state_machine<D, R, S, NID: NodeId, NI: NodeInfo>(
     sto: Arc<S>,
-    entries: &[&Entry<D>],
+    entries: &[&Entry<D, NID, NI>],
     max_keep: u64,
-) -> Result<Vec<R>, StorageError>
+) -> Result<Vec<R>, StorageError<NID>>
 where
     D: AppData,
     R: AppDataResponse,
-    S: RaftStorage<D, R>,
+    S: RaftStorage<D, R, NID, NI>,
 {
     tracing::debug!(entries=%entries.summary(), max_keep, "apply_to_state_machine");
 
-    let last = entries.last().map(|x| x.log_id);
+    let last = entries.last().map(|x| x.log_id.clone());
 
     if let Some(last_applied) = last {
         // TODO(xp): apply_to_state_machine should return the last applied
@@ -585,11 +772,15 @@ where
 }
 
 #[tracing::instrument(level = "trace", skip(sto))]
-async fn purge_applied_logs<D, R, S>(sto: Arc<S>, last_applied: &LogId, max_keep: u64) -> Result<(), StorageError>
+async fn purge_applied_logs<D, R, S, NID: NodeId, NI: NodeInfo>(
+    sto: Arc<S>,
+    last_applied: &LogId<NID>,
+    max_keep: u64,
+) -> Result<(), StorageError<NID>>
 where
     D: AppData,
     R: AppDataResponse,
-    S: RaftStorage<D, R>,
+    S: RaftStorage<D, R, NID, NI>,
 {
     // TODO(xp): periodically batch delete
     let end = last_applied.index + 1;
@@ -603,13 +794,13 @@ where
 
     let st = sto.get_log_state().await?;
 
-    if st.last_log_id < Some(*last_applied) {
-        sto.purge_logs_upto(*last_applied).await?;
+    if st.last_log_id < Some(last_applied.clone()) {
+        sto.purge_logs_upto(last_applied.clone()).await?;
         return Ok(());
     }
 
     // non applied logs are deleted. it is a bug.
-    assert!(st.last_purged_log_id <= Some(*last_applied));
+    assert!(st.last_purged_log_id <= Some(last_applied.clone()));
 
     if st.last_purged_log_id.index() >= Some(end - 1) {
         return Ok(());
@@ -619,6 +810,16 @@ where
     sto.purge_logs_upto(log_id).await
 }
 
+/// Wait for the next periodic check of the snapshot policy, or forever if the policy does not need it.
+async fn snapshot_check_tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => futures::future::pending().await,
+    }
+}
+
 /// The current snapshot state of the Raft node.
 pub(self) enum SnapshotState<S> {
     /// The Raft node is compacting itself.
@@ -636,14 +837,16 @@ pub(self) enum SnapshotState<S> {
         id: String,
         /// A handle to the snapshot writer.
         snapshot: Box<S>,
+        /// The checksum of the bytes written so far.
+        checksum: crc32fast::Hasher,
     },
 }
 
 /// An update on a snapshot creation process.
 #[derive(Debug)]
-pub(self) enum SnapshotUpdate {
-    /// Snapshot creation has finished successfully and covers the given index.
-    SnapshotComplete(LogId),
+pub(self) enum SnapshotUpdate<NID: NodeId> {
+    /// Snapshot creation has finished successfully, with the meta of the snapshot.
+    SnapshotComplete(SnapshotMeta<NID>),
     /// Snapshot creation failed.
     SnapshotFailed,
 }
@@ -661,6 +864,9 @@ pub enum State {
     Candidate,
     /// The node is the Raft cluster leader.
     Leader,
+    /// The node is a witness: it votes and replicates log ids but stores no application data, never builds a snapshot
+    /// and never campaigns.
+    Witness,
     /// The Raft node is shutting down.
     Shutdown,
 }
@@ -676,6 +882,11 @@ impl State {
         matches!(self, Self::Follower)
     }
 
+    /// Check if currently in witness state.
+    pub fn is_witness(&self) -> bool {
+        matches!(self, Self::Witness)
+    }
+
     /// Check if currently in candidate state.
     pub fn is_candidate(&self) -> bool {
         matches!(self, Self::Candidate)
@@ -690,29 +901,101 @@ impl State {
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// Volatile state specific to the Raft leader.
-struct LeaderState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    pub(super) core: &'a mut RaftCore<D, R, N, S>,
+struct LeaderState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID, NI>,
+    S: RaftStorage<D, R, NID, NI>,
+    NID: NodeId,
+    NI: NodeInfo,
+> {
+    pub(super) core: &'a mut RaftCore<D, R, N, S, NID, NI>,
 
     /// A mapping of node IDs the replication state of the target node.
-    pub(super) nodes: BTreeMap<NodeId, ReplicationState>,
+    pub(super) nodes: BTreeMap<NID, ReplicationState<NID, NI>>,
 
     /// The metrics about a leader
-    pub leader_metrics: LeaderMetrics,
+    pub leader_metrics: LeaderMetrics<NID>,
 
     /// The stream of events coming from replication streams.
-    pub(super) replication_rx: mpsc::UnboundedReceiver<(ReplicaEvent<S::SnapshotData>, Span)>,
+    pub(super) replication_rx: mpsc::UnboundedReceiver<(ReplicaEvent<S::SnapshotData, NID>, Span)>,
 
     /// The cloneable sender channel for replication stream events.
-    pub(super) replication_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
+    pub(super) replication_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData, NID>, Span)>,
 
     /// A buffer of client requests which have been appended locally and are awaiting to be committed to the cluster.
-    pub(super) awaiting_committed: Vec<ClientRequestEntry<D, R>>,
+    pub(super) awaiting_committed: Vec<ClientRequestEntry<D, R, NID, NI>>,
+
+    /// The leadership transfer in progress, if any.
+    ///
+    /// No write is accepted while it is `Some`.
+    pub(super) leader_transfer: Option<LeaderTransfer<NID, NI>>,
+
+    /// Learners to be promoted to voters once they catch up with this leader.
+    ///
+    /// The marks are kept only for the term of this leader. They are not passed on to the next leader.
+    pub(super) promoting: BTreeSet<NID>,
+
+    /// The log id of the joint membership config this leader changes to the uniform config once it is committed, if
+    /// any: either one this leader proposed to promote learners, or one left by a previous leader.
+    pub(super) joint_to_finish: Option<LogId<NID>>,
+
+    /// Set once a `TimeoutNow` is sent.
+    ///
+    /// Voters then grant a vote to the transfer target regardless of the lease they promised to this leader.
+    /// Thus this leader must not serve a read with its lease any more.
+    pub(super) lease_revoked: bool,
+
+    /// When this node became leader. A voter that has not responded yet is regarded as responded at this time.
+    pub(super) elected_at: Instant,
+
+    /// The next time to check whether a quorum is still in contact with this leader.
+    pub(super) next_quorum_check: Instant,
+
+    /// The last time this leader started to transfer its leadership to a voter with a higher priority.
+    pub(super) priority_transfer_at: Option<Instant>,
+
+    /// The next time to check whether to transfer the leadership to a voter with a higher priority.
+    ///
+    /// A failed transfer is retried by this check even if no replication progress is made, e.g., on an idle cluster.
+    pub(super) next_priority_check: Instant,
+
+    /// The last time the metrics of this leader were reported.
+    pub(super) metrics_reported_at: Instant,
+
+    /// The log id of the blank log this leader appended when it was elected.
+    pub(super) blank_log_id: Option<LogId<NID>>,
+
+    /// Reads waiting for the next round to confirm the leadership.
+    pub(super) queued_reads: Vec<PendingRead<NID, NI>>,
+
+    /// Reads being confirmed by the round in progress, if any.
+    pub(super) confirming_reads: Option<Vec<PendingRead<NID, NI>>>,
+
+    /// The stream of results of rounds to confirm the leadership.
+    pub(super) rx_read_round: mpsc::UnboundedReceiver<ReadRoundResult<NID>>,
+
+    /// The cloneable sender channel for the results of rounds to confirm the leadership.
+    pub(super) tx_read_round: mpsc::UnboundedSender<ReadRoundResult<NID>>,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > LeaderState<'a, D, R, N, S, NID, NI>
+{
     /// Create a new instance.
-    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S>) -> Self {
+    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S, NID, NI>) -> Self {
         let (replication_tx, replication_rx) = mpsc::unbounded_channel();
+        let (tx_read_round, rx_read_round) = mpsc::unbounded_channel();
+        let now = Instant::now();
+        let next_quorum_check = now + Duration::from_millis(core.config.heartbeat_interval);
         Self {
             core,
             nodes: BTreeMap::new(),
@@ -720,12 +1003,26 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             replication_tx,
             replication_rx,
             awaiting_committed: Vec::new(),
+            leader_transfer: None,
+            promoting: BTreeSet::new(),
+            joint_to_finish: None,
+            lease_revoked: false,
+            elected_at: now,
+            next_quorum_check,
+            priority_transfer_at: None,
+            next_priority_check: next_quorum_check,
+            metrics_reported_at: now,
+            blank_log_id: None,
+            queued_reads: Vec::new(),
+            confirming_reads: None,
+            rx_read_round,
+            tx_read_round,
         }
     }
 
     /// Transition to the Raft leader state.
-    #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id, raft_state="leader"))]
-    pub(self) async fn run(mut self) -> Result<(), Fatal> {
+    #[tracing::instrument(level="debug", skip(self), fields(id=%self.core.id, raft_state="leader"))]
+    pub(self) async fn run(mut self) -> Result<(), Fatal<NID>> {
         // Setup state as leader.
         self.core.last_heartbeat = None;
         self.core.next_election_timeout = None;
@@ -742,32 +1039,44 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             .collect::<Vec<_>>();
 
         for target in targets {
-            let state = self.spawn_replication_stream(*target, None);
-            self.nodes.insert(*target, state);
+            let state = self.spawn_replication_stream(target.clone(), None);
+            self.nodes.insert(target.clone(), state);
         }
 
         // spawn replication streams for learners.
         let learners = self.core.effective_membership.membership.all_learners();
         for node_id in learners {
-            let state = self.spawn_replication_stream(*node_id, None);
-            self.nodes.insert(*node_id, state);
+            let state = self.spawn_replication_stream(node_id.clone(), None);
+            self.nodes.insert(node_id.clone(), state);
         }
 
         self.leader_report_metrics();
 
+        // A previous leader that proposed a joint config may have lost its leadership before changing it to the
+        // uniform config. Nobody else would finish it.
+        if self.core.effective_membership.membership.is_in_joint_consensus() {
+            self.joint_to_finish = Some(self.core.effective_membership.log_id.clone());
+        }
+
         self.commit_initial_leader_entry().await?;
 
+        // The initial log is committed at once if this leader is the only voter.
+        self.try_promote_learners().await?;
+
         self.leader_loop().await?;
 
         Ok(())
     }
 
-    #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id))]
-    pub(self) async fn leader_loop(mut self) -> Result<(), Fatal> {
+    #[tracing::instrument(level="debug", skip(self), fields(id=%self.core.id))]
+    pub(self) async fn leader_loop(mut self) -> Result<(), Fatal<NID>> {
         loop {
             if !self.core.target_state.is_leader() {
                 tracing::info!("id={} state becomes: {:?}", self.core.id, self.core.target_state);
 
+                self.finish_leader_transfer();
+                self.reject_pending_reads();
+
                 // implicit drop replication_rx
                 // notify to all nodes DO NOT send replication event any more.
                 return Ok(());
@@ -776,9 +1085,13 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             let span = tracing::debug_span!("CHrx:LeaderState");
             let _ent = span.enter();
 
+            let transfer_deadline = self.leader_transfer.as_ref().map(|t| t.deadline).unwrap_or_else(Instant::now);
+            let next_quorum_check = self.next_quorum_check;
+            let next_priority_check = self.next_priority_check;
+
             tokio::select! {
                 Some((msg,span)) = self.core.rx_api.recv() => {
-                    self.handle_msg(msg).instrument(span).await?;
+                    self.handle_api_msg(msg, span).await?;
                 },
 
                 Some(update) = self.core.rx_compaction.recv() => {
@@ -786,11 +1099,37 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                     self.core.update_snapshot_state(update);
                 }
 
+                _ = snapshot_check_tick(&mut self.core.snapshot_check_interval) => {
+                    self.core.trigger_log_compaction_if_needed(false);
+                }
+
+                Some(res) = self.core.rx_applied.recv() => {
+                    self.core.handle_applied(res)?;
+                }
+
                 Some((event, span)) = self.replication_rx.recv() => {
                     tracing::info!("leader recv from replication_rx: {:?}", event.summary());
                     self.handle_replica_event(event).instrument(span).await?;
                 }
 
+                Some(result) = self.rx_read_round.recv() => {
+                    self.handle_read_round_result(result).await?;
+                }
+
+                _ = sleep_until(transfer_deadline), if self.leader_transfer.is_some() => {
+                    self.abort_leader_transfer();
+                }
+
+                _ = sleep_until(next_quorum_check), if self.core.config.enable_check_quorum => {
+                    self.check_quorum();
+                }
+
+                _ = sleep_until(next_priority_check) => {
+                    let interval = Duration::from_millis(self.core.config.heartbeat_interval);
+                    self.next_priority_check = Instant::now() + interval;
+                    self.try_transfer_to_higher_priority();
+                }
+
                 Ok(_) = &mut self.core.rx_shutdown => {
                     tracing::info!("leader recv from rx_shudown");
                     self.core.set_target_state(State::Shutdown);
@@ -799,8 +1138,51 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         }
     }
 
-    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "leader", id=self.core.id))]
-    pub async fn handle_msg(&mut self, msg: RaftMsg<D, R>) -> Result<(), Fatal> {
+    /// Handle a message received from `rx_api`.
+    ///
+    /// A client write is grouped with the client writes already queued after it, up to `max_payload_entries`,
+    /// so that they are appended to the log and replicated together.
+    /// The first other message taken from the queue is handled right after the group.
+    async fn handle_api_msg(&mut self, msg: RaftMsg<D, R, NID, NI>, span: Span) -> Result<(), Fatal<NID>> {
+        let mut writes = match msg {
+            RaftMsg::ClientWriteRequest { rpc, tx } if self.leader_transfer.is_none() => vec![(rpc, tx)],
+            RaftMsg::ClientWriteMany { reqs } if self.leader_transfer.is_none() => reqs,
+            _ => return self.handle_msg(msg).instrument(span).await,
+        };
+
+        let mut next = None;
+
+        // The grouped writes are handled in the span of the first one, which is linked to the spans of the others.
+        while (writes.len() as u64) < self.core.config.max_payload_entries {
+            match self.core.rx_api.try_recv() {
+                Ok((RaftMsg::ClientWriteRequest { rpc, tx }, s)) => {
+                    span.follows_from(&s);
+                    writes.push((rpc, tx));
+                }
+                Ok((RaftMsg::ClientWriteMany { reqs }, s)) => {
+                    span.follows_from(&s);
+                    writes.extend(reqs);
+                }
+                Ok(other) => {
+                    next = Some(other);
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+
+        tracing::debug!(writes = writes.len(), "group client writes");
+        self.handle_client_write_requests(writes).instrument(span).await?;
+
+        if let Some((msg, span)) = next {
+            self.handle_msg(msg).instrument(span).await?;
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "leader", id=%self.core.id))]
+    pub async fn handle_msg(&mut self, msg: RaftMsg<D, R, NID, NI>) -> Result<(), Fatal<NID>> {
         tracing::debug!("recv from rx_api: {}", msg.summary());
 
         match msg {
@@ -816,25 +1198,75 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 let res = self.core.handle_install_snapshot_request(rpc).await.extract_fatal()?;
                 let _ = tx.send(res);
             }
+            RaftMsg::TimeoutNow { rpc, tx } => {
+                let _ = tx.send(self.core.handle_timeout_now_request(rpc));
+            }
             RaftMsg::ClientReadRequest { tx } => {
-                self.handle_client_read_request(tx).await;
+                self.handle_client_read_request(PendingRead::Read { tx });
+            }
+            RaftMsg::ClientReadIndex { tx } => {
+                // The read index is taken before confirming the leadership,
+                // thus it includes every log committed before the request is received.
+                let read_log_id = self.read_log_id();
+                self.handle_client_read_request(PendingRead::ReadIndex { read_log_id, tx });
+            }
+            RaftMsg::ReadIndex { rpc: _, tx } => {
+                let read_log_id = self.read_log_id();
+                self.handle_client_read_request(PendingRead::ReadIndexRpc { read_log_id, tx });
             }
             RaftMsg::ClientWriteRequest { rpc, tx } => {
-                self.handle_client_write_request(rpc, tx).await?;
+                if self.leader_transfer.is_some() {
+                    self.reject_during_leader_transfer(tx);
+                } else {
+                    self.handle_client_write_requests(vec![(rpc, tx)]).await?;
+                }
+            }
+            RaftMsg::ClientWriteMany { reqs } => {
+                if self.leader_transfer.is_some() {
+                    for (_, tx) in reqs {
+                        self.reject_during_leader_transfer(tx);
+                    }
+                } else {
+                    self.handle_client_write_requests(reqs).await?;
+                }
             }
             RaftMsg::Initialize { tx, .. } => {
                 self.core.reject_init_with_config(tx);
             }
-            RaftMsg::AddLearner { id, tx, blocking } => {
-                self.add_learner(id, tx, blocking).await;
+            RaftMsg::AddLearner { id, node, tx, blocking } => {
+                if self.leader_transfer.is_some() {
+                    self.reject_during_leader_transfer(tx);
+                } else {
+                    self.add_learner(id, node, tx, blocking).await;
+                }
             }
             RaftMsg::ChangeMembership {
-                members,
+                changes,
                 blocking,
                 turn_to_learner,
                 tx,
             } => {
-                self.change_membership(members, blocking, turn_to_learner, tx).await?;
+                if self.leader_transfer.is_some() {
+                    self.reject_during_leader_transfer(tx);
+                } else {
+                    self.change_membership(changes, blocking, turn_to_learner, tx).await?;
+                }
+            }
+            RaftMsg::PromoteLearner { id, promote, tx } => {
+                if self.leader_transfer.is_some() {
+                    self.reject_during_leader_transfer(tx);
+                } else {
+                    self.promote_learner(id, promote, tx).await?;
+                }
+            }
+            RaftMsg::TransferLeader { target, tx } => {
+                self.transfer_leader(target, tx);
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                self.core.handle_trigger_snapshot(tx);
+            }
+            RaftMsg::PurgeLog { upto, tx } => {
+                self.core.handle_purge_log(upto, tx);
             }
         };
 
@@ -844,21 +1276,42 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     /// Report metrics with leader specific states.
     #[tracing::instrument(level = "trace", skip(self))]
     pub fn leader_report_metrics(&mut self) {
+        self.metrics_reported_at = Instant::now();
         self.core.report_metrics(Update::Update(Some(&self.leader_metrics)));
     }
 }
 
+/// The state of a leadership transfer from the perspective of the leader.
+struct LeaderTransfer<NID: NodeId, NI: NodeInfo> {
+    /// The voter to transfer leadership to.
+    pub target: NID,
+
+    /// The transfer is aborted and the leader resumes accepting writes if it is still leader at this time.
+    pub deadline: Instant,
+
+    /// Whether `TimeoutNow` has been sent to `target`.
+    pub timeout_now_sent: bool,
+
+    /// The response channel to the caller, which is `None` for a transfer to a voter with a higher priority.
+    /// It is consumed once `TimeoutNow` has been sent to `target`, and is responded with `deadline`.
+    pub tx: Option<RaftRespTx<Instant, TransferLeaderError<NID, NI>>>,
+}
+
 /// A struct tracking the state of a replication stream from the perspective of the Raft actor.
-struct ReplicationState {
-    pub matched: Option<LogId>,
+struct ReplicationState<NID: NodeId, NI: NodeInfo> {
+    pub matched: Option<LogId<NID>>,
+
+    /// The send time of the latest AppendEntries the target acknowledged, to extend the leader lease.
+    pub acked_at: Option<Instant>,
+
     pub remove_since: Option<u64>,
-    pub repl_stream: ReplicationStream,
+    pub repl_stream: ReplicationStream<NID, NI>,
 
     /// The response channel to use for when this node has successfully synced with the cluster.
-    pub tx: Option<RaftRespTx<AddLearnerResponse, AddLearnerError>>,
+    pub tx: Option<RaftRespTx<AddLearnerResponse<NID>, AddLearnerError<NID, NI>>>,
 }
 
-impl MessageSummary for ReplicationState {
+impl<NID: NodeId, NI: NodeInfo> MessageSummary for ReplicationState<NID, NI> {
     fn summary(&self) -> String {
         format!(
             "matched: {:?}, remove_after_commit: {:?}",
@@ -867,31 +1320,52 @@ impl MessageSummary for ReplicationState {
     }
 }
 
-impl ReplicationState {
+impl<NID: NodeId, NI: NodeInfo> ReplicationState<NID, NI> {
     // TODO(xp): make this a method of Config?
 
     /// Return true if the distance behind last_log_id is smaller than the threshold to join.
-    pub fn is_line_rate(&self, last_log_id: &Option<LogId>, config: &Config) -> bool {
+    pub fn is_line_rate(&self, last_log_id: &Option<LogId<NID>>, config: &Config) -> bool {
         is_matched_upto_date(&self.matched, last_log_id, config)
     }
 }
 
-pub fn is_matched_upto_date(matched: &Option<LogId>, last_log_id: &Option<LogId>, config: &Config) -> bool {
+pub fn is_matched_upto_date<NID: NodeId>(
+    matched: &Option<LogId<NID>>,
+    last_log_id: &Option<LogId<NID>>,
+    config: &Config,
+) -> bool {
     let my_index = matched.next_index();
     let distance = last_log_id.next_index().saturating_sub(my_index);
     distance <= config.replication_lag_threshold
 }
 
 /// Volatile state specific to a Raft node in candidate state.
-struct CandidateState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    core: &'a mut RaftCore<D, R, N, S>,
+struct CandidateState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID, NI>,
+    S: RaftStorage<D, R, NID, NI>,
+    NID: NodeId,
+    NI: NodeInfo,
+> {
+    core: &'a mut RaftCore<D, R, N, S, NID, NI>,
 
     /// Ids of the nodes that has granted our vote request.
-    granted: BTreeSet<NodeId>,
+    granted: BTreeSet<NID>,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> CandidateState<'a, D, R, N, S> {
-    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S>) -> Self {
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > CandidateState<'a, D, R, N, S, NID, NI>
+{
+    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S, NID, NI>) -> Self {
         Self {
             core,
             granted: btreeset! {},
@@ -899,8 +1373,10 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 
     /// Run the candidate loop.
-    #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id, raft_state="candidate"))]
-    pub(self) async fn run(mut self) -> Result<(), Fatal> {
+    #[tracing::instrument(level="debug", skip(self), fields(id=%self.core.id, raft_state="candidate"))]
+    pub(self) async fn run(mut self) -> Result<(), Fatal<NID>> {
+        self.core.report_metrics(Update::Update(None));
+
         // Each iteration of the outer loop represents a new term.
 
         loop {
@@ -908,22 +1384,38 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 return Ok(());
             }
 
-            // Setup new term.
             self.core.update_next_election_timeout(false); // Generates a new rand value within range.
 
-            self.core.vote = Vote::new(self.core.vote.term + 1, self.core.id);
+            // Only the first election after a `TimeoutNow` is a leadership transfer.
+            let leader_transfer = std::mem::take(&mut self.core.timeout_now);
+
+            // The leader has stepped aside on purpose for a leadership transfer, there is no need to pre-vote.
+            if self.core.config.enable_pre_vote && !leader_transfer {
+                if !self.pre_vote().await? {
+                    continue;
+                }
+
+                // Give the real election a full election timeout.
+                self.core.update_next_election_timeout(false);
+            }
+
+            // Setup new term.
+            self.core.vote = Vote::new(self.core.vote.term + 1, self.core.id.clone());
 
             self.core.save_vote().await?;
             self.core.report_metrics(Update::Update(None));
 
+            // Votes granted in a previous term or in the pre-vote round do not count.
+            self.granted.clear();
+
             // vote for itself.
             self.handle_vote_response(
                 VoteResponse {
-                    vote: self.core.vote,
+                    vote: self.core.vote.clone(),
                     vote_granted: true,
-                    last_log_id: self.core.last_log_id,
+                    last_log_id: self.core.last_log_id.clone(),
                 },
-                self.core.id,
+                self.core.id.clone(),
             )
             .await?;
             if !self.core.target_state.is_candidate() {
@@ -931,7 +1423,9 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             }
 
             // Send RPCs to all members in parallel.
-            let mut pending_votes = self.spawn_parallel_vote_requests();
+            let mut rpc = VoteRequest::new(self.core.vote.clone(), self.core.last_log_id.clone());
+            rpc.leader_transfer = leader_transfer;
+            let mut pending_votes = self.spawn_parallel_vote_requests(&rpc);
 
             // Inner processing loop for this Raft state.
             loop {
@@ -956,14 +1450,76 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
                     Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
 
+                    _ = snapshot_check_tick(&mut self.core.snapshot_check_interval) => {
+                        self.core.trigger_log_compaction_if_needed(false);
+                    }
+
+                    Some(res) = self.core.rx_applied.recv() => self.core.handle_applied(res)?,
+
                     Ok(_) = &mut self.core.rx_shutdown => self.core.set_target_state(State::Shutdown),
                 }
             }
         }
     }
 
-    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "candidate", id=self.core.id))]
-    pub async fn handle_msg(&mut self, msg: RaftMsg<D, R>) -> Result<(), Fatal> {
+    /// Run a pre-vote round (§9.6 of the thesis), asking the voters whether this node could win an election.
+    ///
+    /// The next vote is not persisted and no state on other nodes is changed.
+    /// Return true if a quorum would grant it, or false if the round times out or this node is no longer a candidate.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn pre_vote(&mut self) -> Result<bool, Fatal<NID>> {
+        self.granted = btreeset! {self.core.id.clone()};
+
+        if self.core.quorum_set().is_quorum(&self.granted) {
+            return Ok(true);
+        }
+
+        let next_vote = Vote::new(self.core.vote.term + 1, self.core.id.clone());
+        let mut rpc = VoteRequest::new(next_vote.clone(), self.core.last_log_id.clone());
+        rpc.pre_vote = true;
+        let mut pending_votes = self.spawn_parallel_vote_requests(&rpc);
+
+        loop {
+            if !self.core.target_state.is_candidate() {
+                return Ok(false);
+            }
+            let timeout_fut = sleep_until(self.core.get_next_election_timeout());
+
+            let span = tracing::debug_span!("CHrx:CandidateState:pre_vote");
+            let _ent = span.enter();
+
+            tokio::select! {
+                _ = timeout_fut => {
+                    tracing::debug!(%next_vote, "pre-vote timed out");
+                    return Ok(false);
+                },
+
+                Some((res, peer)) = pending_votes.recv() => {
+                    if self.handle_pre_vote_response(res, peer).await? {
+                        tracing::debug!(%next_vote, "pre-vote granted by a quorum");
+                        return Ok(true);
+                    }
+                },
+
+                Some((msg,span)) = self.core.rx_api.recv() => {
+                    self.handle_msg(msg).instrument(span).await?;
+                },
+
+                Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
+
+                _ = snapshot_check_tick(&mut self.core.snapshot_check_interval) => {
+                    self.core.trigger_log_compaction_if_needed(false);
+                }
+
+                Some(res) = self.core.rx_applied.recv() => self.core.handle_applied(res)?,
+
+                Ok(_) = &mut self.core.rx_shutdown => self.core.set_target_state(State::Shutdown),
+            }
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "candidate", id=%self.core.id))]
+    pub async fn handle_msg(&mut self, msg: RaftMsg<D, R, NID, NI>) -> Result<(), Fatal<NID>> {
         tracing::debug!("recv from rx_api: {}", msg.summary());
         match msg {
             RaftMsg::AppendEntries { rpc, tx } => {
@@ -975,12 +1531,26 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::InstallSnapshot { rpc, tx } => {
                 let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await.extract_fatal()?);
             }
+            RaftMsg::TimeoutNow { rpc, tx } => {
+                let _ = tx.send(self.core.handle_timeout_now_request(rpc));
+            }
             RaftMsg::ClientReadRequest { tx } => {
                 self.core.reject_with_forward_to_leader(tx);
             }
+            RaftMsg::ClientReadIndex { tx } => {
+                self.core.reject_with_forward_to_leader(tx);
+            }
+            RaftMsg::ReadIndex { tx, .. } => {
+                self.core.reject_with_forward_to_leader(tx);
+            }
             RaftMsg::ClientWriteRequest { rpc: _, tx } => {
                 self.core.reject_with_forward_to_leader(tx);
             }
+            RaftMsg::ClientWriteMany { reqs } => {
+                for (_, tx) in reqs {
+                    self.core.reject_with_forward_to_leader(tx);
+                }
+            }
             RaftMsg::Initialize { tx, .. } => {
                 self.core.reject_init_with_config(tx);
             }
@@ -990,6 +1560,18 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::ChangeMembership { tx, .. } => {
                 self.core.reject_with_forward_to_leader(tx);
             }
+            RaftMsg::PromoteLearner { tx, .. } => {
+                self.core.reject_with_forward_to_leader(tx);
+            }
+            RaftMsg::TransferLeader { tx, .. } => {
+                self.core.reject_with_forward_to_leader(tx);
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                self.core.handle_trigger_snapshot(tx);
+            }
+            RaftMsg::PurgeLog { upto, tx } => {
+                self.core.handle_purge_log(upto, tx);
+            }
         };
         Ok(())
     }
@@ -998,18 +1580,35 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// Volatile state specific to a Raft node in follower state.
-pub struct FollowerState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    core: &'a mut RaftCore<D, R, N, S>,
+pub struct FollowerState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID, NI>,
+    S: RaftStorage<D, R, NID, NI>,
+    NID: NodeId = u64,
+    NI: NodeInfo = Node,
+> {
+    core: &'a mut RaftCore<D, R, N, S, NID, NI>,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> FollowerState<'a, D, R, N, S> {
-    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S>) -> Self {
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > FollowerState<'a, D, R, N, S, NID, NI>
+{
+    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S, NID, NI>) -> Self {
         Self { core }
     }
 
     /// Run the follower loop.
-    #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id, raft_state="follower"))]
-    pub(self) async fn run(mut self) -> Result<(), Fatal> {
+    #[tracing::instrument(level="debug", skip(self), fields(id=%self.core.id, raft_state="follower"))]
+    pub(self) async fn run(mut self) -> Result<(), Fatal<NID>> {
         self.core.report_metrics(Update::Update(None));
 
         loop {
@@ -1032,13 +1631,19 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
                 Some(update) = self.core.rx_compaction.recv() => self.core.update_snapshot_state(update),
 
+                _ = snapshot_check_tick(&mut self.core.snapshot_check_interval) => {
+                    self.core.trigger_log_compaction_if_needed(false);
+                }
+
+                Some(res) = self.core.rx_applied.recv() => self.core.handle_applied(res)?,
+
                 Ok(_) = &mut self.core.rx_shutdown => self.core.set_target_state(State::Shutdown),
             }
         }
     }
 
-    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "follower", id=self.core.id))]
-    pub(crate) async fn handle_msg(&mut self, msg: RaftMsg<D, R>) -> Result<(), Fatal> {
+    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "follower", id=%self.core.id))]
+    pub(crate) async fn handle_msg(&mut self, msg: RaftMsg<D, R, NID, NI>) -> Result<(), Fatal<NID>> {
         tracing::debug!("recv from rx_api: {}", msg.summary());
 
         match msg {
@@ -1051,12 +1656,26 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::InstallSnapshot { rpc, tx } => {
                 let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await.extract_fatal()?);
             }
+            RaftMsg::TimeoutNow { rpc, tx } => {
+                let _ = tx.send(self.core.handle_timeout_now_request(rpc));
+            }
             RaftMsg::ClientReadRequest { tx } => {
                 self.core.reject_with_forward_to_leader(tx);
             }
+            RaftMsg::ClientReadIndex { tx } => {
+                self.core.forward_read_index_to_leader(tx);
+            }
+            RaftMsg::ReadIndex { tx, .. } => {
+                self.core.reject_with_forward_to_leader(tx);
+            }
             RaftMsg::ClientWriteRequest { rpc: _, tx } => {
                 self.core.reject_with_forward_to_leader(tx);
             }
+            RaftMsg::ClientWriteMany { reqs } => {
+                for (_, tx) in reqs {
+                    self.core.reject_with_forward_to_leader(tx);
+                }
+            }
             RaftMsg::Initialize { tx, .. } => {
                 self.core.reject_init_with_config(tx);
             }
@@ -1066,6 +1685,18 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::ChangeMembership { tx, .. } => {
                 self.core.reject_with_forward_to_leader(tx);
             }
+            RaftMsg::PromoteLearner { tx, .. } => {
+                self.core.reject_with_forward_to_leader(tx);
+            }
+            RaftMsg::TransferLeader { tx, .. } => {
+                self.core.reject_with_forward_to_leader(tx);
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                self.core.handle_trigger_snapshot(tx);
+            }
+            RaftMsg::PurgeLog { upto, tx } => {
+                self.core.handle_purge_log(upto, tx);
+            }
         };
         Ok(())
     }
@@ -1073,23 +1704,44 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
-/// Volatile state specific to a Raft node in learner state.
-pub struct LearnerState<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    core: &'a mut RaftCore<D, R, N, S>,
+/// Volatile state specific to a Raft node in learner or witness state.
+pub struct LearnerState<
+    'a,
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID, NI>,
+    S: RaftStorage<D, R, NID, NI>,
+    NID: NodeId = u64,
+    NI: NodeInfo = Node,
+> {
+    core: &'a mut RaftCore<D, R, N, S, NID, NI>,
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LearnerState<'a, D, R, N, S> {
-    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S>) -> Self {
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > LearnerState<'a, D, R, N, S, NID, NI>
+{
+    pub(self) fn new(core: &'a mut RaftCore<D, R, N, S, NID, NI>) -> Self {
         Self { core }
     }
 
     /// Run the learner loop.
-    #[tracing::instrument(level="debug", skip(self), fields(id=self.core.id, raft_state="learner"))]
-    pub(self) async fn run(mut self) -> Result<(), Fatal> {
+    ///
+    /// A witness runs the same loop as a learner: it handles RPCs from the leader but never times out to campaign.
+    #[tracing::instrument(level="debug", skip(self), fields(id=%self.core.id, raft_state=?self.core.target_state))]
+    pub(self) async fn run(mut self) -> Result<(), Fatal<NID>> {
         self.core.report_metrics(Update::Update(None));
 
+        let state = self.core.target_state;
+
         loop {
-            if !self.core.target_state.is_learner() {
+            if self.core.target_state != state {
                 return Ok(());
             }
 
@@ -1105,14 +1757,22 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                     self.core.update_snapshot_state(update);
                 },
 
+                _ = snapshot_check_tick(&mut self.core.snapshot_check_interval) => {
+                    self.core.trigger_log_compaction_if_needed(false);
+                },
+
+                Some(res) = self.core.rx_applied.recv() => {
+                    self.core.handle_applied(res)?;
+                },
+
                 Ok(_) = &mut self.core.rx_shutdown => self.core.set_target_state(State::Shutdown),
             }
         }
     }
 
     // TODO(xp): define a handle_msg method in RaftCore that decides what to do by current State.
-    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "learner", id=self.core.id))]
-    pub(crate) async fn handle_msg(&mut self, msg: RaftMsg<D, R>) -> Result<(), Fatal> {
+    #[tracing::instrument(level = "debug", skip(self, msg), fields(state = "learner", id=%self.core.id))]
+    pub(crate) async fn handle_msg(&mut self, msg: RaftMsg<D, R, NID, NI>) -> Result<(), Fatal<NID>> {
         tracing::debug!("recv from rx_api: {}", msg.summary());
 
         match msg {
@@ -1125,12 +1785,26 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::InstallSnapshot { rpc, tx } => {
                 let _ = tx.send(self.core.handle_install_snapshot_request(rpc).await.extract_fatal()?);
             }
+            RaftMsg::TimeoutNow { rpc, tx } => {
+                let _ = tx.send(self.core.handle_timeout_now_request(rpc));
+            }
             RaftMsg::ClientReadRequest { tx } => {
                 self.core.reject_with_forward_to_leader(tx);
             }
+            RaftMsg::ClientReadIndex { tx } => {
+                self.core.forward_read_index_to_leader(tx);
+            }
+            RaftMsg::ReadIndex { tx, .. } => {
+                self.core.reject_with_forward_to_leader(tx);
+            }
             RaftMsg::ClientWriteRequest { rpc: _, tx } => {
                 self.core.reject_with_forward_to_leader(tx);
             }
+            RaftMsg::ClientWriteMany { reqs } => {
+                for (_, tx) in reqs {
+                    self.core.reject_with_forward_to_leader(tx);
+                }
+            }
             RaftMsg::Initialize { members, tx } => {
                 let _ = tx.send(self.handle_init_with_config(members).await);
             }
@@ -1140,6 +1814,18 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             RaftMsg::ChangeMembership { tx, .. } => {
                 self.core.reject_with_forward_to_leader(tx);
             }
+            RaftMsg::PromoteLearner { tx, .. } => {
+                self.core.reject_with_forward_to_leader(tx);
+            }
+            RaftMsg::TransferLeader { tx, .. } => {
+                self.core.reject_with_forward_to_leader(tx);
+            }
+            RaftMsg::TriggerSnapshot { tx } => {
+                self.core.handle_trigger_snapshot(tx);
+            }
+            RaftMsg::PurgeLog { upto, tx } => {
+                self.core.handle_purge_log(upto, tx);
+            }
         };
         Ok(())
     }
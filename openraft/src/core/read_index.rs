@@ -0,0 +1,108 @@
+use tokio::time::timeout;
+use tokio::time::Duration;
+use tracing_futures::Instrument;
+
+use crate::core::LeaderState;
+use crate::core::RaftCore;
+use crate::error::ClientReadError;
+use crate::error::NetworkError;
+use crate::error::RPCError;
+use crate::error::Timeout;
+use crate::raft::RaftRespTx;
+use crate::raft::ReadIndexRequest;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::LogId;
+use crate::NodeId;
+use crate::NodeInfo;
+use crate::RPCTypes;
+use crate::RaftNetwork;
+use crate::RaftStorage;
+
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > LeaderState<'a, D, R, N, S, NID, NI>
+{
+    /// The log id a node has to apply before serving a linearizable read.
+    ///
+    /// A newly elected leader does not know which logs from previous terms are committed,
+    /// until the blank log it appended is committed.
+    /// Thus the read index is the greater one of the committed log id and the blank log id.
+    pub(super) fn read_log_id(&self) -> Option<LogId<NID>> {
+        std::cmp::max(self.core.committed.clone(), self.blank_log_id.clone())
+    }
+}
+
+impl<
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > RaftCore<D, R, N, S, NID, NI>
+{
+    /// Ask the leader for a read index with a ReadIndex RPC, on behalf of a local client.
+    ///
+    /// It responds with `ForwardToLeader` if the leader is unknown.
+    #[tracing::instrument(level = "trace", skip(self, tx))]
+    pub(super) fn forward_read_index_to_leader(&self, tx: RaftRespTx<Option<LogId<NID>>, ClientReadError<NID, NI>>) {
+        let leader = match self.current_leader() {
+            Some(leader) if leader != self.id => leader,
+            _ => {
+                self.reject_with_forward_to_leader(tx);
+                return;
+            }
+        };
+
+        let rpc = ReadIndexRequest { from: self.id.clone() };
+        let leader_node = self.effective_membership.membership.get_node(&leader).cloned();
+        let my_id = self.id.clone();
+        let network = self.network.clone();
+
+        // The leader may need a heartbeat round to confirm its leadership.
+        let ttl = Duration::from_millis(self.config.election_timeout_min);
+
+        let span = tracing::debug_span!("send_read_index", target = %leader);
+        let _ = tokio::spawn(
+            async move {
+                let res = timeout(ttl, network.send_read_index(leader.clone(), leader_node.as_ref(), rpc)).await;
+
+                let res = match res {
+                    Ok(Ok(resp)) => Ok(resp.read_log_id),
+                    Ok(Err(rpc_err)) => {
+                        tracing::warn!(error=%rpc_err, %leader, "error sending ReadIndex RPC to leader");
+
+                        let err = match rpc_err {
+                            RPCError::NodeNotFound(e) => NetworkError::new(&e).into(),
+                            RPCError::Timeout(e) => e.into(),
+                            RPCError::Network(e) => e.into(),
+                            RPCError::RemoteError(e) => e.source,
+                        };
+                        Err(err)
+                    }
+                    Err(_timeout) => {
+                        tracing::warn!(%leader, "timeout while sending ReadIndex RPC to leader");
+
+                        Err(Timeout {
+                            action: RPCTypes::ReadIndex,
+                            id: my_id,
+                            target: leader,
+                            timeout: ttl,
+                        }
+                        .into())
+                    }
+                };
+
+                let _ = tx.send(res);
+            }
+            .instrument(span),
+        );
+    }
+}
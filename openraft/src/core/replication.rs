@@ -1,14 +1,16 @@
 use std::collections::BTreeMap;
+use std::time::Duration;
+use std::time::SystemTime;
 
 use tokio::sync::oneshot;
 use tracing_futures::Instrument;
 
-use crate::config::SnapshotPolicy;
 use crate::core::LeaderState;
 use crate::core::ReplicationState;
 use crate::core::SnapshotState;
 use crate::core::State;
 use crate::error::AddLearnerError;
+use crate::membership::quorum;
 use crate::raft::AddLearnerResponse;
 use crate::raft::RaftRespTx;
 use crate::replication::RaftEvent;
@@ -21,31 +23,44 @@ use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
 use crate::NodeId;
+use crate::NodeInfo;
 use crate::RaftNetwork;
 use crate::RaftStorage;
 use crate::ReplicationMetrics;
 use crate::StorageError;
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> LeaderState<'a, D, R, N, S> {
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > LeaderState<'a, D, R, N, S, NID, NI>
+{
     /// Spawn a new replication stream returning its replication state handle.
     #[tracing::instrument(level = "debug", skip(self, caller_tx))]
     pub(super) fn spawn_replication_stream(
         &self,
-        target: NodeId,
-        caller_tx: Option<RaftRespTx<AddLearnerResponse, AddLearnerError>>,
-    ) -> ReplicationState {
+        target: NID,
+        caller_tx: Option<RaftRespTx<AddLearnerResponse<NID>, AddLearnerError<NID, NI>>>,
+    ) -> ReplicationState<NID, NI> {
         let repl_stream = ReplicationStream::new(
-            target,
-            self.core.vote,
+            target.clone(),
+            self.core.effective_membership.membership.get_node(&target).cloned(),
+            self.core.effective_membership.membership.is_witness(&target),
+            self.core.vote.clone(),
             self.core.config.clone(),
-            self.core.last_log_id,
-            self.core.committed,
+            self.core.last_log_id.clone(),
+            self.core.committed.clone(),
             self.core.network.clone(),
             self.core.storage.clone(),
             self.replication_tx.clone(),
         );
         ReplicationState {
             matched: None,
+            acked_at: None,
             repl_stream,
             remove_since: None,
             tx: caller_tx,
@@ -56,14 +71,21 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     #[tracing::instrument(level = "trace", skip(self, event), fields(event=%event.summary()))]
     pub(super) async fn handle_replica_event(
         &mut self,
-        event: ReplicaEvent<S::SnapshotData>,
-    ) -> Result<(), StorageError> {
+        event: ReplicaEvent<S::SnapshotData, NID>,
+    ) -> Result<(), StorageError<NID>> {
         match event {
             ReplicaEvent::RevertToFollower { target, vote } => {
                 self.handle_revert_to_follower(target, vote).await?;
             }
             ReplicaEvent::UpdateMatched { target, matched } => {
                 self.handle_update_matched(target, matched).await?;
+                self.try_promote_learners().await?;
+                self.try_transfer_to_higher_priority();
+            }
+            ReplicaEvent::Acked { target, sent_at } => {
+                if let Some(state) = self.nodes.get_mut(&target) {
+                    state.acked_at = std::cmp::max(state.acked_at, Some(sent_at));
+                }
             }
             ReplicaEvent::NeedsSnapshot {
                 target: _,
@@ -72,6 +94,47 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             } => {
                 self.handle_needs_snapshot(must_include, tx).await?;
             }
+            ReplicaEvent::Failed { target, error } => {
+                self.update_replication_metrics(target, |m| {
+                    m.failures += 1;
+                    m.last_error = Some(error);
+                    true
+                });
+            }
+            ReplicaEvent::Responded { target, latency } => {
+                // The latency and the last response time change with every response, including a heartbeat.
+                // They are reported at most once every `heartbeat_interval`, unless the target recovers.
+                let interval = Duration::from_millis(self.core.config.heartbeat_interval);
+                let due = self.metrics_reported_at.elapsed() >= interval;
+
+                self.update_replication_metrics(target, |m| {
+                    let recovered = m.failures > 0 || m.last_error.is_some() || m.last_success_at.is_none();
+
+                    m.failures = 0;
+                    m.last_error = None;
+                    m.last_success_at = Some(SystemTime::now());
+                    m.last_rpc_latency = Some(latency);
+
+                    recovered || due
+                });
+            }
+            ReplicaEvent::UpdateProgress {
+                target,
+                state,
+                bytes_sent,
+                snapshot_transfer,
+            } => {
+                self.update_replication_metrics(target, |m| {
+                    let changed =
+                        m.state != state || m.bytes_sent != bytes_sent || m.snapshot_transfer != snapshot_transfer;
+
+                    m.state = state;
+                    m.bytes_sent = bytes_sent;
+                    m.snapshot_transfer = snapshot_transfer;
+
+                    changed
+                });
+            }
             ReplicaEvent::Shutdown => {
                 self.core.set_target_state(State::Shutdown);
             }
@@ -82,7 +145,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
     /// Handle events from replication streams for when this node needs to revert to follower state.
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn handle_revert_to_follower(&mut self, _: NodeId, vote: Vote) -> Result<(), StorageError> {
+    pub(super) async fn handle_revert_to_follower(&mut self, _: NID, vote: Vote<NID>) -> Result<(), StorageError<NID>> {
         if vote > self.core.vote {
             self.core.vote = vote;
             self.core.save_vote().await?;
@@ -92,7 +155,11 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn handle_update_matched(&mut self, target: NodeId, matched: Option<LogId>) -> Result<(), StorageError> {
+    async fn handle_update_matched(
+        &mut self,
+        target: NID,
+        matched: Option<LogId<NID>>,
+    ) -> Result<(), StorageError<NID>> {
         // Update target's match index & check if it is awaiting removal.
 
         if let Some(state) = self.nodes.get_mut(&target) {
@@ -100,7 +167,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
 
             assert!(matched >= state.matched, "the matched increments monotonically");
 
-            state.matched = matched;
+            state.matched = matched.clone();
 
             // Issue a response on the learners response channel if needed.
             if state.is_line_rate(&self.core.last_log_id, &self.core.config) {
@@ -109,7 +176,9 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
                 // When adding a learner, it blocks until the replication becomes line-rate.
                 if let Some(tx) = state.tx.take() {
                     // TODO(xp): define a specific response type for learner matched event.
-                    let x = AddLearnerResponse { matched: state.matched };
+                    let x = AddLearnerResponse {
+                        matched: state.matched.clone(),
+                    };
                     let _ = tx.send(Ok(x));
                 }
             }
@@ -118,12 +187,14 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         }
 
         // Drop replication stream if needed.
-        if self.try_remove_replication(target) {
+        if self.try_remove_replication(target.clone()) {
             // nothing to do
         } else {
-            self.update_leader_metrics(target, matched);
+            self.update_leader_metrics(target, matched.clone());
         }
 
+        self.try_send_timeout_now();
+
         if matched <= self.core.committed {
             self.leader_report_metrics();
             return Ok(());
@@ -141,28 +212,21 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             for node in self.nodes.values() {
                 let _ = node.repl_stream.repl_tx.send((
                     RaftEvent::UpdateCommittedLogId {
-                        committed: self.core.committed,
+                        committed: self.core.committed.clone(),
                     },
                     tracing::debug_span!("CH"),
                 ));
             }
 
             // Check if there are any pending requests which need to be processed.
-            let filter = self
+            let n = self
                 .awaiting_committed
                 .iter()
-                .enumerate()
-                .take_while(|(_idx, elem)| Some(elem.entry.log_id) <= self.core.committed)
-                .last()
-                .map(|(idx, _)| idx);
-
-            if let Some(offset) = filter {
-                // Build a new ApplyLogsTask from each of the given client requests.
+                .take_while(|elem| Some(elem.entry.log_id.clone()) <= self.core.committed)
+                .count();
 
-                for request in self.awaiting_committed.drain(..=offset).collect::<Vec<_>>() {
-                    self.client_request_post_commit(request).await?;
-                }
-            }
+            let requests = self.awaiting_committed.drain(..n).collect::<Vec<_>>();
+            self.client_requests_post_commit(requests);
         }
 
         // TODO(xp): does this update too frequently?
@@ -172,35 +236,56 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    fn update_leader_metrics(&mut self, target: NodeId, matched: Option<LogId>) {
+    fn update_leader_metrics(&mut self, target: NID, matched: Option<LogId<NID>>) {
         tracing::debug!(%target, ?matched, "update_leader_metrics");
-        self.leader_metrics.replication.insert(target, ReplicationMetrics { matched });
+        self.leader_metrics.replication.entry(target).or_default().matched = matched;
+    }
+
+    /// Update the replication metrics of a target other than the matched log id.
+    ///
+    /// They are reported only if `f` returns true, i.e., there is a change worth publishing.
+    /// Events from a replication stream that is already removed are ignored.
+    #[tracing::instrument(level = "trace", skip(self, f))]
+    fn update_replication_metrics(&mut self, target: NID, f: impl FnOnce(&mut ReplicationMetrics<NID>) -> bool) {
+        let matched = match self.nodes.get(&target) {
+            Some(state) => state.matched.clone(),
+            None => return,
+        };
+
+        let metrics = self.leader_metrics.replication.entry(target).or_insert_with(|| ReplicationMetrics {
+            matched,
+            ..Default::default()
+        });
+
+        if f(metrics) {
+            self.leader_report_metrics();
+        }
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    fn calc_commit_log_id(&self) -> Option<LogId> {
+    fn calc_commit_log_id(&self) -> Option<LogId<NID>> {
         let repl_indexes = self.get_match_log_ids();
 
-        let committed = self.core.effective_membership.membership.greatest_majority_value(&repl_indexes);
+        let committed = quorum::greatest_quorum_value(&self.core.quorum_set(), &repl_indexes);
 
         // TODO(xp): remove this line
-        std::cmp::max(committed.cloned(), self.core.committed)
+        std::cmp::max(committed.cloned(), self.core.committed.clone())
 
         // *committed.unwrap_or(&self.core.committed)
     }
 
     /// Collect indexes of the greatest matching log on every replica(include the leader itself)
-    fn get_match_log_ids(&self) -> BTreeMap<NodeId, LogId> {
+    fn get_match_log_ids(&self) -> BTreeMap<NID, LogId<NID>> {
         let node_ids = self.core.effective_membership.membership.all_members();
 
         let mut res = BTreeMap::new();
 
         for id in node_ids.iter() {
             let matched = if *id == self.core.id {
-                self.core.last_log_id
+                self.core.last_log_id.clone()
             } else {
                 let repl_state = self.nodes.get(id);
-                repl_state.map(|x| x.matched).unwrap_or_default()
+                repl_state.map(|x| x.matched.clone()).unwrap_or_default()
             };
 
             // Mismatching term can not prevent other replica with higher term log from being chosen as leader,
@@ -208,7 +293,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             // Thus it is not considered as committed.
             if let Some(log_id) = matched {
                 if log_id.leader_id == self.core.vote.leader_id() {
-                    res.insert(*id, log_id);
+                    res.insert(id.clone(), log_id);
                 }
             }
         }
@@ -222,13 +307,11 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
     #[tracing::instrument(level = "debug", skip(self, tx))]
     async fn handle_needs_snapshot(
         &mut self,
-        must_include: Option<LogId>,
-        tx: oneshot::Sender<Snapshot<S::SnapshotData>>,
-    ) -> Result<(), StorageError> {
-        // Ensure snapshotting is configured, else do nothing.
-        let threshold = match &self.core.config.snapshot_policy {
-            SnapshotPolicy::LogsSinceLast(threshold) => *threshold,
-        };
+        must_include: Option<LogId<NID>>,
+        tx: oneshot::Sender<Snapshot<S::SnapshotData, NID>>,
+    ) -> Result<(), StorageError<NID>> {
+        // Without a number of logs in the policy, any snapshot including `must_include` is good enough.
+        let threshold = self.core.config.snapshot_policy.logs_threshold();
 
         // Check for existence of current snapshot.
         let current_snapshot_opt = self.core.storage.get_current_snapshot().await?;
@@ -242,11 +325,16 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
             } else {
                 // If snapshot exists, ensure its distance from the leader's last log index is <= half
                 // of the configured snapshot threshold, else create a new snapshot.
-                if snapshot_is_within_half_of_threshold(
-                    &snapshot.meta.last_log_id.index,
-                    &self.core.last_log_id.unwrap_or_default().index,
-                    &threshold,
-                ) {
+                let recent_enough = match threshold {
+                    Some(threshold) => snapshot_is_within_half_of_threshold(
+                        &snapshot.meta.last_log_id.index,
+                        &self.core.last_log_id.clone().unwrap_or_default().index,
+                        &threshold,
+                    ),
+                    None => true,
+                };
+
+                if recent_enough {
                     let _ = tx.send(snapshot);
                     return Ok(());
                 }
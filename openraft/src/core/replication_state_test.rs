@@ -12,7 +12,10 @@ fn test_is_line_rate() -> anyhow::Result<()> {
         ..Default::default()
     };
 
-    assert!(is_matched_upto_date(&None, &None, &cfg(0)), "matched, threshold=0");
+    assert!(
+        is_matched_upto_date::<u64>(&None, &None, &cfg(0)),
+        "matched, threshold=0"
+    );
     assert!(
         is_matched_upto_date(
             &None,
@@ -0,0 +1,57 @@
+use crate::core::RaftCore;
+use crate::core::State;
+use crate::error::TimeoutNowError;
+use crate::raft::TimeoutNowRequest;
+use crate::raft::TimeoutNowResponse;
+use crate::summary::MessageSummary;
+use crate::AppData;
+use crate::AppDataResponse;
+use crate::NodeId;
+use crate::NodeInfo;
+use crate::RaftNetwork;
+use crate::RaftStorage;
+
+impl<
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > RaftCore<D, R, N, S, NID, NI>
+{
+    /// An RPC invoked by the leader to transfer leadership to this node.
+    ///
+    /// The leader sends it only when this node has caught up with the leader's last log.
+    /// This node then starts an election at once, instead of waiting for an election timeout.
+    #[tracing::instrument(level = "debug", skip(self, req), fields(req=%req.summary()))]
+    pub(super) fn handle_timeout_now_request(
+        &mut self,
+        req: TimeoutNowRequest<NID>,
+    ) -> Result<TimeoutNowResponse<NID>, TimeoutNowError<NID>> {
+        // Only the leader this node is following is allowed to hand over its leadership.
+        if req.vote != self.vote {
+            tracing::debug!(%req.vote, ?self.vote, "TimeoutNow RPC vote does not match current vote, ignore");
+            return Ok(TimeoutNowResponse {
+                vote: self.vote.clone(),
+            });
+        }
+
+        // A learner never campaigns; a candidate or leader has nothing to take over.
+        if !self.target_state.is_follower() {
+            tracing::debug!(?self.target_state, "TimeoutNow RPC received by a non-follower, ignore");
+            return Ok(TimeoutNowResponse {
+                vote: self.vote.clone(),
+            });
+        }
+
+        tracing::info!(%req.vote, "leadership is transferred to this node, start election at once");
+
+        self.timeout_now = true;
+        self.set_target_state(State::Candidate);
+
+        Ok(TimeoutNowResponse {
+            vote: self.vote.clone(),
+        })
+    }
+}
@@ -6,28 +6,41 @@ use crate::core::CandidateState;
 use crate::core::RaftCore;
 use crate::core::State;
 use crate::error::VoteError;
+use crate::membership::quorum::QuorumSet;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::summary::MessageSummary;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::NodeId;
+use crate::NodeInfo;
 use crate::RaftNetwork;
 use crate::RaftStorage;
 use crate::StorageError;
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> RaftCore<D, R, N, S> {
+impl<
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > RaftCore<D, R, N, S, NID, NI>
+{
     /// An RPC invoked by candidates to gather votes (§5.2).
     ///
     /// See `receiver implementation: RequestVote RPC` in raft-essentials.md in this repo.
     #[tracing::instrument(level = "debug", skip(self, req), fields(req=%req.summary()))]
-    pub(super) async fn handle_vote_request(&mut self, req: VoteRequest) -> Result<VoteResponse, VoteError> {
+    pub(super) async fn handle_vote_request(
+        &mut self,
+        req: VoteRequest<NID>,
+    ) -> Result<VoteResponse<NID>, VoteError<NID>> {
         tracing::debug!(
             %req.vote,
             ?self.vote,
             "start handle_vote_request"
         );
-        let last_log_id = self.last_log_id;
+        let last_log_id = self.last_log_id.clone();
 
         if req.vote < self.vote {
             tracing::debug!(
@@ -36,14 +49,15 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 "RequestVote RPC term is less than current term"
             );
             return Ok(VoteResponse {
-                vote: self.vote,
+                vote: self.vote.clone(),
                 vote_granted: false,
                 last_log_id,
             });
         }
 
         // Do not respond to the request if we've received a heartbeat within the election timeout minimum.
-        if let Some(inst) = &self.last_heartbeat {
+        // Unless the candidate is campaigning because the leader transferred its leadership to it.
+        if let Some(inst) = self.last_heartbeat.as_ref().filter(|_| !req.leader_transfer) {
             let now = Instant::now();
             let delta = now.duration_since(*inst);
             if self.config.election_timeout_min >= (delta.as_millis() as u64) {
@@ -53,7 +67,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                     "rejecting vote request received within election timeout minimum"
                 );
                 return Ok(VoteResponse {
-                    vote: self.vote,
+                    vote: self.vote.clone(),
                     vote_granted: false,
                     last_log_id,
                 });
@@ -68,14 +82,28 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 "rejecting vote request as candidate's log is not up-to-date"
             );
             return Ok(VoteResponse {
-                vote: self.vote,
+                vote: self.vote.clone(),
                 vote_granted: false,
                 last_log_id,
             });
         }
 
+        // A pre-vote only tells the candidate whether it could win. Nothing is changed on this node.
+        if req.pre_vote {
+            // A leader always believes there is a leader.
+            let vote_granted = !self.target_state.is_leader();
+
+            tracing::debug!(%req.vote, vote_granted, "handled pre-vote request");
+
+            return Ok(VoteResponse {
+                vote: self.vote.clone(),
+                vote_granted,
+                last_log_id,
+            });
+        }
+
         self.update_next_election_timeout(false);
-        self.vote = req.vote;
+        self.vote = req.vote.clone();
         self.save_vote().await?;
 
         self.set_target_state(State::Follower);
@@ -83,18 +111,31 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         tracing::debug!(%req.vote, "voted for candidate");
 
         Ok(VoteResponse {
-            vote: self.vote,
+            vote: self.vote.clone(),
             vote_granted: true,
             last_log_id,
         })
     }
 }
 
-impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> CandidateState<'a, D, R, N, S> {
+impl<
+        'a,
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > CandidateState<'a, D, R, N, S, NID, NI>
+{
     /// Handle response from a vote request sent to a peer.
     #[tracing::instrument(level = "debug", skip(self, res))]
-    pub(super) async fn handle_vote_response(&mut self, res: VoteResponse, target: NodeId) -> Result<(), StorageError> {
-        tracing::debug!(res=?res, target, "recv vote response");
+    pub(super) async fn handle_vote_response(
+        &mut self,
+        res: VoteResponse<NID>,
+        target: NID,
+    ) -> Result<(), StorageError<NID>> {
+        tracing::debug!(res=?res, %target, "recv vote response");
 
         // If peer's vote is greater than current vote, revert to follower state.
 
@@ -126,7 +167,7 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         if res.vote_granted {
             self.granted.insert(target);
 
-            if self.core.effective_membership.membership.is_majority(&self.granted) {
+            if self.core.quorum_set().is_quorum(&self.granted) {
                 tracing::debug!("transitioning to leader state as minimum number of votes have been received");
                 self.core.set_target_state(State::Leader);
                 return Ok(());
@@ -137,28 +178,67 @@ impl<'a, D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>
         Ok(())
     }
 
+    /// Handle response from a pre-vote request sent to a peer.
+    ///
+    /// Return true if a quorum would grant `vote` in a real election.
+    /// A greater vote in the response is handled the same way as in a real election: this node takes the greater vote
+    /// and reverts to follower. Otherwise it would keep pre-voting with a vote every peer rejects.
+    #[tracing::instrument(level = "debug", skip(self, res))]
+    pub(super) async fn handle_pre_vote_response(
+        &mut self,
+        res: VoteResponse<NID>,
+        target: NID,
+    ) -> Result<bool, StorageError<NID>> {
+        tracing::debug!(res=?res, %target, "recv pre-vote response");
+
+        if res.vote > self.core.vote {
+            tracing::debug!(
+                id = %self.core.id,
+                %res.vote,
+                %self.core.vote,
+                "reverting to follower state due to greater vote observed in pre-vote response");
+
+            self.core.set_target_state(State::Follower);
+            self.core.vote = res.vote;
+            self.core.save_vote().await?;
+
+            return Ok(false);
+        }
+
+        if res.vote_granted {
+            self.granted.insert(target);
+        }
+
+        Ok(self.core.quorum_set().is_quorum(&self.granted))
+    }
+
     /// Spawn parallel vote requests to all cluster members.
-    #[tracing::instrument(level = "trace", skip(self))]
-    pub(super) fn spawn_parallel_vote_requests(&self) -> mpsc::Receiver<(VoteResponse, NodeId)> {
+    #[tracing::instrument(level = "trace", skip(self, rpc), fields(rpc=%rpc.summary()))]
+    pub(super) fn spawn_parallel_vote_requests(
+        &self,
+        rpc: &VoteRequest<NID>,
+    ) -> mpsc::Receiver<(VoteResponse<NID>, NID)> {
         let all_nodes = self.core.effective_membership.membership.all_members().clone();
         let (tx, rx) = mpsc::channel(all_nodes.len());
 
         for member in all_nodes.into_iter().filter(|member| member != &self.core.id) {
-            let rpc = VoteRequest::new(self.core.vote, self.core.last_log_id);
+            let rpc = rpc.clone();
+            let target_node = self.core.effective_membership.membership.get_node(&member).cloned();
 
             let (network, tx_inner) = (self.core.network.clone(), tx.clone());
+            let span = tracing::debug_span!("send_vote_req", target = %member);
             let _ = tokio::spawn(
                 async move {
-                    let res = network.send_vote(member, rpc).await;
+                    let res = network.send_vote(member.clone(), target_node.as_ref(), rpc).await;
 
                     match res {
                         Ok(vote_resp) => {
                             let _ = tx_inner.send((vote_resp, member)).await;
                         }
-                        Err(err) => tracing::error!({error=%err, target=member}, "while requesting vote"),
+                        Err(err) => tracing::error!({error=%err, target=%member}, "while requesting vote"),
                     }
                 }
-                .instrument(tracing::debug_span!("send_vote_req", target = member)),
+                .instrument(span),
             );
         }
         rx
@@ -11,6 +11,9 @@ use crate::AppDataResponse;
 use crate::DefensiveError;
 use crate::ErrorSubject;
 use crate::LogId;
+use crate::Node;
+use crate::NodeId;
+use crate::NodeInfo;
 use crate::RaftStorage;
 use crate::StorageError;
 use crate::Violation;
@@ -19,12 +22,14 @@ use crate::Wrapper;
 
 /// Defines methods of defensive checks for RaftStorage.
 #[async_trait]
-pub trait DefensiveCheck<D, R, T>
+pub trait DefensiveCheck<D, R, T, NID = u64, NI = Node>
 where
     D: AppData,
     R: AppDataResponse,
-    T: RaftStorage<D, R>,
-    Self: Wrapper<D, R, T>,
+    T: RaftStorage<D, R, NID, NI>,
+    NID: NodeId,
+    NI: NodeInfo,
+    Self: Wrapper<D, R, T, NID, NI>,
 {
     /// Enable or disable defensive check when calling storage APIs.
     fn set_defensive(&self, v: bool);
@@ -33,7 +38,7 @@ where
 
     /// Ensure that logs that have greater index than last_applied should have greater log_id.
     /// Invariant must hold: `log.log_id.index > last_applied.index` implies `log.log_id > last_applied`.
-    async fn defensive_no_dirty_log(&self) -> Result<(), StorageError> {
+    async fn defensive_no_dirty_log(&self) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -43,7 +48,7 @@ where
 
         if last_log_id.index() > last_applied.index() && last_log_id < last_applied {
             return Err(
-                DefensiveError::new(ErrorSubject::Log(last_log_id.unwrap()), Violation::DirtyLog {
+                DefensiveError::new(ErrorSubject::Log(last_log_id.clone().unwrap()), Violation::DirtyLog {
                     higher_index_log_id: last_log_id.unwrap(),
                     lower_index_log_id: last_applied.unwrap(),
                 })
@@ -56,7 +61,7 @@ where
 
     /// Ensure that current_term must increment for every update, and for every term there could be only one value for
     /// voted_for.
-    async fn defensive_incremental_vote(&self, vote: &Vote) -> Result<(), StorageError> {
+    async fn defensive_incremental_vote(&self, vote: &Vote<NID>) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -76,12 +81,16 @@ where
         if vote >= &curr {
             Ok(())
         } else {
-            Err(DefensiveError::new(ErrorSubject::Vote, Violation::NonIncrementalVote { curr, to: *vote }).into())
+            Err(DefensiveError::new(ErrorSubject::Vote, Violation::NonIncrementalVote {
+                curr,
+                to: vote.clone(),
+            })
+            .into())
         }
     }
 
     /// The log entries fed into a store must be consecutive otherwise it is a bug.
-    async fn defensive_consecutive_input(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn defensive_consecutive_input(&self, entries: &[&Entry<D, NID, NI>]) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -90,18 +99,18 @@ where
             return Ok(());
         }
 
-        let mut prev_log_id = entries[0].log_id;
+        let mut prev_log_id = entries[0].log_id.clone();
 
         for e in entries.iter().skip(1) {
             if e.log_id.index != prev_log_id.index + 1 {
                 return Err(DefensiveError::new(ErrorSubject::Logs, Violation::LogsNonConsecutive {
                     prev: Some(prev_log_id),
-                    next: e.log_id,
+                    next: e.log_id.clone(),
                 })
                 .into());
             }
 
-            prev_log_id = e.log_id;
+            prev_log_id = e.log_id.clone();
         }
 
         Ok(())
@@ -110,7 +119,7 @@ where
     /// Trying to feed in emtpy entries slice is an inappropriate action.
     ///
     /// The impl has to avoid this otherwise it may be a bug.
-    async fn defensive_nonempty_input(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn defensive_nonempty_input(&self, entries: &[&Entry<D, NID, NI>]) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -123,17 +132,20 @@ where
     }
 
     /// The entries to append has to be last_log_id.index + 1
-    async fn defensive_append_log_index_is_last_plus_one(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn defensive_append_log_index_is_last_plus_one(
+        &self,
+        entries: &[&Entry<D, NID, NI>],
+    ) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
 
         let last_id = self.inner().get_log_state().await?.last_log_id;
 
-        let first_id = entries[0].log_id;
+        let first_id = entries[0].log_id.clone();
         if last_id.next_index() != first_id.index {
             return Err(
-                DefensiveError::new(ErrorSubject::Log(first_id), Violation::LogsNonConsecutive {
+                DefensiveError::new(ErrorSubject::Log(first_id.clone()), Violation::LogsNonConsecutive {
                     prev: last_id,
                     next: first_id,
                 })
@@ -145,19 +157,19 @@ where
     }
 
     /// The entries to append has to be greater than any known log ids
-    async fn defensive_append_log_id_gt_last(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn defensive_append_log_id_gt_last(&self, entries: &[&Entry<D, NID, NI>]) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
 
         let last_id = self.inner().get_log_state().await?.last_log_id;
 
-        let first_id = entries[0].log_id;
+        let first_id = entries[0].log_id.clone();
         // TODO(xp): test first eq last.
         // TODO(xp): test last == None is ok
-        if last_id.is_some() && Some(first_id) <= last_id {
+        if last_id.is_some() && Some(first_id.clone()) <= last_id {
             return Err(
-                DefensiveError::new(ErrorSubject::Log(first_id), Violation::LogsNonConsecutive {
+                DefensiveError::new(ErrorSubject::Log(first_id.clone()), Violation::LogsNonConsecutive {
                     prev: last_id,
                     next: first_id,
                 })
@@ -168,11 +180,11 @@ where
         Ok(())
     }
 
-    async fn defensive_purge_applied_le_last_applied(&self, upto: LogId) -> Result<(), StorageError> {
+    async fn defensive_purge_applied_le_last_applied(&self, upto: LogId<NID>) -> Result<(), StorageError<NID>> {
         let (last_applied, _) = self.inner().last_applied_state().await?;
         if Some(upto.index) > last_applied.index() {
             return Err(
-                DefensiveError::new(ErrorSubject::Log(upto), Violation::PurgeNonApplied {
+                DefensiveError::new(ErrorSubject::Log(upto.clone()), Violation::PurgeNonApplied {
                     last_applied,
                     purge_upto: upto,
                 })
@@ -182,11 +194,11 @@ where
         Ok(())
     }
 
-    async fn defensive_delete_conflict_gt_last_applied(&self, since: LogId) -> Result<(), StorageError> {
+    async fn defensive_delete_conflict_gt_last_applied(&self, since: LogId<NID>) -> Result<(), StorageError<NID>> {
         let (last_applied, _) = self.inner().last_applied_state().await?;
         if Some(since.index) <= last_applied.index() {
             return Err(
-                DefensiveError::new(ErrorSubject::Log(since), Violation::AppliedWontConflict {
+                DefensiveError::new(ErrorSubject::Log(since.clone()), Violation::AppliedWontConflict {
                     last_applied,
                     first_conflict_log_id: since,
                 })
@@ -197,17 +209,20 @@ where
     }
 
     /// The entries to apply to state machien has to be last_applied_log_id.index + 1
-    async fn defensive_apply_index_is_last_applied_plus_one(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn defensive_apply_index_is_last_applied_plus_one(
+        &self,
+        entries: &[&Entry<D, NID, NI>],
+    ) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
 
         let (last_id, _) = self.inner().last_applied_state().await?;
 
-        let first_id = entries[0].log_id;
+        let first_id = entries[0].log_id.clone();
         if last_id.next_index() != first_id.index {
             return Err(
-                DefensiveError::new(ErrorSubject::Apply(first_id), Violation::ApplyNonConsecutive {
+                DefensiveError::new(ErrorSubject::Apply(first_id.clone()), Violation::ApplyNonConsecutive {
                     prev: last_id,
                     next: first_id,
                 })
@@ -222,7 +237,7 @@ where
     async fn defensive_nonempty_range<RB: RangeBounds<u64> + Clone + Debug + Send>(
         &self,
         range: RB,
-    ) -> Result<(), StorageError> {
+    ) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -254,7 +269,7 @@ where
     async fn defensive_half_open_range<RB: RangeBounds<u64> + Clone + Debug + Send>(
         &self,
         range: RB,
-    ) -> Result<(), StorageError> {
+    ) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -278,8 +293,8 @@ where
     async fn defensive_range_hits_logs<RB: RangeBounds<u64> + Debug + Send>(
         &self,
         range: RB,
-        logs: &[Entry<D>],
-    ) -> Result<(), StorageError> {
+        logs: &[Entry<D, NID, NI>],
+    ) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
@@ -289,18 +304,18 @@ where
     }
 
     /// The log id of the entries to apply has to be greater than the last known one.
-    async fn defensive_apply_log_id_gt_last(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn defensive_apply_log_id_gt_last(&self, entries: &[&Entry<D, NID, NI>]) -> Result<(), StorageError<NID>> {
         if !self.is_defensive() {
             return Ok(());
         }
 
         let (last_id, _) = self.inner().last_applied_state().await?;
 
-        let first_id = entries[0].log_id;
+        let first_id = entries[0].log_id.clone();
         // TODO(xp): test first eq last
-        if Some(first_id) <= last_id {
+        if Some(first_id.clone()) <= last_id {
             return Err(
-                DefensiveError::new(ErrorSubject::Apply(first_id), Violation::ApplyNonConsecutive {
+                DefensiveError::new(ErrorSubject::Apply(first_id.clone()), Violation::ApplyNonConsecutive {
                     prev: last_id,
                     next: first_id,
                 })
@@ -310,12 +325,34 @@ where
 
         Ok(())
     }
+
+    /// The log id to record as the last applied has to be greater than the last applied log id.
+    async fn defensive_skip_apply_gt_last_applied(&self, last_applied: &LogId<NID>) -> Result<(), StorageError<NID>> {
+        if !self.is_defensive() {
+            return Ok(());
+        }
+
+        let (last_id, _) = self.inner().last_applied_state().await?;
+
+        if Some(last_applied.clone()) <= last_id {
+            return Err(DefensiveError::new(
+                ErrorSubject::Apply(last_applied.clone()),
+                Violation::ApplyNonConsecutive {
+                    prev: last_id,
+                    next: last_applied.clone(),
+                },
+            )
+            .into());
+        }
+
+        Ok(())
+    }
 }
 
-pub fn check_range_matches_entries<D: AppData, RB: RangeBounds<u64> + Debug + Send>(
+pub fn check_range_matches_entries<D: AppData, RB: RangeBounds<u64> + Debug + Send, NID: NodeId, NI: NodeInfo>(
     range: RB,
-    entries: &[Entry<D>],
-) -> Result<(), StorageError> {
+    entries: &[Entry<D, NID, NI>],
+) -> Result<(), StorageError<NID>> {
     let want_first = match range.start_bound() {
         Bound::Included(i) => Some(*i),
         Bound::Excluded(i) => Some(*i + 1),
@@ -9,18 +9,23 @@ use anyerror::AnyError;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::raft_types::SnapshotId;
 use crate::raft_types::SnapshotSegmentId;
 use crate::LogId;
+use crate::Node;
 use crate::NodeId;
+use crate::NodeInfo;
 use crate::RPCTypes;
+use crate::SnapshotCompression;
 use crate::StorageError;
 use crate::Vote;
 
 /// Fatal is unrecoverable and shuts down raft at once.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
-pub enum Fatal {
+#[serde(bound = "")]
+pub enum Fatal<NID: NodeId = u64> {
     #[error(transparent)]
-    StorageError(#[from] StorageError),
+    StorageError(#[from] StorageError<NID>),
 
     #[error("raft stopped")]
     Stopped,
@@ -30,16 +35,16 @@ pub enum Fatal {
 ///
 /// Fatal will shutdown the raft and needs to be dealt separately,
 /// such as StorageError.
-pub trait ExtractFatal
+pub trait ExtractFatal<NID: NodeId>
 where Self: Sized
 {
-    fn extract_fatal(self) -> Result<Self, Fatal>;
+    fn extract_fatal(self) -> Result<Self, Fatal<NID>>;
 }
 
-impl<T, E> ExtractFatal for Result<T, E>
-where E: TryInto<Fatal> + Clone
+impl<NID: NodeId, T, E> ExtractFatal<NID> for Result<T, E>
+where E: TryInto<Fatal<NID>> + Clone
 {
-    fn extract_fatal(self) -> Result<Self, Fatal> {
+    fn extract_fatal(self) -> Result<Self, Fatal<NID>> {
         if let Err(e) = &self {
             let fatal = e.clone().try_into();
             if let Ok(f) = fatal {
@@ -51,126 +56,248 @@ where E: TryInto<Fatal> + Clone
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, thiserror::Error, derive_more::TryInto)]
-pub enum AppendEntriesError {
+#[serde(bound = "")]
+pub enum AppendEntriesError<NID: NodeId = u64> {
     #[error(transparent)]
-    Fatal(#[from] Fatal),
+    Fatal(#[from] Fatal<NID>),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, thiserror::Error, derive_more::TryInto)]
-pub enum VoteError {
+#[serde(bound = "")]
+pub enum VoteError<NID: NodeId = u64> {
     #[error(transparent)]
-    Fatal(#[from] Fatal),
+    Fatal(#[from] Fatal<NID>),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, thiserror::Error, derive_more::TryInto)]
-pub enum InstallSnapshotError {
+#[serde(bound = "")]
+pub enum TimeoutNowError<NID: NodeId = u64> {
+    #[error(transparent)]
+    Fatal(#[from] Fatal<NID>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, thiserror::Error, derive_more::TryInto)]
+#[serde(bound = "")]
+pub enum InstallSnapshotError<NID: NodeId = u64> {
     #[error(transparent)]
     SnapshotMismatch(#[from] SnapshotMismatch),
 
     #[error(transparent)]
-    Fatal(#[from] Fatal),
+    ChecksumMismatch(#[from] SnapshotChecksumMismatch),
+
+    #[error(transparent)]
+    Decompress(#[from] SnapshotDecompressError),
+
+    #[error(transparent)]
+    Fatal(#[from] Fatal<NID>),
 }
 
 /// An error related to a client read request.
 #[derive(Debug, Clone, Serialize, Deserialize, thiserror::Error, derive_more::TryInto)]
-pub enum ClientReadError {
+#[serde(bound = "")]
+pub enum ClientReadError<NID: NodeId = u64, NI: NodeInfo = Node> {
     #[error(transparent)]
-    ForwardToLeader(#[from] ForwardToLeader),
+    ForwardToLeader(#[from] ForwardToLeader<NID, NI>),
 
     #[error(transparent)]
-    QuorumNotEnough(#[from] QuorumNotEnough),
+    QuorumNotEnough(#[from] QuorumNotEnough<NID>),
 
+    /// Timeout when a follower or learner asks the leader for a read index.
     #[error(transparent)]
-    Fatal(#[from] Fatal),
+    Timeout(#[from] Timeout<NID>),
+
+    /// Network error when a follower or learner asks the leader for a read index.
+    #[error(transparent)]
+    Network(#[from] NetworkError),
+
+    #[error(transparent)]
+    Fatal(#[from] Fatal<NID>),
 }
 
 /// An error related to a client write request.
 #[derive(Debug, Clone, Serialize, Deserialize, thiserror::Error, derive_more::TryInto)]
-pub enum ClientWriteError {
+#[serde(bound = "")]
+pub enum ClientWriteError<NID: NodeId = u64, NI: NodeInfo = Node> {
     #[error(transparent)]
-    ForwardToLeader(#[from] ForwardToLeader),
+    ForwardToLeader(#[from] ForwardToLeader<NID, NI>),
 
     /// When writing a change-membership entry.
     #[error(transparent)]
-    ChangeMembershipError(#[from] ChangeMembershipError),
+    ChangeMembershipError(#[from] ChangeMembershipError<NID>),
 
     #[error(transparent)]
-    Fatal(#[from] Fatal),
+    Fatal(#[from] Fatal<NID>),
 }
 
 /// The set of errors which may take place when requesting to propose a config change.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
-pub enum ChangeMembershipError {
+#[serde(bound = "")]
+pub enum ChangeMembershipError<NID: NodeId = u64> {
     #[error(transparent)]
-    InProgress(#[from] InProgress),
+    InProgress(#[from] InProgress<NID>),
 
     #[error(transparent)]
     EmptyMembership(#[from] EmptyMembership),
 
     // TODO(xp): 111 test it
     #[error(transparent)]
-    LearnerNotFound(#[from] LearnerNotFound),
+    LearnerNotFound(#[from] LearnerNotFound<NID>),
 
     #[error(transparent)]
-    LearnerIsLagging(#[from] LearnerIsLagging),
+    LearnerIsLagging(#[from] LearnerIsLagging<NID>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error, derive_more::TryInto)]
-pub enum AddLearnerError {
+#[serde(bound = "")]
+pub enum AddLearnerError<NID: NodeId = u64, NI: NodeInfo = Node> {
     #[error(transparent)]
-    ForwardToLeader(#[from] ForwardToLeader),
+    ForwardToLeader(#[from] ForwardToLeader<NID, NI>),
 
     #[error("node {0} is already a learner")]
-    Exists(NodeId),
+    #[try_into(ignore)]
+    Exists(NID),
+
+    #[error(transparent)]
+    Fatal(#[from] Fatal<NID>),
+}
+
+/// An error related to promoting a learner to a voter once it catches up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error, derive_more::TryInto)]
+#[serde(bound = "")]
+pub enum PromoteLearnerError<NID: NodeId = u64, NI: NodeInfo = Node> {
+    #[error(transparent)]
+    ForwardToLeader(#[from] ForwardToLeader<NID, NI>),
+
+    #[error(transparent)]
+    LearnerNotFound(#[from] LearnerNotFound<NID>),
+
+    #[error(transparent)]
+    Fatal(#[from] Fatal<NID>),
+}
+
+/// An error related to transferring leadership to another node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error, derive_more::TryInto)]
+#[serde(bound = "")]
+pub enum TransferLeaderError<NID: NodeId = u64, NI: NodeInfo = Node> {
+    #[error(transparent)]
+    ForwardToLeader(#[from] ForwardToLeader<NID, NI>),
+
+    #[error(transparent)]
+    NotVoter(#[from] NotVoter<NID>),
+
+    #[error(transparent)]
+    IsWitness(#[from] IsWitness<NID>),
+
+    #[error(transparent)]
+    InProgress(#[from] TransferLeaderInProgress<NID>),
+
+    #[error(transparent)]
+    Timeout(#[from] TransferLeaderTimeout<NID>),
+
+    #[error(transparent)]
+    Fatal(#[from] Fatal<NID>),
+}
+
+/// An error related to building a snapshot on demand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error, derive_more::TryInto)]
+#[serde(bound = "")]
+pub enum TriggerSnapshotError<NID: NodeId = u64> {
+    /// A snapshot from the leader is being installed.
+    #[error("a snapshot from the leader is being installed")]
+    InstallingSnapshot,
+
+    #[error("no applied log to build a snapshot with")]
+    NothingApplied,
+
+    /// Building the snapshot failed or is aborted. The error is logged by the node.
+    #[error("building snapshot failed or is aborted")]
+    BuildFailed,
 
     #[error(transparent)]
-    Fatal(#[from] Fatal),
+    Fatal(#[from] Fatal<NID>),
+}
+
+/// An error related to purging logs on demand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error, derive_more::TryInto)]
+#[serde(bound = "")]
+pub enum PurgeLogError<NID: NodeId = u64> {
+    #[error(transparent)]
+    NotApplied(#[from] NotApplied<NID>),
+
+    #[error(transparent)]
+    Fatal(#[from] Fatal<NID>),
 }
 
 /// The set of errors which may take place when initializing a pristine Raft node.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
-pub enum InitializeError {
+#[serde(bound = "")]
+pub enum InitializeError<NID: NodeId = u64> {
     /// The requested action is not allowed due to the Raft node's current state.
     #[error("the requested action is not allowed due to the Raft node's current state")]
     NotAllowed,
 
     #[error(transparent)]
-    Fatal(#[from] Fatal),
+    Fatal(#[from] Fatal<NID>),
 }
 
-impl From<StorageError> for AppendEntriesError {
-    fn from(s: StorageError) -> Self {
-        let f: Fatal = s.into();
+impl<NID: NodeId> From<StorageError<NID>> for AppendEntriesError<NID> {
+    fn from(s: StorageError<NID>) -> Self {
+        let f: Fatal<NID> = s.into();
         f.into()
     }
 }
-impl From<StorageError> for VoteError {
-    fn from(s: StorageError) -> Self {
-        let f: Fatal = s.into();
+impl<NID: NodeId> From<StorageError<NID>> for VoteError<NID> {
+    fn from(s: StorageError<NID>) -> Self {
+        let f: Fatal<NID> = s.into();
         f.into()
     }
 }
-impl From<StorageError> for InstallSnapshotError {
-    fn from(s: StorageError) -> Self {
-        let f: Fatal = s.into();
+impl<NID: NodeId> From<StorageError<NID>> for TimeoutNowError<NID> {
+    fn from(s: StorageError<NID>) -> Self {
+        let f: Fatal<NID> = s.into();
         f.into()
     }
 }
-impl From<StorageError> for ClientReadError {
-    fn from(s: StorageError) -> Self {
-        let f: Fatal = s.into();
+impl<NID: NodeId> From<StorageError<NID>> for InstallSnapshotError<NID> {
+    fn from(s: StorageError<NID>) -> Self {
+        let f: Fatal<NID> = s.into();
         f.into()
     }
 }
-impl From<StorageError> for InitializeError {
-    fn from(s: StorageError) -> Self {
-        let f: Fatal = s.into();
+impl<NID: NodeId, NI: NodeInfo> From<StorageError<NID>> for ClientReadError<NID, NI> {
+    fn from(s: StorageError<NID>) -> Self {
+        let f: Fatal<NID> = s.into();
         f.into()
     }
 }
-impl From<StorageError> for AddLearnerError {
-    fn from(s: StorageError) -> Self {
-        let f: Fatal = s.into();
+impl<NID: NodeId> From<StorageError<NID>> for InitializeError<NID> {
+    fn from(s: StorageError<NID>) -> Self {
+        let f: Fatal<NID> = s.into();
+        f.into()
+    }
+}
+impl<NID: NodeId, NI: NodeInfo> From<StorageError<NID>> for AddLearnerError<NID, NI> {
+    fn from(s: StorageError<NID>) -> Self {
+        let f: Fatal<NID> = s.into();
+        f.into()
+    }
+}
+impl<NID: NodeId, NI: NodeInfo> From<StorageError<NID>> for TransferLeaderError<NID, NI> {
+    fn from(s: StorageError<NID>) -> Self {
+        let f: Fatal<NID> = s.into();
+        f.into()
+    }
+}
+
+impl<NID: NodeId> From<StorageError<NID>> for TriggerSnapshotError<NID> {
+    fn from(s: StorageError<NID>) -> Self {
+        let f: Fatal<NID> = s.into();
+        f.into()
+    }
+}
+impl<NID: NodeId> From<StorageError<NID>> for PurgeLogError<NID> {
+    fn from(s: StorageError<NID>) -> Self {
+        let f: Fatal<NID> = s.into();
         f.into()
     }
 }
@@ -178,15 +305,15 @@ impl From<StorageError> for AddLearnerError {
 /// Error variants related to the Replication.
 #[derive(Debug, thiserror::Error)]
 #[allow(clippy::large_enum_variant)]
-pub enum ReplicationError {
+pub enum ReplicationError<NID: NodeId = u64> {
     #[error(transparent)]
-    HigherVote(#[from] HigherVote),
+    HigherVote(#[from] HigherVote<NID>),
 
     #[error("Replication is closed")]
     Closed,
 
     #[error(transparent)]
-    LackEntry(#[from] LackEntry),
+    LackEntry(#[from] LackEntry<NID>),
 
     #[error(transparent)]
     CommittedAdvanceTooMany(#[from] CommittedAdvanceTooMany),
@@ -194,54 +321,57 @@ pub enum ReplicationError {
     // TODO(xp): two sub type: StorageError / TransportError
     // TODO(xp): a sub error for just send_append_entries()
     #[error(transparent)]
-    StorageError(#[from] StorageError),
+    StorageError(#[from] StorageError<NID>),
 
     #[error(transparent)]
-    NodeNotFound(#[from] NodeNotFound),
+    NodeNotFound(#[from] NodeNotFound<NID>),
 
     #[error(transparent)]
-    Timeout(#[from] Timeout),
+    Timeout(#[from] Timeout<NID>),
 
     #[error(transparent)]
     Network(#[from] NetworkError),
 
     #[error(transparent)]
-    RemoteError(#[from] RemoteError<AppendEntriesError>),
+    RemoteError(#[from] RemoteError<AppendEntriesError<NID>, NID>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
-pub enum RPCError<T: Error> {
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))]
+pub enum RPCError<T: Error, NID: NodeId = u64> {
     #[error(transparent)]
-    NodeNotFound(#[from] NodeNotFound),
+    NodeNotFound(#[from] NodeNotFound<NID>),
 
     #[error(transparent)]
-    Timeout(#[from] Timeout),
+    Timeout(#[from] Timeout<NID>),
 
     #[error(transparent)]
     Network(#[from] NetworkError),
 
     #[error(transparent)]
-    RemoteError(#[from] RemoteError<T>),
+    RemoteError(#[from] RemoteError<T, NID>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))]
 #[error("error occur on remote peer {target}: {source}")]
-pub struct RemoteError<T: std::error::Error> {
-    pub target: NodeId,
+pub struct RemoteError<T: std::error::Error, NID: NodeId = u64> {
+    pub target: NID,
     pub source: T,
 }
 
-impl<T: std::error::Error> RemoteError<T> {
-    pub fn new(target: NodeId, e: T) -> Self {
+impl<T: std::error::Error, NID: NodeId> RemoteError<T, NID> {
+    pub fn new(target: NID, e: T) -> Self {
         Self { target, source: e }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
 #[error("seen a higher vote: {higher} GT mine: {mine}")]
-pub struct HigherVote {
-    pub higher: Vote,
-    pub mine: Vote,
+pub struct HigherVote<NID: NodeId = u64> {
+    pub higher: Vote<NID>,
+    pub mine: Vote<NID>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
@@ -267,25 +397,31 @@ impl NetworkError {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
 #[error("timeout after {timeout:?} when {action} {id}->{target}")]
-pub struct Timeout {
+pub struct Timeout<NID: NodeId = u64> {
     pub action: RPCTypes,
-    pub id: NodeId,
-    pub target: NodeId,
+    pub id: NID,
+    pub target: NID,
     pub timeout: Duration,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
 #[error("store has no log at: {index:?}, last purged: {last_purged_log_id:?}")]
-pub struct LackEntry {
+pub struct LackEntry<NID: NodeId = u64> {
     pub index: Option<u64>,
-    pub last_purged_log_id: Option<LogId>,
+    pub last_purged_log_id: Option<LogId<NID>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
-#[error("has to forward request to: {leader_id:?}")]
-pub struct ForwardToLeader {
-    pub leader_id: Option<NodeId>,
+#[serde(bound = "")]
+#[error("has to forward request to: {leader_id:?}, {leader_node:?}")]
+pub struct ForwardToLeader<NID: NodeId = u64, NI: NodeInfo = Node> {
+    pub leader_id: Option<NID>,
+
+    /// The info of the leader, e.g., its address, if it is provided when the leader is added.
+    pub leader_node: Option<NI>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
@@ -295,41 +431,101 @@ pub struct SnapshotMismatch {
     pub got: SnapshotSegmentId,
 }
 
+/// The received snapshot data does not match the checksum sent along with the last chunk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[error("snapshot checksum mismatch, snapshot: {snapshot_id}, expect: {expect:08x}, got: {got:08x}")]
+pub struct SnapshotChecksumMismatch {
+    pub snapshot_id: SnapshotId,
+    pub expect: u32,
+    pub got: u32,
+}
+
+/// A snapshot chunk can not be decompressed, e.g., its compression is not enabled on the receiver.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[error("failed to decompress snapshot chunk {segment} with {compression}: {reason}")]
+pub struct SnapshotDecompressError {
+    pub segment: SnapshotSegmentId,
+    pub compression: SnapshotCompression,
+    pub reason: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
 #[error("not enough for a quorum, cluster: {cluster}, got: {got:?}")]
-pub struct QuorumNotEnough {
+pub struct QuorumNotEnough<NID: NodeId = u64> {
     pub cluster: String,
-    pub got: BTreeSet<NodeId>,
+    pub got: BTreeSet<NID>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
 #[error("the cluster is already undergoing a configuration change at log {membership_log_id}")]
-pub struct InProgress {
-    pub membership_log_id: LogId,
+pub struct InProgress<NID: NodeId = u64> {
+    pub membership_log_id: LogId<NID>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
 #[error("to add a member {node_id} first need to add it as learner")]
-pub struct LearnerNotFound {
-    pub node_id: NodeId,
+pub struct LearnerNotFound<NID: NodeId = u64> {
+    pub node_id: NID,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
 #[error("replication to learner {node_id} is lagging {distance}, matched: {matched:?}, can not add as member")]
-pub struct LearnerIsLagging {
-    pub node_id: NodeId,
-    pub matched: Option<LogId>,
+pub struct LearnerIsLagging<NID: NodeId = u64> {
+    pub node_id: NID,
+    pub matched: Option<LogId<NID>>,
     pub distance: u64,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
+#[error("node {node_id} is not a voter, can not transfer leadership to it")]
+pub struct NotVoter<NID: NodeId = u64> {
+    pub node_id: NID,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
+#[error("node {node_id} is a witness, can not transfer leadership to it")]
+pub struct IsWitness<NID: NodeId = u64> {
+    pub node_id: NID,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
+#[error("leadership is already being transferred to {target}")]
+pub struct TransferLeaderInProgress<NID: NodeId = u64> {
+    pub target: NID,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
+#[error("leadership transfer to {target} did not complete in {timeout:?}")]
+pub struct TransferLeaderTimeout<NID: NodeId = u64> {
+    pub target: NID,
+    pub timeout: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
+#[error("can not purge logs upto {upto} that are not applied, last_applied: {last_applied:?}")]
+pub struct NotApplied<NID: NodeId = u64> {
+    pub upto: u64,
+    pub last_applied: Option<LogId<NID>>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
 #[error("new membership can not be empty")]
 pub struct EmptyMembership {}
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(bound = "")]
 #[error("node not found: {node_id}, source: {source}")]
-pub struct NodeNotFound {
-    pub node_id: NodeId,
+pub struct NodeNotFound<NID: NodeId = u64> {
+    pub node_id: NID,
     pub source: AnyError,
 }
 
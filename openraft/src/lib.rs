@@ -23,17 +23,27 @@ pub mod testing;
 #[cfg(test)]
 mod metrics_wait_test;
 
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::hash::Hash;
+
 pub use async_trait;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 pub use crate::config::Config;
 pub use crate::config::ConfigError;
+pub use crate::config::SnapshotCompression;
 pub use crate::config::SnapshotPolicy;
 pub use crate::core::EffectiveMembership;
 pub use crate::core::State;
 pub use crate::defensive::DefensiveCheck;
+pub use crate::membership::quorum;
+pub use crate::membership::ChangeMembers;
+pub use crate::membership::IntoNodes;
 pub use crate::membership::Membership;
+pub use crate::membership::Node;
+pub use crate::membership::NodeInfo;
 pub use crate::metrics::RaftMetrics;
 pub use crate::network::RPCTypes;
 pub use crate::network::RaftNetwork;
@@ -45,6 +55,8 @@ pub use crate::raft_types::SnapshotSegmentId;
 pub use crate::raft_types::StateMachineChanges;
 pub use crate::raft_types::Update;
 pub use crate::replication::ReplicationMetrics;
+pub use crate::replication::SnapshotTransfer;
+pub use crate::replication::TargetReplState;
 pub use crate::storage::RaftStorage;
 pub use crate::storage::RaftStorageDebug;
 pub use crate::storage::SnapshotMeta;
@@ -61,8 +73,38 @@ pub use crate::summary::MessageSummary;
 pub use crate::vote::LeaderId;
 pub use crate::vote::Vote;
 
-/// A Raft node's ID.
-pub type NodeId = u64;
+/// A trait defining the type of a Raft node's ID.
+///
+/// Any type that is `Ord + Clone + Serialize + Debug`, along with a few other common traits, is a `NodeId`, e.g.,
+/// `u64` or a `String` of UUID. `u64` is the default node id type of all types in this crate that have a `NID` type
+/// parameter, e.g., `Raft`, `LogId` or `Membership`.
+///
+/// **Breaking change**: `NodeId` used to be a type alias of `u64`. Code that uses `openraft::NodeId` as a type, e.g.,
+/// `BTreeSet<NodeId>`, does not compile anymore. Import [`DefaultNodeId`] instead:
+/// `use openraft::DefaultNodeId as NodeId;`.
+pub trait NodeId:
+    Sized + Send + Sync + Eq + Ord + Hash + Clone + Default + Debug + Display + Serialize + DeserializeOwned + 'static
+{
+}
+
+impl<T> NodeId for T where T: Sized
+        + Send
+        + Sync
+        + Eq
+        + Ord
+        + Hash
+        + Clone
+        + Default
+        + Debug
+        + Display
+        + Serialize
+        + DeserializeOwned
+        + 'static
+{
+}
+
+/// The default type of node id, which used to be `openraft::NodeId` before it became a trait.
+pub type DefaultNodeId = u64;
 
 /// A trait defining application specific data.
 ///
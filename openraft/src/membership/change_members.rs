@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use crate::membership::IntoNodes;
+use crate::Membership;
+use crate::Node;
+use crate::NodeId;
+use crate::NodeInfo;
+
+/// An operation to change the membership config, passed to `Raft::change_membership()`.
+///
+/// The operation is computed against the effective membership on the leader, when the leader handles it.
+/// Thus two concurrent operations such as adding two different voters do not override each other.
+///
+/// The node info provided along with a node id, if any, is stored in the membership config.
+///
+/// A `BTreeSet<NodeId>`, `BTreeMap<NodeId, NodeInfo>` or `BTreeMap<NodeId, Option<NodeInfo>>` converts into
+/// `ReplaceAllVoters`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeMembers<NID: NodeId = u64, NI: NodeInfo = Node> {
+    /// Add voters to the current voter set. A voter to add must already be a learner.
+    AddVoters(BTreeMap<NID, Option<NI>>),
+
+    /// Remove voters from the current voter set.
+    RemoveVoters(BTreeSet<NID>),
+
+    /// Replace the current voter set with the given one.
+    ReplaceAllVoters(BTreeMap<NID, Option<NI>>),
+
+    /// Add learners. A node that is already a voter or learner only has its node info updated.
+    AddLearners(BTreeMap<NID, Option<NI>>),
+
+    /// Remove learners. Replication to a removed learner is stopped once the change is committed.
+    RemoveLearners(BTreeSet<NID>),
+
+    /// Add learners that are witnesses, which can then be added as voters with `AddVoters`.
+    ///
+    /// A witness only stores log ids and membership configs and never becomes a leader.
+    /// A node that is already a voter or learner can not be turned into a witness and only has its node info updated.
+    AddWitnesses(BTreeMap<NID, Option<NI>>),
+}
+
+impl<NID: NodeId, NI: NodeInfo> From<BTreeSet<NID>> for ChangeMembers<NID, NI> {
+    fn from(members: BTreeSet<NID>) -> Self {
+        ChangeMembers::ReplaceAllVoters(members.into_nodes())
+    }
+}
+
+impl<NID: NodeId, NI: NodeInfo> From<BTreeMap<NID, NI>> for ChangeMembers<NID, NI> {
+    fn from(members: BTreeMap<NID, NI>) -> Self {
+        ChangeMembers::ReplaceAllVoters(members.into_nodes())
+    }
+}
+
+impl<NID: NodeId, NI: NodeInfo> From<BTreeMap<NID, Option<NI>>> for ChangeMembers<NID, NI> {
+    fn from(members: BTreeMap<NID, Option<NI>>) -> Self {
+        ChangeMembers::ReplaceAllVoters(members)
+    }
+}
+
+impl<NID: NodeId, NI: NodeInfo> ChangeMembers<NID, NI> {
+    /// Returns the next membership to change to by applying this operation to `curr`.
+    ///
+    /// A change to voters returns the next safe membership towards the expected voter set, which is a joint config if
+    /// the expected voter set is not in `curr`.
+    /// See [`Membership::next_safe`].
+    ///
+    /// A change to learners keeps the voter configs of `curr` unchanged.
+    pub fn apply_to(self, curr: &Membership<NID, NI>, turn_to_learner: bool) -> Membership<NID, NI> {
+        let last = curr.get_configs().last().cloned().unwrap_or_default();
+
+        let (goal, nodes) = match self {
+            ChangeMembers::AddVoters(m) => {
+                let (ids, nodes) = split_nodes(m);
+                (last.union(&ids).cloned().collect(), nodes)
+            }
+            ChangeMembers::RemoveVoters(ids) => (last.difference(&ids).cloned().collect(), BTreeMap::new()),
+            ChangeMembers::ReplaceAllVoters(m) => split_nodes(m),
+            ChangeMembers::AddLearners(m) => {
+                let (ids, nodes) = split_nodes(m);
+                let mut next = curr.clone();
+                for id in ids.iter() {
+                    if !next.contains(id) {
+                        next = next.add_learner(id);
+                    }
+                }
+                return next.with_nodes(nodes);
+            }
+            ChangeMembers::AddWitnesses(m) => {
+                let (ids, nodes) = split_nodes(m);
+                let mut next = curr.clone();
+                let mut witnesses = BTreeSet::new();
+                for id in ids.iter() {
+                    if !next.contains(id) {
+                        next = next.add_learner(id);
+                        witnesses.insert(id.clone());
+                    }
+                }
+                return next.with_nodes(nodes).with_witnesses(witnesses);
+            }
+            ChangeMembers::RemoveLearners(ids) => {
+                let mut next = curr.clone();
+                for id in ids.iter() {
+                    next.remove_learner(id);
+                }
+                return next;
+            }
+        };
+
+        curr.next_safe(goal, turn_to_learner).with_nodes(nodes)
+    }
+}
+
+/// Split node ids and the node info provided along with them.
+pub(crate) fn split_nodes<NID: NodeId, NI: NodeInfo>(
+    members: BTreeMap<NID, Option<NI>>,
+) -> (BTreeSet<NID>, BTreeMap<NID, NI>) {
+    let ids = members.keys().cloned().collect();
+    let nodes = members.into_iter().filter_map(|(id, n)| n.map(|n| (id, n))).collect();
+    (ids, nodes)
+}
@@ -1,7 +1,5 @@
 use core::cmp::Ord;
 use core::option::Option;
-use core::option::Option::None;
-use core::option::Option::Some;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 
@@ -10,26 +8,45 @@ use serde::Deserialize;
 use serde::Serialize;
 
 use crate::membership::quorum;
+use crate::membership::quorum::Joint;
+use crate::membership::quorum::QuorumSet;
+use crate::membership::quorum::QuorumSystem;
+use crate::membership::Node;
+use crate::membership::NodeInfo;
 use crate::MessageSummary;
 use crate::NodeId;
 
 /// The membership configuration of the cluster.
 ///
-/// It could be a joint of one, two or more configs, i.e., a quorum is a node set that is superset of a majority of
-/// every config.
+/// It could be a joint of one, two or more configs, i.e., a quorum is a node set that is superset of a quorum of
+/// every config. By default a quorum of a config is a majority of it. See [`quorum::QuorumSystem`].
 #[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Membership {
+#[serde(bound = "")]
+pub struct Membership<NID: NodeId = u64, NI: NodeInfo = Node> {
     /// learners set
-    learners: BTreeSet<NodeId>,
+    learners: BTreeSet<NID>,
 
     /// Multi configs.
-    configs: Vec<BTreeSet<NodeId>>,
+    configs: Vec<BTreeSet<NID>>,
 
     /// Cache of all node ids.
-    all_members: BTreeSet<NodeId>,
+    all_members: BTreeSet<NID>,
+
+    /// Additional info of members and learners, e.g., the address to connect to.
+    ///
+    /// A node present in `configs` or `learners` does not have to have an entry in it.
+    #[serde(default)]
+    nodes: BTreeMap<NID, NI>,
+
+    /// Members and learners that are witnesses.
+    ///
+    /// A witness votes and counts in quorums like any other member, but it only stores log ids and membership
+    /// configs, without any application data. It never builds a snapshot and never becomes a leader.
+    #[serde(default)]
+    witnesses: BTreeSet<NID>,
 }
 
-impl MessageSummary for Membership {
+impl<NID: NodeId, NI: NodeInfo> MessageSummary for Membership<NID, NI> {
     fn summary(&self) -> String {
         let mut res = vec!["members:[".to_string()];
         for (i, c) in self.configs.iter().enumerate() {
@@ -48,12 +65,28 @@ impl MessageSummary for Membership {
             res.push(format!("{:?}", learner_id));
         }
         res.push("]".to_string());
+
+        if !self.nodes.is_empty() {
+            res.push(",nodes:{".to_string());
+            for (i, (id, node)) in self.nodes.iter().enumerate() {
+                if i > 0 {
+                    res.push(",".to_string());
+                }
+                res.push(format!("{}:{}", id, node));
+            }
+            res.push("}".to_string());
+        }
+
+        if !self.witnesses.is_empty() {
+            res.push(format!(",witnesses:{:?}", self.witnesses));
+        }
+
         res.join("")
     }
 }
 
-impl Membership {
-    pub fn new_single(members: BTreeSet<NodeId>) -> Self {
+impl<NID: NodeId, NI: NodeInfo> Membership<NID, NI> {
+    pub fn new_single(members: BTreeSet<NID>) -> Self {
         let configs = vec![members];
         let all_members = Self::build_all_members(&configs);
         let learners = BTreeSet::new();
@@ -61,92 +94,142 @@ impl Membership {
             learners,
             configs,
             all_members,
+            nodes: BTreeMap::new(),
+            witnesses: BTreeSet::new(),
         }
     }
 
-    pub fn new_single_with_learners(members: BTreeSet<NodeId>, learners: BTreeSet<NodeId>) -> Self {
+    pub fn new_single_with_learners(members: BTreeSet<NID>, learners: BTreeSet<NID>) -> Self {
         let configs = vec![members];
         let all_members = Self::build_all_members(&configs);
         Membership {
             learners,
             configs,
             all_members,
+            nodes: BTreeMap::new(),
+            witnesses: BTreeSet::new(),
         }
     }
 
-    pub fn new_multi(configs: Vec<BTreeSet<NodeId>>) -> Self {
+    pub fn new_multi(configs: Vec<BTreeSet<NID>>) -> Self {
         let all_members = Self::build_all_members(&configs);
         let learners = BTreeSet::new();
         Membership {
             learners,
             configs,
             all_members,
+            nodes: BTreeMap::new(),
+            witnesses: BTreeSet::new(),
         }
     }
 
-    pub fn new_multi_with_learners(configs: Vec<BTreeSet<NodeId>>, learners: BTreeSet<NodeId>) -> Self {
+    pub fn new_multi_with_learners(configs: Vec<BTreeSet<NID>>, learners: BTreeSet<NID>) -> Self {
         let all_members = Self::build_all_members(&configs);
         Membership {
             learners,
             configs,
             all_members,
+            nodes: BTreeMap::new(),
+            witnesses: BTreeSet::new(),
         }
     }
 
     #[must_use]
-    pub fn add_learner(&self, id: &NodeId) -> Self {
+    pub fn add_learner(&self, id: &NID) -> Self {
         let mut learners = self.learners.clone();
-        learners.insert(*id);
+        learners.insert(id.clone());
         let configs = self.configs.clone();
         let all_members = Self::build_all_members(&self.configs);
         Membership {
             learners,
             configs,
             all_members,
+            nodes: self.nodes.clone(),
+            witnesses: self.witnesses.clone(),
         }
     }
 
-    pub fn remove_learner(&mut self, id: &NodeId) {
+    pub fn remove_learner(&mut self, id: &NID) {
         self.learners.remove(id);
+        self.retain_nodes();
+    }
+
+    /// Returns a membership with node info set.
+    ///
+    /// Node info of a node that is neither a member nor a learner is ignored.
+    #[must_use]
+    pub fn with_nodes(mut self, nodes: BTreeMap<NID, NI>) -> Self {
+        self.nodes.extend(nodes);
+        self.retain_nodes();
+        self
+    }
+
+    /// Returns a membership with the given members or learners marked as witnesses.
+    ///
+    /// An id that is neither a member nor a learner is ignored.
+    #[must_use]
+    pub fn with_witnesses(mut self, witnesses: BTreeSet<NID>) -> Self {
+        self.witnesses.extend(witnesses);
+        self.retain_nodes();
+        self
+    }
+
+    /// Returns the info of a member or learner, if it is provided when the node is added.
+    pub fn get_node(&self, id: &NID) -> Option<&NI> {
+        self.nodes.get(id)
+    }
+
+    /// Returns the election priority of a node, which is 0 if no node info is provided.
+    pub fn get_priority(&self, id: &NID) -> u64 {
+        self.nodes.get(id).map(|n| n.priority()).unwrap_or_default()
+    }
+
+    pub fn nodes(&self) -> &BTreeMap<NID, NI> {
+        &self.nodes
+    }
+
+    pub fn witnesses(&self) -> &BTreeSet<NID> {
+        &self.witnesses
     }
 
-    pub fn all_learners(&self) -> &BTreeSet<NodeId> {
+    pub fn all_learners(&self) -> &BTreeSet<NID> {
         &self.learners
     }
 
-    pub fn all_members(&self) -> &BTreeSet<NodeId> {
+    pub fn all_members(&self) -> &BTreeSet<NID> {
         &self.all_members
     }
 
-    pub fn replace(&mut self, new_configs: Vec<BTreeSet<NodeId>>) {
+    pub fn replace(&mut self, new_configs: Vec<BTreeSet<NID>>) {
         self.configs = new_configs;
         self.all_members = Self::build_all_members(&self.configs);
+        self.retain_nodes();
     }
 
-    pub fn push(&mut self, new_config: BTreeSet<NodeId>) {
+    pub fn push(&mut self, new_config: BTreeSet<NID>) {
         self.configs.push(new_config);
         self.all_members = Self::build_all_members(&self.configs);
     }
 
-    pub fn get_configs(&self) -> &Vec<BTreeSet<NodeId>> {
+    pub fn get_configs(&self) -> &Vec<BTreeSet<NID>> {
         &self.configs
     }
 
-    pub fn get_ith_config(&self, i: usize) -> Option<&BTreeSet<NodeId>> {
+    pub fn get_ith_config(&self, i: usize) -> Option<&BTreeSet<NID>> {
         self.configs.get(i)
     }
 
     // TODO(xp): remove this
-    pub fn ith_config(&self, i: usize) -> Vec<NodeId> {
+    pub fn ith_config(&self, i: usize) -> Vec<NID> {
         self.configs[i].iter().cloned().collect()
     }
 
-    pub fn contains(&self, target: &NodeId) -> bool {
+    pub fn contains(&self, target: &NID) -> bool {
         self.is_member(target) || self.is_learner(target)
     }
 
-    /// Check if the given NodeId exists in this membership config.
-    pub fn is_member(&self, x: &NodeId) -> bool {
+    /// Check if the given NID exists in this membership config.
+    pub fn is_member(&self, x: &NID) -> bool {
         for c in self.configs.iter() {
             if c.contains(x) {
                 return true;
@@ -155,10 +238,14 @@ impl Membership {
         false
     }
 
-    pub fn is_learner(&self, x: &NodeId) -> bool {
+    pub fn is_learner(&self, x: &NID) -> bool {
         self.learners.contains(x)
     }
 
+    pub fn is_witness(&self, x: &NID) -> bool {
+        self.witnesses.contains(x)
+    }
+
     /// Check to see if the config is currently in joint consensus.
     pub fn is_in_joint_consensus(&self) -> bool {
         self.configs.len() > 1
@@ -166,7 +253,7 @@ impl Membership {
 
     // TODO(xp): rename this
     /// Create a new initial config containing only the given node ID.
-    pub fn new_initial(id: NodeId) -> Self {
+    pub fn new_initial(id: NID) -> Self {
         Membership::new_single(btreeset! {id})
     }
 
@@ -175,20 +262,21 @@ impl Membership {
         assert!(!self.configs.is_empty());
 
         let last = self.configs.last().cloned().unwrap();
-        Membership::new_single(last)
+        Membership::new_single(last).with_nodes(self.nodes.clone()).with_witnesses(self.witnesses.clone())
+    }
+
+    /// Returns the quorum set of this membership config built by `quorum_system`.
+    ///
+    /// It is the joint of the quorum sets of every config.
+    pub fn quorum_set(&self, quorum_system: &dyn QuorumSystem<NID, NI>) -> Joint<Box<dyn QuorumSet<NID>>> {
+        Joint::new(self.configs.iter().map(|c| quorum_system.quorum_set(c, &self.nodes)).collect())
     }
 
     /// Return true if the given set of ids constitutes a majority.
     ///
     /// I.e. the id set includes a majority of every config.
-    pub fn is_majority(&self, granted: &BTreeSet<NodeId>) -> bool {
-        for config in self.configs.iter() {
-            if !Self::is_majority_of_single_config(granted, config) {
-                return false;
-            }
-        }
-
-        true
+    pub fn is_majority(&self, granted: &BTreeSet<NID>) -> bool {
+        Joint::new(self.configs.iter().collect()).is_quorum(granted)
     }
 
     /// Returns the greatest value that presents in `values` that constitutes a joint majority.
@@ -197,34 +285,9 @@ impl Membership {
     /// `10` constitutes a majoirty in the first config {1,2,3}.
     /// `20` constitutes a majority in the second config {4,5,6}.
     /// Thus the minimal value `10` is the greatest joint majority for this membership config.
-    pub fn greatest_majority_value<'v, V>(&self, values: &'v BTreeMap<NodeId, V>) -> Option<&'v V>
+    pub fn greatest_majority_value<'v, V>(&self, values: &'v BTreeMap<NID, V>) -> Option<&'v V>
     where V: Ord {
-        let mut res = vec![];
-        for config in self.configs.iter() {
-            let mut vs = Vec::with_capacity(config.len());
-
-            for id in config.iter() {
-                let v = values.get(id);
-                if let Some(v) = v {
-                    vs.push(v)
-                }
-            }
-
-            let majority = quorum::majority_of(config.len());
-
-            if vs.len() < majority {
-                res.push(None);
-                continue;
-            }
-
-            vs.sort_unstable();
-
-            let majority_greatest = Some(vs[vs.len() - majority]);
-            res.push(majority_greatest);
-        }
-
-        let min_greatest = res.into_iter().min();
-        min_greatest.unwrap_or(None)
+        quorum::greatest_quorum_value(&Joint::new(self.configs.iter().collect()), values)
     }
 
     /// Check if the `other` membership is safe to change to.
@@ -259,38 +322,40 @@ impl Membership {
     /// }
     /// ```
     #[must_use]
-    pub fn next_safe(&self, goal: BTreeSet<NodeId>, turn_to_learner: bool) -> Self {
+    pub fn next_safe(&self, goal: BTreeSet<NID>, turn_to_learner: bool) -> Self {
         let learners = if turn_to_learner {
             let curr = self.clone();
             // add removed members into learners
             let removed_members = curr.all_members().difference(&goal);
             let mut learners = curr.all_learners().clone();
             for id in removed_members {
-                learners.insert(*id);
+                learners.insert(id.clone());
             }
             learners
         } else {
             self.learners.clone()
         };
-        if self.configs.contains(&goal) {
+        let next = if self.configs.contains(&goal) {
             Membership::new_single_with_learners(goal, learners)
         } else {
             Membership::new_multi_with_learners(vec![self.configs.last().cloned().unwrap(), goal], learners)
-        }
-    }
+        };
 
-    fn is_majority_of_single_config(granted: &BTreeSet<NodeId>, single_config: &BTreeSet<NodeId>) -> bool {
-        let d = granted.intersection(single_config);
-        let n_granted = d.fold(0, |a, _x| a + 1);
+        next.with_nodes(self.nodes.clone()).with_witnesses(self.witnesses.clone())
+    }
 
-        let majority = quorum::majority_of(single_config.len());
-        n_granted >= majority
+    /// Remove the info and witness marks of nodes that are neither members nor learners.
+    fn retain_nodes(&mut self) {
+        let all_members = &self.all_members;
+        let learners = &self.learners;
+        self.nodes.retain(|id, _| all_members.contains(id) || learners.contains(id));
+        self.witnesses.retain(|id| all_members.contains(id) || learners.contains(id));
     }
 
-    fn build_all_members(configs: &[BTreeSet<NodeId>]) -> BTreeSet<NodeId> {
+    fn build_all_members(configs: &[BTreeSet<NID>]) -> BTreeSet<NID> {
         let mut members = BTreeSet::new();
         for config in configs.iter() {
-            members.extend(config)
+            members.extend(config.iter().cloned())
         }
         members
     }
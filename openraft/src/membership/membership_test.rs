@@ -1,16 +1,23 @@
 use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
 
 use maplit::btreemap;
 use maplit::btreeset;
+use serde::Deserialize;
+use serde::Serialize;
 
+use crate::ChangeMembers;
 use crate::Membership;
-use crate::NodeId;
+use crate::MessageSummary;
+use crate::Node;
+use crate::NodeInfo;
 
 #[test]
 fn test_membership() -> anyhow::Result<()> {
-    let m1 = Membership::new_multi(vec![btreeset! {1}]);
-    let m123 = Membership::new_multi(vec![btreeset! {1,2,3}]);
-    let m123_345 = Membership::new_multi(vec![btreeset! {1,2,3}, btreeset! {3,4,5}]);
+    let m1: Membership = Membership::new_multi(vec![btreeset! {1}]);
+    let m123: Membership = Membership::new_multi(vec![btreeset! {1,2,3}]);
+    let m123_345: Membership = Membership::new_multi(vec![btreeset! {1,2,3}, btreeset! {3,4,5}]);
 
     assert_eq!(Some(btreeset! {1}), m1.get_ith_config(0).cloned());
     assert_eq!(Some(btreeset! {1,2,3}), m123.get_ith_config(0).cloned());
@@ -46,7 +53,7 @@ fn test_membership() -> anyhow::Result<()> {
 fn test_membership_with_learners() -> anyhow::Result<()> {
     // test multi membership with learners
     {
-        let m1_2 = Membership::new_multi_with_learners(vec![btreeset! {1}], btreeset! {2});
+        let m1_2: Membership = Membership::new_multi_with_learners(vec![btreeset! {1}], btreeset! {2});
         let m1_23 = m1_2.add_learner(&3);
 
         // test learner and membership
@@ -62,7 +69,7 @@ fn test_membership_with_learners() -> anyhow::Result<()> {
 
     // test single membership with learners
     {
-        let s1_2 = Membership::new_single_with_learners(btreeset! {1}, btreeset! {2});
+        let s1_2: Membership = Membership::new_single_with_learners(btreeset! {1}, btreeset! {2});
         let s1_23 = s1_2.add_learner(&3);
 
         // test learner and membership
@@ -82,7 +89,7 @@ fn test_membership_with_learners() -> anyhow::Result<()> {
 fn test_membership_update() -> anyhow::Result<()> {
     // --- replace
 
-    let mut m123 = Membership::new_single(btreeset! {1,2,3});
+    let mut m123: Membership = Membership::new_single(btreeset! {1,2,3});
     m123.replace(vec![btreeset! {2,3}, btreeset! {3,4}]);
 
     assert_eq!(&btreeset! {2,3,4}, m123.all_members());
@@ -112,7 +119,7 @@ fn test_membership_update() -> anyhow::Result<()> {
 fn test_membership_update_with_learners() -> anyhow::Result<()> {
     // test multi membership with learners
     {
-        let mut m1_2 = Membership::new_multi_with_learners(vec![btreeset! {1}], btreeset! {2});
+        let mut m1_2: Membership = Membership::new_multi_with_learners(vec![btreeset! {1}], btreeset! {2});
 
         // test replace and push, does not affect learners
         m1_2.replace(vec![btreeset! {3,4}]);
@@ -126,7 +133,7 @@ fn test_membership_update_with_learners() -> anyhow::Result<()> {
 
     // test single membership with learners
     {
-        let mut s1_2 = Membership::new_single_with_learners(btreeset! {1}, btreeset! {2});
+        let mut s1_2: Membership = Membership::new_single_with_learners(btreeset! {1}, btreeset! {2});
 
         // test replace and push, does not affect learners
         s1_2.replace(vec![btreeset! {3,4}]);
@@ -143,7 +150,7 @@ fn test_membership_update_with_learners() -> anyhow::Result<()> {
 #[test]
 fn test_membership_majority() -> anyhow::Result<()> {
     {
-        let m12345 = Membership::new_single(btreeset! {1,2,3,4,5});
+        let m12345: Membership = Membership::new_single(btreeset! {1,2,3,4,5});
         assert!(!m12345.is_majority(&btreeset! {0}));
         assert!(!m12345.is_majority(&btreeset! {0,1,2}));
         assert!(!m12345.is_majority(&btreeset! {6,7,8}));
@@ -153,7 +160,7 @@ fn test_membership_majority() -> anyhow::Result<()> {
     }
 
     {
-        let m12345_678 = Membership::new_multi(vec![btreeset! {1,2,3,4,5}, btreeset! {6,7,8}]);
+        let m12345_678: Membership = Membership::new_multi(vec![btreeset! {1,2,3,4,5}, btreeset! {6,7,8}]);
         assert!(!m12345_678.is_majority(&btreeset! {0}));
         assert!(!m12345_678.is_majority(&btreeset! {0,1,2}));
         assert!(!m12345_678.is_majority(&btreeset! {6,7,8}));
@@ -168,8 +175,8 @@ fn test_membership_majority() -> anyhow::Result<()> {
 #[test]
 fn test_membership_greatest_majority_value() -> anyhow::Result<()> {
     {
-        let m123 = Membership::new_single(btreeset! {1,2,3});
-        assert_eq!(None, m123.greatest_majority_value(&BTreeMap::<NodeId, u64>::new()));
+        let m123: Membership = Membership::new_single(btreeset! {1,2,3});
+        assert_eq!(None, m123.greatest_majority_value(&BTreeMap::<u64, u64>::new()));
         assert_eq!(None, m123.greatest_majority_value(&btreemap! {0=>10}));
         assert_eq!(None, m123.greatest_majority_value(&btreemap! {0=>10,1=>10}));
         assert_eq!(Some(&10), m123.greatest_majority_value(&btreemap! {0=>10,1=>10,2=>20}));
@@ -180,7 +187,7 @@ fn test_membership_greatest_majority_value() -> anyhow::Result<()> {
     }
 
     {
-        let m123_678 = Membership::new_multi(vec![btreeset! {1,2,3}, btreeset! {6,7,8}]);
+        let m123_678: Membership = Membership::new_multi(vec![btreeset! {1,2,3}, btreeset! {6,7,8}]);
         assert_eq!(None, m123_678.greatest_majority_value(&btreemap! {0=>10}));
         assert_eq!(None, m123_678.greatest_majority_value(&btreemap! {0=>10,1=>10}));
         assert_eq!(None, m123_678.greatest_majority_value(&btreemap! {0=>10,1=>10,2=>20}));
@@ -207,10 +214,10 @@ fn test_membership_is_safe_to() -> anyhow::Result<()> {
     let set345 = || btreeset! {3,4,5};
     let set789 = || btreeset! {7,8,9};
 
-    let m123 = Membership::new_single(set123());
-    let m345 = Membership::new_single(set345());
-    let m123_345 = Membership::new_multi(vec![set123(), set345()]);
-    let m345_789 = Membership::new_multi(vec![set345(), set789()]);
+    let m123: Membership = Membership::new_single(set123());
+    let m345: Membership = Membership::new_single(set345());
+    let m123_345: Membership = Membership::new_multi(vec![set123(), set345()]);
+    let m345_789: Membership = Membership::new_multi(vec![set345(), set789()]);
 
     assert!(m123.is_safe_to(&m123));
     assert!(!m123.is_safe_to(&m345));
@@ -241,10 +248,10 @@ fn test_membership_next_safe() -> anyhow::Result<()> {
     let c2 = || btreeset! {3,4,5};
     let c3 = || btreeset! {7,8,9};
 
-    let m1 = Membership::new_single(c1());
-    let m2 = Membership::new_single(c2());
-    let m12 = Membership::new_multi(vec![c1(), c2()]);
-    let m23 = Membership::new_multi(vec![c2(), c3()]);
+    let m1: Membership = Membership::new_single(c1());
+    let m2: Membership = Membership::new_single(c2());
+    let m12: Membership = Membership::new_multi(vec![c1(), c2()]);
+    let m23: Membership = Membership::new_multi(vec![c2(), c3()]);
 
     assert_eq!(m1, m1.next_safe(c1(), false));
     assert_eq!(m12, m1.next_safe(c2(), false));
@@ -254,9 +261,187 @@ fn test_membership_next_safe() -> anyhow::Result<()> {
 
     let old_learners = || btreeset! {1, 2};
     let learners = || btreeset! {1, 2, 3, 4, 5};
-    let m23_with_learners_old = Membership::new_multi_with_learners(vec![c2(), c3()], old_learners());
-    let m23_with_learners_new = Membership::new_multi_with_learners(vec![c3()], learners());
+    let m23_with_learners_old: Membership = Membership::new_multi_with_learners(vec![c2(), c3()], old_learners());
+    let m23_with_learners_new: Membership = Membership::new_multi_with_learners(vec![c3()], learners());
     assert_eq!(m23_with_learners_new, m23_with_learners_old.next_safe(c3(), true));
 
     Ok(())
 }
+
+#[test]
+fn test_membership_apply_change_members() -> anyhow::Result<()> {
+    let m: Membership = Membership::new_single_with_learners(btreeset! {1,2,3}, btreeset! {4,5});
+
+    // Changes to voters are computed against the last config.
+    assert_eq!(
+        Membership::new_multi_with_learners(vec![btreeset! {1,2,3}, btreeset! {1,2,3,4}], btreeset! {4,5}),
+        ChangeMembers::AddVoters(btreemap! {4 => None}).apply_to(&m, false)
+    );
+    assert_eq!(
+        Membership::new_multi_with_learners(vec![btreeset! {1,2,3}, btreeset! {1,2}], btreeset! {3,4,5}),
+        ChangeMembers::RemoveVoters(btreeset! {3}).apply_to(&m, true)
+    );
+    assert_eq!(
+        Membership::new_multi_with_learners(vec![btreeset! {1,2,3}, btreeset! {4,5}], btreeset! {4,5}),
+        ChangeMembers::from(btreeset! {4,5}).apply_to(&m, false)
+    );
+
+    // Changes to learners do not change voters.
+    assert_eq!(
+        Membership::new_single_with_learners(btreeset! {1,2,3}, btreeset! {4,5,6})
+            .with_nodes(btreemap! {6 => Node::new("6")}),
+        ChangeMembers::AddLearners(btreemap! {1 => None, 6 => Some(Node::new("6"))}).apply_to(&m, false)
+    );
+    assert_eq!(
+        Membership::new_single_with_learners(btreeset! {1,2,3}, btreeset! {5}),
+        ChangeMembers::RemoveLearners(btreeset! {3,4}).apply_to(&m, false)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_witnesses() -> anyhow::Result<()> {
+    let m: Membership = Membership::new_single_with_learners(btreeset! {1,2}, btreeset! {3});
+
+    // Only a node that is not yet a voter or learner is added as a witness.
+    let m = ChangeMembers::AddWitnesses(btreemap! {3 => None, 4 => None}).apply_to(&m, false);
+    assert_eq!(&btreeset! {3,4}, m.all_learners());
+    assert_eq!(&btreeset! {4}, m.witnesses());
+    assert!(m.is_witness(&4));
+    assert!(!m.is_witness(&3));
+    assert_eq!("members:[{1, 2}],learners:[3,4],witnesses:{4}", m.summary());
+
+    // A witness is kept when it becomes a voter.
+    let mut m = ChangeMembers::AddVoters(btreemap! {4 => None}).apply_to(&m, false);
+    m.remove_learner(&4);
+    assert_eq!(&btreeset! {4}, m.witnesses());
+
+    let m = m.to_final_config();
+    assert_eq!(&vec![btreeset! {1,2,4}], m.get_configs());
+    assert_eq!(&btreeset! {4}, m.witnesses());
+
+    // A witness is removed along with the node.
+    let m = ChangeMembers::RemoveVoters(btreeset! {4}).apply_to(&m, false).to_final_config();
+    assert_eq!(&btreeset! {}, m.witnesses());
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_with_nodes() -> anyhow::Result<()> {
+    let node = |addr: &str| Node::new(addr);
+
+    // Node info of a node that is neither a member nor a learner is ignored.
+    let m = Membership::new_single_with_learners(btreeset! {1,2}, btreeset! {3}).with_nodes(btreemap! {
+        1 => node("a"),
+        3 => node("c"),
+        4 => node("d"),
+    });
+    assert_eq!(&btreemap! {1 => node("a"), 3 => node("c")}, m.nodes());
+    assert_eq!(Some(&node("a")), m.get_node(&1));
+    assert_eq!(None, m.get_node(&2));
+    assert_eq!(None, m.get_node(&4));
+
+    assert_eq!("members:[{1, 2}],learners:[3],nodes:{1:a,3:c}", m.summary());
+
+    // Node info is updated.
+    let m = m.with_nodes(btreemap! {1 => node("b")});
+    assert_eq!(Some(&node("b")), m.get_node(&1));
+
+    // Node info is kept when adding a learner or changing membership.
+    let m = m.add_learner(&5).with_nodes(btreemap! {5 => node("e")});
+    assert_eq!(&btreemap! {1 => node("b"), 3 => node("c"), 5 => node("e")}, m.nodes());
+
+    let m = m.next_safe(btreeset! {1,3}, false);
+    assert_eq!(&btreemap! {1 => node("b"), 3 => node("c"), 5 => node("e")}, m.nodes());
+
+    let m = m.to_final_config();
+    assert_eq!(&btreemap! {1 => node("b"), 3 => node("c")}, m.nodes());
+
+    // Node info is removed along with the node.
+    let mut m = Membership::new_single_with_learners(btreeset! {1}, btreeset! {2})
+        .with_nodes(btreemap! {1 => node("a"), 2 => node("b")});
+    m.remove_learner(&2);
+    assert_eq!(&btreemap! {1 => node("a")}, m.nodes());
+
+    Ok(())
+}
+
+#[test]
+fn test_membership_string_node_id() -> anyhow::Result<()> {
+    let s = |x: &str| x.to_string();
+
+    let m: Membership<String> =
+        Membership::new_multi_with_learners(vec![btreeset! {s("a"), s("b"), s("c")}], btreeset! {s("d")});
+    assert!(m.is_member(&s("a")));
+    assert!(m.is_learner(&s("d")));
+    assert!(m.is_majority(&btreeset! {s("a"), s("c")}));
+    assert!(!m.is_majority(&btreeset! {s("a"), s("d")}));
+
+    let m = m.next_safe(btreeset! {s("b"), s("c")}, false);
+    assert_eq!(
+        &vec![btreeset! {s("a"), s("b"), s("c")}, btreeset! {s("b"), s("c")}],
+        m.get_configs()
+    );
+    assert_eq!(r#"members:[{"a", "b", "c"},{"b", "c"}],learners:["d"]"#, m.summary());
+
+    let m = ChangeMembers::AddLearners(btreemap! {s("e") => Some(Node::new("e"))}).apply_to(&m, false);
+    assert!(m.is_learner(&s("e")));
+    assert_eq!(Some(&Node::new("e")), m.get_node(&s("e")));
+
+    let m = ChangeMembers::RemoveVoters(btreeset! {s("c")}).apply_to(&m.to_final_config(), true);
+    assert_eq!(&vec![btreeset! {s("b"), s("c")}, btreeset! {s("b")}], m.get_configs());
+    assert!(m.is_learner(&s("c")));
+
+    let encoded = bincode::serialize(&m)?;
+    let decoded: Membership<String> = bincode::deserialize(&encoded)?;
+    assert_eq!(m, decoded);
+
+    Ok(())
+}
+
+/// An application defined node type.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct AppNode {
+    rack: String,
+    priority: u64,
+}
+
+impl Display for AppNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.rack, self.priority)
+    }
+}
+
+impl NodeInfo for AppNode {
+    fn priority(&self) -> u64 {
+        self.priority
+    }
+}
+
+#[test]
+fn test_membership_app_node() -> anyhow::Result<()> {
+    let node = |rack: &str, priority: u64| AppNode {
+        rack: rack.to_string(),
+        priority,
+    };
+
+    let m = Membership::new_single_with_learners(btreeset! {1,2}, btreeset! {3}).with_nodes(btreemap! {
+        1 => node("r1", 0),
+        2 => node("r2", 5),
+    });
+    assert_eq!(Some(&node("r2", 5)), m.get_node(&2));
+    assert_eq!(5, m.get_priority(&2));
+    assert_eq!(0, m.get_priority(&3));
+    assert_eq!("members:[{1, 2}],learners:[3],nodes:{1:r1/0,2:r2/5}", m.summary());
+
+    let m = ChangeMembers::AddLearners(btreemap! {3 => Some(node("r3", 1))}).apply_to(&m, false);
+    assert_eq!(Some(&node("r3", 1)), m.get_node(&3));
+
+    let encoded = bincode::serialize(&m)?;
+    let decoded: Membership<u64, AppNode> = bincode::deserialize(&encoded)?;
+    assert_eq!(m, decoded);
+
+    Ok(())
+}
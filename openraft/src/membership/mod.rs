@@ -1,9 +1,18 @@
+mod change_members;
 #[allow(clippy::module_inception)]
 mod membership;
 
 #[cfg(test)]
 mod membership_test;
+mod node;
 
 pub mod quorum;
+#[cfg(test)]
+mod quorum_test;
 
+pub(crate) use change_members::split_nodes;
+pub use change_members::ChangeMembers;
 pub use membership::Membership;
+pub use node::IntoNodes;
+pub use node::Node;
+pub use node::NodeInfo;
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::NodeId;
+
+/// A trait defining the type of the additional info about a cluster node, e.g., the address to connect to it.
+///
+/// Node info is stored in the membership config along with the node id, thus it is replicated with the membership log
+/// and is available on every node. An application defines its own node type to carry whatever it needs to reach a
+/// node. [`Node`] is the default node type of all types in this crate that have a `NI` type parameter.
+pub trait NodeInfo:
+    Sized + Send + Sync + Eq + Clone + Default + Debug + Display + Serialize + DeserializeOwned + 'static
+{
+    /// Returns the election priority of this node. It is 0 by default.
+    ///
+    /// A voter with a lower priority delays its election, and a leader hands its leadership over to a voter with a
+    /// higher priority once the voter catches up.
+    fn priority(&self) -> u64 {
+        0
+    }
+}
+
+/// The default node info: the address to connect to a node and some application defined data.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Node {
+    /// The address to reach this node, in whatever format the `RaftNetwork` implementation understands.
+    pub addr: String,
+
+    /// Application defined node data.
+    #[serde(default)]
+    pub data: BTreeMap<String, String>,
+
+    /// The election priority of this node. It is 0 by default.
+    ///
+    /// A voter with a lower priority delays its election, and a leader hands its leadership over to a voter with a
+    /// higher priority once the voter catches up.
+    #[serde(default)]
+    pub priority: u64,
+}
+
+impl Node {
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns a node with the election priority set.
+    #[must_use]
+    pub fn with_priority(mut self, priority: u64) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+impl NodeInfo for Node {
+    fn priority(&self) -> u64 {
+        self.priority
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.addr)?;
+        if !self.data.is_empty() {
+            write!(f, "; {:?}", self.data)?;
+        }
+        if self.priority > 0 {
+            write!(f, "; priority={}", self.priority)?;
+        }
+        Ok(())
+    }
+}
+
+/// Convert a collection of node ids, optionally with node info, into a map of node id to node info.
+///
+/// It allows `Raft::initialize()` and `Raft::change_membership()` to accept either a `BTreeSet<NodeId>` or a
+/// `BTreeMap<NodeId, NodeInfo>`.
+pub trait IntoNodes<NID: NodeId = u64, NI: NodeInfo = Node> {
+    fn into_nodes(self) -> BTreeMap<NID, Option<NI>>;
+}
+
+impl<NID: NodeId, NI: NodeInfo> IntoNodes<NID, NI> for BTreeSet<NID> {
+    fn into_nodes(self) -> BTreeMap<NID, Option<NI>> {
+        self.into_iter().map(|id| (id, None)).collect()
+    }
+}
+
+impl<NID: NodeId, NI: NodeInfo> IntoNodes<NID, NI> for BTreeMap<NID, NI> {
+    fn into_nodes(self) -> BTreeMap<NID, Option<NI>> {
+        self.into_iter().map(|(id, n)| (id, Some(n))).collect()
+    }
+}
+
+impl<NID: NodeId, NI: NodeInfo> IntoNodes<NID, NI> for BTreeMap<NID, Option<NI>> {
+    fn into_nodes(self) -> BTreeMap<NID, Option<NI>> {
+        self
+    }
+}
@@ -1,3 +1,133 @@
+//! Quorum systems that decide which sets of voters are able to elect a leader or to commit a log.
+//!
+//! The default is [`Majority`]: a set of voters is a quorum if it includes more than half of them.
+//! An application may supply another [`QuorumSystem`] with `Raft::with_quorum_system()`, e.g., weighted voters, or a
+//! grid in which the quorums to elect a leader differ from the quorums to commit a log, as in Flexible Paxos.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use crate::Node;
+use crate::NodeId;
+use crate::NodeInfo;
+
 pub fn majority_of(n: usize) -> usize {
     n / 2 + 1
 }
+
+/// A set of quorums, each of which is a set of node ids.
+///
+/// A leader is elected by the votes of an election quorum, and a log is committed once a replication quorum has
+/// accepted it.
+/// To be safe, every election quorum must intersect every replication quorum, and a superset of a quorum must also be
+/// a quorum.
+pub trait QuorumSet<NID: NodeId>: Send + Sync {
+    /// Returns true if `ids` includes an election quorum.
+    fn is_quorum(&self, ids: &BTreeSet<NID>) -> bool;
+
+    /// Returns true if `ids` includes a replication quorum.
+    ///
+    /// It is also used to confirm the leadership, e.g., for a linearizable read or a leader lease, since a replication
+    /// quorum intersects any election quorum that elects another leader.
+    fn is_replication_quorum(&self, ids: &BTreeSet<NID>) -> bool {
+        self.is_quorum(ids)
+    }
+}
+
+/// A set of voters is a majority quorum set: any set including more than half of the voters is a quorum.
+impl<NID: NodeId> QuorumSet<NID> for BTreeSet<NID> {
+    fn is_quorum(&self, ids: &BTreeSet<NID>) -> bool {
+        let n = ids.intersection(self).count();
+        n >= majority_of(self.len())
+    }
+}
+
+impl<NID: NodeId, T: QuorumSet<NID> + ?Sized> QuorumSet<NID> for &T {
+    fn is_quorum(&self, ids: &BTreeSet<NID>) -> bool {
+        (**self).is_quorum(ids)
+    }
+
+    fn is_replication_quorum(&self, ids: &BTreeSet<NID>) -> bool {
+        (**self).is_replication_quorum(ids)
+    }
+}
+
+impl<NID: NodeId, T: QuorumSet<NID> + ?Sized> QuorumSet<NID> for Box<T> {
+    fn is_quorum(&self, ids: &BTreeSet<NID>) -> bool {
+        (**self).is_quorum(ids)
+    }
+
+    fn is_replication_quorum(&self, ids: &BTreeSet<NID>) -> bool {
+        (**self).is_replication_quorum(ids)
+    }
+}
+
+/// The joint of several quorum sets, e.g., the configs of a joint membership config.
+///
+/// A set of ids is a quorum of the joint only if it is a quorum of every quorum set in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Joint<Q> {
+    children: Vec<Q>,
+}
+
+impl<Q> Joint<Q> {
+    pub fn new(children: Vec<Q>) -> Self {
+        Self { children }
+    }
+
+    pub fn children(&self) -> &Vec<Q> {
+        &self.children
+    }
+}
+
+impl<NID: NodeId, Q: QuorumSet<NID>> QuorumSet<NID> for Joint<Q> {
+    fn is_quorum(&self, ids: &BTreeSet<NID>) -> bool {
+        self.children.iter().all(|q| q.is_quorum(ids))
+    }
+
+    fn is_replication_quorum(&self, ids: &BTreeSet<NID>) -> bool {
+        self.children.iter().all(|q| q.is_replication_quorum(ids))
+    }
+}
+
+/// Returns the greatest value in `values` that is accepted by a replication quorum of `quorum_set`.
+///
+/// I.e., the greatest `v` such that the ids with a value no less than `v` constitute a replication quorum.
+/// E.g., the greatest log id that is committed, if `values` are the matched log ids of every node.
+pub fn greatest_quorum_value<'v, NID, Q, V>(quorum_set: &Q, values: &'v BTreeMap<NID, V>) -> Option<&'v V>
+where
+    NID: NodeId,
+    Q: QuorumSet<NID> + ?Sized,
+    V: Ord,
+{
+    let mut sorted = values.iter().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| b.1.cmp(a.1));
+
+    let mut ids = BTreeSet::new();
+    for (id, v) in sorted {
+        ids.insert(id.clone());
+        if quorum_set.is_replication_quorum(&ids) {
+            return Some(v);
+        }
+    }
+
+    None
+}
+
+/// Builds the quorum set of the voters in a config of a membership config.
+///
+/// `nodes` is the node info stored in the membership config, from which an application may load additional info
+/// about a voter, e.g., its weight.
+pub trait QuorumSystem<NID: NodeId, NI: NodeInfo = Node>: Send + Sync + 'static {
+    fn quorum_set(&self, voters: &BTreeSet<NID>, nodes: &BTreeMap<NID, NI>) -> Box<dyn QuorumSet<NID>>;
+}
+
+/// The default quorum system, in which a quorum is a majority of the voters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Majority;
+
+impl<NID: NodeId, NI: NodeInfo> QuorumSystem<NID, NI> for Majority {
+    fn quorum_set(&self, voters: &BTreeSet<NID>, _nodes: &BTreeMap<NID, NI>) -> Box<dyn QuorumSet<NID>> {
+        Box::new(voters.clone())
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use maplit::btreemap;
+use maplit::btreeset;
+
+use crate::membership::quorum::greatest_quorum_value;
+use crate::membership::quorum::Joint;
+use crate::membership::quorum::Majority;
+use crate::membership::quorum::QuorumSet;
+use crate::membership::quorum::QuorumSystem;
+use crate::Membership;
+use crate::Node;
+
+/// Voters with weights. A quorum has more than half of the total weight.
+struct Weighted {
+    weights: BTreeMap<u64, u64>,
+}
+
+impl QuorumSet<u64> for Weighted {
+    fn is_quorum(&self, ids: &BTreeSet<u64>) -> bool {
+        let total: u64 = self.weights.values().sum();
+        let got: u64 = ids.iter().filter_map(|id| self.weights.get(id)).sum();
+        got * 2 > total
+    }
+}
+
+/// Loads the weight of a voter from `Node::data["weight"]`, 1 by default.
+struct WeightedSystem;
+
+impl QuorumSystem<u64> for WeightedSystem {
+    fn quorum_set(&self, voters: &BTreeSet<u64>, nodes: &BTreeMap<u64, Node>) -> Box<dyn QuorumSet<u64>> {
+        let weights = voters
+            .iter()
+            .map(|id| {
+                let w = nodes.get(id).and_then(|n| n.data.get("weight")).and_then(|w| w.parse().ok()).unwrap_or(1);
+                (*id, w)
+            })
+            .collect();
+        Box::new(Weighted { weights })
+    }
+}
+
+/// Voters in a grid. An election quorum has a voter in every row and a replication quorum is a whole row.
+struct Grid {
+    rows: Vec<BTreeSet<u64>>,
+}
+
+impl QuorumSet<u64> for Grid {
+    fn is_quorum(&self, ids: &BTreeSet<u64>) -> bool {
+        self.rows.iter().all(|row| row.intersection(ids).next().is_some())
+    }
+
+    fn is_replication_quorum(&self, ids: &BTreeSet<u64>) -> bool {
+        self.rows.iter().any(|row| row.is_subset(ids))
+    }
+}
+
+#[test]
+fn test_quorum_majority() -> anyhow::Result<()> {
+    let q = btreeset! {1,2,3};
+
+    assert!(!q.is_quorum(&btreeset! {}));
+    assert!(!q.is_quorum(&btreeset! {1,4,5}));
+    assert!(q.is_quorum(&btreeset! {1,2}));
+    assert!(q.is_replication_quorum(&btreeset! {2,3,4}));
+
+    // An empty set of voters has no quorum.
+    assert!(!btreeset! {}.is_quorum(&btreeset! {1}));
+
+    Ok(())
+}
+
+#[test]
+fn test_quorum_joint() -> anyhow::Result<()> {
+    let q = Joint::new(vec![btreeset! {1,2,3}, btreeset! {3,4,5}]);
+
+    assert!(!q.is_quorum(&btreeset! {1,2}));
+    assert!(!q.is_quorum(&btreeset! {4,5}));
+    assert!(q.is_quorum(&btreeset! {1,2,4,5}));
+    assert!(q.is_quorum(&btreeset! {1,3,4}));
+
+    // Joint of different kinds of quorum sets.
+    let q: Joint<Box<dyn QuorumSet<u64>>> = Joint::new(vec![
+        Box::new(btreeset! {1,2,3}),
+        Box::new(Grid {
+            rows: vec![btreeset! {4,5}, btreeset! {6,7}],
+        }),
+    ]);
+
+    assert!(q.is_quorum(&btreeset! {1,2,4,6}));
+    assert!(!q.is_replication_quorum(&btreeset! {1,2,4,6}));
+    assert!(q.is_replication_quorum(&btreeset! {1,2,4,5}));
+
+    Ok(())
+}
+
+#[test]
+fn test_quorum_greatest_value() -> anyhow::Result<()> {
+    let values = btreemap! {1=>10, 2=>20, 3=>30, 4=>40, 5=>50};
+
+    assert_eq!(Some(&20), greatest_quorum_value(&btreeset! {1,2,3}, &values));
+    assert_eq!(Some(&40), greatest_quorum_value(&btreeset! {3,4,5}, &values));
+    assert_eq!(
+        Some(&20),
+        greatest_quorum_value(&Joint::new(vec![btreeset! {1,2,3}, btreeset! {3,4,5}]), &values)
+    );
+    assert_eq!(None, greatest_quorum_value(&btreeset! {1,6,7}, &values));
+
+    // The committed value of a grid is the greatest one accepted by a whole row.
+    let grid = Grid {
+        rows: vec![btreeset! {1,2}, btreeset! {3,4}],
+    };
+    assert_eq!(Some(&30), greatest_quorum_value(&grid, &values));
+
+    Ok(())
+}
+
+#[test]
+fn test_quorum_system() -> anyhow::Result<()> {
+    let heavy = Node {
+        addr: "1".to_string(),
+        data: btreemap! {"weight".to_string() => "3".to_string()},
+        ..Default::default()
+    };
+
+    let m = Membership::new_single(btreeset! {1,2,3,4}).with_nodes(btreemap! {1 => heavy});
+
+    let majority = m.quorum_set(&Majority);
+    assert!(!majority.is_quorum(&btreeset! {1,2}));
+    assert!(majority.is_quorum(&btreeset! {1,2,3}));
+
+    // Voter 1 weighs 3 out of 6.
+    let weighted = m.quorum_set(&WeightedSystem);
+    assert!(!weighted.is_quorum(&btreeset! {1}));
+    assert!(weighted.is_quorum(&btreeset! {1,2}));
+    assert!(!weighted.is_quorum(&btreeset! {2,3,4}));
+
+    let values = btreemap! {1=>10, 2=>20, 3=>30, 4=>40};
+    assert_eq!(Some(&10), greatest_quorum_value(&weighted, &values));
+    assert_eq!(Some(&20), greatest_quorum_value(&majority, &values));
+
+    Ok(())
+}
@@ -24,16 +24,19 @@ use crate::raft_types::LogIdOptionExt;
 use crate::LogId;
 use crate::Membership;
 use crate::MessageSummary;
+use crate::Node;
 use crate::NodeId;
+use crate::NodeInfo;
 use crate::ReplicationMetrics;
 
 /// A set of metrics describing the current state of a Raft node.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct RaftMetrics {
-    pub running_state: Result<(), Fatal>,
+#[serde(bound = "")]
+pub struct RaftMetrics<NID: NodeId = u64, NI: NodeInfo = Node> {
+    pub running_state: Result<(), Fatal<NID>>,
 
     /// The ID of the Raft node.
-    pub id: NodeId,
+    pub id: NID,
     /// The state of the Raft node.
     pub state: State,
     /// The current term of the Raft node.
@@ -41,23 +44,28 @@ pub struct RaftMetrics {
     /// The last log index has been appended to this Raft node's log.
     pub last_log_index: Option<u64>,
     /// The last log index has been applied to this Raft node's state machine.
-    pub last_applied: Option<LogId>,
+    pub last_applied: Option<LogId<NID>>,
     /// The current cluster leader.
-    pub current_leader: Option<NodeId>,
+    pub current_leader: Option<NID>,
     /// The current membership config of the cluster.
-    pub membership_config: EffectiveMembership,
+    pub membership_config: EffectiveMembership<NID, NI>,
 
     /// The id of the last log included in snapshot.
     /// If there is no snapshot, it is (0,0).
-    pub snapshot: Option<LogId>,
+    pub snapshot: Option<LogId<NID>>,
 
     /// The metrics about the leader. It is Some() only when this node is leader.
-    pub leader_metrics: Option<LeaderMetrics>,
+    pub leader_metrics: Option<LeaderMetrics<NID>>,
+
+    /// The number of times this node stepped down from leader because a quorum did not respond to it within
+    /// `election_timeout_max`. See `Config::enable_check_quorum`.
+    #[serde(default)]
+    pub quorum_lost: u64,
 }
 
-impl MessageSummary for RaftMetrics {
+impl<NID: NodeId, NI: NodeInfo> MessageSummary for RaftMetrics<NID, NI> {
     fn summary(&self) -> String {
-        format!("Metrics{{id:{},{:?}, term:{}, last_log:{:?}, last_applied:{:?}, leader:{:?}, membership:{}, snapshot:{:?}, replication:{}",
+        format!("Metrics{{id:{},{:?}, term:{}, last_log:{:?}, last_applied:{:?}, leader:{:?}, membership:{}, snapshot:{:?}, replication:{}, quorum_lost:{}",
             self.id,
             self.state,
             self.current_term,
@@ -67,18 +75,26 @@ impl MessageSummary for RaftMetrics {
             self.membership_config.summary(),
             self.snapshot,
             self.leader_metrics.as_ref().map(|x| x.summary()).unwrap_or_default(),
+            self.quorum_lost,
         )
     }
 }
 
 /// The metrics about the leader. It is Some() only when this node is leader.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
-pub struct LeaderMetrics {
+#[serde(bound = "")]
+pub struct LeaderMetrics<NID: NodeId = u64> {
     /// Replication metrics of all known replication target: voters and learners
-    pub replication: HashMap<NodeId, ReplicationMetrics>,
+    pub replication: HashMap<NID, ReplicationMetrics<NID>>,
+
+    /// Learners to be promoted to voters once they catch up with the leader.
+    ///
+    /// The marks are kept only by this leader and are cleared when the leadership changes.
+    #[serde(default)]
+    pub promoting: BTreeSet<NID>,
 }
 
-impl MessageSummary for LeaderMetrics {
+impl<NID: NodeId> MessageSummary for LeaderMetrics<NID> {
     fn summary(&self) -> String {
         let mut res = vec!["LeaderMetrics{".to_string()];
         for (i, (k, v)) in self.replication.iter().enumerate() {
@@ -88,14 +104,18 @@ impl MessageSummary for LeaderMetrics {
             res.push(format!("{}:{}", k, v.summary()));
         }
 
+        if !self.promoting.is_empty() {
+            res.push(format!(",promoting:{:?}", self.promoting));
+        }
+
         res.push("}".to_string());
         res.join("")
     }
 }
 
-impl RaftMetrics {
-    pub(crate) fn new_initial(id: NodeId) -> Self {
-        let membership_config = Membership::new_initial(id);
+impl<NID: NodeId, NI: NodeInfo> RaftMetrics<NID, NI> {
+    pub(crate) fn new_initial(id: NID) -> Self {
+        let membership_config = Membership::new_initial(id.clone());
         Self {
             running_state: Ok(()),
             id,
@@ -110,6 +130,7 @@ impl RaftMetrics {
             },
             snapshot: None,
             leader_metrics: None,
+            quorum_lost: 0,
         }
     }
 }
@@ -125,16 +146,16 @@ pub enum WaitError {
 }
 
 /// Wait is a wrapper of RaftMetrics channel that impls several utils to wait for metrics to satisfy some condition.
-pub struct Wait {
+pub struct Wait<NID: NodeId = u64, NI: NodeInfo = Node> {
     pub timeout: Duration,
-    pub rx: watch::Receiver<RaftMetrics>,
+    pub rx: watch::Receiver<RaftMetrics<NID, NI>>,
 }
 
-impl Wait {
+impl<NID: NodeId, NI: NodeInfo> Wait<NID, NI> {
     /// Wait for metrics to satisfy some condition or timeout.
     #[tracing::instrument(level = "trace", skip(self, func), fields(msg=%msg.to_string()))]
-    pub async fn metrics<T>(&self, func: T, msg: impl ToString) -> Result<RaftMetrics, WaitError>
-    where T: Fn(&RaftMetrics) -> bool + Send {
+    pub async fn metrics<T>(&self, func: T, msg: impl ToString) -> Result<RaftMetrics<NID, NI>, WaitError>
+    where T: Fn(&RaftMetrics<NID, NI>) -> bool + Send {
         let timeout_at = Instant::now() + self.timeout;
 
         let mut rx = self.rx.clone();
@@ -199,9 +220,9 @@ impl Wait {
 
     /// Wait for `current_leader` to become `Some(leader_id)` until timeout.
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn current_leader(&self, leader_id: NodeId, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn current_leader(&self, leader_id: NID, msg: impl ToString) -> Result<RaftMetrics<NID, NI>, WaitError> {
         self.metrics(
-            |x| x.current_leader == Some(leader_id),
+            |x| x.current_leader == Some(leader_id.clone()),
             &format!("{} .current_leader -> {}", msg.to_string(), leader_id),
         )
         .await
@@ -209,7 +230,11 @@ impl Wait {
 
     /// Wait until applied exactly `want_log`(inclusive) logs or timeout.
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn log(&self, want_log_index: Option<u64>, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn log(
+        &self,
+        want_log_index: Option<u64>,
+        msg: impl ToString,
+    ) -> Result<RaftMetrics<NID, NI>, WaitError> {
         self.metrics(
             |x| x.last_log_index == want_log_index,
             &format!("{} .last_log_index -> {:?}", msg.to_string(), want_log_index),
@@ -225,7 +250,11 @@ impl Wait {
 
     /// Wait until applied at least `want_log`(inclusive) logs or timeout.
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn log_at_least(&self, want_log: Option<u64>, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn log_at_least(
+        &self,
+        want_log: Option<u64>,
+        msg: impl ToString,
+    ) -> Result<RaftMetrics<NID, NI>, WaitError> {
         self.metrics(
             |x| x.last_log_index >= want_log,
             &format!("{} .last_log_index >= {:?}", msg.to_string(), want_log),
@@ -241,7 +270,7 @@ impl Wait {
 
     /// Wait for `state` to become `want_state` or timeout.
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn state(&self, want_state: State, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn state(&self, want_state: State, msg: impl ToString) -> Result<RaftMetrics<NID, NI>, WaitError> {
         self.metrics(
             |x| x.state == want_state,
             &format!("{} .state -> {:?}", msg.to_string(), want_state),
@@ -251,7 +280,11 @@ impl Wait {
 
     /// Wait for `membership_config.members` to become expected node set or timeout.
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn members(&self, want_members: BTreeSet<NodeId>, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn members(
+        &self,
+        want_members: BTreeSet<NID>,
+        msg: impl ToString,
+    ) -> Result<RaftMetrics<NID, NI>, WaitError> {
         self.metrics(
             |x| x.membership_config.membership.get_ith_config(0).cloned().unwrap() == want_members,
             &format!("{} .membership_config.members -> {:?}", msg.to_string(), want_members),
@@ -263,9 +296,9 @@ impl Wait {
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
     pub async fn next_members(
         &self,
-        want_members: Option<BTreeSet<NodeId>>,
+        want_members: Option<BTreeSet<NID>>,
         msg: impl ToString,
-    ) -> Result<RaftMetrics, WaitError> {
+    ) -> Result<RaftMetrics<NID, NI>, WaitError> {
         self.metrics(
             |x| x.membership_config.membership.get_ith_config(1) == want_members.as_ref(),
             &format!("{} .membership_config.next -> {:?}", msg.to_string(), want_members),
@@ -275,9 +308,13 @@ impl Wait {
 
     /// Wait for `snapshot` to become `want_snapshot` or timeout.
     #[tracing::instrument(level = "trace", skip(self), fields(msg=msg.to_string().as_str()))]
-    pub async fn snapshot(&self, want_snapshot: LogId, msg: impl ToString) -> Result<RaftMetrics, WaitError> {
+    pub async fn snapshot(
+        &self,
+        want_snapshot: LogId<NID>,
+        msg: impl ToString,
+    ) -> Result<RaftMetrics<NID, NI>, WaitError> {
         self.metrics(
-            |x| x.snapshot == Some(want_snapshot),
+            |x| x.snapshot == Some(want_snapshot.clone()),
             &format!("{} .snapshot -> {:?}", msg.to_string(), want_snapshot),
         )
         .await
@@ -201,6 +201,7 @@ fn init_wait_test() -> (RaftMetrics, Wait, watch::Sender<RaftMetrics>) {
 
         snapshot: None,
         leader_metrics: None,
+        quorum_lost: 0,
     };
     let (tx, rx) = watch::channel(init.clone());
     let w = Wait {
@@ -7,23 +7,33 @@ use serde::Deserialize;
 use serde::Serialize;
 
 use crate::error::AppendEntriesError;
+use crate::error::ClientReadError;
 use crate::error::InstallSnapshotError;
 use crate::error::RPCError;
+use crate::error::TimeoutNowError;
 use crate::error::VoteError;
 use crate::raft::AppendEntriesRequest;
 use crate::raft::AppendEntriesResponse;
 use crate::raft::InstallSnapshotRequest;
 use crate::raft::InstallSnapshotResponse;
+use crate::raft::ReadIndexRequest;
+use crate::raft::ReadIndexResponse;
+use crate::raft::TimeoutNowRequest;
+use crate::raft::TimeoutNowResponse;
 use crate::raft::VoteRequest;
 use crate::raft::VoteResponse;
 use crate::AppData;
+use crate::Node;
 use crate::NodeId;
+use crate::NodeInfo;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RPCTypes {
     Vote,
     AppendEntries,
     InstallSnapshot,
+    TimeoutNow,
+    ReadIndex,
 }
 
 impl std::fmt::Display for RPCTypes {
@@ -34,26 +44,56 @@ impl std::fmt::Display for RPCTypes {
 
 /// A trait defining the interface for a Raft network between cluster members.
 ///
+/// Every method receives the info of the target node, e.g., its address, which is stored in the membership config.
+/// It is `None` if no node info is provided when the target is added to the cluster.
+///
 /// See the [network chapter of the guide](https://datafuselabs.github.io/openraft/getting-started.html#3-impl-raftnetwork)
 /// for details and discussion on this trait and how to implement it.
 #[async_trait]
-pub trait RaftNetwork<D>: Send + Sync + 'static
-where D: AppData
+pub trait RaftNetwork<D, NID = u64, NI = Node>: Send + Sync + 'static
+where
+    D: AppData,
+    NID: NodeId,
+    NI: NodeInfo,
 {
     /// Send an AppendEntries RPC to the target Raft node (§5).
     async fn send_append_entries(
         &self,
-        target: NodeId,
-        rpc: AppendEntriesRequest<D>,
-    ) -> Result<AppendEntriesResponse, RPCError<AppendEntriesError>>;
+        target: NID,
+        target_node: Option<&NI>,
+        rpc: AppendEntriesRequest<D, NID, NI>,
+    ) -> Result<AppendEntriesResponse<NID>, RPCError<AppendEntriesError<NID>, NID>>;
 
     /// Send an InstallSnapshot RPC to the target Raft node (§7).
     async fn send_install_snapshot(
         &self,
-        target: NodeId,
-        rpc: InstallSnapshotRequest,
-    ) -> Result<InstallSnapshotResponse, RPCError<InstallSnapshotError>>;
+        target: NID,
+        target_node: Option<&NI>,
+        rpc: InstallSnapshotRequest<NID, NI>,
+    ) -> Result<InstallSnapshotResponse<NID>, RPCError<InstallSnapshotError<NID>, NID>>;
 
     /// Send a RequestVote RPC to the target Raft node (§5).
-    async fn send_vote(&self, target: NodeId, rpc: VoteRequest) -> Result<VoteResponse, RPCError<VoteError>>;
+    async fn send_vote(
+        &self,
+        target: NID,
+        target_node: Option<&NI>,
+        rpc: VoteRequest<NID>,
+    ) -> Result<VoteResponse<NID>, RPCError<VoteError<NID>, NID>>;
+
+    /// Send a TimeoutNow RPC to the target Raft node, to make it start an election at once (§3.10 of the thesis).
+    async fn send_timeout_now(
+        &self,
+        target: NID,
+        target_node: Option<&NI>,
+        rpc: TimeoutNowRequest<NID>,
+    ) -> Result<TimeoutNowResponse<NID>, RPCError<TimeoutNowError<NID>, NID>>;
+
+    /// Send a ReadIndex RPC to the leader, to get the log id a follower has to apply before serving a linearizable
+    /// read (§6.4 of the thesis).
+    async fn send_read_index(
+        &self,
+        target: NID,
+        target_node: Option<&NI>,
+        rpc: ReadIndexRequest<NID>,
+    ) -> Result<ReadIndexResponse<NID>, RPCError<ClientReadError<NID, NI>, NID>>;
 }
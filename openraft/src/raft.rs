@@ -1,6 +1,6 @@
 //! Public Raft interface and data types.
 
-use std::collections::BTreeSet;
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::sync::Arc;
 use std::time::Duration;
@@ -13,9 +13,11 @@ use tokio::sync::watch;
 use tokio::sync::Mutex;
 use tokio::task::JoinError;
 use tokio::task::JoinHandle;
+use tokio::time::Instant;
 use tracing::Span;
 
 use crate::config::Config;
+use crate::core::EffectiveMembership;
 use crate::core::RaftCore;
 use crate::error::AddLearnerError;
 use crate::error::AppendEntriesError;
@@ -24,24 +26,50 @@ use crate::error::ClientWriteError;
 use crate::error::Fatal;
 use crate::error::InitializeError;
 use crate::error::InstallSnapshotError;
+use crate::error::PromoteLearnerError;
+use crate::error::PurgeLogError;
+use crate::error::TimeoutNowError;
+use crate::error::TransferLeaderError;
+use crate::error::TransferLeaderTimeout;
+use crate::error::TriggerSnapshotError;
 use crate::error::VoteError;
+use crate::membership::quorum::Majority;
+use crate::membership::quorum::QuorumSystem;
 use crate::metrics::RaftMetrics;
 use crate::metrics::Wait;
+use crate::metrics::WaitError;
+use crate::raft_types::LogIdOptionExt;
 use crate::AppData;
 use crate::AppDataResponse;
+use crate::ChangeMembers;
+use crate::IntoNodes;
 use crate::LogId;
 use crate::Membership;
 use crate::MessageSummary;
+use crate::Node;
 use crate::NodeId;
+use crate::NodeInfo;
 use crate::RaftNetwork;
 use crate::RaftStorage;
+use crate::SnapshotCompression;
 use crate::SnapshotMeta;
 use crate::Vote;
 
-struct RaftInner<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    tx_api: mpsc::UnboundedSender<(RaftMsg<D, R>, Span)>,
-    rx_metrics: watch::Receiver<RaftMetrics>,
-    raft_handle: Mutex<Option<JoinHandle<Result<(), Fatal>>>>,
+/// The handle of the spawned `RaftCore` task.
+type RaftCoreHandle<NID> = JoinHandle<Result<(), Fatal<NID>>>;
+
+struct RaftInner<
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID, NI>,
+    S: RaftStorage<D, R, NID, NI>,
+    NID: NodeId,
+    NI: NodeInfo,
+> {
+    config: Arc<Config>,
+    tx_api: mpsc::UnboundedSender<(RaftMsg<D, R, NID, NI>, Span)>,
+    rx_metrics: watch::Receiver<RaftMetrics<NID, NI>>,
+    raft_handle: Mutex<Option<RaftCoreHandle<NID>>>,
     tx_shutdown: Mutex<Option<oneshot::Sender<()>>>,
     marker_n: std::marker::PhantomData<N>,
     marker_s: std::marker::PhantomData<S>,
@@ -68,11 +96,26 @@ struct RaftInner<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStora
 /// is shutting down (potentially for data safety reasons due to a storage error), and the `shutdown`
 /// method should be called on this type to await the shutdown of the node. If the parent
 /// application needs to shutdown the Raft node for any reason, calling `shutdown` will do the trick.
-pub struct Raft<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
-    inner: Arc<RaftInner<D, R, N, S>>,
+pub struct Raft<
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID, NI>,
+    S: RaftStorage<D, R, NID, NI>,
+    NID: NodeId = u64,
+    NI: NodeInfo = Node,
+> {
+    inner: Arc<RaftInner<D, R, N, S, NID, NI>>,
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Raft<D, R, N, S> {
+impl<
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > Raft<D, R, N, S, NID, NI>
+{
     /// Create and spawn a new Raft task.
     ///
     /// ### `id`
@@ -92,14 +135,41 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// An implementation of the `RaftStorage` trait which will be used by Raft for data storage.
     /// See the docs on the `RaftStorage` trait for more details.
     #[tracing::instrument(level="debug", skip(config, network, storage), fields(cluster=%config.cluster_name))]
-    pub fn new(id: NodeId, config: Arc<Config>, network: Arc<N>, storage: Arc<S>) -> Self {
+    pub fn new(id: NID, config: Arc<Config>, network: Arc<N>, storage: Arc<S>) -> Self {
+        Self::with_quorum_system(id, config, Arc::new(Majority), network, storage)
+    }
+
+    /// Create and spawn a new Raft task that uses `quorum_system` to decide quorums.
+    ///
+    /// `quorum_system` builds the quorum set of every config in a membership config, which is used to count the votes
+    /// of an election and to calculate the committed log id. `Raft::new()` uses the [`Majority`] quorum system.
+    ///
+    /// Every node in a cluster must use the same quorum system.
+    #[tracing::instrument(level="debug", skip(config, quorum_system, network, storage), fields(cluster=%config.cluster_name))]
+    pub fn with_quorum_system(
+        id: NID,
+        config: Arc<Config>,
+        quorum_system: Arc<dyn QuorumSystem<NID, NI>>,
+        network: Arc<N>,
+        storage: Arc<S>,
+    ) -> Self {
         let (tx_api, rx_api) = mpsc::unbounded_channel();
-        let (tx_metrics, rx_metrics) = watch::channel(RaftMetrics::new_initial(id));
+        let (tx_metrics, rx_metrics) = watch::channel(RaftMetrics::new_initial(id.clone()));
         let (tx_shutdown, rx_shutdown) = oneshot::channel();
 
-        let raft_handle = RaftCore::spawn(id, config, network, storage, rx_api, tx_metrics, rx_shutdown);
+        let raft_handle = RaftCore::spawn(
+            id,
+            config.clone(),
+            quorum_system,
+            network,
+            storage,
+            rx_api,
+            tx_metrics,
+            rx_shutdown,
+        );
 
         let inner = RaftInner {
+            config,
             tx_api,
             rx_metrics,
             raft_handle: Mutex::new(Some(raft_handle)),
@@ -117,8 +187,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     #[tracing::instrument(level = "trace", skip(self, rpc), fields(rpc=%rpc.summary()))]
     pub async fn append_entries(
         &self,
-        rpc: AppendEntriesRequest<D>,
-    ) -> Result<AppendEntriesResponse, AppendEntriesError> {
+        rpc: AppendEntriesRequest<D, NID, NI>,
+    ) -> Result<AppendEntriesResponse<NID>, AppendEntriesError<NID>> {
         let (tx, rx) = oneshot::channel();
         self.call_core(RaftMsg::AppendEntries { rpc, tx }, rx).await
     }
@@ -127,7 +197,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     ///
     /// These RPCs are sent by cluster peers which are in candidate state attempting to gather votes (§5.2).
     #[tracing::instrument(level = "debug", skip(self, rpc), fields(rpc=%rpc.summary()))]
-    pub async fn vote(&self, rpc: VoteRequest) -> Result<VoteResponse, VoteError> {
+    pub async fn vote(&self, rpc: VoteRequest<NID>) -> Result<VoteResponse<NID>, VoteError<NID>> {
         let (tx, rx) = oneshot::channel();
         self.call_core(RaftMsg::RequestVote { rpc, tx }, rx).await
     }
@@ -139,32 +209,86 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     #[tracing::instrument(level = "debug", skip(self, rpc), fields(snapshot_id=%rpc.meta.last_log_id))]
     pub async fn install_snapshot(
         &self,
-        rpc: InstallSnapshotRequest,
-    ) -> Result<InstallSnapshotResponse, InstallSnapshotError> {
+        rpc: InstallSnapshotRequest<NID, NI>,
+    ) -> Result<InstallSnapshotResponse<NID>, InstallSnapshotError<NID>> {
         let (tx, rx) = oneshot::channel();
         self.call_core(RaftMsg::InstallSnapshot { rpc, tx }, rx).await
     }
 
+    /// Submit a TimeoutNow RPC to this Raft node.
+    ///
+    /// These RPCs are sent by the cluster leader to a fully caught up follower when transferring leadership to it.
+    /// The follower then starts an election at once, without waiting for an election timeout.
+    #[tracing::instrument(level = "debug", skip(self, rpc), fields(rpc=%rpc.summary()))]
+    pub async fn timeout_now(
+        &self,
+        rpc: TimeoutNowRequest<NID>,
+    ) -> Result<TimeoutNowResponse<NID>, TimeoutNowError<NID>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::TimeoutNow { rpc, tx }, rx).await
+    }
+
     /// Get the ID of the current leader from this Raft node.
     ///
     /// This method is based on the Raft metrics system which does a good job at staying
     /// up-to-date; however, the `client_read` method must still be used to guard against stale
     /// reads. This method is perfect for making decisions on where to route client requests.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn current_leader(&self) -> Option<NodeId> {
-        self.metrics().borrow().current_leader
+    pub async fn current_leader(&self) -> Option<NID> {
+        self.metrics().borrow().current_leader.clone()
     }
 
     /// Check to ensure this node is still the cluster leader, in order to guard against stale reads (§8).
     ///
     /// The actual read operation itself is up to the application, this method just ensures that
     /// the read will not be stale.
+    ///
+    /// With `Config::enable_lease_read`, it returns at once while the leader lease is valid.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn client_read(&self) -> Result<(), ClientReadError> {
+    pub async fn client_read(&self) -> Result<(), ClientReadError<NID, NI>> {
         let (tx, rx) = oneshot::channel();
         self.call_core(RaftMsg::ClientReadRequest { tx }, rx).await
     }
 
+    /// Get a read index to serve a linearizable read on this node, which may be a follower or learner (§6.4 of the
+    /// thesis).
+    ///
+    /// The leader confirms its leadership as `client_read` does, and returns its committed log id as the read index.
+    /// A follower or learner asks the leader for it with a ReadIndex RPC.
+    /// This method returns the read index after this node has applied logs up to it.
+    /// Then the application is free to read from the local state machine.
+    ///
+    /// It waits for the local state machine without a timeout. The caller may wrap it in a timeout.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn client_read_index(&self) -> Result<Option<LogId<NID>>, ClientReadError<NID, NI>> {
+        let (tx, rx) = oneshot::channel();
+        let read_log_id = self.call_core(RaftMsg::ClientReadIndex { tx }, rx).await?;
+
+        let mut rx_metrics = self.inner.rx_metrics.clone();
+        loop {
+            if rx_metrics.borrow().last_applied.index() >= read_log_id.index() {
+                return Ok(read_log_id);
+            }
+
+            if rx_metrics.changed().await.is_err() {
+                return Err(Fatal::Stopped.into());
+            }
+        }
+    }
+
+    /// Submit a ReadIndex RPC to this Raft node.
+    ///
+    /// These RPCs are sent by a follower or learner to the leader in `client_read_index`.
+    /// The leader responds with the read index once it has confirmed its leadership.
+    #[tracing::instrument(level = "debug", skip(self, rpc), fields(rpc=%rpc.summary()))]
+    pub async fn read_index(
+        &self,
+        rpc: ReadIndexRequest<NID>,
+    ) -> Result<ReadIndexResponse<NID>, ClientReadError<NID, NI>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::ReadIndex { rpc, tx }, rx).await
+    }
+
     /// Submit a mutating client request to Raft to update the state of the system (§5.1).
     ///
     /// It will be appended to the log, committed to the cluster, and then applied to the
@@ -183,11 +307,55 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// These are application specific requirements, and must be implemented by the application which is
     /// being built on top of Raft.
     #[tracing::instrument(level = "debug", skip(self, rpc))]
-    pub async fn client_write(&self, rpc: ClientWriteRequest<D>) -> Result<ClientWriteResponse<R>, ClientWriteError> {
+    pub async fn client_write(
+        &self,
+        rpc: ClientWriteRequest<D, NID, NI>,
+    ) -> Result<ClientWriteResponse<R, NID, NI>, ClientWriteError<NID, NI>> {
         let (tx, rx) = oneshot::channel();
         self.call_core(RaftMsg::ClientWriteRequest { rpc, tx }, rx).await
     }
 
+    /// Submit several mutating client requests to Raft at once.
+    ///
+    /// The requests are sent to RaftCore in one message, thus their entries are appended to the log with a single
+    /// `RaftStorage::append_to_log` call, in the order of `rpcs`, no matter how many there are.
+    /// Other client requests already queued are grouped with them only while the group has less than
+    /// `Config::max_payload_entries` entries.
+    ///
+    /// Every request gets its own result, in the same order, just like calling `client_write` for each of them.
+    /// An empty `rpcs` returns an empty result at once.
+    #[tracing::instrument(level = "debug", skip(self, rpcs), fields(rpcs = rpcs.len()))]
+    pub async fn client_write_many(
+        &self,
+        rpcs: Vec<ClientWriteRequest<D, NID, NI>>,
+    ) -> Vec<Result<ClientWriteResponse<R, NID, NI>, ClientWriteError<NID, NI>>> {
+        if rpcs.is_empty() {
+            return vec![];
+        }
+
+        let mut reqs = Vec::with_capacity(rpcs.len());
+        let mut rxs = Vec::with_capacity(rpcs.len());
+
+        for rpc in rpcs {
+            let (tx, rx) = oneshot::channel();
+            reqs.push((rpc, tx));
+            rxs.push(rx);
+        }
+
+        let mes = RaftMsg::ClientWriteMany { reqs };
+        let sum = mes.summary();
+
+        if let Err(fatal) = self.send_to_core(mes, &sum) {
+            return rxs.iter().map(|_| Err(fatal.clone().into())).collect();
+        }
+
+        let mut results = Vec::with_capacity(rxs.len());
+        for rx in rxs {
+            results.push(self.recv_from_core(rx, &sum).await);
+        }
+        results
+    }
+
     /// Initialize a pristine Raft node with the given config.
     ///
     /// This command should be called on pristine nodes — where the log index is 0 and the node is
@@ -216,10 +384,20 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// Every member of the cluster should perform these actions. This routine is race-condition
     /// free, and Raft guarantees that the first node to become the cluster leader will propagate
     /// only its own config.
-    #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn initialize(&self, members: BTreeSet<NodeId>) -> Result<(), InitializeError> {
+    ///
+    /// `members` is either a `BTreeSet<NodeId>`, or a `BTreeMap<NodeId, NodeInfo>` to store the node info, e.g., the
+    /// addresses, in the initial membership config.
+    #[tracing::instrument(level = "debug", skip(self, members))]
+    pub async fn initialize(&self, members: impl IntoNodes<NID, NI>) -> Result<(), InitializeError<NID>> {
         let (tx, rx) = oneshot::channel();
-        self.call_core(RaftMsg::Initialize { members, tx }, rx).await
+        self.call_core(
+            RaftMsg::Initialize {
+                members: members.into_nodes(),
+                tx,
+            },
+            rx,
+        )
+        .await
     }
 
     /// Synchronize a new Raft node, optionally, blocking until up-to-speed (§6).
@@ -233,11 +411,20 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     ///
     /// If blocking is false, this function returns at once as successfully setting up the replication.
     ///
-    /// If the node to add is already a voter or learner, it returns `RaftResponse::NoChange` at once.
-    #[tracing::instrument(level = "debug", skip(self, id), fields(target=id))]
-    pub async fn add_learner(&self, id: NodeId, blocking: bool) -> Result<AddLearnerResponse, AddLearnerError> {
+    /// If the node to add is already a voter or learner, it returns at once. Its node info is updated if a different
+    /// one is provided.
+    ///
+    /// `node` is the info of the learner, e.g., its address. It is stored in the membership config and is passed to
+    /// `RaftNetwork` when sending RPCs to the learner.
+    #[tracing::instrument(level = "debug", skip(self, id), fields(target=%id))]
+    pub async fn add_learner(
+        &self,
+        id: NID,
+        node: Option<NI>,
+        blocking: bool,
+    ) -> Result<AddLearnerResponse<NID>, AddLearnerError<NID, NI>> {
         let (tx, rx) = oneshot::channel();
-        self.call_core(RaftMsg::AddLearner { id, blocking, tx }, rx).await
+        self.call_core(RaftMsg::AddLearner { id, node, blocking, tx }, rx).await
     }
 
     /// Propose a cluster configuration change.
@@ -255,16 +442,24 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// If `turn_to_learner` is true, then all the members which not exists in the new membership,
     /// will be turned into learners, otherwise will be removed.
     ///
-    /// If it lost leadership or crashed before committing the second **uniform** config log, the cluster is left in the
-    /// **joint** config.
-    #[tracing::instrument(level = "debug", skip(self))]
+    /// If it lost leadership or crashed before committing the second **uniform** config log, the next leader changes
+    /// the **joint** config to the uniform config, and this call returns an error.
+    ///
+    /// `changes` is a [`ChangeMembers`] operation, which is applied to the membership config on the leader.
+    /// A `BTreeSet<NodeId>`, or a `BTreeMap<NodeId, NodeInfo>` to update the node info, replaces all voters.
+    ///
+    /// A change to learners, i.e., `ChangeMembers::AddLearners`, `ChangeMembers::RemoveLearners` or
+    /// `ChangeMembers::AddWitnesses`, commits a single config log.
+    #[tracing::instrument(level = "debug", skip(self, changes))]
     pub async fn change_membership(
         &self,
-        members: BTreeSet<NodeId>,
+        changes: impl Into<ChangeMembers<NID, NI>>,
         blocking: bool,
         turn_to_learner: bool,
-    ) -> Result<ClientWriteResponse<R>, ClientWriteError> {
-        tracing::info!("change_membership: start to commit joint config");
+    ) -> Result<ClientWriteResponse<R, NID, NI>, ClientWriteError<NID, NI>> {
+        let changes = changes.into();
+
+        tracing::info!(?changes, "change_membership: start to commit joint config");
 
         let (tx, rx) = oneshot::channel();
         // res is error if membership can not be changed.
@@ -272,7 +467,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         let res = self
             .call_core(
                 RaftMsg::ChangeMembership {
-                    members: members.clone(),
+                    changes,
                     blocking,
                     turn_to_learner,
                     tx,
@@ -283,21 +478,24 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
 
         tracing::info!("res of first change_membership: {:?}", res.summary());
 
-        let (log_id, joint) = (res.log_id, res.membership.clone().unwrap());
+        let (log_id, joint) = (res.log_id.clone(), res.membership.clone().unwrap());
 
         // There is a previously in progress joint state and it becomes the membership config we want.
         if !joint.is_in_joint_consensus() {
             return Ok(res);
         }
 
+        // The goal voter set is the last config of the joint config. Node info is already stored in it.
+        let goal = joint.get_configs().last().cloned().unwrap_or_default();
+
         tracing::debug!("committed a joint config: {} {:?}", log_id, joint);
-        tracing::debug!("the second step is to change to uniform config: {:?}", members);
+        tracing::debug!("the second step is to change to uniform config: {:?}", goal);
 
         let (tx, rx) = oneshot::channel();
         let res = self
             .call_core(
                 RaftMsg::ChangeMembership {
-                    members,
+                    changes: ChangeMembers::from(goal),
                     blocking,
                     turn_to_learner,
                     tx,
@@ -311,14 +509,108 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
         Ok(res)
     }
 
+    /// Mark the learner `id` to be promoted to a voter once it catches up with the leader.
+    ///
+    /// The leader promotes a marked learner when its replication becomes line-rate, i.e., it lags behind the leader by
+    /// no more than `Config::replication_lag_threshold` logs. The promotion goes through the same joint consensus as
+    /// `change_membership()` with `ChangeMembers::AddVoters`.
+    ///
+    /// It returns once the learner is marked. A marked learner is listed in `LeaderMetrics::promoting` until it is
+    /// promoted.
+    ///
+    /// The mark is kept only by the current leader, for its term. If the leadership changes, the marks are cleared
+    /// along with the `LeaderMetrics` of the leader, and have to be set again on the new leader. A joint config
+    /// already proposed to promote a learner is changed to the uniform config by the new leader.
+    ///
+    /// It returns `Ok` at once if `id` is already a voter.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn promote_learner_when_ready(&self, id: NID) -> Result<(), PromoteLearnerError<NID, NI>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::PromoteLearner { id, promote: true, tx }, rx).await
+    }
+
+    /// Cancel the promotion of the learner `id` marked by `promote_learner_when_ready()`.
+    ///
+    /// It has no effect if the leader has already proposed the membership config that promotes it.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn cancel_learner_promotion(&self, id: NID) -> Result<(), PromoteLearnerError<NID, NI>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::PromoteLearner { id, promote: false, tx }, rx).await
+    }
+
+    /// Transfer leadership to the voter `target`, e.g., before shutting down the current leader for maintenance.
+    ///
+    /// It must be called on the leader. The leader stops accepting writes, waits for the replication to `target` to
+    /// catch up with its last log, then sends a `TimeoutNow` RPC to make `target` start an election at once.
+    ///
+    /// It returns when this node sees `target` as the new leader.
+    /// If the transfer does not complete within `election_timeout_max` since it is started, it returns
+    /// `TransferLeaderError::Timeout` and the leader, if it still is, resumes accepting writes.
+    ///
+    /// Transferring leadership to the leader itself is a no-op.
+    /// A witness never becomes leader: transferring leadership to it returns `TransferLeaderError::IsWitness`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn transfer_leader(&self, target: NID) -> Result<(), TransferLeaderError<NID, NI>> {
+        let (tx, rx) = oneshot::channel();
+        let deadline = self
+            .call_core(
+                RaftMsg::TransferLeader {
+                    target: target.clone(),
+                    tx,
+                },
+                rx,
+            )
+            .await?;
+
+        // `TimeoutNow` has been sent to `target`. Wait for it to win the election, until the leader aborts the
+        // transfer.
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let res = self.wait(Some(remaining)).current_leader(target.clone(), "transfer_leader").await;
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(WaitError::Timeout(_, _)) => {
+                let timeout = Duration::from_millis(self.inner.config.election_timeout_max);
+                Err(TransferLeaderTimeout { target, timeout }.into())
+            }
+            Err(WaitError::ShuttingDown) => Err(Fatal::Stopped.into()),
+        }
+    }
+
+    /// Build a snapshot at once, without waiting for the `SnapshotPolicy` to fire, e.g., before a backup.
+    ///
+    /// It returns the meta of the built snapshot.
+    /// If a snapshot is already being built, it waits for that one instead of building another.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn trigger_snapshot(&self) -> Result<SnapshotMeta<NID>, TriggerSnapshotError<NID>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::TriggerSnapshot { tx }, rx).await
+    }
+
+    /// Purge logs upto index `upto`, inclusive, e.g., to reclaim disk space after a bulk import.
+    ///
+    /// Only applied logs can be purged, otherwise it returns `PurgeLogError::NotApplied`.
+    /// Purging logs that are already purged is a no-op.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub async fn purge_log(&self, upto: u64) -> Result<(), PurgeLogError<NID>> {
+        let (tx, rx) = oneshot::channel();
+        self.call_core(RaftMsg::PurgeLog { upto, tx }, rx).await
+    }
+
     /// Invoke RaftCore by sending a RaftMsg and blocks waiting for response.
     #[tracing::instrument(level = "debug", skip(self, mes, rx))]
-    pub(crate) async fn call_core<T, E>(&self, mes: RaftMsg<D, R>, rx: RaftRespRx<T, E>) -> Result<T, E>
-    where E: From<Fatal> {
-        let span = tracing::Span::current();
-
+    pub(crate) async fn call_core<T, E>(&self, mes: RaftMsg<D, R, NID, NI>, rx: RaftRespRx<T, E>) -> Result<T, E>
+    where E: From<Fatal<NID>> {
         let sum = mes.summary();
 
+        self.send_to_core(mes, &sum)?;
+        self.recv_from_core(rx, &sum).await
+    }
+
+    /// Send a RaftMsg to RaftCore, returns the error that stopped RaftCore if it can not be sent.
+    fn send_to_core(&self, mes: RaftMsg<D, R, NID, NI>, sum: &str) -> Result<(), Fatal<NID>> {
+        let span = tracing::Span::current();
+
         let send_res = self.inner.tx_api.send((mes, span));
         if let Err(send_err) = send_res {
             let last_err = self.inner.rx_metrics.borrow().running_state.clone();
@@ -332,9 +624,15 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
                 Err(e) => e,
             };
 
-            return Err(err.into());
+            return Err(err);
         }
 
+        Ok(())
+    }
+
+    /// Wait for the response to a RaftMsg sent to RaftCore.
+    async fn recv_from_core<T, E>(&self, rx: RaftRespRx<T, E>, sum: &str) -> Result<T, E>
+    where E: From<Fatal<NID>> {
         let recv_res = rx.await;
         let res = match recv_res {
             Ok(x) => x,
@@ -358,7 +656,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     }
 
     /// Get a handle to the metrics channel.
-    pub fn metrics(&self) -> watch::Receiver<RaftMetrics> {
+    pub fn metrics(&self) -> watch::Receiver<RaftMetrics<NID, NI>> {
         self.inner.rx_metrics.clone()
     }
 
@@ -379,7 +677,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     /// // wait for raft state to become a follower
     /// r.wait(None).state(State::Follower, "state").await?;
     /// ```
-    pub fn wait(&self, timeout: Option<Duration>) -> Wait {
+    pub fn wait(&self, timeout: Option<Duration>) -> Wait<NID, NI> {
         let timeout = match timeout {
             Some(t) => t,
             None => Duration::from_millis(500),
@@ -402,7 +700,15 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Ra
     }
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Clone for Raft<D, R, N, S> {
+impl<
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > Clone for Raft<D, R, N, S, NID, NI>
+{
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -414,48 +720,73 @@ pub(crate) type RaftRespTx<T, E> = oneshot::Sender<Result<T, E>>;
 pub(crate) type RaftRespRx<T, E> = oneshot::Receiver<Result<T, E>>;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct AddLearnerResponse {
-    pub matched: Option<LogId>,
+#[serde(bound = "")]
+pub struct AddLearnerResponse<NID: NodeId = u64> {
+    pub matched: Option<LogId<NID>>,
 }
 
 /// A message coming from the Raft API.
-pub(crate) enum RaftMsg<D: AppData, R: AppDataResponse> {
+pub(crate) enum RaftMsg<D: AppData, R: AppDataResponse, NID: NodeId, NI: NodeInfo> {
     AppendEntries {
-        rpc: AppendEntriesRequest<D>,
-        tx: RaftRespTx<AppendEntriesResponse, AppendEntriesError>,
+        rpc: AppendEntriesRequest<D, NID, NI>,
+        tx: RaftRespTx<AppendEntriesResponse<NID>, AppendEntriesError<NID>>,
     },
     RequestVote {
-        rpc: VoteRequest,
-        tx: RaftRespTx<VoteResponse, VoteError>,
+        rpc: VoteRequest<NID>,
+        tx: RaftRespTx<VoteResponse<NID>, VoteError<NID>>,
     },
     InstallSnapshot {
-        rpc: InstallSnapshotRequest,
-        tx: RaftRespTx<InstallSnapshotResponse, InstallSnapshotError>,
+        rpc: InstallSnapshotRequest<NID, NI>,
+        tx: RaftRespTx<InstallSnapshotResponse<NID>, InstallSnapshotError<NID>>,
+    },
+    TimeoutNow {
+        rpc: TimeoutNowRequest<NID>,
+        tx: RaftRespTx<TimeoutNowResponse<NID>, TimeoutNowError<NID>>,
     },
     ClientWriteRequest {
-        rpc: ClientWriteRequest<D>,
-        tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
+        rpc: ClientWriteRequest<D, NID, NI>,
+        tx: RaftRespTx<ClientWriteResponse<R, NID, NI>, ClientWriteError<NID, NI>>,
+    },
+    /// Several client writes to append together, in order.
+    ClientWriteMany {
+        #[allow(clippy::type_complexity)]
+        reqs: Vec<(
+            ClientWriteRequest<D, NID, NI>,
+            RaftRespTx<ClientWriteResponse<R, NID, NI>, ClientWriteError<NID, NI>>,
+        )>,
     },
     ClientReadRequest {
-        tx: RaftRespTx<(), ClientReadError>,
+        tx: RaftRespTx<(), ClientReadError<NID, NI>>,
+    },
+    /// Get a read index for a linearizable read on this node.
+    ClientReadIndex {
+        tx: RaftRespTx<Option<LogId<NID>>, ClientReadError<NID, NI>>,
+    },
+    ReadIndex {
+        rpc: ReadIndexRequest<NID>,
+        tx: RaftRespTx<ReadIndexResponse<NID>, ClientReadError<NID, NI>>,
     },
     Initialize {
-        members: BTreeSet<NodeId>,
-        tx: RaftRespTx<(), InitializeError>,
+        members: BTreeMap<NID, Option<NI>>,
+        tx: RaftRespTx<(), InitializeError<NID>>,
     },
     // TODO(xp): make tx a field of a struct
     /// Request raft core to setup a new replication to a learner.
     AddLearner {
-        id: NodeId,
+        id: NID,
+
+        /// The node info to store in the membership config.
+        node: Option<NI>,
 
         /// If block until the newly added learner becomes line-rate.
         blocking: bool,
 
         /// Send the log id when the replication becomes line-rate.
-        tx: RaftRespTx<AddLearnerResponse, AddLearnerError>,
+        tx: RaftRespTx<AddLearnerResponse<NID>, AddLearnerError<NID, NI>>,
     },
     ChangeMembership {
-        members: BTreeSet<NodeId>,
+        /// The change to apply to the current membership config.
+        changes: ChangeMembers<NID, NI>,
         /// with blocking==false, respond to client a ChangeMembershipError::LearnerIsLagging error at once if a
         /// non-member is lagging.
         ///
@@ -466,11 +797,36 @@ pub(crate) enum RaftMsg<D: AppData, R: AppDataResponse> {
         /// will be turned into learners, otherwise will be removed.
         turn_to_learner: bool,
 
-        tx: RaftRespTx<ClientWriteResponse<R>, ClientWriteError>,
+        tx: RaftRespTx<ClientWriteResponse<R, NID, NI>, ClientWriteError<NID, NI>>,
+    },
+    /// Mark or unmark a learner to be promoted to a voter once it catches up with the leader.
+    PromoteLearner {
+        id: NID,
+
+        /// Mark the learner if it is true, otherwise unmark it.
+        promote: bool,
+
+        tx: RaftRespTx<(), PromoteLearnerError<NID, NI>>,
+    },
+    /// Request the leader to transfer leadership to `target`.
+    ///
+    /// It responds with the deadline of the transfer once `TimeoutNow` has been sent to `target`.
+    TransferLeader {
+        target: NID,
+        tx: RaftRespTx<Instant, TransferLeaderError<NID, NI>>,
+    },
+    /// Build a snapshot at once.
+    TriggerSnapshot {
+        tx: RaftRespTx<SnapshotMeta<NID>, TriggerSnapshotError<NID>>,
+    },
+    /// Purge applied logs upto index `upto`, inclusive.
+    PurgeLog {
+        upto: u64,
+        tx: RaftRespTx<(), PurgeLogError<NID>>,
     },
 }
 
-impl<D, R> MessageSummary for RaftMsg<D, R>
+impl<D, R, NID: NodeId, NI: NodeInfo> MessageSummary for RaftMsg<D, R, NID, NI>
 where
     D: AppData,
     R: AppDataResponse,
@@ -486,27 +842,47 @@ where
             RaftMsg::InstallSnapshot { rpc, .. } => {
                 format!("InstallSnapshot: {}", rpc.summary())
             }
+            RaftMsg::TimeoutNow { rpc, .. } => {
+                format!("TimeoutNow: {}", rpc.summary())
+            }
             RaftMsg::ClientWriteRequest { rpc, .. } => {
                 format!("ClientWriteRequest: {}", rpc.summary())
             }
+            RaftMsg::ClientWriteMany { reqs } => {
+                format!("ClientWriteMany: {} requests", reqs.len())
+            }
             RaftMsg::ClientReadRequest { .. } => "ClientReadRequest".to_string(),
+            RaftMsg::ClientReadIndex { .. } => "ClientReadIndex".to_string(),
+            RaftMsg::ReadIndex { rpc, .. } => {
+                format!("ReadIndex: {}", rpc.summary())
+            }
             RaftMsg::Initialize { members, .. } => {
                 format!("Initialize: {:?}", members)
             }
-            RaftMsg::AddLearner { id, blocking, .. } => {
-                format!("AddLearner: id: {}, blocking: {}", id, blocking)
+            RaftMsg::AddLearner { id, node, blocking, .. } => {
+                format!("AddLearner: id: {}, node: {:?}, blocking: {}", id, node, blocking)
             }
             RaftMsg::ChangeMembership {
-                members,
+                changes,
                 blocking,
                 turn_to_learner,
                 ..
             } => {
                 format!(
-                    "ChangeMembership: members: {:?}, blocking: {}, turn_to_learner: {}",
-                    members, blocking, turn_to_learner,
+                    "ChangeMembership: changes: {:?}, blocking: {}, turn_to_learner: {}",
+                    changes, blocking, turn_to_learner,
                 )
             }
+            RaftMsg::PromoteLearner { id, promote, .. } => {
+                format!("PromoteLearner: id: {}, promote: {}", id, promote)
+            }
+            RaftMsg::TransferLeader { target, .. } => {
+                format!("TransferLeader: target: {}", target)
+            }
+            RaftMsg::TriggerSnapshot { .. } => "TriggerSnapshot".to_string(),
+            RaftMsg::PurgeLog { upto, .. } => {
+                format!("PurgeLog: upto: {}", upto)
+            }
         }
     }
 }
@@ -515,23 +891,24 @@ where
 
 /// An RPC sent by a cluster leader to replicate log entries (§5.3), and as a heartbeat (§5.2).
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AppendEntriesRequest<D: AppData> {
-    pub vote: Vote,
+#[serde(bound = "")]
+pub struct AppendEntriesRequest<D: AppData, NID: NodeId = u64, NI: NodeInfo = Node> {
+    pub vote: Vote<NID>,
 
-    pub prev_log_id: Option<LogId>,
+    pub prev_log_id: Option<LogId<NID>>,
 
     /// The new log entries to store.
     ///
     /// This may be empty when the leader is sending heartbeats. Entries
     /// are batched for efficiency.
     #[serde(bound = "D: AppData")]
-    pub entries: Vec<Entry<D>>,
+    pub entries: Vec<Entry<D, NID, NI>>,
 
     /// The leader's committed log id.
-    pub leader_commit: Option<LogId>,
+    pub leader_commit: Option<LogId<NID>>,
 }
 
-impl<D: AppData> MessageSummary for AppendEntriesRequest<D> {
+impl<D: AppData, NID: NodeId, NI: NodeInfo> MessageSummary for AppendEntriesRequest<D, NID, NI> {
     fn summary(&self) -> String {
         format!(
             "vote={}, prev_log_id={}, leader_commit={}, entries={}",
@@ -545,38 +922,58 @@ impl<D: AppData> MessageSummary for AppendEntriesRequest<D> {
 
 /// The response to an `AppendEntriesRequest`.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct AppendEntriesResponse {
-    pub vote: Vote,
+#[serde(bound = "")]
+pub struct AppendEntriesResponse<NID: NodeId = u64> {
+    pub vote: Vote<NID>,
     pub success: bool,
     pub conflict: bool,
+
+    /// Where the log of the follower diverges, sent along with a conflict.
+    ///
+    /// The leader uses it to find the matching log in one or two round trips, instead of bisecting the log.
+    #[serde(default)]
+    pub conflict_hint: Option<ConflictHint<NID>>,
 }
 
-impl MessageSummary for AppendEntriesResponse {
+impl<NID: NodeId> MessageSummary for AppendEntriesResponse<NID> {
     fn summary(&self) -> String {
         format!(
-            "vote:{}, success:{:?}, conflict:{:?}",
-            self.vote, self.success, self.conflict
+            "vote:{}, success:{:?}, conflict:{:?}, conflict_hint:{:?}",
+            self.vote, self.success, self.conflict, self.conflict_hint
         )
     }
 }
 
+/// A hint from a follower about where its log diverges from the `prev_log_id` of an AppendEntries RPC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum ConflictHint<NID: NodeId = u64> {
+    /// The follower has no log at `prev_log_id.index`; this is its last log id.
+    LastLogId(Option<LogId<NID>>),
+
+    /// The log at `prev_log_id.index` on the follower is proposed by another leader; this is the first index of the
+    /// logs proposed by that leader, which are all regarded as conflicting.
+    ConflictTermStart(u64),
+}
+
 /// A Raft log entry.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct Entry<D: AppData> {
-    pub log_id: LogId,
+#[serde(bound = "")]
+pub struct Entry<D: AppData, NID: NodeId = u64, NI: NodeInfo = Node> {
+    pub log_id: LogId<NID>,
 
     /// This entry's payload.
     #[serde(bound = "D: AppData")]
-    pub payload: EntryPayload<D>,
+    pub payload: EntryPayload<D, NID, NI>,
 }
 
-impl<D: AppData> MessageSummary for Entry<D> {
+impl<D: AppData, NID: NodeId, NI: NodeInfo> MessageSummary for Entry<D, NID, NI> {
     fn summary(&self) -> String {
         format!("{}:{}", self.log_id, self.payload.summary())
     }
 }
 
-impl<D: AppData> MessageSummary for Option<Entry<D>> {
+impl<D: AppData, NID: NodeId, NI: NodeInfo> MessageSummary for Option<Entry<D, NID, NI>> {
     fn summary(&self) -> String {
         match self {
             None => "None".to_string(),
@@ -585,14 +982,14 @@ impl<D: AppData> MessageSummary for Option<Entry<D>> {
     }
 }
 
-impl<D: AppData> MessageSummary for &[Entry<D>] {
+impl<D: AppData, NID: NodeId, NI: NodeInfo> MessageSummary for &[Entry<D, NID, NI>] {
     fn summary(&self) -> String {
         let entry_refs: Vec<_> = self.iter().collect();
         entry_refs.as_slice().summary()
     }
 }
 
-impl<D: AppData> MessageSummary for &[&Entry<D>] {
+impl<D: AppData, NID: NodeId, NI: NodeInfo> MessageSummary for &[&Entry<D, NID, NI>] {
     fn summary(&self) -> String {
         let mut res = Vec::with_capacity(self.len());
         if self.len() <= 5 {
@@ -613,7 +1010,8 @@ impl<D: AppData> MessageSummary for &[&Entry<D>] {
 
 /// Log entry payload variants.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub enum EntryPayload<D: AppData> {
+#[serde(bound = "")]
+pub enum EntryPayload<D: AppData, NID: NodeId = u64, NI: NodeInfo = Node> {
     /// An empty payload committed by a new cluster leader.
     Blank,
 
@@ -621,10 +1019,10 @@ pub enum EntryPayload<D: AppData> {
     Normal(D),
 
     /// A change-membership log entry.
-    Membership(Membership),
+    Membership(Membership<NID, NI>),
 }
 
-impl<D: AppData> MessageSummary for EntryPayload<D> {
+impl<D: AppData, NID: NodeId, NI: NodeInfo> MessageSummary for EntryPayload<D, NID, NI> {
     fn summary(&self) -> String {
         match self {
             EntryPayload::Blank => "blank".to_string(),
@@ -637,56 +1035,145 @@ impl<D: AppData> MessageSummary for EntryPayload<D> {
 }
 
 /// An RPC sent by candidates to gather votes (§5.2).
-#[derive(Debug, Serialize, Deserialize)]
-pub struct VoteRequest {
-    pub vote: Vote,
-    pub last_log_id: Option<LogId>,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct VoteRequest<NID: NodeId = u64> {
+    pub vote: Vote<NID>,
+    pub last_log_id: Option<LogId<NID>>,
+
+    /// The candidate is campaigning because the leader transferred leadership to it.
+    ///
+    /// A voter does not reject such a request for having heard from the leader recently.
+    #[serde(default)]
+    pub leader_transfer: bool,
+
+    /// This is a pre-vote request (§9.6 of the thesis).
+    ///
+    /// `vote` is the vote the candidate would use in the next election and has not been persisted.
+    /// A voter only tells whether it would grant it, without changing any of its state.
+    #[serde(default)]
+    pub pre_vote: bool,
 }
 
-impl MessageSummary for VoteRequest {
+impl<NID: NodeId> MessageSummary for VoteRequest<NID> {
     fn summary(&self) -> String {
-        format!("{}, last_log:{:?}", self.vote, self.last_log_id)
+        format!(
+            "{}, last_log:{:?}, leader_transfer:{}, pre_vote:{}",
+            self.vote, self.last_log_id, self.leader_transfer, self.pre_vote
+        )
     }
 }
 
-impl VoteRequest {
-    pub fn new(vote: Vote, last_log_id: Option<LogId>) -> Self {
-        Self { vote, last_log_id }
+impl<NID: NodeId> VoteRequest<NID> {
+    pub fn new(vote: Vote<NID>, last_log_id: Option<LogId<NID>>) -> Self {
+        Self {
+            vote,
+            last_log_id,
+            leader_transfer: false,
+            pre_vote: false,
+        }
     }
 }
 
 /// The response to a `VoteRequest`.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct VoteResponse {
-    pub vote: Vote,
+#[serde(bound = "")]
+pub struct VoteResponse<NID: NodeId = u64> {
+    pub vote: Vote<NID>,
 
     /// Will be true if the candidate received a vote from the responder.
     pub vote_granted: bool,
 
     /// The last log id stored on the remote voter.
-    pub last_log_id: Option<LogId>,
+    pub last_log_id: Option<LogId<NID>>,
+}
+
+/// An RPC sent by the leader to a caught up follower, asking it to start an election at once.
+///
+/// It is used to transfer leadership.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct TimeoutNowRequest<NID: NodeId = u64> {
+    /// The vote of the leader that is transferring leadership.
+    pub vote: Vote<NID>,
+}
+
+impl<NID: NodeId> MessageSummary for TimeoutNowRequest<NID> {
+    fn summary(&self) -> String {
+        format!("{}", self.vote)
+    }
+}
+
+/// The response to a `TimeoutNowRequest`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct TimeoutNowResponse<NID: NodeId = u64> {
+    pub vote: Vote<NID>,
+}
+
+/// An RPC sent by a follower or learner to the leader, asking for the log id to apply before serving a linearizable
+/// read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ReadIndexRequest<NID: NodeId = u64> {
+    /// The node asking for a read index.
+    pub from: NID,
+}
+
+impl<NID: NodeId> MessageSummary for ReadIndexRequest<NID> {
+    fn summary(&self) -> String {
+        format!("from: {}", self.from)
+    }
+}
+
+/// The response to a `ReadIndexRequest`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ReadIndexResponse<NID: NodeId = u64> {
+    /// The log id a node has to apply before serving a linearizable read.
+    pub read_log_id: Option<LogId<NID>>,
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
 
 /// An RPC sent by the Raft leader to send chunks of a snapshot to a follower (§7).
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct InstallSnapshotRequest {
-    pub vote: Vote,
+#[serde(bound = "")]
+pub struct InstallSnapshotRequest<NID: NodeId = u64, NI: NodeInfo = Node> {
+    pub vote: Vote<NID>,
 
     /// Metadata of a snapshot: snapshot_id, last_log_ed membership etc.
-    pub meta: SnapshotMeta,
+    pub meta: SnapshotMeta<NID>,
 
     /// The byte offset where this chunk of data is positioned in the snapshot file.
     pub offset: u64,
     /// The raw bytes of the snapshot chunk, starting at `offset`.
     pub data: Vec<u8>,
 
+    /// The compression of `data`.
+    ///
+    /// `offset` is always a position in the uncompressed snapshot.
+    #[serde(default)]
+    pub compression: SnapshotCompression,
+
     /// Will be `true` if this is the last chunk in the snapshot.
     pub done: bool,
+
+    /// The CRC32 checksum of the whole snapshot, which is sent only along with the last chunk.
+    ///
+    /// The receiver verifies it before installing the snapshot.
+    #[serde(default)]
+    pub checksum: Option<u32>,
+
+    /// The membership config of the leader, which is sent only to a witness.
+    ///
+    /// A witness has no state machine: it is sent no snapshot data, and uses this membership config instead of the
+    /// one in the snapshot.
+    #[serde(default)]
+    pub witness_membership: Option<EffectiveMembership<NID, NI>>,
 }
 
-impl MessageSummary for InstallSnapshotRequest {
+impl<NID: NodeId, NI: NodeInfo> MessageSummary for InstallSnapshotRequest<NID, NI> {
     fn summary(&self) -> String {
         format!(
             "vote={}, meta={:?}, offset={}, len={}, done={}",
@@ -701,8 +1188,9 @@ impl MessageSummary for InstallSnapshotRequest {
 
 /// The response to an `InstallSnapshotRequest`.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct InstallSnapshotResponse {
-    pub vote: Vote,
+#[serde(bound = "")]
+pub struct InstallSnapshotResponse<NID: NodeId = u64> {
+    pub vote: Vote<NID>,
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
@@ -712,38 +1200,40 @@ pub struct InstallSnapshotResponse {
 /// The entry of this payload will be appended to the Raft log and then applied to the Raft state
 /// machine according to the Raft protocol.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct ClientWriteRequest<D: AppData> {
+#[serde(bound = "")]
+pub struct ClientWriteRequest<D: AppData, NID: NodeId = u64, NI: NodeInfo = Node> {
     /// The application specific contents of this client request.
     #[serde(bound = "D: AppData")]
-    pub(crate) payload: EntryPayload<D>,
+    pub(crate) payload: EntryPayload<D, NID, NI>,
 }
 
-impl<D: AppData> MessageSummary for ClientWriteRequest<D> {
+impl<D: AppData, NID: NodeId, NI: NodeInfo> MessageSummary for ClientWriteRequest<D, NID, NI> {
     fn summary(&self) -> String {
         self.payload.summary()
     }
 }
 
-impl<D: AppData> ClientWriteRequest<D> {
-    pub fn new(entry: EntryPayload<D>) -> Self {
+impl<D: AppData, NID: NodeId, NI: NodeInfo> ClientWriteRequest<D, NID, NI> {
+    pub fn new(entry: EntryPayload<D, NID, NI>) -> Self {
         Self { payload: entry }
     }
 }
 
 /// The response to a `ClientRequest`.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct ClientWriteResponse<R: AppDataResponse> {
-    pub log_id: LogId,
+#[serde(bound = "")]
+pub struct ClientWriteResponse<R: AppDataResponse, NID: NodeId = u64, NI: NodeInfo = Node> {
+    pub log_id: LogId<NID>,
 
     /// Application specific response data.
     #[serde(bound = "R: AppDataResponse")]
     pub data: R,
 
     /// If the log entry is a change-membership entry.
-    pub membership: Option<Membership>,
+    pub membership: Option<Membership<NID, NI>>,
 }
 
-impl<R: AppDataResponse> MessageSummary for ClientWriteResponse<R> {
+impl<R: AppDataResponse, NID: NodeId, NI: NodeInfo> MessageSummary for ClientWriteResponse<R, NID, NI> {
     fn summary(&self) -> String {
         format!("log_id: {}, membership: {:?}", self.log_id, self.membership)
     }
@@ -6,22 +6,24 @@ use serde::Serialize;
 
 use crate::LeaderId;
 use crate::MessageSummary;
+use crate::NodeId;
 
 /// The identity of a raft log.
 /// A term, node_id and an index identifies an log globally.
 #[derive(Debug, Default, Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
-pub struct LogId {
-    pub leader_id: LeaderId,
+#[serde(bound = "")]
+pub struct LogId<NID: NodeId = u64> {
+    pub leader_id: LeaderId<NID>,
     pub index: u64,
 }
 
-impl Display for LogId {
+impl<NID: NodeId> Display for LogId<NID> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}-{}", self.leader_id, self.index)
     }
 }
 
-impl MessageSummary for Option<LogId> {
+impl<NID: NodeId> MessageSummary for Option<LogId<NID>> {
     fn summary(&self) -> String {
         match self {
             None => "None".to_string(),
@@ -32,8 +34,8 @@ impl MessageSummary for Option<LogId> {
     }
 }
 
-impl LogId {
-    pub fn new(leader_id: LeaderId, index: u64) -> Self {
+impl<NID: NodeId> LogId<NID> {
+    pub fn new(leader_id: LeaderId<NID>, index: u64) -> Self {
         if leader_id.term == 0 || index == 0 {
             assert_eq!(
                 leader_id.term, 0,
@@ -41,9 +43,11 @@ impl LogId {
                 leader_id, index
             );
             assert_eq!(
-                leader_id.node_id, 0,
+                leader_id.node_id,
+                NID::default(),
                 "zero-th log entry must be (0,0,0), but {} {}",
-                leader_id, index
+                leader_id,
+                index
             );
             assert_eq!(
                 index, 0,
@@ -60,9 +64,9 @@ pub trait LogIdOptionExt {
     fn next_index(&self) -> u64;
 }
 
-impl LogIdOptionExt for Option<LogId> {
+impl<NID: NodeId> LogIdOptionExt for Option<LogId<NID>> {
     fn index(&self) -> Option<u64> {
-        self.map(|x| x.index)
+        self.as_ref().map(|x| x.index)
     }
 
     fn next_index(&self) -> u64 {
@@ -142,7 +146,7 @@ pub enum Update<T> {
 /// The changes of a state machine.
 /// E.g. when applying a log to state machine, or installing a state machine from snapshot.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct StateMachineChanges {
-    pub last_applied: LogId,
+pub struct StateMachineChanges<NID: NodeId = u64> {
+    pub last_applied: LogId<NID>,
     pub is_snapshot: bool,
 }
@@ -0,0 +1,76 @@
+use rand::thread_rng;
+use rand::Rng;
+use tokio::time::Duration;
+use tokio::time::Instant;
+
+use crate::config::Config;
+
+/// Exponential backoff with jitter, for retrying RPCs to a failing target.
+///
+/// It is shared by the heartbeat task and the replication stream of a target, so that a target that fails both of
+/// them is backed off and counted as one.
+#[derive(Debug, Clone)]
+pub(super) struct Backoff {
+    min: Duration,
+    max: Duration,
+
+    /// The number of consecutive failures.
+    failures: u64,
+
+    /// The time to retry after the last counted failure.
+    retry_at: Option<Instant>,
+}
+
+impl Backoff {
+    pub(super) fn new(config: &Config) -> Self {
+        Self {
+            min: Duration::from_millis(config.replication_backoff_min),
+            max: Duration::from_millis(config.replication_backoff_max),
+            failures: 0,
+            retry_at: None,
+        }
+    }
+
+    /// Record a failure, returns `true` if it is counted as a new failure.
+    ///
+    /// A failure before the retry time of the last one, e.g., a heartbeat fails while the replication stream is
+    /// backing off, is not counted and does not extend the backoff.
+    ///
+    /// The delay is `min * 2^(failures-1)` capped by `max`, randomized to between half of it and all of it, so that
+    /// the retries to different targets do not line up.
+    pub(super) fn fail(&mut self) -> bool {
+        let now = Instant::now();
+
+        if self.retry_at.map(|t| now < t).unwrap_or(false) {
+            return false;
+        }
+
+        self.failures += 1;
+
+        let shift = std::cmp::min(self.failures - 1, 16) as u32;
+        let delay = std::cmp::min(self.min * 2u32.pow(shift), self.max);
+
+        let half = delay / 2;
+        let delay = if half.is_zero() {
+            delay
+        } else {
+            half + thread_rng().gen_range(Duration::ZERO..=half)
+        };
+
+        self.retry_at = Some(now + delay);
+        true
+    }
+
+    /// The time to retry after the last failure, or now if it is not failing.
+    pub(super) fn retry_at(&self) -> Instant {
+        self.retry_at.unwrap_or_else(Instant::now)
+    }
+
+    /// Record a success, returns `true` if it recovers from failures.
+    pub(super) fn succeed(&mut self) -> bool {
+        let recovered = self.failures > 0;
+        self.failures = 0;
+        self.retry_at = None;
+        recovered
+    }
+}
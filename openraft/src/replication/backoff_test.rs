@@ -0,0 +1,31 @@
+use tokio::time::Duration;
+use tokio::time::Instant;
+
+use crate::replication::backoff::Backoff;
+use crate::Config;
+
+#[test]
+fn test_backoff_counts_a_failure_once_per_retry() {
+    let config = Config {
+        replication_backoff_min: 1_000,
+        replication_backoff_max: 10_000,
+        ..Default::default()
+    };
+    let mut backoff = Backoff::new(&config);
+
+    let now = Instant::now();
+    assert!(backoff.retry_at() <= Instant::now(), "not failing, retry at once");
+
+    assert!(backoff.fail());
+    let retry_at = backoff.retry_at();
+    assert!(retry_at >= now + Duration::from_millis(500));
+    assert!(retry_at <= Instant::now() + Duration::from_millis(1_000));
+
+    // Another failure before the retry time, e.g., of a heartbeat, is not counted and does not extend the backoff.
+    assert!(!backoff.fail());
+    assert_eq!(retry_at, backoff.retry_at());
+
+    assert!(backoff.succeed());
+    assert!(backoff.retry_at() <= Instant::now(), "recovered, retry at once");
+    assert!(!backoff.succeed());
+}
@@ -0,0 +1,227 @@
+//! Heartbeat to a replication target, sent independently of the log and snapshot replication.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use tokio::io::AsyncRead;
+use tokio::io::AsyncSeek;
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tokio::sync::Notify;
+use tokio::time::sleep_until;
+use tokio::time::timeout;
+use tokio::time::Duration;
+use tokio::time::Instant;
+use tracing::Span;
+
+use crate::config::Config;
+use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::replication::backoff::Backoff;
+use crate::replication::throttle::ResponseThrottle;
+use crate::replication::ReplicaEvent;
+use crate::AppData;
+use crate::LogId;
+use crate::NodeId;
+use crate::NodeInfo;
+use crate::RaftNetwork;
+use crate::Vote;
+
+/// The replication progress a heartbeat is built from, published by the replication stream.
+#[derive(Debug, Clone)]
+pub(super) struct HeartbeatState<NID: NodeId, NI: NodeInfo> {
+    /// The last log id known to be on the target, used as the `prev_log_id` of a heartbeat.
+    pub(super) matched: Option<LogId<NID>>,
+
+    /// The committed log id of the leader.
+    pub(super) committed: Option<LogId<NID>>,
+
+    /// The info of the target node.
+    pub(super) target_node: Option<NI>,
+}
+
+/// A task sending a heartbeat to the target every `heartbeat_interval`.
+///
+/// A heartbeat is an AppendEntries RPC without entries, whose `prev_log_id` is the matched log id, thus it always
+/// matches on the target. It runs in its own task, so that a large AppendEntries or snapshot chunk in flight does not
+/// delay it and the target does not start an election.
+///
+/// After a failure, the next heartbeat is delayed by the replication backoff if it is longer than
+/// `heartbeat_interval`. The backoff is shared with the replication stream and is capped by
+/// `replication_backoff_max`, thus a failing target is still probed periodically.
+///
+/// It quits when the replication stream publishing the [`HeartbeatState`] quits.
+pub(super) struct Heartbeat<D, N, NID, NI, SD>
+where
+    D: AppData,
+    N: RaftNetwork<D, NID, NI>,
+    NID: NodeId,
+    NI: NodeInfo,
+    SD: AsyncRead + AsyncSeek + Send + Unpin + 'static,
+{
+    target: NID,
+    vote: Vote<NID>,
+    config: Arc<Config>,
+    network: Arc<N>,
+    state_rx: watch::Receiver<HeartbeatState<NID, NI>>,
+    reachable: Arc<Notify>,
+    backoff: Arc<Mutex<Backoff>>,
+    response_throttle: Arc<Mutex<ResponseThrottle>>,
+    raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<SD, NID>, Span)>,
+    marker_d: std::marker::PhantomData<D>,
+}
+
+impl<D, N, NID, NI, SD> Heartbeat<D, N, NID, NI, SD>
+where
+    D: AppData,
+    N: RaftNetwork<D, NID, NI>,
+    NID: NodeId,
+    NI: NodeInfo,
+    SD: AsyncRead + AsyncSeek + Send + Unpin + 'static,
+{
+    pub(super) fn new(
+        target: NID,
+        vote: Vote<NID>,
+        config: Arc<Config>,
+        network: Arc<N>,
+        state_rx: watch::Receiver<HeartbeatState<NID, NI>>,
+        reachable: Arc<Notify>,
+        backoff: Arc<Mutex<Backoff>>,
+        response_throttle: Arc<Mutex<ResponseThrottle>>,
+        raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<SD, NID>, Span)>,
+    ) -> Self {
+        Self {
+            target,
+            vote,
+            config,
+            network,
+            state_rx,
+            reachable,
+            backoff,
+            response_throttle,
+            raft_core_tx,
+            marker_d: std::marker::PhantomData,
+        }
+    }
+
+    #[tracing::instrument(level="trace", skip(self), fields(vote=%self.vote, target=%self.target))]
+    pub(super) async fn main(mut self) {
+        let heartbeat_interval = Duration::from_millis(self.config.heartbeat_interval);
+        let mut next_at = Instant::now();
+
+        loop {
+            tokio::select! {
+                _ = sleep_until(next_at) => {
+                    let sent_at = Instant::now();
+
+                    next_at = sent_at + heartbeat_interval;
+
+                    match self.send_heartbeat(heartbeat_interval).await {
+                        Ok(resp) => {
+                            if self.handle_response(resp, sent_at) {
+                                return;
+                            }
+                        }
+                        Err(error) => {
+                            let (counted, retry_at) = {
+                                let mut backoff = self.backoff.lock().unwrap();
+                                (backoff.fail(), backoff.retry_at())
+                            };
+                            next_at = std::cmp::max(next_at, retry_at);
+                            tracing::debug!(%error, ?next_at, "failed to send heartbeat to target");
+
+                            // A failure within the backoff of a previous one has been reported.
+                            if counted {
+                                let _ = self.raft_core_tx.send((
+                                    ReplicaEvent::Failed {
+                                        target: self.target.clone(),
+                                        error,
+                                    },
+                                    tracing::debug_span!("CH"),
+                                ));
+                            }
+                        }
+                    };
+                }
+
+                changed = self.state_rx.changed() => {
+                    if changed.is_err() {
+                        tracing::debug!("replication stream quit, stop heartbeat");
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send a heartbeat, returns the error message if it fails.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn send_heartbeat(&mut self, the_timeout: Duration) -> Result<AppendEntriesResponse<NID>, String> {
+        let state = self.state_rx.borrow().clone();
+
+        let payload = AppendEntriesRequest {
+            vote: self.vote.clone(),
+            prev_log_id: state.matched,
+            entries: vec![],
+            leader_commit: state.committed,
+        };
+
+        let res = timeout(
+            the_timeout,
+            self.network.send_append_entries(self.target.clone(), state.target_node.as_ref(), payload),
+        )
+        .await;
+
+        match res {
+            Ok(Ok(x)) => Ok(x),
+            Ok(Err(err)) => Err(err.to_string()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    /// Report the response of a heartbeat to RaftCore, unless it is throttled.
+    ///
+    /// It returns true if the target has seen a greater vote, after which no more heartbeat is sent.
+    fn handle_response(&mut self, resp: AppendEntriesResponse<NID>, sent_at: Instant) -> bool {
+        let recovered = self.backoff.lock().unwrap().succeed();
+        if recovered {
+            // Only wake up a replication stream that is backing off, without leaving a permit for a later backoff.
+            self.reachable.notify_waiters();
+        }
+
+        if self.response_throttle.lock().unwrap().respond(recovered) {
+            let _ = self.raft_core_tx.send((
+                ReplicaEvent::Responded {
+                    target: self.target.clone(),
+                    latency: sent_at.elapsed(),
+                },
+                tracing::debug_span!("CH"),
+            ));
+        }
+
+        if resp.vote > self.vote {
+            tracing::debug!(%resp.vote, "heartbeat is rejected by a greater vote, reverting to follower");
+
+            let _ = self.raft_core_tx.send((
+                ReplicaEvent::RevertToFollower {
+                    target: self.target.clone(),
+                    vote: resp.vote,
+                },
+                tracing::debug_span!("CH"),
+            ));
+            return true;
+        }
+
+        if self.config.lease_duration().is_some() || self.config.enable_check_quorum {
+            let _ = self.raft_core_tx.send((
+                ReplicaEvent::Acked {
+                    target: self.target.clone(),
+                    sent_at,
+                },
+                tracing::debug_span!("CH"),
+            ));
+        }
+
+        false
+    }
+}
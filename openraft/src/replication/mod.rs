@@ -1,9 +1,22 @@
 //! Replication stream.
 
+mod backoff;
+#[cfg(test)]
+mod backoff_test;
+mod heartbeat;
+mod throttle;
+#[cfg(test)]
+mod throttle_test;
+
 use std::io::SeekFrom;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::SystemTime;
 
+use futures::future::BoxFuture;
 use futures::future::FutureExt;
+use futures::stream::FuturesUnordered;
+use futures::stream::StreamExt;
 use serde::Deserialize;
 use serde::Serialize;
 use tokio::io::AsyncRead;
@@ -12,27 +25,38 @@ use tokio::io::AsyncSeek;
 use tokio::io::AsyncSeekExt;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
-use tokio::time::interval;
+use tokio::sync::watch;
+use tokio::sync::Notify;
+use tokio::time::sleep_until;
 use tokio::time::timeout;
 use tokio::time::Duration;
-use tokio::time::Interval;
+use tokio::time::Instant;
 use tracing::Instrument;
 use tracing::Span;
 
 use crate::config::Config;
-use crate::config::SnapshotPolicy;
 use crate::error::AppendEntriesError;
 use crate::error::CommittedAdvanceTooMany;
 use crate::error::HigherVote;
+use crate::error::InstallSnapshotError;
 use crate::error::LackEntry;
 use crate::error::RPCError;
 use crate::error::ReplicationError;
 use crate::error::Timeout;
 use crate::raft::AppendEntriesRequest;
+use crate::raft::AppendEntriesResponse;
+use crate::raft::ConflictHint;
+use crate::raft::Entry;
+use crate::raft::EntryPayload;
 use crate::raft::InstallSnapshotRequest;
 use crate::raft_types::LogIdOptionExt;
 use crate::raft_types::LogIndexOptionExt;
+use crate::replication::backoff::Backoff;
+use crate::replication::heartbeat::Heartbeat;
+use crate::replication::heartbeat::HeartbeatState;
+use crate::replication::throttle::ResponseThrottle;
 use crate::storage::Snapshot;
+use crate::storage::SnapshotMeta;
 use crate::AppData;
 use crate::AppDataResponse;
 use crate::ErrorSubject;
@@ -40,45 +64,147 @@ use crate::ErrorVerb;
 use crate::LogId;
 use crate::MessageSummary;
 use crate::NodeId;
+use crate::NodeInfo;
 use crate::RPCTypes;
 use crate::RaftNetwork;
 use crate::RaftStorage;
+use crate::SnapshotCompression;
+use crate::SnapshotId;
+use crate::StorageError;
 use crate::ToStorageResult;
 use crate::Vote;
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct ReplicationMetrics {
-    pub matched: Option<LogId>,
+#[serde(bound = "")]
+pub struct ReplicationMetrics<NID: NodeId = u64> {
+    pub matched: Option<LogId<NID>>,
+
+    /// The number of consecutive RPCs to the target that failed with a transport error, reset when it responds.
+    #[serde(default)]
+    pub failures: u64,
+
+    /// The error of the last failed RPC, while the target is failing.
+    #[serde(default)]
+    pub last_error: Option<String>,
+
+    /// The time the target last responded to an RPC, including a heartbeat.
+    #[serde(default)]
+    pub last_success_at: Option<SystemTime>,
+
+    /// The round trip time of the last RPC the target responded to.
+    #[serde(default)]
+    pub last_rpc_latency: Option<Duration>,
+
+    /// Whether the replication stream is replicating logs or sending a snapshot.
+    #[serde(default)]
+    pub state: TargetReplState<NID>,
+
+    /// The number of bytes of logs and snapshot data the target has accepted, not including heartbeats.
+    ///
+    /// Data in an RPC that fails or is rejected is not counted.
+    #[serde(default)]
+    pub bytes_sent: u64,
+
+    /// The progress of the snapshot being sent to the target, if there is one.
+    #[serde(default)]
+    pub snapshot_transfer: Option<SnapshotTransfer>,
 }
 
-impl MessageSummary for ReplicationMetrics {
+impl<NID: NodeId> MessageSummary for ReplicationMetrics<NID> {
     fn summary(&self) -> String {
-        format!("{:?}", self.matched)
+        let mut res = format!(
+            "{:?}, state: {:?}, last_success_at: {:?}",
+            self.matched, self.state, self.last_success_at
+        );
+
+        if let Some(t) = &self.snapshot_transfer {
+            res.push_str(&format!(", snapshot: {}", t.summary()));
+        }
+
+        if let Some(latency) = &self.last_rpc_latency {
+            res.push_str(&format!(", latency: {:?}", latency));
+        }
+
+        if self.bytes_sent > 0 {
+            res.push_str(&format!(", bytes_sent: {}", self.bytes_sent));
+        }
+
+        if self.failures > 0 {
+            res.push_str(&format!(
+                ", failures: {}, last_error: {}",
+                self.failures,
+                self.last_error.as_deref().unwrap_or_default()
+            ));
+        }
+
+        res
+    }
+}
+
+/// The state of the replication stream.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum TargetReplState<NID: NodeId = u64> {
+    /// The replication stream is running at line rate.
+    LineRate,
+
+    /// The replication stream is streaming a snapshot over to the target node.
+    Snapshotting { must_include: Option<LogId<NID>> },
+
+    /// The replication stream is shutting down.
+    Shutdown,
+}
+
+impl<NID: NodeId> Default for TargetReplState<NID> {
+    fn default() -> Self {
+        TargetReplState::LineRate
+    }
+}
+
+/// The progress of sending a snapshot to a replication target.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SnapshotTransfer {
+    pub snapshot_id: SnapshotId,
+
+    /// The number of bytes of the snapshot data the target has received.
+    pub offset: u64,
+
+    /// The size in bytes of the snapshot data.
+    pub size: u64,
+}
+
+impl MessageSummary for SnapshotTransfer {
+    fn summary(&self) -> String {
+        format!("{}+{}/{}", self.snapshot_id, self.offset, self.size)
     }
 }
 
 /// The public handle to a spawned replication stream.
-pub(crate) struct ReplicationStream {
+pub(crate) struct ReplicationStream<NID: NodeId, NI: NodeInfo> {
     /// The spawn handle the `ReplicationCore` task.
     // pub handle: JoinHandle<()>,
     /// The channel used for communicating with the replication task.
-    pub repl_tx: mpsc::UnboundedSender<(RaftEvent, Span)>,
+    pub repl_tx: mpsc::UnboundedSender<(RaftEvent<NID, NI>, Span)>,
 }
 
-impl ReplicationStream {
+impl<NID: NodeId, NI: NodeInfo> ReplicationStream<NID, NI> {
     /// Create a new replication stream for the target peer.
-    pub(crate) fn new<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>>(
-        target: NodeId,
-        vote: Vote,
+    pub(crate) fn new<D: AppData, R: AppDataResponse, N: RaftNetwork<D, NID, NI>, S: RaftStorage<D, R, NID, NI>>(
+        target: NID,
+        target_node: Option<NI>,
+        witness: bool,
+        vote: Vote<NID>,
         config: Arc<Config>,
-        last_log: Option<LogId>,
-        committed: Option<LogId>,
+        last_log: Option<LogId<NID>>,
+        committed: Option<LogId<NID>>,
         network: Arc<N>,
         storage: Arc<S>,
-        replication_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
+        replication_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData, NID>, Span)>,
     ) -> Self {
         ReplicationCore::spawn(
             target,
+            target_node,
+            witness,
             vote,
             config,
             last_log,
@@ -92,21 +218,38 @@ impl ReplicationStream {
 
 /// A task responsible for sending replication events to a target follower in the Raft cluster.
 ///
-/// NOTE: we do not stack replication requests to targets because this could result in
-/// out-of-order delivery. We always buffer until we receive a success response, then send the
+/// NOTE: by default we do not stack replication requests to targets because this could result in
+/// out-of-order delivery. We buffer until we receive a success response, then send the
 /// next payload from the buffer.
-struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> {
+/// With `Config::max_in_flight_append_entries` greater than 1, requests are stacked once the matching log on the
+/// target is found. A reordered request is then rejected as a conflict, and replication falls back to one request at a
+/// time.
+struct ReplicationCore<
+    D: AppData,
+    R: AppDataResponse,
+    N: RaftNetwork<D, NID, NI>,
+    S: RaftStorage<D, R, NID, NI>,
+    NID: NodeId,
+    NI: NodeInfo,
+> {
     /// The ID of the target Raft node which replication events are to be sent to.
-    target: NodeId,
+    target: NID,
+
+    /// The info of the target node stored in the membership config, e.g., its address.
+    target_node: Option<NI>,
+
+    /// Whether the target is a witness, to which only log ids and membership configs are sent, without application
+    /// data.
+    witness: bool,
 
     /// The vote of the leader.
-    vote: Vote,
+    vote: Vote<NID>,
 
     /// A channel for sending events to the Raft node.
-    raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
+    raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData, NID>, Span)>,
 
     /// A channel for receiving events from the Raft node.
-    repl_rx: mpsc::UnboundedReceiver<(RaftEvent, Span)>,
+    repl_rx: mpsc::UnboundedReceiver<(RaftEvent<NID, NI>, Span)>,
 
     /// The `RaftNetwork` interface.
     network: Arc<N>,
@@ -122,13 +265,13 @@ struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: Raf
     //////////////////////////////////////////////////////////////////////////
     // Dynamic Fields ////////////////////////////////////////////////////////
     /// The target state of this replication stream.
-    target_repl_state: TargetReplState,
+    target_repl_state: TargetReplState<NID>,
 
     /// The id of the log entry to most recently be appended to the log by the leader.
-    last_log_id: Option<LogId>,
+    last_log_id: Option<LogId<NID>>,
 
     /// The log id of the highest log entry which is known to be committed in the cluster.
-    committed: Option<LogId>,
+    committed: Option<LogId<NID>>,
 
     /// The last know log to be successfully replicated on the target.
     ///
@@ -137,51 +280,140 @@ struct ReplicationCore<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: Raf
     /// behind. This is defined in §5.3.
     /// This will be initialized to the leader's (last_log_term, last_log_index), and will be updated as
     /// replication proceeds.
-    matched: Option<LogId>,
+    matched: Option<LogId<NID>>,
 
     // The last possible matching entry on a follower.
     max_possible_matched_index: Option<u64>,
 
-    /// The heartbeat interval for ensuring that heartbeats are always delivered in a timely fashion.
-    heartbeat: Interval,
+    /// Whether `max_possible_matched_index` is set by a conflict hint from the target.
+    ///
+    /// If it is, the next RPC probes `max_possible_matched_index` directly instead of bisecting.
+    conflict_hinted: bool,
+
+    /// Whether AppendEntries RPCs can be pipelined, i.e., the last RPC succeeded.
+    ///
+    /// It is reset by a conflict or an error, then replication falls back to one RPC at a time.
+    pipeline_allowed: bool,
+
+    /// The maximum number of entries to send in an AppendEntries RPC, up to `max_payload_entries`.
+    ///
+    /// It is halved after an RPC with more than one entry fails with a transport error, e.g., the payload exceeds the
+    /// frame limit of the transport, and then grows by one entry with every successful RPC.
+    payload_entries: u64,
+
+    /// The average size in bytes of the logs last sent, to estimate the lag in bytes for
+    /// `SnapshotPolicy::BytesSinceLast`.
+    ///
+    /// Before any log is sent, it is estimated with the logs in storage the target lags behind.
+    entry_bytes: u64,
+
+    /// The number of bytes of logs and snapshot data the target has accepted.
+    bytes_sent: u64,
+
+    /// The progress of the snapshot being sent to the target.
+    snapshot_transfer: Option<SnapshotTransfer>,
+
+    /// Publishes the replication progress to the heartbeat task of this target, which sends heartbeats on its own,
+    /// without waiting for the log or snapshot replication in flight.
+    heartbeat_tx: watch::Sender<HeartbeatState<NID, NI>>,
+
+    /// Notified by the heartbeat task when the target responds again after failures, to retry at once.
+    reachable: Arc<Notify>,
+
+    /// The backoff before retrying after a transport error, shared with the heartbeat task.
+    backoff: Arc<Mutex<Backoff>>,
+
+    /// Throttles the responses reported to RaftCore, shared with the heartbeat task.
+    response_throttle: Arc<Mutex<ResponseThrottle>>,
+
+    /// The last time the progress was reported to RaftCore.
+    progress_reported_at: Option<Instant>,
+
+    /// Whether there is progress held back by the throttling of `report_progress()`.
+    progress_pending: bool,
 
     /// The timeout for sending snapshot segment.
     install_snapshot_timeout: Duration,
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> ReplicationCore<D, R, N, S> {
+impl<
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > ReplicationCore<D, R, N, S, NID, NI>
+{
     /// Spawn a new replication task for the target node.
     #[tracing::instrument(level = "trace", skip(config, network, storage, raft_core_tx))]
     pub(self) fn spawn(
-        target: NodeId,
-        vote: Vote,
+        target: NID,
+        target_node: Option<NI>,
+        witness: bool,
+        vote: Vote<NID>,
         config: Arc<Config>,
-        last_log: Option<LogId>,
-        committed: Option<LogId>,
+        last_log: Option<LogId<NID>>,
+        committed: Option<LogId<NID>>,
         network: Arc<N>,
         storage: Arc<S>,
-        raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData>, Span)>,
-    ) -> ReplicationStream {
+        raft_core_tx: mpsc::UnboundedSender<(ReplicaEvent<S::SnapshotData, NID>, Span)>,
+    ) -> ReplicationStream<NID, NI> {
         // other component to ReplicationStream
         let (repl_tx, repl_rx) = mpsc::unbounded_channel();
-        let heartbeat_timeout = Duration::from_millis(config.heartbeat_interval);
         let install_snapshot_timeout = Duration::from_millis(config.install_snapshot_timeout);
+        let payload_entries = config.max_payload_entries;
+        let reachable = Arc::new(Notify::new());
+        let backoff = Arc::new(Mutex::new(Backoff::new(&config)));
+        let response_throttle = Arc::new(Mutex::new(ResponseThrottle::new(&config)));
+
+        let (heartbeat_tx, heartbeat_rx) = watch::channel(HeartbeatState {
+            matched: None,
+            committed: committed.clone(),
+            target_node: target_node.clone(),
+        });
+
+        let heartbeat = Heartbeat::new(
+            target.clone(),
+            vote.clone(),
+            config.clone(),
+            network.clone(),
+            heartbeat_rx,
+            reachable.clone(),
+            backoff.clone(),
+            response_throttle.clone(),
+            raft_core_tx.clone(),
+        );
+        let _handle = tokio::spawn(heartbeat.main().instrument(tracing::trace_span!("spawn-heartbeat").or_current()));
 
         let this = Self {
             target,
+            target_node,
+            witness,
             vote,
             network,
             storage,
             config,
             marker_r: std::marker::PhantomData,
             target_repl_state: TargetReplState::LineRate,
-            last_log_id: last_log,
+            last_log_id: last_log.clone(),
             committed,
             matched: None,
             max_possible_matched_index: last_log.index(),
+            conflict_hinted: false,
+            pipeline_allowed: false,
+            payload_entries,
+            entry_bytes: 0,
+            bytes_sent: 0,
+            snapshot_transfer: None,
             raft_core_tx,
             repl_rx,
-            heartbeat: interval(heartbeat_timeout),
+            heartbeat_tx,
+            reachable,
+            backoff,
+            response_throttle,
+            progress_reported_at: None,
+            progress_pending: false,
             install_snapshot_timeout,
         };
 
@@ -193,14 +425,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         }
     }
 
-    #[tracing::instrument(level="trace", skip(self), fields(vote=%self.vote, target=self.target, cluster=%self.config.cluster_name))]
+    #[tracing::instrument(level="trace", skip(self), fields(vote=%self.vote, target=%self.target, cluster=%self.config.cluster_name))]
     async fn main(mut self) {
         loop {
             // If it returns Ok(), always go back to LineRate state.
             let res = match &self.target_repl_state {
                 TargetReplState::LineRate => self.line_rate_loop().await,
                 TargetReplState::Snapshotting { must_include } => {
-                    let must = *must_include;
+                    let must = must_include.clone();
                     self.replicate_snapshot(must).await
                 }
                 TargetReplState::Shutdown => return,
@@ -267,16 +499,69 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
 
     /// Send an AppendEntries RPC to the target.
     ///
-    /// This request will timeout if no response is received within the
-    /// configured heartbeat interval.
+    /// This request will timeout if no response is received within `append_entries_timeout`.
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn send_append_entries(&mut self) -> Result<(), ReplicationError> {
-        // find the mid position aligning to 8
-        let diff = self.max_possible_matched_index.next_index() - self.matched.next_index();
-        let offset = diff / 16 * 8;
+    async fn send_append_entries(&mut self) -> Result<(), ReplicationError<NID>> {
+        let prev_index = if self.conflict_hinted {
+            self.max_possible_matched_index
+        } else {
+            // find the mid position aligning to 8
+            let diff = self.max_possible_matched_index.next_index() - self.matched.next_index();
+            let offset = diff / 16 * 8;
+
+            self.matched.index().add(offset)
+        };
+
+        let (payload, in_flight) = self.build_append_entries(prev_index).await?;
+        let (in_flight, res) = self.send_payload(payload, in_flight).await;
+
+        self.handle_append_entries_result(in_flight, res)?;
+        Ok(())
+    }
+
+    /// Replicate logs to a target whose matching log is found, with up to `max_in_flight_append_entries` RPCs in
+    /// flight.
+    ///
+    /// Every RPC is built upon the last log sent, assuming the RPCs in flight will succeed.
+    /// It returns when all logs are sent and acknowledged.
+    /// On a conflict it returns at once and the RPCs in flight are abandoned.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn pipeline_append_entries(&mut self) -> Result<(), ReplicationError<NID>> {
+        let max_in_flight = self.config.max_in_flight_append_entries as usize;
+        let mut in_flight = FuturesUnordered::new();
+
+        // The last log id on the target if every RPC in flight succeeds.
+        let mut sent = self.matched.clone();
+
+        loop {
+            // Check raft channel to send newly appended logs.
+            self.try_drain_raft_rx().await?;
+
+            while in_flight.len() < max_in_flight && sent.index() < self.last_log_id.index() {
+                let (payload, req) = self.build_append_entries(sent.index()).await?;
+                sent = req.last_log_id.clone();
+                in_flight.push(self.send_payload(payload, req));
+            }
+
+            let (req, res) = match in_flight.next().await {
+                Some(x) => x,
+                None => return Ok(()),
+            };
 
-        let mut prev_index = self.matched.index().add(offset);
+            let success = self.handle_append_entries_result(req, res)?;
+            if !success {
+                return Ok(());
+            }
+        }
+    }
 
+    /// Build an AppendEntries RPC with the logs following `prev_index`, at most `max_payload_entries` of them, and at
+    /// most `max_payload_bytes` in size unless the first one is larger.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn build_append_entries(
+        &mut self,
+        mut prev_index: Option<u64>,
+    ) -> Result<Payload<D, NID, NI>, ReplicationError<NID>> {
         let (prev_log_id, logs) = loop {
             // TODO(xp): test heartbeat when all logs are removed.
 
@@ -284,14 +569,14 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
 
             let last_purged = log_state.last_purged_log_id;
 
-            self.check_consecutive(last_purged)?;
+            self.check_consecutive(last_purged.clone())?;
 
             if prev_index < last_purged.index() {
                 prev_index = last_purged.index();
             }
 
             let start = prev_index.next_index();
-            let end = std::cmp::min(start + self.config.max_payload_entries, self.last_log_id.next_index());
+            let end = std::cmp::min(start + self.payload_entries, self.last_log_id.next_index());
 
             tracing::debug!(
                 ?self.matched,
@@ -336,62 +621,180 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             break (prev_log_id, logs);
         };
 
-        let conflict = prev_log_id;
-        let matched = if logs.is_empty() {
-            prev_log_id
+        if !logs.is_empty() && self.config.snapshot_policy.has_bytes() {
+            self.entry_bytes = average_entry_bytes(&logs);
+        }
+
+        // A witness does not store application data: only the log ids and membership configs are sent.
+        let logs = if self.witness {
+            logs.into_iter()
+                .map(|ent| match ent.payload {
+                    EntryPayload::Normal(_) => Entry {
+                        log_id: ent.log_id,
+                        payload: EntryPayload::Blank,
+                    },
+                    _ => ent,
+                })
+                .collect()
+        } else {
+            logs
+        };
+
+        let logs = self.limit_payload_bytes(logs);
+
+        let bytes = if logs.is_empty() {
+            0
+        } else {
+            bincode::serialized_size(&logs).unwrap_or_default()
+        };
+
+        let last_log_id = if logs.is_empty() {
+            prev_log_id.clone()
         } else {
-            Some(logs[logs.len() - 1].log_id)
+            Some(logs[logs.len() - 1].log_id.clone())
         };
 
         // Build the heartbeat frame to be sent to the follower.
         let payload = AppendEntriesRequest {
-            vote: self.vote,
-            prev_log_id,
-            leader_commit: self.committed,
+            vote: self.vote.clone(),
+            prev_log_id: prev_log_id.clone(),
+            leader_commit: self.committed.clone(),
             entries: logs,
         };
 
-        // Send the payload.
-        tracing::debug!(
-            payload=%payload.summary(),
-            "start sending append_entries, timeout: {:?}",
-            self.config.heartbeat_interval
-        );
+        let in_flight = InFlight {
+            prev_log_id,
+            last_log_id,
+            bytes,
+            sent_at: Instant::now(),
+        };
 
-        let the_timeout = Duration::from_millis(self.config.heartbeat_interval);
-        let res = timeout(the_timeout, self.network.send_append_entries(self.target, payload)).await;
+        Ok((payload, in_flight))
+    }
 
-        let append_resp = match res {
-            Ok(append_res) => match append_res {
-                Ok(res) => res,
-                Err(err) => {
+    /// Keep the leading logs whose total size is within `max_payload_bytes`, but at least one of them.
+    fn limit_payload_bytes(&self, mut logs: Vec<Entry<D, NID, NI>>) -> Vec<Entry<D, NID, NI>> {
+        let max_bytes = self.config.max_payload_bytes;
+        if max_bytes == 0 {
+            return logs;
+        }
+
+        let mut total = 0;
+        let n = logs
+            .iter()
+            .take_while(|ent| {
+                total += bincode::serialized_size(ent).unwrap_or_default();
+                total <= max_bytes
+            })
+            .count();
+
+        logs.truncate(std::cmp::max(n, 1));
+        logs
+    }
+
+    /// Send an AppendEntries RPC, with a timeout of `append_entries_timeout`.
+    ///
+    /// The returned future does not borrow `self`, thus several of them can be in flight at the same time.
+    fn send_payload(
+        &self,
+        payload: AppendEntriesRequest<D, NID, NI>,
+        mut in_flight: InFlight<NID>,
+    ) -> BoxFuture<'static, SendResult<NID>> {
+        let network = self.network.clone();
+        let target = self.target.clone();
+        let target_node = self.target_node.clone();
+        let id = self.vote.node_id.clone();
+        let the_timeout = Duration::from_millis(self.config.append_entries_timeout);
+
+        async move {
+            // Send the payload.
+            tracing::debug!(
+                payload=%payload.summary(),
+                "start sending append_entries, timeout: {:?}",
+                the_timeout
+            );
+
+            in_flight.sent_at = Instant::now();
+            let res = timeout(
+                the_timeout,
+                network.send_append_entries(target.clone(), target_node.as_ref(), payload),
+            )
+            .await;
+
+            let res = match res {
+                Ok(append_res) => append_res.map_err(|err| {
                     tracing::warn!(error=%err, "error sending AppendEntries RPC to target");
-                    let repl_err = match err {
+                    match err {
                         RPCError::NodeNotFound(e) => ReplicationError::NodeNotFound(e),
                         RPCError::Timeout(e) => ReplicationError::Timeout(e),
                         RPCError::Network(e) => ReplicationError::Network(e),
                         RPCError::RemoteError(e) => ReplicationError::RemoteError(e),
-                    };
-                    return Err(repl_err);
+                    }
+                }),
+                Err(timeout_err) => {
+                    tracing::warn!(error=%timeout_err, "timeout while sending AppendEntries RPC to target");
+                    Err(ReplicationError::Timeout(Timeout {
+                        action: RPCTypes::AppendEntries,
+                        id,
+                        target,
+                        timeout: the_timeout,
+                    }))
                 }
-            },
-            Err(timeout_err) => {
-                tracing::warn!(error=%timeout_err, "timeout while sending AppendEntries RPC to target");
-                return Err(ReplicationError::Timeout(Timeout {
-                    action: RPCTypes::AppendEntries,
-                    id: self.vote.node_id,
-                    target: self.target,
-                    timeout: the_timeout,
-                }));
+            };
+
+            (in_flight, res)
+        }
+        .instrument(tracing::debug_span!("send_payload"))
+        .boxed()
+    }
+
+    /// Handle the result of an AppendEntries RPC.
+    ///
+    /// It returns `Ok(true)` if the logs are accepted by the target, or `Ok(false)` if `prev_log_id` conflicts.
+    #[tracing::instrument(level = "debug", skip(self, res))]
+    fn handle_append_entries_result(
+        &mut self,
+        in_flight: InFlight<NID>,
+        res: Result<AppendEntriesResponse<NID>, ReplicationError<NID>>,
+    ) -> Result<bool, ReplicationError<NID>> {
+        let append_resp = match res {
+            Ok(x) => x,
+            Err(err) => {
+                self.pipeline_allowed = false;
+
+                if let ReplicationError::Network(_) | ReplicationError::Timeout(_) = err {
+                    let n_entries = in_flight.last_log_id.next_index() - in_flight.prev_log_id.next_index();
+                    if n_entries > 1 {
+                        self.payload_entries = std::cmp::min(self.payload_entries, n_entries / 2);
+                        tracing::info!(n_entries, self.payload_entries, "shrink payload after transport error");
+                    }
+                }
+
+                return Err(err);
             }
         };
 
         tracing::debug!("append_entries resp: {:?}", append_resp);
 
+        self.succeed(in_flight.sent_at);
+
+        if self.payload_entries < self.config.max_payload_entries {
+            self.payload_entries += 1;
+        }
+
+        // The target has reset its election timer if it did not see a higher vote, no matter whether logs match.
+        if append_resp.vote <= self.vote {
+            self.update_acked(in_flight.sent_at);
+        }
+
         // Handle success conditions.
         if append_resp.success {
-            self.update_matched(matched);
-            return Ok(());
+            self.pipeline_allowed = true;
+            self.conflict_hinted = false;
+            self.bytes_sent += in_flight.bytes;
+            self.report_progress(false);
+            self.update_matched(in_flight.last_log_id);
+            return Ok(true);
         }
 
         // Failed
@@ -402,10 +805,12 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
 
             return Err(ReplicationError::HigherVote(HigherVote {
                 higher: append_resp.vote,
-                mine: self.vote,
+                mine: self.vote.clone(),
             }));
         }
 
+        let conflict = in_flight.prev_log_id;
+
         tracing::debug!(
             ?conflict,
             %append_resp.vote,
@@ -415,19 +820,36 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         assert!(conflict.is_some(), "prev_log_id=None never conflict");
         let conflict = conflict.unwrap();
 
+        self.pipeline_allowed = false;
+
         // Continue to find the matching log id on follower.
-        self.max_possible_matched_index = if conflict.index == 0 {
+        let mut max_possible_matched_index = if conflict.index == 0 {
             None
         } else {
             Some(conflict.index - 1)
         };
 
-        Ok(())
+        // A hint from the target tells where its log diverges, the next RPC probes there directly.
+        self.conflict_hinted = append_resp.conflict_hint.is_some();
+
+        if let Some(hint) = &append_resp.conflict_hint {
+            let hinted = match hint {
+                ConflictHint::LastLogId(last_log_id) => last_log_id.index(),
+                ConflictHint::ConflictTermStart(first) => first.checked_sub(1),
+            };
+            max_possible_matched_index = std::cmp::min(max_possible_matched_index, hinted);
+        }
+
+        // With pipelining, a conflict may be caused by reordered RPCs, and it may be received after a later RPC
+        // succeeded. The target has at least the logs up to `matched`.
+        self.max_possible_matched_index = std::cmp::max(max_possible_matched_index, self.matched.index());
+
+        Ok(false)
     }
 
     /// max_possible_matched_index is the least index for `prev_log_id` to form a consecutive log sequence
     #[tracing::instrument(level = "trace", skip(self), fields(max_possible_matched_index=self.max_possible_matched_index))]
-    fn check_consecutive(&self, last_purged: Option<LogId>) -> Result<(), ReplicationError> {
+    fn check_consecutive(&self, last_purged: Option<LogId<NID>>) -> Result<(), ReplicationError<NID>> {
         tracing::debug!(?last_purged, ?self.max_possible_matched_index, "check_consecutive");
 
         if last_purged.index() > self.max_possible_matched_index {
@@ -441,16 +863,17 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    fn set_target_repl_state(&mut self, state: TargetReplState) {
+    fn set_target_repl_state(&mut self, state: TargetReplState<NID>) {
         tracing::debug!(?state, "set_target_repl_state");
         self.target_repl_state = state;
+        self.report_progress(true);
     }
 
     /// Update the `matched` and `max_possible_matched_index`, which both are for tracking
     /// follower replication(the left and right cursor in a bsearch).
     /// And also report the matched log id to RaftCore to commit an entry etc.
     #[tracing::instrument(level = "trace", skip(self))]
-    fn update_matched(&mut self, new_matched: Option<LogId>) {
+    fn update_matched(&mut self, new_matched: Option<LogId<NID>>) {
         tracing::debug!(
             self.max_possible_matched_index,
             ?self.matched,
@@ -465,31 +888,182 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
 
             tracing::debug!(target=%self.target, matched=?self.matched, "matched updated");
 
+            self.update_heartbeat_state();
+
             let _ = self.raft_core_tx.send((
                 ReplicaEvent::UpdateMatched {
-                    target: self.target,
-                    matched: self.matched,
+                    target: self.target.clone(),
+                    matched: self.matched.clone(),
                 },
                 tracing::debug_span!("CH"),
             ));
         }
     }
 
+    /// Record a transport error and wait for the backoff before retrying.
+    ///
+    /// The wait ends early if the heartbeat task finds the target responds again. Events from RaftCore are processed
+    /// while waiting.
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn backoff(&mut self, error: String) -> Result<(), ReplicationError<NID>> {
+        let (counted, deadline) = {
+            let mut backoff = self.backoff.lock().unwrap();
+            (backoff.fail(), backoff.retry_at())
+        };
+
+        tracing::info!(%error, ?deadline, "replication to target failed, retry after backoff");
+
+        // A failure within the backoff of a previous one, e.g., of a heartbeat, has been reported.
+        if counted {
+            let _ = self.raft_core_tx.send((
+                ReplicaEvent::Failed {
+                    target: self.target.clone(),
+                    error,
+                },
+                tracing::debug_span!("CH"),
+            ));
+        }
+
+        loop {
+            // The heartbeat task may reset the backoff while a RaftEvent is being processed.
+            let deadline = self.backoff.lock().unwrap().retry_at();
+
+            tokio::select! {
+                _ = sleep_until(deadline) => {
+                    return Ok(());
+                }
+
+                _ = self.reachable.notified() => {
+                    tracing::debug!("target is reachable, retry at once");
+                    return Ok(());
+                }
+
+                event_span = self.repl_rx.recv() => {
+                    match event_span {
+                        Some((event, _span)) => self.process_raft_event(event)?,
+                        None => {
+                            tracing::debug!("received: RaftEvent::Terminate: closed");
+                            return Err(ReplicationError::Closed);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Record a response from the target to an RPC sent at `sent_at`, and report it to RaftCore unless it is
+    /// throttled.
+    fn succeed(&mut self, sent_at: Instant) {
+        let recovered = self.backoff.lock().unwrap().succeed();
+
+        if !self.response_throttle.lock().unwrap().respond(recovered) {
+            return;
+        }
+
+        let _ = self.raft_core_tx.send((
+            ReplicaEvent::Responded {
+                target: self.target.clone(),
+                latency: sent_at.elapsed(),
+            },
+            tracing::debug_span!("CH"),
+        ));
+    }
+
+    /// Report the state of the replication stream, the bytes sent and the snapshot transfer progress to RaftCore.
+    ///
+    /// The bytes sent and the snapshot transfer progress change with every RPC, thus they are reported at most once
+    /// every `heartbeat_interval` unless `force` is true, e.g., for a change of the state. The progress held back is
+    /// reported by `flush_progress()` once the replication stream becomes idle.
+    fn report_progress(&mut self, force: bool) {
+        let now = Instant::now();
+        let interval = Duration::from_millis(self.config.heartbeat_interval);
+
+        let due = match self.progress_reported_at {
+            Some(t) => force || now >= t + interval,
+            None => true,
+        };
+
+        if !due {
+            self.progress_pending = true;
+            return;
+        }
+
+        self.progress_reported_at = Some(now);
+        self.progress_pending = false;
+
+        let _ = self.raft_core_tx.send((
+            ReplicaEvent::UpdateProgress {
+                target: self.target.clone(),
+                state: self.target_repl_state.clone(),
+                bytes_sent: self.bytes_sent,
+                snapshot_transfer: self.snapshot_transfer.clone(),
+            },
+            tracing::debug_span!("CH"),
+        ));
+    }
+
+    /// Report the progress held back by the throttling of `report_progress()`, if there is any.
+    fn flush_progress(&mut self) {
+        if self.progress_pending {
+            self.report_progress(true);
+        }
+    }
+
+    /// Publish the progress for the heartbeat task to build heartbeats from.
+    fn update_heartbeat_state(&self) {
+        let _ = self.heartbeat_tx.send(HeartbeatState {
+            matched: self.matched.clone(),
+            committed: self.committed.clone(),
+            target_node: self.target_node.clone(),
+        });
+    }
+
+    /// Report to RaftCore that the target acknowledged the leadership with an AppendEntries sent at `sent_at`.
+    ///
+    /// It is only used to extend the leader lease and to check the quorum, thus nothing is sent if neither is enabled.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn update_acked(&mut self, sent_at: Instant) {
+        if self.config.lease_duration().is_none() && !self.config.enable_check_quorum {
+            return;
+        }
+
+        let _ = self.raft_core_tx.send((
+            ReplicaEvent::Acked {
+                target: self.target.clone(),
+                sent_at,
+            },
+            tracing::debug_span!("CH"),
+        ));
+    }
+
     /// Perform a check to see if this replication stream is lagging behind far enough that a
     /// snapshot is warranted.
     #[tracing::instrument(level = "trace", skip(self))]
-    pub(self) fn needs_snapshot(&self) -> bool {
-        match &self.config.snapshot_policy {
-            SnapshotPolicy::LogsSinceLast(threshold) => {
-                let c = self.committed.next_index();
-                let m = self.matched.next_index();
+    pub(self) async fn needs_snapshot(&mut self) -> Result<bool, StorageError<NID>> {
+        let c = self.committed.next_index();
+        let m = self.matched.next_index();
+        let lag = c.saturating_sub(m);
 
-                let needs_snap = c.saturating_sub(m) >= *threshold;
-
-                tracing::trace!("snapshot needed: {}", needs_snap);
-                needs_snap
-            }
+        if lag > 0 && self.entry_bytes == 0 && self.config.snapshot_policy.has_bytes() {
+            self.entry_bytes = self.sample_entry_bytes(m, c).await?;
         }
+        let lag_bytes = lag * self.entry_bytes;
+
+        let needs_snap = self.config.snapshot_policy.is_lag_satisfied(lag, lag_bytes);
+
+        tracing::trace!(lag, lag_bytes, "snapshot needed: {}", needs_snap);
+        Ok(needs_snap)
+    }
+
+    /// Estimate the average size of the logs in `[start, end)` with the last `max_payload_entries` of them in storage.
+    ///
+    /// It is used before any log is sent to the target. Purged logs are not counted.
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn sample_entry_bytes(&self, start: u64, end: u64) -> Result<u64, StorageError<NID>> {
+        let start = std::cmp::max(start, end.saturating_sub(self.config.max_payload_entries));
+        let logs = self.storage.try_get_log_entries(start..end).await?;
+
+        Ok(average_entry_bytes(&logs))
     }
 
     /// Perform a check to see if this replication stream has more log to replicate
@@ -498,8 +1072,19 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
         self.last_log_id.index() > self.matched.index()
     }
 
+    /// Check if the logs to replicate can be sent with more than one AppendEntries RPC in flight.
+    ///
+    /// It requires the last RPC to have succeeded and the matching log on the target to have been found.
     #[tracing::instrument(level = "trace", skip(self))]
-    pub async fn try_drain_raft_rx(&mut self) -> Result<(), ReplicationError> {
+    pub(self) fn can_pipeline(&self) -> bool {
+        self.config.max_in_flight_append_entries > 1
+            && self.pipeline_allowed
+            && self.matched.index() == self.max_possible_matched_index
+            && self.has_more_log()
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub async fn try_drain_raft_rx(&mut self) -> Result<(), ReplicationError<NID>> {
         tracing::debug!("try_drain_raft_rx");
 
         for _i in 0..self.config.max_payload_entries {
@@ -528,7 +1113,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     }
 
     #[tracing::instrument(level = "trace", skip(self), fields(event=%event.summary()))]
-    pub fn process_raft_event(&mut self, event: RaftEvent) -> Result<(), ReplicationError> {
+    pub fn process_raft_event(&mut self, event: RaftEvent<NID, NI>) -> Result<(), ReplicationError<NID>> {
         tracing::debug!(event=%event.summary(), "process_raft_event");
 
         match event {
@@ -539,51 +1124,68 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             RaftEvent::Replicate { appended, committed } => {
                 self.committed = committed;
 
-                assert!(self.last_log_id < Some(appended));
+                assert!(self.last_log_id < Some(appended.clone()));
                 self.last_log_id = Some(appended);
             }
+
+            RaftEvent::UpdateNode { node } => {
+                self.target_node = node;
+            }
         }
 
+        self.update_heartbeat_state();
+
         Ok(())
     }
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////////////
 
-/// The state of the replication stream.
-#[derive(Debug, Eq, PartialEq)]
-enum TargetReplState {
-    /// The replication stream is running at line rate.
-    LineRate,
+/// An AppendEntries RPC sent to the target.
+#[derive(Debug)]
+struct InFlight<NID: NodeId> {
+    /// The `prev_log_id` of the RPC.
+    prev_log_id: Option<LogId<NID>>,
 
-    /// The replication stream is streaming a snapshot over to the target node.
-    Snapshotting { must_include: Option<LogId> },
+    /// The last log id in the RPC, which is matched on the target if the RPC succeeds.
+    last_log_id: Option<LogId<NID>>,
 
-    /// The replication stream is shutting down.
-    Shutdown,
+    /// The size in bytes of the logs in the RPC, added to `bytes_sent` if the target accepts them.
+    bytes: u64,
+
+    /// The time the RPC was sent.
+    sent_at: Instant,
 }
 
+/// An AppendEntries RPC to send, along with the in-flight info of it.
+type Payload<D, NID, NI> = (AppendEntriesRequest<D, NID, NI>, InFlight<NID>);
+
+/// The in-flight info of a sent AppendEntries RPC, along with the result of it.
+type SendResult<NID> = (InFlight<NID>, Result<AppendEntriesResponse<NID>, ReplicationError<NID>>);
+
 // TODO(xp): remove Replicate
 /// An event from the Raft node.
-pub(crate) enum RaftEvent {
+pub(crate) enum RaftEvent<NID: NodeId, NI: NodeInfo> {
     Replicate {
         /// The new entry which needs to be replicated.
         ///
         /// The logId of the most recent entry to have been appended to the log, its index is the
         /// new last_log_index value.
-        appended: LogId,
+        appended: LogId<NID>,
 
         /// The index of the highest log entry which is known to be committed in the cluster.
-        committed: Option<LogId>,
+        committed: Option<LogId<NID>>,
     },
     /// A message from Raft indicating a new commit index value.
     UpdateCommittedLogId {
         /// The index of the highest log entry which is known to be committed in the cluster.
-        committed: Option<LogId>,
+        committed: Option<LogId<NID>>,
     },
+    /// The info of the target node is changed by a membership config.
+    UpdateNode { node: Option<NI> },
 }
 
-impl MessageSummary for RaftEvent {
+impl<NID: NodeId, NI: NodeInfo> MessageSummary for RaftEvent<NID, NI> {
     fn summary(&self) -> String {
         match self {
             RaftEvent::Replicate { appended: _, committed } => {
@@ -594,44 +1196,85 @@ impl MessageSummary for RaftEvent {
             } => {
                 format!("UpdateCommitIndex: commit_index: {:?}", commit_index)
             }
+            RaftEvent::UpdateNode { node } => {
+                format!("UpdateNode: node: {:?}", node)
+            }
         }
     }
 }
 
 /// An event coming from a replication stream.
-pub(crate) enum ReplicaEvent<S>
-where S: AsyncRead + AsyncSeek + Send + Unpin + 'static
+pub(crate) enum ReplicaEvent<S, NID>
+where
+    S: AsyncRead + AsyncSeek + Send + Unpin + 'static,
+    NID: NodeId,
 {
     /// An event from a replication stream which updates the target node's match index.
     UpdateMatched {
         /// The ID of the target node for which the match index is to be updated.
-        target: NodeId,
+        target: NID,
         /// The log of the most recent log known to have been successfully replicated on the target.
-        matched: Option<LogId>,
+        matched: Option<LogId<NID>>,
+    },
+    /// An event from a replication stream telling the target has acknowledged the leadership.
+    Acked {
+        /// The ID of the target node that acknowledged.
+        target: NID,
+        /// The time the acknowledged AppendEntries RPC was sent.
+        sent_at: Instant,
     },
     /// An event indicating that the Raft node needs to revert to follower state.
     RevertToFollower {
         /// The ID of the target node from which the new term was observed.
-        target: NodeId,
+        target: NID,
 
         /// The new vote observed.
-        vote: Vote,
+        vote: Vote<NID>,
     },
     /// An event from a replication stream requesting snapshot info.
     NeedsSnapshot {
-        target: NodeId,
+        target: NID,
 
         /// The log id the caller requires the snapshot has to include.
-        must_include: Option<LogId>,
+        must_include: Option<LogId<NID>>,
 
         /// The response channel for delivering the snapshot data.
-        tx: oneshot::Sender<Snapshot<S>>,
+        tx: oneshot::Sender<Snapshot<S, NID>>,
+    },
+    /// An event from a replication stream telling an RPC to the target failed with a transport error.
+    Failed {
+        /// The ID of the target node.
+        target: NID,
+        /// The error of the failed RPC.
+        error: String,
+    },
+    /// An event from a replication stream telling the target responded to an RPC.
+    ///
+    /// It is sent at most once every `heartbeat_interval`, unless the target recovers from failures.
+    Responded {
+        /// The ID of the target node.
+        target: NID,
+        /// The round trip time of the RPC.
+        latency: Duration,
+    },
+    /// An event from a replication stream reporting its progress other than the matched log id.
+    ///
+    /// It is sent at once for a change of the state, otherwise at most once every `heartbeat_interval`.
+    UpdateProgress {
+        /// The ID of the target node.
+        target: NID,
+        /// The state of the replication stream.
+        state: TargetReplState<NID>,
+        /// The number of bytes of logs and snapshot data the target has accepted.
+        bytes_sent: u64,
+        /// The progress of the snapshot being sent.
+        snapshot_transfer: Option<SnapshotTransfer>,
     },
     /// Some critical error has taken place, and Raft needs to shutdown.
     Shutdown,
 }
 
-impl<S: AsyncRead + AsyncSeek + Send + Unpin + 'static> MessageSummary for ReplicaEvent<S> {
+impl<S: AsyncRead + AsyncSeek + Send + Unpin + 'static, NID: NodeId> MessageSummary for ReplicaEvent<S, NID> {
     fn summary(&self) -> String {
         match self {
             ReplicaEvent::UpdateMatched {
@@ -640,6 +1283,12 @@ impl<S: AsyncRead + AsyncSeek + Send + Unpin + 'static> MessageSummary for Repli
             } => {
                 format!("UpdateMatchIndex: target: {}, matched: {:?}", target, matched)
             }
+            ReplicaEvent::Acked {
+                ref target,
+                ref sent_at,
+            } => {
+                format!("Acked: target: {}, sent_at: {:?}", target, sent_at)
+            }
             ReplicaEvent::RevertToFollower { ref target, ref vote } => {
                 format!("RevertToFollower: target: {}, vote: {}", target, vote)
             }
@@ -650,14 +1299,42 @@ impl<S: AsyncRead + AsyncSeek + Send + Unpin + 'static> MessageSummary for Repli
             } => {
                 format!("NeedsSnapshot: target: {}, must_include: {:?}", target, must_include)
             }
+            ReplicaEvent::Failed { ref target, ref error } => {
+                format!("Failed: target: {}, error: {}", target, error)
+            }
+            ReplicaEvent::Responded {
+                ref target,
+                ref latency,
+            } => {
+                format!("Responded: target: {}, latency: {:?}", target, latency)
+            }
+            ReplicaEvent::UpdateProgress {
+                ref target,
+                ref state,
+                ref bytes_sent,
+                ref snapshot_transfer,
+            } => {
+                format!(
+                    "UpdateProgress: target: {}, state: {:?}, bytes_sent: {}, snapshot_transfer: {:?}",
+                    target, state, bytes_sent, snapshot_transfer
+                )
+            }
             ReplicaEvent::Shutdown => "Shutdown".to_string(),
         }
     }
 }
 
-impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> ReplicationCore<D, R, N, S> {
+impl<
+        D: AppData,
+        R: AppDataResponse,
+        N: RaftNetwork<D, NID, NI>,
+        S: RaftStorage<D, R, NID, NI>,
+        NID: NodeId,
+        NI: NodeInfo,
+    > ReplicationCore<D, R, N, S, NID, NI>
+{
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn line_rate_loop(&mut self) -> Result<(), ReplicationError> {
+    pub async fn line_rate_loop(&mut self) -> Result<(), ReplicationError<NID>> {
         loop {
             loop {
                 tracing::debug!(
@@ -666,17 +1343,19 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                     self.max_possible_matched_index
                 );
 
-                let res = self.send_append_entries().await;
+                let res = if self.can_pipeline() {
+                    self.pipeline_append_entries().await
+                } else {
+                    self.send_append_entries().await
+                };
 
                 if let Err(err) = res {
                     tracing::error!(error=%err, "error replication to target={}", self.target);
 
-                    // For transport error, just keep retrying.
+                    // For transport error, keep retrying after a backoff.
                     match err {
-                        ReplicationError::Timeout { .. } => {
-                            break;
-                        }
-                        ReplicationError::Network { .. } => {
+                        ReplicationError::Timeout { .. } | ReplicationError::Network { .. } => {
+                            self.backoff(err.to_string()).await?;
                             break;
                         }
                         _ => {
@@ -690,7 +1369,7 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 }
             }
 
-            if self.needs_snapshot() {
+            if self.needs_snapshot().await? {
                 return Err(ReplicationError::CommittedAdvanceTooMany(CommittedAdvanceTooMany {
                     // TODO(xp) fill them
                     committed_index: 0,
@@ -708,30 +1387,26 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 continue;
             }
 
-            tokio::select! {
-                _ = self.heartbeat.tick() => {
-                    tracing::debug!("heartbeat triggered");
-                    // continue
+            // Heartbeats are sent by the heartbeat task, there is nothing to do until a new event.
+            self.flush_progress();
+            match self.repl_rx.recv().await {
+                Some((event, _span)) => {
+                    self.process_raft_event(event)?;
+                    self.try_drain_raft_rx().await?;
                 }
-
-                event_span = self.repl_rx.recv() => {
-                    match event_span {
-                        Some((event, _span)) => {
-                            self.process_raft_event(event)?;
-                            self.try_drain_raft_rx().await?;
-                        },
-                        None => {
-                            tracing::debug!("received: RaftEvent::Terminate: closed");
-                            return Err(ReplicationError::Closed);
-                        },
-                    }
+                None => {
+                    tracing::debug!("received: RaftEvent::Terminate: closed");
+                    return Err(ReplicationError::Closed);
                 }
             }
         }
     }
 
     #[tracing::instrument(level = "debug", skip(self), fields(state = "snapshotting"))]
-    pub async fn replicate_snapshot(&mut self, snapshot_must_include: Option<LogId>) -> Result<(), ReplicationError> {
+    pub async fn replicate_snapshot(
+        &mut self,
+        snapshot_must_include: Option<LogId<NID>>,
+    ) -> Result<(), ReplicationError<NID>> {
         let snapshot = self.wait_for_snapshot(snapshot_must_include).await?;
         self.stream_snapshot(snapshot).await?;
 
@@ -745,8 +1420,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     #[tracing::instrument(level = "debug", skip(self))]
     async fn wait_for_snapshot(
         &mut self,
-        snapshot_must_include: Option<LogId>,
-    ) -> Result<Snapshot<S::SnapshotData>, ReplicationError> {
+        snapshot_must_include: Option<LogId<NID>>,
+    ) -> Result<Snapshot<S::SnapshotData, NID>, ReplicationError<NID>> {
         // Ask raft core for a snapshot.
         // - If raft core has a ready snapshot, it sends back through tx.
         // - Otherwise raft core starts a new task taking snapshot, and **close** `tx` when finished. Thus there has to
@@ -760,8 +1435,8 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
             // ReplicationError::Closed.
             let _ = self.raft_core_tx.send((
                 ReplicaEvent::NeedsSnapshot {
-                    target: self.target,
-                    must_include: snapshot_must_include,
+                    target: self.target.clone(),
+                    must_include: snapshot_must_include.clone(),
                     tx,
                 },
                 tracing::debug_span!("CH"),
@@ -769,25 +1444,9 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
 
             let mut waiting_for_snapshot = true;
 
-            // TODO(xp): use a watch channel to let the core to send one of the 3 event:
-            //           heartbeat, new-log, or snapshot is ready.
+            // Heartbeats are sent by the heartbeat task while waiting for the snapshot.
             while waiting_for_snapshot {
                 tokio::select! {
-                    _ = self.heartbeat.tick() => {
-                        // TODO(xp): just heartbeat:
-                        let res = self.send_append_entries().await;
-                        match res {
-                            Ok(_) => {
-                                //
-                            },
-                            Err(err) => {
-                                if let ReplicationError::StorageError(_) = err {
-                                    return Err(err);
-                                }
-                            }
-                        }
-                    },
-
                     event_span = self.repl_rx.recv() =>  {
                         match event_span {
 
@@ -822,28 +1481,82 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
     }
 
     #[tracing::instrument(level = "trace", skip(self, snapshot))]
-    async fn stream_snapshot(&mut self, mut snapshot: Snapshot<S::SnapshotData>) -> Result<(), ReplicationError> {
+    async fn stream_snapshot(
+        &mut self,
+        mut snapshot: Snapshot<S::SnapshotData, NID>,
+    ) -> Result<(), ReplicationError<NID>> {
         let err_x = || (ErrorSubject::Snapshot(snapshot.meta.clone()), ErrorVerb::Read);
 
         let end = snapshot.snapshot.seek(SeekFrom::End(0)).await.sto_res(err_x)?;
 
         let mut offset = 0;
 
+        self.snapshot_transfer = Some(SnapshotTransfer {
+            snapshot_id: snapshot.meta.snapshot_id.clone(),
+            offset,
+            size: end,
+        });
+        self.report_progress(true);
+
+        // The checksum of the snapshot data before `offset`, which the target has received.
+        let mut checksum = crc32fast::Hasher::new();
+
+        // Falls back to no compression if the target can not decompress a chunk.
+        let mut compression = if self.witness {
+            SnapshotCompression::None
+        } else {
+            self.config.snapshot_compression
+        };
+
+        // The time to send the next chunk, to keep the average rate under `snapshot_max_bytes_per_sec`.
+        let mut next_send_at = Instant::now();
+
         let mut buf = Vec::with_capacity(self.config.snapshot_max_chunk_size as usize);
 
+        // A witness has no state machine to install the snapshot data to. It is sent only the snapshot meta along
+        // with the membership config, in a single RPC.
+        let witness_membership = if self.witness {
+            self.storage.get_membership().await?
+        } else {
+            None
+        };
+
         loop {
             // Build the RPC.
-            snapshot.snapshot.seek(SeekFrom::Start(offset)).await.sto_res(err_x)?;
+            let n_read = if self.witness {
+                0
+            } else {
+                snapshot.snapshot.seek(SeekFrom::Start(offset)).await.sto_res(err_x)?;
+                snapshot.snapshot.read_buf(&mut buf).await.sto_res(err_x)?
+            };
+
+            let done = self.witness || (offset + n_read as u64) == end; // If bytes read == 0, then we're done.
+
+            let mut next_checksum = checksum.clone();
+            next_checksum.update(&buf[..n_read]);
 
-            let n_read = snapshot.snapshot.read_buf(&mut buf).await.sto_res(err_x)?;
+            let data = match compression.compress(&buf[..n_read]) {
+                Ok(x) => x,
+                Err(reason) => {
+                    tracing::warn!(%compression, reason = display(&reason), "failed to compress snapshot chunk");
+                    compression = SnapshotCompression::None;
+                    Vec::from(&buf[..n_read])
+                }
+            };
 
-            let done = (offset + n_read as u64) == end; // If bytes read == 0, then we're done.
             let req = InstallSnapshotRequest {
-                vote: self.vote,
+                vote: self.vote.clone(),
                 meta: snapshot.meta.clone(),
                 offset,
-                data: Vec::from(&buf[..n_read]),
+                data,
+                compression,
                 done,
+                checksum: if done && !self.witness {
+                    Some(next_checksum.clone().finalize())
+                } else {
+                    None
+                },
+                witness_membership: witness_membership.clone(),
             };
             buf.clear();
 
@@ -856,34 +1569,93 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 "sending snapshot chunk"
             );
 
+            let data_len = req.data.len() as u64;
+
+            sleep_until(next_send_at).await;
+            if self.config.snapshot_max_bytes_per_sec > 0 {
+                let cost = data_len as f64 / self.config.snapshot_max_bytes_per_sec as f64;
+                next_send_at = std::cmp::max(next_send_at, Instant::now()) + Duration::from_secs_f64(cost);
+            }
+
+            let sent_at = Instant::now();
             let res = timeout(
                 self.install_snapshot_timeout,
-                self.network.send_install_snapshot(self.target, req),
+                self.network.send_install_snapshot(self.target.clone(), self.target_node.as_ref(), req),
             )
             .await;
 
             let res = match res {
                 Ok(outer_res) => match outer_res {
                     Ok(res) => res,
+                    Err(RPCError::RemoteError(remote)) => {
+                        // The target tells where the data it has received ends. Resume from there instead of
+                        // sending the snapshot from the beginning again.
+                        let resume_at = match &remote.source {
+                            InstallSnapshotError::SnapshotMismatch(mismatch) => {
+                                if mismatch.expect.id == snapshot.meta.snapshot_id {
+                                    mismatch.expect.offset
+                                } else {
+                                    0
+                                }
+                            }
+                            InstallSnapshotError::ChecksumMismatch(_) => 0,
+                            InstallSnapshotError::Decompress(_) => {
+                                compression = SnapshotCompression::None;
+                                offset
+                            }
+                            // The target is not able to receive a snapshot, e.g., its storage fails. Retry after a
+                            // backoff instead of resending at once.
+                            InstallSnapshotError::Fatal(_) => {
+                                tracing::warn!(error=%remote, "InstallSnapshot RPC failed on target");
+                                self.backoff(remote.to_string()).await?;
+                                continue;
+                            }
+                        };
+
+                        self.succeed(sent_at);
+
+                        tracing::warn!(error=%remote, resume_at, "InstallSnapshot RPC is rejected by target");
+
+                        let (o, c) = Self::seek_snapshot_data(
+                            &snapshot.meta,
+                            &mut snapshot.snapshot,
+                            offset,
+                            checksum,
+                            resume_at,
+                            end,
+                        )
+                        .await?;
+                        offset = o;
+                        checksum = c;
+                        self.update_snapshot_transfer(offset);
+                        continue;
+                    }
                     Err(err) => {
                         tracing::warn!(error=%err, "error sending InstallSnapshot RPC to target");
+                        self.backoff(err.to_string()).await?;
                         continue;
                     }
                 },
                 Err(err) => {
                     tracing::warn!(error=%err, "timeout while sending InstallSnapshot RPC to target");
+                    self.backoff(err.to_string()).await?;
                     continue;
                 }
             };
 
+            self.succeed(sent_at);
+
             // Handle response conditions.
             if res.vote > self.vote {
                 return Err(ReplicationError::HigherVote(HigherVote {
                     higher: res.vote,
-                    mine: self.vote,
+                    mine: self.vote.clone(),
                 }));
             }
 
+            self.update_acked(sent_at);
+            self.bytes_sent += data_len;
+
             // If we just sent the final chunk of the snapshot, then transition to lagging state.
             if done {
                 tracing::debug!(
@@ -893,15 +1665,71 @@ impl<D: AppData, R: AppDataResponse, N: RaftNetwork<D>, S: RaftStorage<D, R>> Re
                 );
 
                 self.update_matched(Some(snapshot.meta.last_log_id));
+                self.snapshot_transfer = None;
 
                 return Ok(());
             }
 
             // Everything is good, so update offset for sending the next chunk.
             offset += n_read as u64;
+            checksum = next_checksum;
+            self.update_snapshot_transfer(offset);
 
             // Check raft channel to ensure we are staying up-to-date, then loop.
             self.try_drain_raft_rx().await?;
         }
     }
+
+    /// Update the offset of the snapshot data the target has received, and report it to RaftCore.
+    fn update_snapshot_transfer(&mut self, offset: u64) {
+        if let Some(t) = &mut self.snapshot_transfer {
+            t.offset = offset;
+        }
+        self.report_progress(false);
+    }
+
+    /// Move the offset of the next chunk to send from `offset` to `to`, and return the new offset and the checksum
+    /// of the data before it.
+    ///
+    /// Data skipped forward is read to update the checksum. An offset beyond the end of the snapshot is invalid, and
+    /// the snapshot is sent from the beginning.
+    async fn seek_snapshot_data(
+        meta: &SnapshotMeta<NID>,
+        data: &mut Box<S::SnapshotData>,
+        mut offset: u64,
+        mut checksum: crc32fast::Hasher,
+        to: u64,
+        end: u64,
+    ) -> Result<(u64, crc32fast::Hasher), StorageError<NID>> {
+        let err_x = || (ErrorSubject::Snapshot(meta.clone()), ErrorVerb::Read);
+
+        let to = if to > end { 0 } else { to };
+
+        if to < offset {
+            offset = 0;
+            checksum = crc32fast::Hasher::new();
+        }
+
+        data.seek(SeekFrom::Start(offset)).await.sto_res(err_x)?;
+
+        let mut buf = vec![0; 64 * 1024];
+        while offset < to {
+            let n = std::cmp::min(buf.len() as u64, to - offset) as usize;
+            data.read_exact(&mut buf[..n]).await.sto_res(err_x)?;
+            checksum.update(&buf[..n]);
+            offset += n as u64;
+        }
+
+        Ok((offset, checksum))
+    }
+}
+
+/// The average serialized size in bytes of `logs`, or 0 if there is none.
+fn average_entry_bytes<D: AppData, NID: NodeId, NI: NodeInfo>(logs: &[Entry<D, NID, NI>]) -> u64 {
+    if logs.is_empty() {
+        return 0;
+    }
+
+    let bytes: u64 = logs.iter().map(|ent| bincode::serialized_size(ent).unwrap_or_default()).sum();
+    bytes / logs.len() as u64
 }
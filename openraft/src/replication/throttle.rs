@@ -0,0 +1,41 @@
+use tokio::time::Duration;
+use tokio::time::Instant;
+
+use crate::config::Config;
+
+/// Throttles the `Responded` events a replication target reports to RaftCore.
+///
+/// It is shared by the heartbeat task and the replication stream of a target. Every RPC and heartbeat is responded
+/// to, but a response is reported at most once every `heartbeat_interval`, unless it is the first one or the target
+/// recovers from failures.
+#[derive(Debug, Clone)]
+pub(super) struct ResponseThrottle {
+    interval: Duration,
+
+    /// The last time a response was reported.
+    reported_at: Option<Instant>,
+}
+
+impl ResponseThrottle {
+    pub(super) fn new(config: &Config) -> Self {
+        Self {
+            interval: Duration::from_millis(config.heartbeat_interval),
+            reported_at: None,
+        }
+    }
+
+    /// Record a response, returns `true` if it should be reported.
+    pub(super) fn respond(&mut self, recovered: bool) -> bool {
+        let now = Instant::now();
+
+        let due = match self.reported_at {
+            Some(t) => recovered || now >= t + self.interval,
+            None => true,
+        };
+
+        if due {
+            self.reported_at = Some(now);
+        }
+        due
+    }
+}
@@ -0,0 +1,27 @@
+use crate::replication::throttle::ResponseThrottle;
+use crate::Config;
+
+#[test]
+fn test_response_throttle_reports_once_per_interval() {
+    let config = Config {
+        heartbeat_interval: 60_000,
+        ..Default::default()
+    };
+    let mut throttle = ResponseThrottle::new(&config);
+
+    assert!(throttle.respond(false), "the first response is reported");
+    assert!(!throttle.respond(false), "within heartbeat_interval");
+    assert!(!throttle.respond(false), "within heartbeat_interval");
+
+    assert!(throttle.respond(true), "a recovered target is reported at once");
+    assert!(!throttle.respond(false));
+
+    let config = Config {
+        heartbeat_interval: 0,
+        ..Default::default()
+    };
+    let mut throttle = ResponseThrottle::new(&config);
+
+    assert!(throttle.respond(false));
+    assert!(throttle.respond(false), "heartbeat_interval has passed");
+}
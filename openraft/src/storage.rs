@@ -20,13 +20,17 @@ use crate::AppData;
 use crate::AppDataResponse;
 use crate::LogId;
 use crate::LogIdOptionExt;
+use crate::Node;
+use crate::NodeId;
+use crate::NodeInfo;
 use crate::StorageError;
 use crate::Vote;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-pub struct SnapshotMeta {
+#[serde(bound = "")]
+pub struct SnapshotMeta<NID: NodeId = u64> {
     // Log entries upto which this snapshot includes, inclusive.
-    pub last_log_id: LogId,
+    pub last_log_id: LogId<NID>,
 
     /// To identify a snapshot when transferring.
     /// Caveat: even when two snapshot is built with the same `last_log_id`, they still could be different in bytes.
@@ -35,11 +39,13 @@ pub struct SnapshotMeta {
 
 /// The data associated with the current snapshot.
 #[derive(Debug)]
-pub struct Snapshot<S>
-where S: AsyncRead + AsyncSeek + Send + Unpin + 'static
+pub struct Snapshot<S, NID = u64>
+where
+    S: AsyncRead + AsyncSeek + Send + Unpin + 'static,
+    NID: NodeId,
 {
     /// metadata of a snapshot
-    pub meta: SnapshotMeta,
+    pub meta: SnapshotMeta<NID>,
 
     /// A read handle to the associated snapshot.
     pub snapshot: Box<S>,
@@ -47,31 +53,31 @@ where S: AsyncRead + AsyncSeek + Send + Unpin + 'static
 
 /// A struct used to represent the initial state which a Raft node needs when first starting.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
-pub struct InitialState {
+pub struct InitialState<NID: NodeId = u64, NI: NodeInfo = Node> {
     /// The last entry.
-    pub last_log_id: Option<LogId>,
+    pub last_log_id: Option<LogId<NID>>,
 
     /// The LogId of the last log applied to the state machine.
-    pub last_applied: Option<LogId>,
+    pub last_applied: Option<LogId<NID>>,
 
-    pub vote: Vote,
+    pub vote: Vote<NID>,
 
     /// The latest cluster membership configuration found, in log or in state machine, else a new initial
     /// membership config consisting only of this node's ID.
-    pub last_membership: Option<EffectiveMembership>,
+    pub last_membership: Option<EffectiveMembership<NID, NI>>,
 }
 
 /// The state about logs.
 ///
 /// Invariance: last_purged_log_id <= last_applied <= last_log_id
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
-pub struct LogState {
+pub struct LogState<NID: NodeId = u64> {
     /// The greatest log id that has been purged after being applied to state machine.
-    pub last_purged_log_id: Option<LogId>,
+    pub last_purged_log_id: Option<LogId<NID>>,
 
     /// The log id of the last present entry if there are any entries.
     /// Otherwise the same value as `last_purged_log_id`.
-    pub last_log_id: Option<LogId>,
+    pub last_log_id: Option<LogId<NID>>,
 }
 
 /// A trait defining the interface for a Raft storage system.
@@ -79,10 +85,12 @@ pub struct LogState {
 /// See the [storage chapter of the guide](https://datafuselabs.github.io/openraft/storage.html)
 /// for details and discussion on this trait and how to implement it.
 #[async_trait]
-pub trait RaftStorage<D, R>: Send + Sync + 'static
+pub trait RaftStorage<D, R, NID = u64, NI = Node>: Send + Sync + 'static
 where
     D: AppData,
     R: AppDataResponse,
+    NID: NodeId,
+    NI: NodeInfo,
 {
     // TODO(xp): simplify storage API
 
@@ -93,7 +101,7 @@ where
     type SnapshotData: AsyncRead + AsyncWrite + AsyncSeek + Send + Sync + Unpin + 'static;
 
     /// Returns the last membership config found in log or state machine.
-    async fn get_membership(&self) -> Result<Option<EffectiveMembership>, StorageError> {
+    async fn get_membership(&self) -> Result<Option<EffectiveMembership<NID, NI>>, StorageError<NID>> {
         let (_, sm_mem) = self.last_applied_state().await?;
 
         let sm_mem_index = match &sm_mem {
@@ -115,7 +123,10 @@ where
     /// This method should returns membership with the greatest log index which is `>=since_index`.
     /// If no such membership log is found, it returns `None`, e.g., when logs are cleaned after being applied.
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn last_membership_in_log(&self, since_index: u64) -> Result<Option<EffectiveMembership>, StorageError> {
+    async fn last_membership_in_log(
+        &self,
+        since_index: u64,
+    ) -> Result<Option<EffectiveMembership<NID, NI>>, StorageError<NID>> {
         let st = self.get_log_state().await?;
 
         let mut end = st.last_log_id.next_index();
@@ -128,7 +139,7 @@ where
             for ent in entries.iter().rev() {
                 if let EntryPayload::Membership(ref mem) = ent.payload {
                     return Ok(Some(EffectiveMembership {
-                        log_id: ent.log_id,
+                        log_id: ent.log_id.clone(),
                         membership: mem.clone(),
                     }));
                 }
@@ -144,7 +155,7 @@ where
     ///
     /// When the Raft node is first started, it will call this interface to fetch the last known state from stable
     /// storage.
-    async fn get_initial_state(&self) -> Result<InitialState, StorageError> {
+    async fn get_initial_state(&self) -> Result<InitialState<NID, NI>, StorageError<NID>> {
         let vote = self.read_vote().await?;
         let st = self.get_log_state().await?;
         let mut last_log_id = st.last_log_id;
@@ -153,8 +164,8 @@ where
 
         // Clean up dirty state: snapshot is installed but logs are not cleaned.
         if last_log_id < last_applied {
-            self.purge_logs_upto(last_applied.unwrap()).await?;
-            last_log_id = last_applied;
+            self.purge_logs_upto(last_applied.clone().unwrap()).await?;
+            last_log_id = last_applied.clone();
         }
 
         Ok(InitialState {
@@ -172,7 +183,7 @@ where
     async fn get_log_entries<RB: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RB,
-    ) -> Result<Vec<Entry<D>>, StorageError> {
+    ) -> Result<Vec<Entry<D, NID, NI>>, StorageError<NID>> {
         let res = self.try_get_log_entries(range.clone()).await?;
 
         check_range_matches_entries(range, &res)?;
@@ -183,13 +194,13 @@ where
     /// Try to get an log entry.
     ///
     /// It does not return an error if the log entry at `log_index` is not found.
-    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<D>>, StorageError> {
+    async fn try_get_log_entry(&self, log_index: u64) -> Result<Option<Entry<D, NID, NI>>, StorageError<NID>> {
         let mut res = self.try_get_log_entries(log_index..(log_index + 1)).await?;
         Ok(res.pop())
     }
 
     /// Get the log id of the entry at `index`.
-    async fn get_log_id(&self, log_index: u64) -> Result<LogId, StorageError> {
+    async fn get_log_id(&self, log_index: u64) -> Result<LogId<NID>, StorageError<NID>> {
         let st = self.get_log_state().await?;
 
         if Some(log_index) == st.last_purged_log_id.index() {
@@ -198,14 +209,14 @@ where
 
         let entries = self.get_log_entries(log_index..=log_index).await?;
 
-        Ok(entries[0].log_id)
+        Ok(entries[0].log_id.clone())
     }
 
     // --- Vote
 
-    async fn save_vote(&self, vote: &Vote) -> Result<(), StorageError>;
+    async fn save_vote(&self, vote: &Vote<NID>) -> Result<(), StorageError<NID>>;
 
-    async fn read_vote(&self) -> Result<Option<Vote>, StorageError>;
+    async fn read_vote(&self) -> Result<Option<Vote<NID>>, StorageError<NID>>;
 
     // --- Log
 
@@ -214,7 +225,7 @@ where
     /// The impl should not consider the applied log id in state machine.
     /// The returned `last_log_id` could be the log id of the last present log entry, or the `last_purged_log_id` if
     /// there is no entry at all.
-    async fn get_log_state(&self) -> Result<LogState, StorageError>;
+    async fn get_log_state(&self) -> Result<LogState<NID>, StorageError<NID>>;
 
     /// Get a series of log entries from storage.
     ///
@@ -224,25 +235,27 @@ where
     async fn try_get_log_entries<RB: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RB,
-    ) -> Result<Vec<Entry<D>>, StorageError>;
+    ) -> Result<Vec<Entry<D, NID, NI>>, StorageError<NID>>;
 
     /// Append a payload of entries to the log.
     ///
     /// Though the entries will always be presented in order, each entry's index should be used to
     /// determine its location to be written in the log.
-    async fn append_to_log(&self, entries: &[&Entry<D>]) -> Result<(), StorageError>;
+    async fn append_to_log(&self, entries: &[&Entry<D, NID, NI>]) -> Result<(), StorageError<NID>>;
 
     /// Delete conflict log entries since `log_id`, inclusive.
-    async fn delete_conflict_logs_since(&self, log_id: LogId) -> Result<(), StorageError>;
+    async fn delete_conflict_logs_since(&self, log_id: LogId<NID>) -> Result<(), StorageError<NID>>;
 
     /// Delete applied log entries upto `log_id`, inclusive.
-    async fn purge_logs_upto(&self, log_id: LogId) -> Result<(), StorageError>;
+    async fn purge_logs_upto(&self, log_id: LogId<NID>) -> Result<(), StorageError<NID>>;
 
     // --- State Machine
 
     /// Returns the last applied log id which is recorded in state machine, and the last applied membership log id and
     /// membership config.
-    async fn last_applied_state(&self) -> Result<(Option<LogId>, Option<EffectiveMembership>), StorageError>;
+    async fn last_applied_state(
+        &self,
+    ) -> Result<(Option<LogId<NID>>, Option<EffectiveMembership<NID, NI>>), StorageError<NID>>;
 
     /// Apply the given payload of entries to the state machine.
     ///
@@ -258,7 +271,20 @@ where
     /// - Store the last applied log id.
     /// - Deal with the EntryPayload::Normal() log, which is business logic log.
     /// - Deal with EntryPayload::Membership, store the membership config.
-    async fn apply_to_state_machine(&self, entries: &[&Entry<D>]) -> Result<Vec<R>, StorageError>;
+    async fn apply_to_state_machine(&self, entries: &[&Entry<D, NID, NI>]) -> Result<Vec<R>, StorageError<NID>>;
+
+    /// Record `last_applied` as the last applied log id without applying any log to the state machine.
+    ///
+    /// It is only called on a witness, which is sent no snapshot data, when the logs it has not yet applied are
+    /// included in a snapshot of the leader. The logs upto `last_applied` are purged after this call.
+    ///
+    /// `membership` is the last membership config upto `last_applied`, if it is known, and should be returned by
+    /// `last_applied_state()` from then on.
+    async fn skip_apply_upto(
+        &self,
+        last_applied: &LogId<NID>,
+        membership: Option<&EffectiveMembership<NID, NI>>,
+    ) -> Result<(), StorageError<NID>>;
 
     // --- Snapshot
 
@@ -269,7 +295,7 @@ where
     /// Building snapshot can be done by:
     /// - Performing log compaction, e.g. merge log entries that operates on the same key, like a LSM-tree does,
     /// - or by fetching a snapshot from the state machine.
-    async fn build_snapshot(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError>;
+    async fn build_snapshot(&self) -> Result<Snapshot<Self::SnapshotData, NID>, StorageError<NID>>;
 
     /// Create a new blank snapshot, returning a writable handle to the snapshot object.
     ///
@@ -278,7 +304,7 @@ where
     /// ### implementation guide
     /// See the [storage chapter of the guide](https://datafuselabs.github.io/openraft/storage.html)
     /// for details on log compaction / snapshotting.
-    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError>;
+    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError<NID>>;
 
     /// Install a snapshot which has finished streaming from the cluster leader.
     ///
@@ -288,9 +314,9 @@ where
     /// A snapshot created from an earlier call to `begin_receiving_snapshot` which provided the snapshot.
     async fn install_snapshot(
         &self,
-        meta: &SnapshotMeta,
+        meta: &SnapshotMeta<NID>,
         snapshot: Box<Self::SnapshotData>,
-    ) -> Result<StateMachineChanges, StorageError>;
+    ) -> Result<StateMachineChanges<NID>, StorageError<NID>>;
 
     /// Get a readable handle to the current snapshot, along with its metadata.
     ///
@@ -303,7 +329,7 @@ where
     ///
     /// A proper snapshot implementation will store the term, index and membership config as part
     /// of the snapshot, which should be decoded for creating this method's response data.
-    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError>;
+    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData, NID>>, StorageError<NID>>;
 }
 
 /// APIs for debugging a store.
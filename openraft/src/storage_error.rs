@@ -7,6 +7,7 @@ use serde::Deserialize;
 use serde::Serialize;
 
 use crate::LogId;
+use crate::NodeId;
 use crate::SnapshotMeta;
 use crate::Vote;
 
@@ -15,13 +16,13 @@ pub trait ToStorageResult<T> {
     /// Convert Result<T, E> to Result<T, StorageError::IO(StorageIOError)>
     ///
     /// `f` provides error context for building the StorageIOError.
-    fn sto_res<F>(self, f: F) -> Result<T, StorageError>
-    where F: FnOnce() -> (ErrorSubject, ErrorVerb);
+    fn sto_res<NID: NodeId, F>(self, f: F) -> Result<T, StorageError<NID>>
+    where F: FnOnce() -> (ErrorSubject<NID>, ErrorVerb);
 }
 
 impl<T> ToStorageResult<T> for Result<T, std::io::Error> {
-    fn sto_res<F>(self, f: F) -> Result<T, StorageError>
-    where F: FnOnce() -> (ErrorSubject, ErrorVerb) {
+    fn sto_res<NID: NodeId, F>(self, f: F) -> Result<T, StorageError<NID>>
+    where F: FnOnce() -> (ErrorSubject<NID>, ErrorVerb) {
         match self {
             Ok(x) => Ok(x),
             Err(e) => {
@@ -36,18 +37,19 @@ impl<T> ToStorageResult<T> for Result<T, std::io::Error> {
 /// An error that occurs when the RaftStore impl runs defensive check of input or output.
 /// E.g. re-applying an log entry is a violation that may be a potential bug.
 #[derive(Debug, Clone, thiserror::Error, PartialEq, Eq, Serialize, Deserialize)]
-pub struct DefensiveError {
+#[serde(bound = "")]
+pub struct DefensiveError<NID: NodeId = u64> {
     /// The subject that violates store defensive check, e.g. hard-state, log or state machine.
-    pub subject: ErrorSubject,
+    pub subject: ErrorSubject<NID>,
 
     /// The description of the violation.
-    pub violation: Violation,
+    pub violation: Violation<NID>,
 
     pub backtrace: String,
 }
 
-impl DefensiveError {
-    pub fn new(subject: ErrorSubject, violation: Violation) -> DefensiveError {
+impl<NID: NodeId> DefensiveError<NID> {
+    pub fn new(subject: ErrorSubject<NID>, violation: Violation<NID>) -> Self {
         DefensiveError {
             subject,
             violation,
@@ -56,14 +58,15 @@ impl DefensiveError {
     }
 }
 
-impl std::fmt::Display for DefensiveError {
+impl<NID: NodeId> std::fmt::Display for DefensiveError<NID> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "'{:?}' violates: '{}'", self.subject, self.violation)
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub enum ErrorSubject {
+#[serde(bound = "")]
+pub enum ErrorSubject<NID: NodeId = u64> {
     /// A general storage error
     Store,
 
@@ -74,19 +77,19 @@ pub enum ErrorSubject {
     Logs,
 
     /// Error about a single log entry
-    Log(LogId),
+    Log(LogId<NID>),
 
     /// Error about a single log entry without knowing the log term.
     LogIndex(u64),
 
     /// Error happened when applying a log entry
-    Apply(LogId),
+    Apply(LogId<NID>),
 
     /// Error happened when operating state machine.
     StateMachine,
 
     /// Error happened when operating snapshot.
-    Snapshot(SnapshotMeta),
+    Snapshot(SnapshotMeta<NID>),
 
     None,
 }
@@ -102,17 +105,18 @@ pub enum ErrorVerb {
 
 /// Violations a store would return when running defensive check.
 #[derive(Debug, Clone, thiserror::Error, PartialEq, Eq, Serialize, Deserialize)]
-pub enum Violation {
+#[serde(bound = "")]
+pub enum Violation<NID: NodeId = u64> {
     #[error("term can only be change to a greater value, current: {curr}, change to {to}")]
     TermNotAscending { curr: u64, to: u64 },
 
     #[error("voted_for can not change from Some() to other Some(), current: {curr:?}, change to {to:?}")]
-    NonIncrementalVote { curr: Vote, to: Vote },
+    NonIncrementalVote { curr: Vote<NID>, to: Vote<NID> },
 
     #[error("log at higher index is obsolete: {higher_index_log_id:?} should GT {lower_index_log_id:?}")]
     DirtyLog {
-        higher_index_log_id: LogId,
-        lower_index_log_id: LogId,
+        higher_index_log_id: LogId<NID>,
+        lower_index_log_id: LogId<NID>,
     },
 
     #[error("try to get log at index {want} but got {got:?}")]
@@ -132,33 +136,34 @@ pub enum Violation {
     StoreLogsEmpty,
 
     #[error("logs are not consecutive, prev: {prev:?}, next: {next}")]
-    LogsNonConsecutive { prev: Option<LogId>, next: LogId },
+    LogsNonConsecutive { prev: Option<LogId<NID>>, next: LogId<NID> },
 
     #[error("invalid next log to apply: prev: {prev:?}, next: {next}")]
-    ApplyNonConsecutive { prev: Option<LogId>, next: LogId },
+    ApplyNonConsecutive { prev: Option<LogId<NID>>, next: LogId<NID> },
 
     #[error("applied log can not conflict, last_applied: {last_applied:?}, delete since: {first_conflict_log_id}")]
     AppliedWontConflict {
-        last_applied: Option<LogId>,
-        first_conflict_log_id: LogId,
+        last_applied: Option<LogId<NID>>,
+        first_conflict_log_id: LogId<NID>,
     },
 
     #[error("not allowed to purge non-applied logs, last_applied: {last_applied:?}, purge upto: {purge_upto}")]
     PurgeNonApplied {
-        last_applied: Option<LogId>,
-        purge_upto: LogId,
+        last_applied: Option<LogId<NID>>,
+        purge_upto: LogId<NID>,
     },
 }
 
 /// A storage error could be either a defensive check error or an error occurred when doing the actual io operation.
 #[derive(Debug, Clone, thiserror::Error, PartialEq, Eq, Serialize, Deserialize)]
-pub enum StorageError {
+#[serde(bound = "")]
+pub enum StorageError<NID: NodeId = u64> {
     /// An error raised by defensive check.
     #[error(transparent)]
     Defensive {
         #[from]
         #[backtrace]
-        source: DefensiveError,
+        source: DefensiveError<NID>,
     },
 
     /// An error raised by io operation.
@@ -166,26 +171,26 @@ pub enum StorageError {
     IO {
         #[from]
         #[backtrace]
-        source: StorageIOError,
+        source: StorageIOError<NID>,
     },
 }
 
-impl StorageError {
-    pub fn into_defensive(self) -> Option<DefensiveError> {
+impl<NID: NodeId> StorageError<NID> {
+    pub fn into_defensive(self) -> Option<DefensiveError<NID>> {
         match self {
             StorageError::Defensive { source } => Some(source),
             _ => None,
         }
     }
 
-    pub fn into_io(self) -> Option<StorageIOError> {
+    pub fn into_io(self) -> Option<StorageIOError<NID>> {
         match self {
             StorageError::IO { source } => Some(source),
             _ => None,
         }
     }
 
-    pub fn from_io_error(subject: ErrorSubject, verb: ErrorVerb, io_error: std::io::Error) -> Self {
+    pub fn from_io_error(subject: ErrorSubject<NID>, verb: ErrorVerb, io_error: std::io::Error) -> Self {
         let sto_io_err = StorageIOError::new(subject, verb, AnyError::new(&io_error));
         StorageError::IO { source: sto_io_err }
     }
@@ -193,21 +198,22 @@ impl StorageError {
 
 /// Error that occurs when operating the store.
 #[derive(Debug, Clone, thiserror::Error, PartialEq, Eq, Serialize, Deserialize)]
-pub struct StorageIOError {
-    subject: ErrorSubject,
+#[serde(bound = "")]
+pub struct StorageIOError<NID: NodeId = u64> {
+    subject: ErrorSubject<NID>,
     verb: ErrorVerb,
     source: AnyError,
     backtrace: String,
 }
 
-impl std::fmt::Display for StorageIOError {
+impl<NID: NodeId> std::fmt::Display for StorageIOError<NID> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "when {:?} {:?}: {}", self.verb, self.subject, self.source)
     }
 }
 
-impl StorageIOError {
-    pub fn new(subject: ErrorSubject, verb: ErrorVerb, source: AnyError) -> StorageIOError {
+impl<NID: NodeId> StorageIOError<NID> {
+    pub fn new(subject: ErrorSubject<NID>, verb: ErrorVerb, source: AnyError) -> Self {
         StorageIOError {
             subject,
             verb,
@@ -13,6 +13,8 @@ use crate::AppDataResponse;
 use crate::DefensiveCheck;
 use crate::EffectiveMembership;
 use crate::LogId;
+use crate::NodeId;
+use crate::NodeInfo;
 use crate::RaftStorage;
 use crate::RaftStorageDebug;
 use crate::SnapshotMeta;
@@ -42,22 +44,26 @@ impl<D, R, T> StoreExt<D, R, T> {
     }
 }
 
-impl<D, R, T> Wrapper<D, R, T> for StoreExt<D, R, T>
+impl<D, R, T, NID, NI> Wrapper<D, R, T, NID, NI> for StoreExt<D, R, T>
 where
     D: AppData,
     R: AppDataResponse,
-    T: RaftStorage<D, R>,
+    T: RaftStorage<D, R, NID, NI>,
+    NID: NodeId,
+    NI: NodeInfo,
 {
     fn inner(&self) -> &T {
         &self.inner
     }
 }
 
-impl<D, R, T> DefensiveCheck<D, R, T> for StoreExt<D, R, T>
+impl<D, R, T, NID, NI> DefensiveCheck<D, R, T, NID, NI> for StoreExt<D, R, T>
 where
     D: AppData,
     R: AppDataResponse,
-    T: RaftStorage<D, R>,
+    T: RaftStorage<D, R, NID, NI>,
+    NID: NodeId,
+    NI: NodeInfo,
 {
     fn set_defensive(&self, d: bool) {
         let mut defensive_flag = self.defensive.write().unwrap();
@@ -72,32 +78,32 @@ where
 #[async_trait]
 impl<D, R, T, SM> RaftStorageDebug<SM> for StoreExt<D, R, T>
 where
-    T: RaftStorage<D, R> + RaftStorageDebug<SM>,
+    T: RaftStorageDebug<SM> + Sync,
     D: AppData,
     R: AppDataResponse,
 {
     async fn get_state_machine(&self) -> SM {
-        self.inner().get_state_machine().await
+        self.inner.get_state_machine().await
     }
 }
 
 #[async_trait]
-impl<D, R, T> RaftStorage<D, R> for StoreExt<D, R, T>
+impl<D, R, T, NID: NodeId, NI: NodeInfo> RaftStorage<D, R, NID, NI> for StoreExt<D, R, T>
 where
-    T: RaftStorage<D, R>,
+    T: RaftStorage<D, R, NID, NI>,
     D: AppData,
     R: AppDataResponse,
 {
     type SnapshotData = T::SnapshotData;
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn save_vote(&self, vote: &Vote) -> Result<(), StorageError> {
+    async fn save_vote(&self, vote: &Vote<NID>) -> Result<(), StorageError<NID>> {
         self.defensive_incremental_vote(vote).await?;
         self.inner().save_vote(vote).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn read_vote(&self) -> Result<Option<Vote>, StorageError> {
+    async fn read_vote(&self) -> Result<Option<Vote<NID>>, StorageError<NID>> {
         self.inner().read_vote().await
     }
 
@@ -105,31 +111,33 @@ where
     async fn try_get_log_entries<RB: RangeBounds<u64> + Clone + Debug + Send + Sync>(
         &self,
         range: RB,
-    ) -> Result<Vec<Entry<D>>, StorageError> {
+    ) -> Result<Vec<Entry<D, NID, NI>>, StorageError<NID>> {
         self.defensive_nonempty_range(range.clone()).await?;
 
         self.inner().try_get_log_entries(range).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn last_applied_state(&self) -> Result<(Option<LogId>, Option<EffectiveMembership>), StorageError> {
+    async fn last_applied_state(
+        &self,
+    ) -> Result<(Option<LogId<NID>>, Option<EffectiveMembership<NID, NI>>), StorageError<NID>> {
         self.inner().last_applied_state().await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn delete_conflict_logs_since(&self, log_id: LogId) -> Result<(), StorageError> {
-        self.defensive_delete_conflict_gt_last_applied(log_id).await?;
+    async fn delete_conflict_logs_since(&self, log_id: LogId<NID>) -> Result<(), StorageError<NID>> {
+        self.defensive_delete_conflict_gt_last_applied(log_id.clone()).await?;
         self.inner().delete_conflict_logs_since(log_id).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn purge_logs_upto(&self, log_id: LogId) -> Result<(), StorageError> {
-        self.defensive_purge_applied_le_last_applied(log_id).await?;
+    async fn purge_logs_upto(&self, log_id: LogId<NID>) -> Result<(), StorageError<NID>> {
+        self.defensive_purge_applied_le_last_applied(log_id.clone()).await?;
         self.inner().purge_logs_upto(log_id).await
     }
 
     #[tracing::instrument(level = "trace", skip(self, entries), fields(entries=%entries.summary()))]
-    async fn append_to_log(&self, entries: &[&Entry<D>]) -> Result<(), StorageError> {
+    async fn append_to_log(&self, entries: &[&Entry<D, NID, NI>]) -> Result<(), StorageError<NID>> {
         self.defensive_nonempty_input(entries).await?;
         self.defensive_consecutive_input(entries).await?;
         self.defensive_append_log_index_is_last_plus_one(entries).await?;
@@ -139,7 +147,7 @@ where
     }
 
     #[tracing::instrument(level = "trace", skip(self, entries), fields(entries=%entries.summary()))]
-    async fn apply_to_state_machine(&self, entries: &[&Entry<D>]) -> Result<Vec<R>, StorageError> {
+    async fn apply_to_state_machine(&self, entries: &[&Entry<D, NID, NI>]) -> Result<Vec<R>, StorageError<NID>> {
         self.defensive_nonempty_input(entries).await?;
         self.defensive_apply_index_is_last_applied_plus_one(entries).await?;
         self.defensive_apply_log_id_gt_last(entries).await?;
@@ -147,31 +155,41 @@ where
         self.inner().apply_to_state_machine(entries).await
     }
 
+    #[tracing::instrument(level = "trace", skip(self, membership))]
+    async fn skip_apply_upto(
+        &self,
+        last_applied: &LogId<NID>,
+        membership: Option<&EffectiveMembership<NID, NI>>,
+    ) -> Result<(), StorageError<NID>> {
+        self.defensive_skip_apply_gt_last_applied(last_applied).await?;
+        self.inner().skip_apply_upto(last_applied, membership).await
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn build_snapshot(&self) -> Result<Snapshot<Self::SnapshotData>, StorageError> {
+    async fn build_snapshot(&self) -> Result<Snapshot<Self::SnapshotData, NID>, StorageError<NID>> {
         self.inner().build_snapshot().await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError> {
+    async fn begin_receiving_snapshot(&self) -> Result<Box<Self::SnapshotData>, StorageError<NID>> {
         self.inner().begin_receiving_snapshot().await
     }
 
     #[tracing::instrument(level = "trace", skip(self, snapshot))]
     async fn install_snapshot(
         &self,
-        meta: &SnapshotMeta,
+        meta: &SnapshotMeta<NID>,
         snapshot: Box<Self::SnapshotData>,
-    ) -> Result<StateMachineChanges, StorageError> {
+    ) -> Result<StateMachineChanges<NID>, StorageError<NID>> {
         self.inner().install_snapshot(meta, snapshot).await
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData>>, StorageError> {
+    async fn get_current_snapshot(&self) -> Result<Option<Snapshot<Self::SnapshotData, NID>>, StorageError<NID>> {
         self.inner().get_current_snapshot().await
     }
 
-    async fn get_log_state(&self) -> Result<LogState, StorageError> {
+    async fn get_log_state(&self) -> Result<LogState<NID>, StorageError<NID>> {
         self.defensive_no_dirty_log().await?;
         self.inner().get_log_state().await
     }
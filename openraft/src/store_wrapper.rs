@@ -1,13 +1,18 @@
 use crate::AppData;
 use crate::AppDataResponse;
+use crate::Node;
+use crate::NodeId;
+use crate::NodeInfo;
 use crate::RaftStorage;
 
 /// A wrapper extends the APIs of a base RaftStore.
-pub trait Wrapper<D, R, T>
+pub trait Wrapper<D, R, T, NID = u64, NI = Node>
 where
     D: AppData,
     R: AppDataResponse,
-    T: RaftStorage<D, R>,
+    T: RaftStorage<D, R, NID, NI>,
+    NID: NodeId,
+    NI: NodeInfo,
 {
     fn inner(&self) -> &T;
 }
@@ -82,6 +82,7 @@ where
         run_fut(Suite::get_log_id(builder))?;
         run_fut(Suite::last_id_in_log(builder))?;
         run_fut(Suite::last_applied_state(builder))?;
+        run_fut(Suite::skip_apply_upto(builder))?;
         run_fut(Suite::delete_logs(builder))?;
         run_fut(Suite::append_to_log(builder))?;
         // run_fut(Suite::apply_single(builder))?;
@@ -643,6 +644,44 @@ where
         Ok(())
     }
 
+    pub async fn skip_apply_upto(builder: &B) -> Result<(), StorageError> {
+        let store = builder.build().await;
+
+        store.apply_to_state_machine(&[&blank(0, 0), &blank(1, 1)]).await?;
+
+        tracing::info!("--- skip applying without membership");
+        {
+            store.skip_apply_upto(&LogId::new(LeaderId::new(1, NODE_ID), 5), None).await?;
+
+            let (applied, membership) = store.last_applied_state().await?;
+            assert_eq!(Some(LogId::new(LeaderId::new(1, NODE_ID), 5)), applied);
+            assert_eq!(None, membership);
+        }
+
+        tracing::info!("--- skip applying with membership");
+        {
+            let m = EffectiveMembership {
+                log_id: LogId::new(LeaderId::new(2, NODE_ID), 7),
+                membership: Membership::new_single(btreeset! {1,2}),
+            };
+            store.skip_apply_upto(&LogId::new(LeaderId::new(2, NODE_ID), 9), Some(&m)).await?;
+
+            let (applied, membership) = store.last_applied_state().await?;
+            assert_eq!(Some(LogId::new(LeaderId::new(2, NODE_ID), 9)), applied);
+            assert_eq!(Some(m), membership);
+        }
+
+        tracing::info!("--- logs are applied after the skipped ones");
+        {
+            store.apply_to_state_machine(&[&blank(2, 10)]).await?;
+
+            let (applied, _) = store.last_applied_state().await?;
+            assert_eq!(Some(LogId::new(LeaderId::new(2, NODE_ID), 10)), applied);
+        }
+
+        Ok(())
+    }
+
     pub async fn delete_logs(builder: &B) -> Result<(), StorageError> {
         tracing::info!("--- delete (-oo, 0]");
         {
@@ -930,6 +969,7 @@ where
         run_fut(Suite::df_apply_nonempty_input(builder))?;
         run_fut(Suite::df_apply_index_eq_last_applied_plus_one(builder))?;
         run_fut(Suite::df_apply_gt_last_applied_id(builder))?;
+        run_fut(Suite::df_skip_apply_gt_last_applied_id(builder))?;
         run_fut(Suite::df_purge_applied_le_last_applied(builder))?;
         run_fut(Suite::df_delete_conflict_gt_last_applied(builder))?;
 
@@ -1413,6 +1453,30 @@ where
         Ok(())
     }
 
+    pub async fn df_skip_apply_gt_last_applied_id(builder: &B) -> Result<(), StorageError> {
+        let store = builder.build().await;
+
+        store.apply_to_state_machine(&[&blank(0, 0), &blank(3, 1)]).await?;
+
+        tracing::info!("--- skip applying upto a log id not greater than last applied");
+        {
+            let res = store.skip_apply_upto(&LogId::new(LeaderId::new(2, NODE_ID), 2), None).await;
+            assert!(res.is_err());
+
+            let e = res.unwrap_err().into_defensive().unwrap();
+            assert_eq!(ErrorSubject::Apply(LogId::new(LeaderId::new(2, NODE_ID), 2)), e.subject);
+            assert_eq!(
+                Violation::ApplyNonConsecutive {
+                    prev: Some(LogId::new(LeaderId::new(3, NODE_ID), 1)),
+                    next: LogId::new(LeaderId::new(2, NODE_ID), 2),
+                },
+                e.violation
+            );
+        }
+
+        Ok(())
+    }
+
     pub async fn df_purge_applied_le_last_applied(builder: &B) -> Result<(), StorageError> {
         let store = builder.build().await;
 
@@ -14,19 +14,20 @@ use crate::NodeId;
 /// But under this(dirty and stupid) simplification, a `Leader` is actually identified by `(term, node_id)`.
 /// By introducing `LeaderId {term, node_id}`, things become easier to understand.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
-pub struct LeaderId {
+#[serde(bound = "")]
+pub struct LeaderId<NID: NodeId = u64> {
     pub term: u64,
-    pub node_id: NodeId,
+    pub node_id: NID,
 }
 
-impl std::fmt::Display for LeaderId {
+impl<NID: NodeId> std::fmt::Display for LeaderId<NID> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}-{}", self.term, self.node_id)
     }
 }
 
-impl LeaderId {
-    pub fn new(term: u64, node_id: NodeId) -> Self {
+impl<NID: NodeId> LeaderId<NID> {
+    pub fn new(term: u64, node_id: NID) -> Self {
         Self { term, node_id }
     }
 }
@@ -13,3 +13,17 @@ fn test_leader_id() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_leader_id_string_node_id() -> anyhow::Result<()> {
+    let la = LeaderId::new(1, "a".to_string());
+    let lb = LeaderId::new(1, "b".to_string());
+    let la2 = LeaderId::new(2, "a".to_string());
+
+    assert!(la < lb);
+    assert!(lb < la2);
+
+    assert_eq!("2-a", la2.to_string());
+
+    Ok(())
+}
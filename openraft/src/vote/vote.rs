@@ -8,27 +8,28 @@ use crate::NodeId;
 
 /// `Vote` represent the privilege of a node.
 #[derive(Debug, Clone, Copy, Default, PartialOrd, Ord, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Vote {
+#[serde(bound = "")]
+pub struct Vote<NID: NodeId = u64> {
     pub term: u64,
-    pub node_id: NodeId,
+    pub node_id: NID,
     pub committed: bool,
 }
 
-impl std::fmt::Display for Vote {
+impl<NID: NodeId> std::fmt::Display for Vote<NID> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "vote:{}-{}", self.term, self.node_id)
     }
 }
 
-impl Vote {
-    pub fn new(term: u64, node_id: u64) -> Self {
+impl<NID: NodeId> Vote<NID> {
+    pub fn new(term: u64, node_id: NID) -> Self {
         Self {
             term,
             node_id,
             committed: false,
         }
     }
-    pub fn new_committed(term: u64, node_id: u64) -> Self {
+    pub fn new_committed(term: u64, node_id: NID) -> Self {
         Self {
             term,
             node_id,
@@ -40,16 +41,16 @@ impl Vote {
         self.committed = true
     }
 
-    pub fn leader_id(&self) -> LeaderId {
-        LeaderId::new(self.term, self.node_id)
+    pub fn leader_id(&self) -> LeaderId<NID> {
+        LeaderId::new(self.term, self.node_id.clone())
     }
 
     /// Get the leader node id.
     ///
     /// Only when a committed vote is seen(granted by a quorum) the voted node id is a valid leader.
-    pub fn leader(&self) -> Option<NodeId> {
+    pub fn leader(&self) -> Option<NID> {
         if self.committed {
-            Some(self.node_id)
+            Some(self.node_id.clone())
         } else {
             None
         }
@@ -4,6 +4,7 @@ use std::time::Duration;
 use anyhow::Result;
 use fixtures::RaftRouter;
 use maplit::btreeset;
+use openraft::error::InstallSnapshotError;
 use openraft::raft::InstallSnapshotRequest;
 use openraft::Config;
 use openraft::LeaderId;
@@ -21,6 +22,7 @@ mod fixtures;
 ///
 /// - build a stable single node cluster.
 /// - send install_snapshot request with matched/mismatched id and offset
+/// - finish a snapshot with a mismatched checksum
 #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
 async fn snapshot_ge_half_threshold() -> Result<()> {
     let (_log_guard, ut_span) = init_ut!();
@@ -57,7 +59,10 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
         },
         offset: 0,
         data: vec![1, 2, 3],
+        compression: Default::default(),
         done: false,
+        checksum: None,
+        witness_membership: None,
     };
 
     tracing::info!("--- only allow to begin a new session when offset is 0");
@@ -102,12 +107,45 @@ async fn snapshot_ge_half_threshold() -> Result<()> {
         n.0.install_snapshot(req).await?;
     }
 
-    tracing::info!("-- continue write with mismatched offset is allowed");
+    tracing::info!("-- continue write with mismatched offset, reject with the offset to resume from");
     {
         let mut req = req0.clone();
         req.offset = 8;
         req.meta.snapshot_id = "ss2".into();
-        n.0.install_snapshot(req).await?;
+        let res = n.0.install_snapshot(req).await;
+        assert_eq!(
+            "snapshot segment id mismatch, expect: ss2+6, got: ss2+8",
+            res.unwrap_err().to_string()
+        );
+
+        let mut req = req0.clone();
+        req.offset = 3;
+        req.meta.snapshot_id = "ss2".into();
+        let res = n.0.install_snapshot(req).await;
+        assert_eq!(
+            "snapshot segment id mismatch, expect: ss2+6, got: ss2+3",
+            res.unwrap_err().to_string()
+        );
+    }
+
+    tracing::info!("-- finish with a mismatched checksum, the snapshot is dropped");
+    {
+        let mut req = req0.clone();
+        req.offset = 6;
+        req.meta.snapshot_id = "ss2".into();
+        req.done = true;
+        req.checksum = Some(0);
+        let res = n.0.install_snapshot(req).await;
+        assert!(matches!(res, Err(InstallSnapshotError::ChecksumMismatch(_))));
+
+        let mut req = req0.clone();
+        req.offset = 9;
+        req.meta.snapshot_id = "ss2".into();
+        let res = n.0.install_snapshot(req).await;
+        assert_eq!(
+            "snapshot segment id mismatch, expect: ss2+0, got: ss2+9",
+            res.unwrap_err().to_string()
+        );
     }
     Ok(())
 }
@@ -52,7 +52,7 @@ async fn conflict_with_empty_entries() -> Result<()> {
         leader_commit: Some(LogId::new(LeaderId::new(1, 0), 5)),
     };
 
-    let resp = router.send_append_entries(0, rpc).await?;
+    let resp = router.send_append_entries(0, None, rpc).await?;
     assert!(!resp.success);
     assert!(resp.conflict);
 
@@ -72,7 +72,7 @@ async fn conflict_with_empty_entries() -> Result<()> {
         leader_commit: Some(LogId::new(LeaderId::new(1, 0), 5)),
     };
 
-    let resp = router.send_append_entries(0, rpc).await?;
+    let resp = router.send_append_entries(0, None, rpc).await?;
     assert!(resp.success);
     assert!(!resp.conflict);
 
@@ -85,7 +85,7 @@ async fn conflict_with_empty_entries() -> Result<()> {
         leader_commit: Some(LogId::new(LeaderId::new(1, 0), 5)),
     };
 
-    let resp = router.send_append_entries(0, rpc).await?;
+    let resp = router.send_append_entries(0, None, rpc).await?;
     assert!(!resp.success);
     assert!(resp.conflict);
 
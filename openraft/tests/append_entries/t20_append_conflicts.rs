@@ -5,6 +5,7 @@ use anyhow::Result;
 use maplit::btreeset;
 use memstore::ClientRequest;
 use openraft::raft::AppendEntriesRequest;
+use openraft::raft::ConflictHint;
 use openraft::raft::Entry;
 use openraft::AppData;
 use openraft::AppDataResponse;
@@ -149,6 +150,10 @@ async fn append_conflicts() -> Result<()> {
     let resp = r0.append_entries(req).await?;
     assert!(!resp.success);
     assert!(resp.conflict);
+    assert_eq!(
+        Some(ConflictHint::LastLogId(Some(LogId::new(LeaderId::new(2, 0), 3)))),
+        resp.conflict_hint
+    );
 
     check_logs(&sto0, vec![0, 1, 1, 2]).await?;
 
@@ -164,6 +169,7 @@ async fn append_conflicts() -> Result<()> {
     let resp = r0.append_entries(req).await?;
     assert!(!resp.success);
     assert!(resp.conflict);
+    assert_eq!(Some(ConflictHint::ConflictTermStart(3)), resp.conflict_hint);
 
     check_logs(&sto0, vec![0, 1, 1]).await?;
 
@@ -210,6 +216,10 @@ async fn append_conflicts() -> Result<()> {
     let resp = r0.append_entries(req).await?;
     assert!(!resp.success);
     assert!(resp.conflict);
+    assert_eq!(
+        Some(ConflictHint::LastLogId(Some(LogId::new(LeaderId::new(3, 0), 4)))),
+        resp.conflict_hint
+    );
 
     Ok(())
 }
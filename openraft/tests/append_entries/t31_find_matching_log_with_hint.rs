@@ -0,0 +1,136 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::Config;
+use openraft::LeaderId;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::State;
+use openraft::Vote;
+
+use crate::fixtures::RaftRouter;
+
+/// With the conflict hint from a follower, the leader finds the matching log in one or two round trips, no matter how
+/// many inconsistent logs the follower has.
+///
+/// - fake a cluster of node 0,1,2. R0 has 3000 uncommitted logs at term 2. R2 has 3000 uncommitted logs at term 3.
+///
+/// ```
+/// R0 ... 2,2999 2,3000
+/// R1
+/// R2 ... 3,2999 3,3000
+/// ```
+///
+/// - Start the cluster and node 2 start to replicate logs.
+/// - test the logs are replicated to node 0, with at most 2 AppendEntries RPCs rejected by a conflict.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn find_matching_log_with_hint() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut n_logs = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- remove all nodes and fake the logs");
+
+    let (r0, sto0) = router.remove_node(0).await.unwrap();
+    let (r1, sto1) = router.remove_node(1).await.unwrap();
+    let (r2, sto2) = router.remove_node(2).await.unwrap();
+
+    r0.shutdown().await?;
+    r1.shutdown().await?;
+    r2.shutdown().await?;
+
+    let logs_of = |term: u64| {
+        (n_logs + 1..=3000)
+            .map(|i| Entry {
+                log_id: LogId::new(LeaderId::new(term, 0), i),
+                payload: EntryPayload::Blank,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    sto0.append_to_log(&logs_of(2).iter().collect::<Vec<_>>()).await?;
+    sto2.append_to_log(&logs_of(3).iter().collect::<Vec<_>>()).await?;
+
+    sto0.save_vote(&Vote {
+        term: 2,
+        node_id: 0,
+        committed: false,
+    })
+    .await?;
+    sto2.save_vote(&Vote {
+        term: 3,
+        node_id: 0,
+        committed: false,
+    })
+    .await?;
+
+    n_logs = 3000;
+
+    let conflicts_before = router.append_entries_conflicts(0);
+
+    tracing::info!("--- restart node 1 and isolate. To let node-2 to become leader, node-1 should not vote for node-0");
+    {
+        router.new_raft_node_with_sto(1, sto1.clone()).await;
+        router.isolate_node(1).await;
+    }
+
+    tracing::info!("--- restart node 0 and 2");
+    {
+        router.new_raft_node_with_sto(0, sto0.clone()).await;
+        router.new_raft_node_with_sto(2, sto2.clone()).await;
+    }
+
+    // leader appends a blank log.
+    n_logs += 1;
+
+    tracing::info!("--- wait for node states");
+    {
+        router
+            .wait_for_state(
+                &btreeset! {0},
+                State::Follower,
+                Some(Duration::from_millis(2000)),
+                "node 0 become follower",
+            )
+            .await?;
+
+        router
+            .wait_for_state(
+                &btreeset! {2},
+                State::Leader,
+                Some(Duration::from_millis(5000)),
+                "node 2 become leader",
+            )
+            .await?;
+    }
+
+    router
+        .wait(&0, Some(Duration::from_millis(5000)))
+        .await?
+        .metrics(|x| x.last_log_index == Some(n_logs), "sync log to node 0")
+        .await?;
+
+    let conflicts = router.append_entries_conflicts(0) - conflicts_before;
+    assert!(
+        conflicts <= 2,
+        "the matching log is found in one or two round trips, conflicts: {}",
+        conflicts
+    );
+
+    let logs = sto0.get_log_entries(1500..=1500).await?;
+    assert_eq!(
+        3,
+        logs.first().unwrap().log_id.leader_id.term,
+        "log is overridden by leader logs"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::Config;
+
+use crate::fixtures::RaftRouter;
+
+/// Replication splits logs into payloads no larger than `max_payload_bytes`, and sends an oversized log alone.
+///
+/// What does this test do?
+///
+/// - bring a cluster with 1 voter and 1 learner up, with a transport that rejects frames larger than 4 KiB.
+/// - isolate the learner, write logs of about 300 bytes and one log of about 2 KiB.
+/// - restore the learner, assert it receives all the logs.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn max_payload_bytes() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            max_payload_bytes: 1024,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::builder(config.clone()).max_frame_bytes(4096).build());
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    router.isolate_node(1).await;
+
+    write_logs(&router, 30, 300).await?;
+    write_logs(&router, 1, 2048).await?;
+    write_logs(&router, 30, 300).await?;
+    log_index += 61;
+
+    router.restore_node(1).await;
+
+    router.wait_for_log(&btreeset! {0,1}, Some(log_index), timeout(), "replicate all logs").await?;
+
+    Ok(())
+}
+
+/// Without a byte limit, replication shrinks the payload after a transport error.
+///
+/// What does this test do?
+///
+/// - bring a cluster with 1 voter and 1 learner up, with a transport that rejects frames larger than 4 KiB.
+/// - isolate the learner, write logs of about 300 bytes, which do not fit in one frame.
+/// - restore the learner, assert it receives all the logs.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn shrink_payload_after_transport_error() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::builder(config.clone()).max_frame_bytes(4096).build());
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    router.isolate_node(1).await;
+
+    write_logs(&router, 60, 300).await?;
+    log_index += 60;
+
+    router.restore_node(1).await;
+
+    router.wait_for_log(&btreeset! {0,1}, Some(log_index), timeout(), "replicate all logs").await?;
+
+    Ok(())
+}
+
+/// Write `n` logs to node-0, each with a status of `size` bytes.
+async fn write_logs(router: &RaftRouter, n: u64, size: usize) -> Result<()> {
+    for serial in 0..n {
+        let req = ClientRequest {
+            client: "foo".to_string(),
+            serial,
+            status: "x".repeat(size),
+        };
+        router.send_client_request(0, req).await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(3000))
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::Config;
+use openraft::State;
+
+use crate::fixtures::RaftRouter;
+
+/// Heartbeats are not blocked by a slow AppendEntries RPC in flight.
+///
+/// What does this test do?
+///
+/// - bring a cluster of 3 voters up, with a transport whose bandwidth is 4 MiB/s.
+/// - write a log of 3 MiB, which takes longer than an election timeout to send.
+/// - assert the followers receive the log without starting an election.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn heartbeat_during_slow_replication() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            append_entries_timeout: 5_000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::builder(config.clone()).bytes_per_sec(4 * 1024 * 1024).build());
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let term = router.get_metrics(&0).await?.current_term;
+
+    tracing::info!("--- write a log that takes about 750 ms to send");
+    {
+        let req = ClientRequest {
+            client: "foo".to_string(),
+            serial: 0,
+            status: "x".repeat(3 * 1024 * 1024),
+        };
+        router.send_client_request(0, req).await?;
+        log_index += 1;
+
+        router
+            .wait_for_log(
+                &btreeset! {0,1,2},
+                Some(log_index),
+                timeout(),
+                "replicate the large log",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- no election is started");
+    {
+        for id in [0, 1, 2] {
+            let m = router.get_metrics(&id).await?;
+            assert_eq!(term, m.current_term, "node {} term", id);
+            assert_eq!(Some(0), m.current_leader, "node {} leader", id);
+        }
+        assert_eq!(State::Leader, router.get_metrics(&0).await?.state);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -6,5 +6,9 @@ mod fixtures;
 // The later tests may depend on the earlier ones.
 
 mod t10_client_writes;
+mod t11_client_write_many;
 mod t20_client_reads;
+mod t21_lease_reads;
+mod t22_read_index;
+mod t23_concurrent_reads;
 mod t50_lagging_network_write;
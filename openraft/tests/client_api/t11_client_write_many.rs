@@ -0,0 +1,91 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::ClientWriteError;
+use openraft::Config;
+use openraft::LeaderId;
+use openraft::LogId;
+
+use crate::fixtures::RaftRouter;
+
+/// Batched client writes.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster with a learner.
+/// - write a batch of requests with `client_write_many` to the leader.
+/// - assert every request gets its own response, with consecutive log ids in the order of the requests.
+/// - assert an empty batch appends nothing.
+/// - assert `client_write_many` to a follower fails every request with `ForwardToLeader`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn client_write_many() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3}).await?;
+
+    tracing::info!("--- write a batch to the leader");
+    {
+        let n = 100;
+        let results = router.client_write_many(0, "client", n).await;
+        assert_eq!(n, results.len());
+
+        for (i, res) in results.into_iter().enumerate() {
+            let resp = res?;
+            assert_eq!(LogId::new(LeaderId::new(1, 0), log_index + 1 + i as u64), resp.log_id);
+
+            // MemStore responds with the status set by the previous request of the same client.
+            let prev_status = if i == 0 {
+                None
+            } else {
+                Some(format!("request-{}", i - 1))
+            };
+            assert_eq!(format!("ClientResponse({:?})", prev_status), format!("{:?}", resp.data));
+        }
+        log_index += n as u64;
+
+        router.wait_for_log(&btreeset! {0,1,2,3}, Some(log_index), timeout(), "write a batch").await?;
+    }
+
+    tracing::info!("--- write an empty batch, nothing is appended");
+    {
+        let results = router.client_write_many(0, "client", 0).await;
+        assert!(results.is_empty());
+
+        router.client_request(0, "client", 0).await;
+        log_index += 1;
+
+        router
+            .wait_for_log(
+                &btreeset! {0,1,2,3},
+                Some(log_index),
+                timeout(),
+                "write after an empty batch",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- write a batch to a follower");
+    {
+        let results = router.client_write_many(1, "client", 3).await;
+        assert_eq!(3, results.len());
+
+        for res in results {
+            match res {
+                Err(ClientWriteError::ForwardToLeader(e)) => assert_eq!(Some(0), e.leader_id),
+                _ => panic!("expect ForwardToLeader, got: {:?}", res),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1000))
+}
@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+
+use crate::fixtures::RaftRouter;
+
+/// Lease read tests.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster with lease read enabled.
+/// - isolate both followers, client_read on the leader still succeeds with the lease.
+/// - wait for the lease to expire, then client_read on the leader fails.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn lease_reads() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config {
+            enable_lease_read: true,
+            election_timeout_min: 1000,
+            election_timeout_max: 1500,
+            lease_read_timeout: 800,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let leader = router.leader().await.expect("leader not found");
+    assert_eq!(0, leader, "expected leader to be node 0, got {}", leader);
+
+    router.client_read(leader).await?;
+
+    tracing::info!("--- isolate node 1 and 2, client read is served with the lease");
+    {
+        router.isolate_node(1).await;
+        router.isolate_node(2).await;
+
+        router.client_read(leader).await?;
+    }
+
+    tracing::info!("--- client read fails after the lease expires");
+    {
+        tokio::time::sleep(Duration::from_millis(config.lease_read_timeout)).await;
+
+        let rst = router.client_read(leader).await;
+        tracing::debug!(?rst, "client_read with majority down and lease expired");
+
+        assert!(rst.is_err());
+    }
+
+    Ok(())
+}
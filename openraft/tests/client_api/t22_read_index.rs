@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::ClientReadError;
+use openraft::Config;
+use openraft::LeaderId;
+use openraft::LogId;
+
+use crate::fixtures::RaftRouter;
+
+/// ReadIndex tests.
+///
+/// What does this test do?
+///
+/// - create a stable 3-node cluster with a learner.
+/// - get a read index on the leader, the followers and the learner, assert it is the last committed log id.
+/// - isolate the learner, getting a read index on it fails.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn read_index() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3}).await?;
+
+    router.client_request_many(0, "client", 10).await;
+    log_index += 10;
+
+    router.wait_for_log(&btreeset! {0,1,2,3}, Some(log_index), None, "write 10 logs").await?;
+
+    tracing::info!("--- read index on every node is the last committed log id");
+    {
+        let want = Some(LogId::new(LeaderId::new(1, 0), log_index));
+
+        for id in [0, 1, 2, 3] {
+            let read_log_id = router.client_read_index(id).await?;
+            assert_eq!(want, read_log_id, "read index on node {}", id);
+
+            let metrics = router.get_metrics(&id).await?;
+            assert!(
+                metrics.last_applied >= read_log_id,
+                "node {} applied up to read index",
+                id
+            );
+        }
+    }
+
+    tracing::info!("--- isolate learner 3, it can not get a read index from the leader");
+    {
+        router.isolate_node(3).await;
+
+        let res = router.client_read_index(3).await;
+        tracing::debug!(?res, "client_read_index on isolated learner");
+
+        assert!(matches!(res, Err(ClientReadError::Network(_))));
+    }
+
+    Ok(())
+}
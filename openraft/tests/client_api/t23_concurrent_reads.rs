@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::stream::FuturesUnordered;
+use futures::stream::StreamExt;
+use maplit::btreeset;
+use openraft::Config;
+use tokio::time::Instant;
+
+use crate::fixtures::RaftRouter;
+
+/// Concurrent client reads do not block the leader.
+///
+/// What does this test do?
+///
+/// - Setup a network with <=200 ms random delay of messages.
+/// - bring a stable 3-node cluster online.
+/// - send many client reads to the leader concurrently, and a client write while they are waiting.
+/// - assert the reads are confirmed by a few heartbeat rounds instead of one round each, and the write is not queued
+///   behind them.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn concurrent_reads() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let delay = 200;
+    let n_reads = 50;
+
+    let config = Arc::new(
+        Config {
+            heartbeat_interval: 500,
+            election_timeout_min: 2000,
+            election_timeout_max: 3000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::builder(config).send_delay(delay).build());
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- send {} client reads and a client write concurrently", n_reads);
+    {
+        let now = Instant::now();
+
+        let mut reads = FuturesUnordered::new();
+        for _ in 0..n_reads {
+            let router = router.clone();
+            reads.push(tokio::spawn(async move { router.client_read(0).await }));
+        }
+
+        router.client_request(0, "client", 1).await;
+        log_index += 1;
+        let write_elapsed = now.elapsed();
+
+        while let Some(res) = reads.next().await {
+            res??;
+        }
+        let reads_elapsed = now.elapsed();
+
+        tracing::info!(?write_elapsed, ?reads_elapsed, "client reads and write finished");
+
+        // Confirming the reads one by one takes about `n_reads` rounds of network delay.
+        assert!(
+            reads_elapsed < Duration::from_millis(delay * 10),
+            "reads are confirmed in a few rounds, elapsed: {:?}",
+            reads_elapsed
+        );
+        assert!(
+            write_elapsed < Duration::from_millis(delay * 10),
+            "write is not blocked by reads, elapsed: {:?}",
+            write_elapsed
+        );
+    }
+
+    router.wait_for_log(&btreeset! {0,1,2}, Some(log_index), timeout(), "write 1 log").await?;
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
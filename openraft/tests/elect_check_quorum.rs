@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::State;
+
+#[macro_use]
+mod fixtures;
+
+/// With check-quorum enabled, a leader that loses contact with a quorum steps down.
+///
+/// - Bring up a cluster of {0,1,2}, the leader keeps its leadership as long as the followers respond.
+/// - Isolate the leader, it steps down and reports it in metrics, while the others elect a new leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_check_quorum() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config {
+            enable_check_quorum: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- leader stays leader while a quorum responds");
+    {
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 3)).await;
+
+        let metrics = router.get_metrics(&0).await?;
+        assert_eq!(State::Leader, metrics.state);
+        assert_eq!(0, metrics.quorum_lost);
+    }
+
+    tracing::info!("--- isolate the leader, it steps down");
+    {
+        router.isolate_node(0).await;
+
+        router
+            .wait(&0, timeout())
+            .await?
+            .metrics(
+                |x| x.quorum_lost >= 1 && x.state != State::Leader,
+                "node 0 steps down due to quorum lost",
+            )
+            .await?;
+
+        router
+            .wait_for_metrics(
+                &1,
+                |x| x.current_leader.is_some() && x.current_leader != Some(0),
+                timeout(),
+                "node 1 follows a new leader",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
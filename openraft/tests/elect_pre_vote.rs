@@ -0,0 +1,198 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::RaftStorage;
+use openraft::State;
+use openraft::Vote;
+
+#[macro_use]
+mod fixtures;
+
+/// With pre-vote enabled, an isolated node does not raise its term, and does not disrupt the leader when it rejoins.
+///
+/// - Bring up a cluster of {0,1,2} and isolate node 2.
+/// - Node 2 keeps trying to elect but its term stays the same.
+/// - Restore node 2, it follows the same leader in the same term and receives new logs.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_pre_vote_rejoin_after_isolation() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config {
+            enable_pre_vote: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let term = router.get_metrics(&0).await?.current_term;
+
+    tracing::info!("--- isolate node 2");
+    {
+        router.isolate_node(2).await;
+
+        router.wait_for_state(&btreeset! {2}, State::Candidate, timeout(), "node 2 starts to elect").await?;
+
+        // Let node 2 go through several election timeouts.
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_max * 5)).await;
+
+        let metrics = router.get_metrics(&2).await?;
+        assert_eq!(term, metrics.current_term, "pre-vote does not raise term of node 2");
+    }
+
+    tracing::info!("--- restore node 2");
+    {
+        router.restore_node(2).await;
+
+        router
+            .wait_for_state(&btreeset! {2}, State::Follower, timeout(), "node 2 rejoins as follower")
+            .await?;
+
+        router.client_request_many(0, "client", 10).await;
+        log_index += 10;
+
+        router
+            .wait_for_log(
+                &btreeset! {0,1,2},
+                Some(log_index),
+                timeout(),
+                "node 2 receives new logs",
+            )
+            .await?;
+
+        for id in [0, 1, 2] {
+            let metrics = router.get_metrics(&id).await?;
+            assert_eq!(term, metrics.current_term, "term of node {} is not changed", id);
+            assert_eq!(Some(0), metrics.current_leader, "node {} follows node 0", id);
+        }
+    }
+
+    Ok(())
+}
+
+/// With pre-vote enabled, a new leader is still elected when the leader is gone.
+///
+/// - Bring up a cluster of {0,1,2} and isolate the leader node 0.
+/// - One of node 1 and 2 passes the pre-vote and becomes the leader in a greater term.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_pre_vote_leader_lost() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config {
+            enable_pre_vote: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    let term = router.get_metrics(&0).await?.current_term;
+
+    tracing::info!("--- isolate leader node 0");
+    {
+        router.isolate_node(0).await;
+
+        router
+            .wait_for_metrics(
+                &1,
+                |x| x.current_leader.is_some() && x.current_leader != Some(0),
+                timeout(),
+                "a new leader is elected",
+            )
+            .await?;
+
+        let leader = router.leader().await.expect("a new leader");
+        assert_ne!(0, leader);
+
+        let metrics = router.get_metrics(&leader).await?;
+        assert!(metrics.current_term > term, "new leader has a greater term");
+    }
+
+    Ok(())
+}
+
+/// A greater vote in a pre-vote response makes the candidate take it and revert to follower, as in a real election.
+///
+/// - Bring up a cluster of {0,1,2}, shut down node 1, and replicate more logs to node 2.
+/// - Shut down all nodes, and give node 1 a greater term, without a leader.
+/// - Restart node 1 and 2, keep node 0 isolated. Node 1 can not win with its stale logs.
+/// - Node 2 pre-votes, learns the greater term from node 1, then wins an election in a term greater than it.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn elect_pre_vote_greater_vote() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(
+        Config {
+            enable_pre_vote: true,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- shut down node 1, node 2 receives more logs than node 1");
+    let (r1, sto1) = router.remove_node(1).await.unwrap();
+    r1.shutdown().await?;
+    {
+        router.client_request_many(0, "client", 10).await;
+        log_index += 10;
+
+        router.wait_for_log(&btreeset! {0,2}, Some(log_index), timeout(), "replicate logs to 0,2").await?;
+    }
+
+    tracing::info!("--- shut down node 0 and 2, node 1 has a greater term");
+    let (r0, sto0) = router.remove_node(0).await.unwrap();
+    let (r2, sto2) = router.remove_node(2).await.unwrap();
+    r0.shutdown().await?;
+    r2.shutdown().await?;
+
+    let greater_term = 5;
+    sto1.save_vote(&Vote {
+        term: greater_term,
+        node_id: 1,
+        committed: false,
+    })
+    .await?;
+
+    tracing::info!("--- restart node 1 and 2, with node 0 isolated");
+    {
+        router.isolate_node(0).await;
+        router.new_raft_node_with_sto(0, sto0.clone()).await;
+        router.new_raft_node_with_sto(1, sto1.clone()).await;
+        router.new_raft_node_with_sto(2, sto2.clone()).await;
+
+        router.wait_for_state(&btreeset! {2}, State::Leader, timeout(), "node 2 becomes leader").await?;
+
+        let metrics = router.get_metrics(&2).await?;
+        assert!(
+            metrics.current_term > greater_term,
+            "node 2 elects in a term greater than {}, got: {}",
+            greater_term,
+            metrics.current_term
+        );
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
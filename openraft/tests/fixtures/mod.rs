@@ -29,8 +29,13 @@ use openraft::error::ClientReadError;
 use openraft::error::ClientWriteError;
 use openraft::error::InstallSnapshotError;
 use openraft::error::NetworkError;
+use openraft::error::PromoteLearnerError;
+use openraft::error::PurgeLogError;
 use openraft::error::RPCError;
 use openraft::error::RemoteError;
+use openraft::error::TimeoutNowError;
+use openraft::error::TransferLeaderError;
+use openraft::error::TriggerSnapshotError;
 use openraft::error::VoteError;
 use openraft::metrics::Wait;
 use openraft::raft::AddLearnerResponse;
@@ -42,19 +47,25 @@ use openraft::raft::Entry;
 use openraft::raft::EntryPayload;
 use openraft::raft::InstallSnapshotRequest;
 use openraft::raft::InstallSnapshotResponse;
+use openraft::raft::ReadIndexRequest;
+use openraft::raft::ReadIndexResponse;
+use openraft::raft::TimeoutNowRequest;
+use openraft::raft::TimeoutNowResponse;
 use openraft::raft::VoteRequest;
 use openraft::raft::VoteResponse;
 use openraft::storage::RaftStorage;
 use openraft::AppData;
+use openraft::ChangeMembers;
 use openraft::Config;
 use openraft::DefensiveCheck;
 use openraft::LeaderId;
 use openraft::LogId;
 use openraft::LogIdOptionExt;
-use openraft::NodeId;
+use openraft::Node;
 use openraft::Raft;
 use openraft::RaftMetrics;
 use openraft::RaftNetwork;
+use openraft::SnapshotMeta;
 use openraft::State;
 use openraft::StoreExt;
 #[allow(unused_imports)]
@@ -124,18 +135,50 @@ pub struct RaftRouter {
     /// The Raft runtime config which all nodes are using.
     config: Arc<Config>,
     /// The table of all nodes currently known to this router instance.
-    routing_table: RwLock<BTreeMap<NodeId, (MemRaft, Arc<StoreWithDefensive>)>>,
+    routing_table: RwLock<BTreeMap<u64, (MemRaft, Arc<StoreWithDefensive>)>>,
     /// Nodes which are isolated can neither send nor receive frames.
-    isolated_nodes: RwLock<HashSet<NodeId>>,
+    isolated_nodes: RwLock<HashSet<u64>>,
 
     /// To enumlate network delay for sending, in milli second.
     /// 0 means no delay.
     send_delay: u64,
+
+    /// To emulate the frame size limit of a transport, an AppendEntries RPC larger than this fails with a network
+    /// error. 0 means no limit.
+    max_frame_bytes: u64,
+
+    /// To emulate the bandwidth of a transport, an RPC takes `size / bytes_per_sec` seconds to deliver.
+    /// 0 means no limit.
+    bytes_per_sec: u64,
+
+    /// The number of AppendEntries RPCs with entries in flight to every target, and the max of it ever seen.
+    append_entries_in_flight: Mutex<BTreeMap<u64, (u64, u64)>>,
+
+    /// The number of AppendEntries RPCs to every target that are rejected because of a conflicting log.
+    append_entries_conflicts: Mutex<BTreeMap<u64, u64>>,
+
+    /// The number of InstallSnapshot RPCs sent to every target.
+    install_snapshot_count: Mutex<BTreeMap<u64, u64>>,
+
+    /// The offsets of the InstallSnapshot RPCs sent to every target, in order.
+    install_snapshot_offsets: Mutex<BTreeMap<u64, Vec<u64>>>,
+
+    /// The offset of the next InstallSnapshot RPC whose response is lost: the target receives the chunk, but the
+    /// sender gets a network error.
+    install_snapshot_reply_to_drop: Mutex<Option<u64>>,
+
+    /// The number of TimeoutNow RPCs sent to every target.
+    timeout_now_count: Mutex<BTreeMap<u64, u64>>,
+
+    /// The number of the next TimeoutNow RPCs to fail with a network error.
+    timeout_now_to_drop: Mutex<u64>,
 }
 
 pub struct Builder {
     config: Arc<Config>,
     send_delay: u64,
+    max_frame_bytes: u64,
+    bytes_per_sec: u64,
 }
 
 impl Builder {
@@ -144,19 +187,69 @@ impl Builder {
         self
     }
 
+    pub fn max_frame_bytes(mut self, bytes: u64) -> Self {
+        self.max_frame_bytes = bytes;
+        self
+    }
+
+    pub fn bytes_per_sec(mut self, bytes: u64) -> Self {
+        self.bytes_per_sec = bytes;
+        self
+    }
+
     pub fn build(self) -> RaftRouter {
         RaftRouter {
             config: self.config,
             routing_table: Default::default(),
             isolated_nodes: Default::default(),
             send_delay: self.send_delay,
+            max_frame_bytes: self.max_frame_bytes,
+            bytes_per_sec: self.bytes_per_sec,
+            append_entries_in_flight: Default::default(),
+            append_entries_conflicts: Default::default(),
+            install_snapshot_count: Default::default(),
+            install_snapshot_offsets: Default::default(),
+            install_snapshot_reply_to_drop: Default::default(),
+            timeout_now_count: Default::default(),
+            timeout_now_to_drop: Default::default(),
+        }
+    }
+}
+
+/// Counts an AppendEntries RPC in flight until it is dropped.
+struct InFlightGuard<'a> {
+    router: &'a RaftRouter,
+    target: u64,
+}
+
+impl<'a> InFlightGuard<'a> {
+    fn new(router: &'a RaftRouter, target: u64) -> Self {
+        let mut in_flight = router.append_entries_in_flight.lock().unwrap();
+        let (cur, max) = in_flight.entry(target).or_default();
+        *cur += 1;
+        *max = std::cmp::max(*max, *cur);
+
+        Self { router, target }
+    }
+}
+
+impl<'a> Drop for InFlightGuard<'a> {
+    fn drop(&mut self) {
+        let mut in_flight = self.router.append_entries_in_flight.lock().unwrap();
+        if let Some((cur, _)) = in_flight.get_mut(&self.target) {
+            *cur -= 1;
         }
     }
 }
 
 impl RaftRouter {
     pub fn builder(config: Arc<Config>) -> Builder {
-        Builder { config, send_delay: 0 }
+        Builder {
+            config,
+            send_delay: 0,
+            max_frame_bytes: 0,
+            bytes_per_sec: 0,
+        }
     }
 
     /// Create a new instance.
@@ -168,6 +261,56 @@ impl RaftRouter {
         self.send_delay = ms;
     }
 
+    /// The max number of AppendEntries RPCs with entries ever in flight to a target at the same time.
+    pub fn max_append_entries_in_flight(&self, target: u64) -> u64 {
+        let in_flight = self.append_entries_in_flight.lock().unwrap();
+        in_flight.get(&target).map(|(_, max)| *max).unwrap_or_default()
+    }
+
+    /// The number of AppendEntries RPCs to a target that are rejected because of a conflicting log.
+    pub fn append_entries_conflicts(&self, target: u64) -> u64 {
+        let conflicts = self.append_entries_conflicts.lock().unwrap();
+        conflicts.get(&target).cloned().unwrap_or_default()
+    }
+
+    /// The number of InstallSnapshot RPCs sent to a target.
+    pub fn install_snapshot_count(&self, target: u64) -> u64 {
+        let count = self.install_snapshot_count.lock().unwrap();
+        count.get(&target).cloned().unwrap_or_default()
+    }
+
+    /// The offsets of the InstallSnapshot RPCs sent to a target, in order.
+    pub fn install_snapshot_offsets(&self, target: u64) -> Vec<u64> {
+        let offsets = self.install_snapshot_offsets.lock().unwrap();
+        offsets.get(&target).cloned().unwrap_or_default()
+    }
+
+    /// Lose the response of the next InstallSnapshot RPC at `offset`, after the target receives the chunk.
+    pub fn drop_install_snapshot_reply(&self, offset: u64) {
+        *self.install_snapshot_reply_to_drop.lock().unwrap() = Some(offset);
+    }
+
+    /// The number of TimeoutNow RPCs sent to a target.
+    pub fn timeout_now_count(&self, target: u64) -> u64 {
+        let count = self.timeout_now_count.lock().unwrap();
+        count.get(&target).cloned().unwrap_or_default()
+    }
+
+    /// Make the next `n` TimeoutNow RPCs fail with a network error.
+    pub fn drop_timeout_now(&self, n: u64) {
+        *self.timeout_now_to_drop.lock().unwrap() = n;
+    }
+
+    /// Wait for the time it takes to transfer `size` bytes, if the bandwidth is limited.
+    async fn transfer_delay(&self, size: u64) {
+        if self.bytes_per_sec == 0 {
+            return;
+        }
+
+        let timeout = Duration::from_secs_f64(size as f64 / self.bytes_per_sec as f64);
+        tokio::time::sleep(timeout).await;
+    }
+
     async fn rand_send_delay(&self) {
         if self.send_delay == 0 {
             return;
@@ -183,8 +326,8 @@ impl RaftRouter {
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn new_nodes_from_single(
         self: &Arc<Self>,
-        node_ids: BTreeSet<NodeId>,
-        learners: BTreeSet<NodeId>,
+        node_ids: BTreeSet<u64>,
+        learners: BTreeSet<u64>,
     ) -> anyhow::Result<u64> {
         assert!(node_ids.contains(&0));
 
@@ -256,7 +399,7 @@ impl RaftRouter {
     }
 
     /// Create and register a new Raft node bearing the given ID.
-    pub async fn new_raft_node(self: &Arc<Self>, id: NodeId) {
+    pub async fn new_raft_node(self: &Arc<Self>, id: u64) {
         let memstore = self.new_store().await;
         self.new_raft_node_with_sto(id, memstore).await
     }
@@ -288,14 +431,14 @@ impl RaftRouter {
     }
 
     #[tracing::instrument(level = "debug", skip(self, sto))]
-    pub async fn new_raft_node_with_sto(self: &Arc<Self>, id: NodeId, sto: Arc<StoreWithDefensive>) {
+    pub async fn new_raft_node_with_sto(self: &Arc<Self>, id: u64, sto: Arc<StoreWithDefensive>) {
         let node = Raft::new(id, self.config.clone(), self.clone(), sto.clone());
         let mut rt = self.routing_table.write().await;
         rt.insert(id, (node, sto));
     }
 
     /// Remove the target node from the routing table & isolation.
-    pub async fn remove_node(&self, id: NodeId) -> Option<(MemRaft, Arc<StoreWithDefensive>)> {
+    pub async fn remove_node(&self, id: u64) -> Option<(MemRaft, Arc<StoreWithDefensive>)> {
         let mut rt = self.routing_table.write().await;
         let opt_handles = rt.remove(&id);
         let mut isolated = self.isolated_nodes.write().await;
@@ -305,10 +448,10 @@ impl RaftRouter {
     }
 
     /// Initialize all nodes based on the config in the routing table.
-    pub async fn initialize_from_single_node(&self, node: NodeId) -> Result<()> {
+    pub async fn initialize_from_single_node(&self, node: u64) -> Result<()> {
         tracing::info!({ node }, "initializing cluster from single node");
         let rt = self.routing_table.read().await;
-        let members: BTreeSet<NodeId> = rt.keys().cloned().collect();
+        let members: BTreeSet<u64> = rt.keys().cloned().collect();
         rt.get(&node)
             .ok_or_else(|| anyhow!("node {} not found in routing table", node))?
             .0
@@ -318,7 +461,7 @@ impl RaftRouter {
     }
 
     /// Initialize cluster with specified node ids.
-    pub async fn initialize_with(&self, node: NodeId, members: BTreeSet<NodeId>) -> Result<()> {
+    pub async fn initialize_with(&self, node: u64, members: BTreeSet<u64>) -> Result<()> {
         tracing::info!({ node }, "initializing cluster from single node");
         let rt = self.routing_table.read().await;
         rt.get(&node)
@@ -331,7 +474,7 @@ impl RaftRouter {
 
     /// Isolate the network of the specified node.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn isolate_node(&self, id: NodeId) {
+    pub async fn isolate_node(&self, id: u64) {
         self.isolated_nodes.write().await.insert(id);
     }
 
@@ -345,7 +488,7 @@ impl RaftRouter {
         metrics
     }
 
-    pub async fn get_metrics(&self, node_id: &NodeId) -> Result<RaftMetrics> {
+    pub async fn get_metrics(&self, node_id: &u64) -> Result<RaftMetrics> {
         let rt = self.routing_table.read().await;
         let x = rt.get(node_id).with_context(|| format!("could not find node {} in routing table", node_id))?;
         let metrics = x.0.metrics().borrow().clone();
@@ -353,7 +496,7 @@ impl RaftRouter {
     }
 
     /// Get a handle to the storage backend for the target node.
-    pub async fn get_storage_handle(&self, node_id: &NodeId) -> Result<Arc<StoreWithDefensive>> {
+    pub async fn get_storage_handle(&self, node_id: &u64) -> Result<Arc<StoreWithDefensive>> {
         let rt = self.routing_table.read().await;
         let addr = rt.get(node_id).with_context(|| format!("could not find node {} in routing table", node_id))?;
         let sto = addr.clone().1;
@@ -364,7 +507,7 @@ impl RaftRouter {
     #[tracing::instrument(level = "info", skip(self, func))]
     pub async fn wait_for_metrics<T>(
         &self,
-        node_id: &NodeId,
+        node_id: &u64,
         func: T,
         timeout: Option<Duration>,
         msg: &str,
@@ -377,7 +520,7 @@ impl RaftRouter {
         Ok(rst)
     }
 
-    pub async fn wait(&self, node_id: &NodeId, timeout: Option<Duration>) -> Result<Wait> {
+    pub async fn wait(&self, node_id: &u64, timeout: Option<Duration>) -> Result<Wait> {
         let rt = self.routing_table.read().await;
         let node = rt.get(node_id).with_context(|| format!("node {} not found", node_id))?;
 
@@ -452,7 +595,7 @@ impl RaftRouter {
     }
 
     /// Get the ID of the current leader.
-    pub async fn leader(&self) -> Option<NodeId> {
+    pub async fn leader(&self) -> Option<u64> {
         let isolated = self.isolated_nodes.read().await;
         self.latest_metrics().await.into_iter().find_map(|node| {
             if node.current_leader == Some(node.id) {
@@ -469,42 +612,92 @@ impl RaftRouter {
 
     /// Restore the network of the specified node.
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn restore_node(&self, id: NodeId) {
+    pub async fn restore_node(&self, id: u64) {
         let mut nodes = self.isolated_nodes.write().await;
         nodes.remove(&id);
     }
 
-    pub async fn add_learner(&self, leader: NodeId, target: NodeId) -> Result<AddLearnerResponse, AddLearnerError> {
+    pub async fn add_learner(&self, leader: u64, target: u64) -> Result<AddLearnerResponse, AddLearnerError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
-        node.0.add_learner(target, true).await
+        let res = node.0.add_learner(target, None, true).await?;
+        drop(rt);
+
+        self.wait_for_learner_applied(leader, target).await;
+        Ok(res)
+    }
+
+    /// Add a learner along with its node info, which is stored in the membership config.
+    pub async fn add_learner_with_node(
+        &self,
+        leader: u64,
+        target: u64,
+        node: Node,
+    ) -> Result<AddLearnerResponse, AddLearnerError> {
+        let rt = self.routing_table.read().await;
+        let n = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        let res = n.0.add_learner(target, Some(node), true).await?;
+        drop(rt);
+
+        self.wait_for_learner_applied(leader, target).await;
+        Ok(res)
     }
 
     pub async fn add_learner_with_blocking(
         &self,
-        leader: NodeId,
-        target: NodeId,
+        leader: u64,
+        target: u64,
         blocking: bool,
     ) -> Result<AddLearnerResponse, AddLearnerError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
-        node.0.add_learner(target, blocking).await
+        let res = node.0.add_learner(target, None, blocking).await?;
+        drop(rt);
+
+        if blocking {
+            self.wait_for_learner_applied(leader, target).await;
+        }
+        Ok(res)
+    }
+
+    /// Wait for a learner that caught up with the leader to apply the logs the leader has applied.
+    ///
+    /// Logs are applied asynchronously, thus a learner may still be applying logs when `add_learner` returns.
+    async fn wait_for_learner_applied(&self, leader: u64, target: u64) {
+        let rt = self.routing_table.read().await;
+        let (leader_node, target_node) = match (rt.get(&leader), rt.get(&target)) {
+            (Some(l), Some(t)) => (l.0.clone(), t.0.clone()),
+            // Adding a learner that is not in the router
+            _ => return,
+        };
+        drop(rt);
+
+        let leader_applied = leader_node.metrics().borrow().last_applied.index();
+
+        target_node
+            .wait(Some(Duration::from_millis(1_000)))
+            .metrics(
+                |x| x.last_applied.index() >= leader_applied,
+                format!("learner {} applied leader logs", target),
+            )
+            .await
+            .unwrap_or_else(|e| panic!("learner {} did not apply logs: {}", target, e));
     }
 
     pub async fn change_membership(
         &self,
-        leader: NodeId,
-        members: BTreeSet<NodeId>,
+        leader: u64,
+        changes: impl Into<ChangeMembers>,
     ) -> Result<ClientWriteResponse<MemClientResponse>, ClientWriteError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
-        node.0.change_membership(members, true, false).await
+        node.0.change_membership(changes, true, false).await
     }
 
     pub async fn change_membership_with_turn_to_learner(
         &self,
-        leader: NodeId,
-        members: BTreeSet<NodeId>,
+        leader: u64,
+        members: BTreeSet<u64>,
         turn_to_learner: bool,
     ) -> Result<ClientWriteResponse<MemClientResponse>, ClientWriteError> {
         let rt = self.routing_table.read().await;
@@ -514,8 +707,8 @@ impl RaftRouter {
 
     pub async fn change_membership_with_blocking(
         &self,
-        leader: NodeId,
-        members: BTreeSet<NodeId>,
+        leader: u64,
+        members: BTreeSet<u64>,
         blocking: bool,
     ) -> Result<ClientWriteResponse<MemClientResponse>, ClientWriteError> {
         let rt = self.routing_table.read().await;
@@ -523,15 +716,54 @@ impl RaftRouter {
         node.0.change_membership(members, blocking, false).await
     }
 
+    pub async fn promote_learner_when_ready(&self, leader: u64, target: u64) -> Result<(), PromoteLearnerError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        node.0.promote_learner_when_ready(target).await
+    }
+
+    pub async fn cancel_learner_promotion(&self, leader: u64, target: u64) -> Result<(), PromoteLearnerError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        node.0.cancel_learner_promotion(target).await
+    }
+
+    pub async fn transfer_leader(&self, leader: u64, target: u64) -> Result<(), TransferLeaderError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&leader).unwrap_or_else(|| panic!("node with ID {} does not exist", leader));
+        node.0.transfer_leader(target).await
+    }
+
+    /// Get a read index on the target node, after it has applied logs up to it.
+    pub async fn client_read_index(&self, target: u64) -> Result<Option<LogId>, ClientReadError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&target).unwrap_or_else(|| panic!("node with ID {} does not exist", target));
+        node.0.client_read_index().await
+    }
+
     /// Send a client read request to the target node.
-    pub async fn client_read(&self, target: NodeId) -> Result<(), ClientReadError> {
+    pub async fn client_read(&self, target: u64) -> Result<(), ClientReadError> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&target).unwrap_or_else(|| panic!("node with ID {} does not exist", target));
         node.0.client_read().await
     }
 
+    /// Build a snapshot on the target node at once.
+    pub async fn trigger_snapshot(&self, target: u64) -> Result<SnapshotMeta, TriggerSnapshotError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&target).unwrap_or_else(|| panic!("node with ID {} does not exist", target));
+        node.0.trigger_snapshot().await
+    }
+
+    /// Purge applied logs upto `upto`, inclusive, on the target node.
+    pub async fn purge_log(&self, target: u64, upto: u64) -> Result<(), PurgeLogError> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&target).unwrap_or_else(|| panic!("node with ID {} does not exist", target));
+        node.0.purge_log(upto).await
+    }
+
     /// Send a client request to the target node, causing test failure on error.
-    pub async fn client_request(&self, target: NodeId, client_id: &str, serial: u64) {
+    pub async fn client_request(&self, target: u64, client_id: &str, serial: u64) {
         let req = MemClientRequest {
             client: client_id.into(),
             serial,
@@ -544,22 +776,47 @@ impl RaftRouter {
     }
 
     /// Request the current leader from the target node.
-    pub async fn current_leader(&self, target: NodeId) -> Option<NodeId> {
+    pub async fn current_leader(&self, target: u64) -> Option<u64> {
         let rt = self.routing_table.read().await;
         let node = rt.get(&target).unwrap_or_else(|| panic!("node with ID {} does not exist", target));
         node.0.current_leader().await
     }
 
     /// Send multiple client requests to the target node, causing test failure on error.
-    pub async fn client_request_many(&self, target: NodeId, client_id: &str, count: usize) {
+    pub async fn client_request_many(&self, target: u64, client_id: &str, count: usize) {
         for idx in 0..count {
             self.client_request(target, client_id, idx as u64).await
         }
     }
 
-    async fn send_client_request(
+    /// Send multiple client requests to the target node at once with `Raft::client_write_many`.
+    pub async fn client_write_many(
         &self,
-        target: NodeId,
+        target: u64,
+        client_id: &str,
+        count: usize,
+    ) -> Vec<std::result::Result<ClientWriteResponse<MemClientResponse>, ClientWriteError>> {
+        let rt = self.routing_table.read().await;
+        let node = rt.get(&target).unwrap_or_else(|| panic!("node '{}' does not exist in routing table", target));
+
+        let rpcs = (0..count)
+            .map(|idx| {
+                let req = MemClientRequest {
+                    client: client_id.into(),
+                    serial: idx as u64,
+                    status: format!("request-{}", idx),
+                };
+                ClientWriteRequest::new(EntryPayload::Normal(req))
+            })
+            .collect::<Vec<_>>();
+
+        node.0.client_write_many(rpcs).await
+    }
+
+    /// Send a client request to the target node.
+    pub async fn send_client_request(
+        &self,
+        target: u64,
         req: MemClientRequest,
     ) -> std::result::Result<MemClientResponse, ClientWriteError> {
         let rt = self.routing_table.read().await;
@@ -845,7 +1102,7 @@ impl RaftRouter {
         Ok(())
     }
 
-    pub async fn check_reachable(&self, id: NodeId, target: NodeId) -> std::result::Result<(), NetworkError> {
+    pub async fn check_reachable(&self, id: u64, target: u64) -> std::result::Result<(), NetworkError> {
         let isolated = self.isolated_nodes.read().await;
 
         if isolated.contains(&target) || isolated.contains(&id) {
@@ -863,13 +1120,31 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
     async fn send_append_entries(
         &self,
         target: u64,
+        _target_node: Option<&Node>,
         rpc: AppendEntriesRequest<MemClientRequest>,
     ) -> std::result::Result<AppendEntriesResponse, RPCError<AppendEntriesError>> {
         tracing::debug!("append_entries to id={} {:?}", target, rpc);
+
+        // Heartbeats are not counted.
+        let _in_flight = if rpc.entries.is_empty() {
+            None
+        } else {
+            Some(InFlightGuard::new(self, target))
+        };
+
         self.rand_send_delay().await;
 
         self.check_reachable(rpc.vote.node_id, target).await?;
 
+        let size = bincode::serialized_size(&rpc).unwrap();
+
+        if self.max_frame_bytes > 0 && size > self.max_frame_bytes {
+            let msg = format!("frame of {} bytes exceeds the limit {}", size, self.max_frame_bytes);
+            return Err(NetworkError::new(&AnyError::error(msg)).into());
+        }
+
+        self.transfer_delay(size).await;
+
         let rt = self.routing_table.read().await;
         let addr = rt.get(&target).expect("target node not found in routing table");
 
@@ -877,6 +1152,10 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
 
         tracing::debug!("append_entries: recv resp from id={} {:?}", target, resp);
         let resp = resp.map_err(|e| RemoteError::new(target, e))?;
+
+        if resp.conflict {
+            *self.append_entries_conflicts.lock().unwrap().entry(target).or_default() += 1;
+        }
         Ok(resp)
     }
 
@@ -884,22 +1163,43 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
     async fn send_install_snapshot(
         &self,
         target: u64,
+        _target_node: Option<&Node>,
         rpc: InstallSnapshotRequest,
     ) -> std::result::Result<InstallSnapshotResponse, RPCError<InstallSnapshotError>> {
+        *self.install_snapshot_count.lock().unwrap().entry(target).or_default() += 1;
+        self.install_snapshot_offsets.lock().unwrap().entry(target).or_default().push(rpc.offset);
+
         self.rand_send_delay().await;
 
         self.check_reachable(rpc.vote.node_id, target).await?;
 
+        self.transfer_delay(rpc.data.len() as u64).await;
+
         let rt = self.routing_table.read().await;
         let addr = rt.get(&target).expect("target node not found in routing table");
 
+        let offset = rpc.offset;
         let resp = addr.0.install_snapshot(rpc).await;
+
+        {
+            let mut to_drop = self.install_snapshot_reply_to_drop.lock().unwrap();
+            if *to_drop == Some(offset) {
+                *to_drop = None;
+                return Err(NetworkError::new(&AnyError::error("InstallSnapshot reply is dropped")).into());
+            }
+        }
+
         let resp = resp.map_err(|e| RemoteError::new(target, e))?;
         Ok(resp)
     }
 
     /// Send a RequestVote RPC to the target Raft node (§5).
-    async fn send_vote(&self, target: u64, rpc: VoteRequest) -> std::result::Result<VoteResponse, RPCError<VoteError>> {
+    async fn send_vote(
+        &self,
+        target: u64,
+        _target_node: Option<&Node>,
+        rpc: VoteRequest,
+    ) -> std::result::Result<VoteResponse, RPCError<VoteError>> {
         self.rand_send_delay().await;
 
         self.check_reachable(rpc.vote.node_id, target).await?;
@@ -911,6 +1211,54 @@ impl RaftNetwork<MemClientRequest> for RaftRouter {
         let resp = resp.map_err(|e| RemoteError::new(target, e))?;
         Ok(resp)
     }
+
+    /// Send a TimeoutNow RPC to the target Raft node (§3.10 of the thesis).
+    async fn send_timeout_now(
+        &self,
+        target: u64,
+        _target_node: Option<&Node>,
+        rpc: TimeoutNowRequest,
+    ) -> std::result::Result<TimeoutNowResponse, RPCError<TimeoutNowError>> {
+        *self.timeout_now_count.lock().unwrap().entry(target).or_default() += 1;
+
+        self.rand_send_delay().await;
+
+        self.check_reachable(rpc.vote.node_id, target).await?;
+
+        {
+            let mut to_drop = self.timeout_now_to_drop.lock().unwrap();
+            if *to_drop > 0 {
+                *to_drop -= 1;
+                return Err(NetworkError::new(&AnyError::error("TimeoutNow is dropped")).into());
+            }
+        }
+
+        let rt = self.routing_table.read().await;
+        let addr = rt.get(&target).expect("target node not found in routing table");
+
+        let resp = addr.0.timeout_now(rpc).await;
+        let resp = resp.map_err(|e| RemoteError::new(target, e))?;
+        Ok(resp)
+    }
+
+    /// Send a ReadIndex RPC to the target Raft node (§6.4 of the thesis).
+    async fn send_read_index(
+        &self,
+        target: u64,
+        _target_node: Option<&Node>,
+        rpc: ReadIndexRequest,
+    ) -> std::result::Result<ReadIndexResponse, RPCError<ClientReadError>> {
+        self.rand_send_delay().await;
+
+        self.check_reachable(rpc.from, target).await?;
+
+        let rt = self.routing_table.read().await;
+        let addr = rt.get(&target).expect("target node not found in routing table");
+
+        let resp = addr.0.read_index(rpc).await;
+        let resp = resp.map_err(|e| RemoteError::new(target, e))?;
+        Ok(resp)
+    }
 }
 
 pub enum ValueTest<T> {
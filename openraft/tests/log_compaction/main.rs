@@ -3,3 +3,5 @@
 mod fixtures;
 
 mod compaction;
+mod snapshot_policy_interval;
+mod trigger_snapshot_and_purge_log;
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LeaderId;
+use openraft::LogId;
+use openraft::SnapshotPolicy;
+
+use crate::fixtures::RaftRouter;
+
+/// A snapshot is built once the interval elapsed since the last snapshot, even without any more logs applied.
+///
+/// What does this test do?
+///
+/// - build a single node cluster with a policy of either 1000 logs or 500 ms since the last snapshot.
+/// - write a few logs, assert a snapshot is built in time, though the number of logs is far below 1000.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_policy_interval() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::Any(vec![
+                SnapshotPolicy::LogsSinceLast(1000),
+                SnapshotPolicy::IntervalSinceLast(500),
+            ]),
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 5).await;
+    log_index += 5;
+    router.wait_for_log(&btreeset! {0}, Some(log_index), timeout(), "write 5 logs").await?;
+
+    router
+        .wait_for_snapshot(
+            &btreeset! {0},
+            LogId::new(LeaderId::new(1, 0), log_index),
+            timeout(),
+            "snapshot by interval",
+        )
+        .await?;
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(2000))
+}
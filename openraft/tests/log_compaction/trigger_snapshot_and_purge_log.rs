@@ -0,0 +1,166 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::NotApplied;
+use openraft::error::PurgeLogError;
+use openraft::Config;
+use openraft::LeaderId;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::SnapshotPolicy;
+
+use crate::fixtures::RaftRouter;
+
+/// Build snapshot and purge logs on demand.
+///
+/// What does this test do?
+///
+/// - build a cluster of 2 voters, with a snapshot policy that never fires.
+/// - trigger a snapshot on the leader and the follower, assert the snapshot includes all applied logs.
+/// - purge logs not applied, expect an error.
+/// - purge applied logs, assert they are removed from the store and the replication goes on.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn trigger_snapshot_and_purge_log() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(1000),
+            max_applied_log_to_keep: 1000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0,1}, btreeset! {}).await?;
+
+    router.client_request_many(0, "0", 10).await;
+    log_index += 10;
+    router.wait_for_log(&btreeset! {0,1}, Some(log_index), timeout(), "write 10 logs").await?;
+
+    tracing::info!("--- trigger snapshot on leader and follower");
+    {
+        let want = LogId::new(LeaderId::new(1, 0), log_index);
+
+        for id in [0, 1] {
+            let meta = router.trigger_snapshot(id).await?;
+            assert_eq!(want, meta.last_log_id);
+
+            router.wait_for_snapshot(&btreeset! {id}, want, timeout(), "trigger snapshot").await?;
+        }
+    }
+
+    tracing::info!("--- purge logs not applied, expect error");
+    {
+        let res = router.purge_log(0, log_index + 1).await;
+        assert_eq!(
+            Err(PurgeLogError::NotApplied(NotApplied {
+                upto: log_index + 1,
+                last_applied: Some(LogId::new(LeaderId::new(1, 0), log_index)),
+            })),
+            res
+        );
+    }
+
+    tracing::info!("--- purge applied logs");
+    {
+        router.purge_log(0, 5).await?;
+
+        let sto = router.get_storage_handle(&0).await?;
+        let st = sto.get_log_state().await?;
+        assert_eq!(Some(5), st.last_purged_log_id.map(|x| x.index));
+
+        let logs = sto.get_log_entries(..).await?;
+        assert_eq!(6, logs[0].log_id.index);
+
+        tracing::info!("--- purge already purged logs is a no-op");
+        router.purge_log(0, 3).await?;
+        let st = sto.get_log_state().await?;
+        assert_eq!(Some(5), st.last_purged_log_id.map(|x| x.index));
+    }
+
+    tracing::info!("--- replication goes on after purging");
+    {
+        router.client_request_many(0, "0", 10).await;
+        log_index += 10;
+        router.wait_for_log(&btreeset! {0,1}, Some(log_index), timeout(), "write 10 more logs").await?;
+    }
+
+    Ok(())
+}
+
+/// Purging logs on demand does not race with the purging of applied logs.
+///
+/// What does this test do?
+///
+/// - build a single node cluster that keeps only 2 applied logs.
+/// - write logs and purge logs on demand at the same time, expect every purge to succeed.
+/// - assert the applied logs are purged and the node keeps working.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn purge_log_while_applying() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::Never,
+            max_applied_log_to_keep: 2,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- write logs and purge logs at the same time");
+    {
+        let done = Arc::new(AtomicBool::new(false));
+
+        let purge = {
+            let router = router.clone();
+            let done = done.clone();
+
+            tokio::spawn(async move {
+                while !done.load(Ordering::Relaxed) {
+                    let last_applied = router.get_metrics(&0).await?.last_applied;
+                    if let Some(upto) = last_applied.map(|x| x.index.saturating_sub(1)) {
+                        router.purge_log(0, upto).await?;
+                    }
+                    tokio::task::yield_now().await;
+                }
+                Ok::<(), anyhow::Error>(())
+            })
+        };
+
+        router.client_request_many(0, "0", 200).await;
+        done.store(true, Ordering::Relaxed);
+        purge.await??;
+
+        log_index += 200;
+        router.wait_for_log(&btreeset! {0}, Some(log_index), timeout(), "write 200 logs").await?;
+    }
+
+    tracing::info!("--- logs are purged upto the last kept applied log");
+    {
+        let sto = router.get_storage_handle(&0).await?;
+        let st = sto.get_log_state().await?;
+        assert!(st.last_purged_log_id.map(|x| x.index) >= Some(log_index - 2));
+
+        router.client_request_many(0, "0", 1).await;
+        log_index += 1;
+        router.wait_for_log(&btreeset! {0}, Some(log_index), timeout(), "write 1 more log").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1000))
+}
@@ -6,9 +6,13 @@ mod fixtures;
 // The later tests may depend on the earlier ones.
 mod t00_learner_restart;
 mod t10_add_learner;
+mod t12_add_learner_with_node;
 
 mod t15_add_remove_follower;
 mod t16_change_membership_cases;
+mod t17_change_members;
+mod t18_promote_learner;
+mod t19_witness;
 // TODO(xp): rename it
 mod t20_change_membership;
 
@@ -17,4 +21,7 @@ mod t25_elect_with_new_config;
 mod t30_commit_joint_config;
 mod t30_step_down;
 mod t40_removed_follower;
+mod t50_transfer_leader;
+mod t55_election_priority;
+mod t56_retry_priority_transfer;
 mod t99_new_leader_auto_commit_uniform_config;
@@ -4,8 +4,8 @@ use std::time::Duration;
 use anyhow::Result;
 use maplit::btreeset;
 use openraft::Config;
+use openraft::DefaultNodeId as NodeId;
 use openraft::LogIdOptionExt;
-use openraft::NodeId;
 use openraft::Raft;
 use openraft::State;
 use tokio::time::sleep;
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::error::ClientWriteError;
+use openraft::error::ForwardToLeader;
+use openraft::Config;
+use openraft::Node;
+
+use crate::fixtures::RaftRouter;
+
+/// Node info provided with `add_learner` and `change_membership` is replicated with the membership config.
+///
+/// What does this test do?
+///
+/// - bring a cluster with 1 voter up.
+/// - add a learner with node info, assert every node sees it in the membership config.
+/// - change membership with node info of the leader, assert the learner sees it.
+/// - add the learner again with new node info, assert the node info is updated.
+/// - write to the follower, assert `ForwardToLeader` includes the leader's node info.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn add_learner_with_node() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- add learner with node info");
+    {
+        router.new_raft_node(1).await;
+        router.add_learner_with_node(0, 1, Node::new("node-1")).await?;
+        log_index += 1;
+
+        router.wait_for_log(&btreeset! {0,1}, Some(log_index), timeout(), "add learner-1").await?;
+
+        for id in [0, 1] {
+            let m = router.get_metrics(&id).await?;
+            assert_eq!(
+                Some(&Node::new("node-1")),
+                m.membership_config.membership.get_node(&1),
+                "node-{} sees node info of learner-1",
+                id
+            );
+        }
+    }
+
+    tracing::info!("--- change membership with node info");
+    {
+        router.change_membership(0, btreemap! {0 => Node::new("node-0"), 1 => Node::new("node-1")}).await?;
+        log_index += 2;
+
+        router.wait_for_log(&btreeset! {0,1}, Some(log_index), timeout(), "change membership").await?;
+
+        let m = router.get_metrics(&1).await?;
+        assert_eq!(
+            &btreemap! {0 => Node::new("node-0"), 1 => Node::new("node-1")},
+            m.membership_config.membership.nodes()
+        );
+    }
+
+    tracing::info!("--- add an existing learner with the same node info, nothing changes");
+    {
+        router.new_raft_node(2).await;
+        router.add_learner_with_node(0, 2, Node::new("node-2")).await?;
+        log_index += 1;
+
+        router.add_learner_with_node(0, 2, Node::new("node-2")).await?;
+
+        router.wait_for_log(&btreeset! {0,1,2}, Some(log_index), timeout(), "add learner-2").await?;
+    }
+
+    tracing::info!("--- add an existing learner with new node info, the node info is updated");
+    {
+        router.add_learner_with_node(0, 2, Node::new("node-2-new")).await?;
+        log_index += 1;
+
+        router.wait_for_log(&btreeset! {0,1,2}, Some(log_index), timeout(), "update learner-2").await?;
+
+        for id in [0, 1, 2] {
+            let m = router.get_metrics(&id).await?;
+            assert_eq!(
+                Some(&Node::new("node-2-new")),
+                m.membership_config.membership.get_node(&2),
+                "node-{} sees the updated node info of learner-2",
+                id
+            );
+        }
+    }
+
+    tracing::info!("--- write to follower, expect ForwardToLeader with leader node info");
+    {
+        let mut res = router.client_write_many(1, "foo", 1).await;
+        let err = res.pop().unwrap().unwrap_err();
+
+        match err {
+            ClientWriteError::ForwardToLeader(ForwardToLeader { leader_id, leader_node }) => {
+                assert_eq!(Some(0), leader_id);
+                assert_eq!(Some(Node::new("node-0")), leader_node);
+            }
+            _ => panic!("expect ForwardToLeader, got: {:?}", err),
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1000))
+}
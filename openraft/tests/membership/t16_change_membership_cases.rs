@@ -4,7 +4,7 @@ use std::time::Duration;
 
 use maplit::btreeset;
 use openraft::Config;
-use openraft::NodeId;
+use openraft::DefaultNodeId as NodeId;
 use openraft::State;
 use tracing_futures::Instrument;
 
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::ChangeMembers;
+use openraft::Config;
+use openraft::Node;
+
+use crate::fixtures::RaftRouter;
+
+/// Change membership with `ChangeMembers` operations, which are applied to the membership config on the leader.
+///
+/// What does this test do?
+///
+/// - bring a cluster with 1 voter up.
+/// - add 2 learners with `AddLearners`, one of them with node info.
+/// - promote the learners one by one with `AddVoters`.
+/// - remove a voter with `RemoveVoters`.
+/// - add then remove a learner, assert the replication to the removed learner is stopped.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn change_members() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- add learners");
+    {
+        router.new_raft_node(1).await;
+        router.new_raft_node(2).await;
+
+        router
+            .change_membership(
+                0,
+                ChangeMembers::AddLearners(btreemap! {1 => Some(Node::new("node-1")), 2 => None}),
+            )
+            .await?;
+        log_index += 1;
+
+        router.wait_for_log(&btreeset! {0,1,2}, Some(log_index), timeout(), "add learners").await?;
+
+        let m = router.get_metrics(&0).await?;
+        let membership = &m.membership_config.membership;
+        assert_eq!(&vec![btreeset! {0}], membership.get_configs());
+        assert_eq!(&btreeset! {1,2}, membership.all_learners());
+        assert_eq!(Some(&Node::new("node-1")), membership.get_node(&1));
+    }
+
+    tracing::info!("--- add voters one by one");
+    {
+        router.change_membership(0, ChangeMembers::AddVoters(btreemap! {1 => None})).await?;
+        log_index += 2;
+
+        router.change_membership(0, ChangeMembers::AddVoters(btreemap! {2 => None})).await?;
+        log_index += 2;
+
+        router.wait_for_log(&btreeset! {0,1,2}, Some(log_index), timeout(), "add voters").await?;
+
+        let m = router.get_metrics(&0).await?;
+        let membership = &m.membership_config.membership;
+        assert_eq!(&vec![btreeset! {0,1,2}], membership.get_configs());
+        assert_eq!(&btreeset! {}, membership.all_learners());
+        assert_eq!(Some(&Node::new("node-1")), membership.get_node(&1));
+    }
+
+    tracing::info!("--- remove voter");
+    {
+        router.change_membership(0, ChangeMembers::RemoveVoters(btreeset! {2})).await?;
+        log_index += 2;
+
+        router.wait_for_log(&btreeset! {0,1}, Some(log_index), timeout(), "remove voter").await?;
+
+        let m = router.get_metrics(&0).await?;
+        assert_eq!(&vec![btreeset! {0,1}], m.membership_config.membership.get_configs());
+    }
+
+    tracing::info!("--- add and remove learner");
+    {
+        router.new_raft_node(3).await;
+
+        router.change_membership(0, ChangeMembers::AddLearners(btreemap! {3 => None})).await?;
+        log_index += 1;
+
+        router.wait_for_log(&btreeset! {0,1,3}, Some(log_index), timeout(), "add learner-3").await?;
+
+        router.change_membership(0, ChangeMembers::RemoveLearners(btreeset! {3})).await?;
+        log_index += 1;
+
+        router.wait_for_log(&btreeset! {0,1}, Some(log_index), timeout(), "remove learner-3").await?;
+
+        let m = router.get_metrics(&0).await?;
+        assert_eq!(&vec![btreeset! {0,1}], m.membership_config.membership.get_configs());
+        assert_eq!(&btreeset! {}, m.membership_config.membership.all_learners());
+
+        router
+            .wait_for_metrics(
+                &0,
+                |x| x.leader_metrics.as_ref().map(|l| !l.replication.contains_key(&3)).unwrap_or(false),
+                timeout(),
+                "replication to learner-3 is removed",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1000))
+}
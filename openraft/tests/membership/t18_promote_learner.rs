@@ -0,0 +1,169 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::error::LearnerNotFound;
+use openraft::error::PromoteLearnerError;
+use openraft::raft::Entry;
+use openraft::raft::EntryPayload;
+use openraft::Config;
+use openraft::LeaderId;
+use openraft::LogId;
+use openraft::Membership;
+use openraft::RaftStorage;
+
+use crate::fixtures::RaftRouter;
+
+/// A learner marked by `promote_learner_when_ready` is promoted to a voter by the leader once it catches up.
+///
+/// What does this test do?
+///
+/// - bring a cluster with 1 voter and 2 learners up, isolate learner-2.
+/// - mark learner-1, assert it is promoted through a joint config.
+/// - mark learner-2, assert it is listed in the metrics while it lags behind.
+/// - cancel the promotion, restore learner-2, assert it stays a learner after it catches up.
+/// - mark a node that is not a learner, expect `LearnerNotFound`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn promote_learner_when_ready() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            replication_lag_threshold: 5,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {1,2}).await?;
+
+    tracing::info!("--- promote learner-1");
+    {
+        router.promote_learner_when_ready(0, 1).await?;
+        log_index += 2; // joint and uniform config
+
+        router.wait_for_log(&btreeset! {0,1,2}, Some(log_index), timeout(), "promote learner-1").await?;
+
+        let m = router
+            .wait_for_metrics(
+                &0,
+                |x| x.leader_metrics.as_ref().map(|l| l.promoting.is_empty()).unwrap_or(false),
+                timeout(),
+                "no learner to promote",
+            )
+            .await?;
+        assert_eq!(&vec![btreeset! {0,1}], m.membership_config.membership.get_configs());
+        assert_eq!(&btreeset! {2}, m.membership_config.membership.all_learners());
+    }
+
+    tracing::info!("--- mark lagging learner-2, then cancel");
+    {
+        router.isolate_node(2).await;
+
+        router.client_request_many(0, "foo", 10).await;
+        log_index += 10;
+
+        router.promote_learner_when_ready(0, 2).await?;
+        router
+            .wait_for_metrics(
+                &0,
+                |x| x.leader_metrics.as_ref().map(|l| l.promoting == btreeset! {2}).unwrap_or(false),
+                timeout(),
+                "learner-2 is waiting for promotion",
+            )
+            .await?;
+
+        router.cancel_learner_promotion(0, 2).await?;
+        router
+            .wait_for_metrics(
+                &0,
+                |x| x.leader_metrics.as_ref().map(|l| l.promoting.is_empty()).unwrap_or(false),
+                timeout(),
+                "promotion of learner-2 is cancelled",
+            )
+            .await?;
+
+        router.restore_node(2).await;
+        router.wait_for_log(&btreeset! {0,1,2}, Some(log_index), timeout(), "learner-2 catches up").await?;
+
+        let m = router.get_metrics(&0).await?;
+        assert_eq!(&vec![btreeset! {0,1}], m.membership_config.membership.get_configs());
+        assert_eq!(&btreeset! {2}, m.membership_config.membership.all_learners());
+    }
+
+    tracing::info!("--- mark a node that is not a learner");
+    {
+        let res = router.promote_learner_when_ready(0, 3).await;
+        assert_eq!(
+            Err(PromoteLearnerError::LearnerNotFound(LearnerNotFound { node_id: 3 })),
+            res
+        );
+    }
+
+    Ok(())
+}
+
+/// A new leader changes a joint config left by the previous leader to the uniform config.
+///
+/// What does this test do?
+///
+/// - bring a cluster with 3 voters and 1 learner up.
+/// - shut down every node, as if leader-0 proposed a joint config to promote learner-3 and crashed after replicating it
+///   to node-1,2,3, before proposing the uniform config.
+/// - restart node-1,2,3, assert the new leader commits the uniform config with learner-3 promoted.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn finish_promotion_after_leader_change() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3}).await?;
+
+    let term = router.get_metrics(&0).await?.current_term;
+
+    tracing::info!("--- shut down all nodes, append a joint config to node-1,2,3, restart node-1,2,3");
+    {
+        let mut stores = vec![];
+        for id in [0, 1, 2, 3] {
+            let (r, sto) = router.remove_node(id).await.unwrap();
+            r.shutdown().await?;
+            stores.push((id, sto));
+        }
+
+        let joint = Entry {
+            log_id: LogId::new(LeaderId::new(term, 0), log_index + 1),
+            payload: EntryPayload::Membership(Membership::new_multi(vec![btreeset! {0,1,2}, btreeset! {0,1,2,3}])),
+        };
+        log_index += 1;
+
+        for (id, sto) in stores.into_iter().filter(|(id, _)| *id != 0) {
+            sto.append_to_log(&[&joint]).await?;
+            router.new_raft_node_with_sto(id, sto).await;
+        }
+    }
+
+    tracing::info!("--- a new leader finishes the joint config");
+    {
+        // The blank log of the new leader and the uniform config.
+        log_index += 2;
+
+        router.wait_for_log(&btreeset! {1,2,3}, Some(log_index), timeout(), "uniform config").await?;
+
+        let leader = router.leader().await.unwrap();
+        let m = router.get_metrics(&leader).await?;
+        assert_eq!(&vec![btreeset! {0,1,2,3}], m.membership_config.membership.get_configs());
+        assert!(m.membership_config.membership.all_learners().is_empty());
+        assert!(m.leader_metrics.unwrap().promoting.is_empty());
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1000))
+}
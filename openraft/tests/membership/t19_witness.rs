@@ -0,0 +1,276 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::error::IsWitness;
+use openraft::error::TransferLeaderError;
+use openraft::raft::EntryPayload;
+use openraft::ChangeMembers;
+use openraft::Config;
+use openraft::LeaderId;
+use openraft::LogId;
+use openraft::LogIdOptionExt;
+use openraft::RaftStorage;
+use openraft::RaftStorageDebug;
+use openraft::SnapshotPolicy;
+use openraft::State;
+
+use crate::fixtures::RaftRouter;
+
+/// A witness votes and counts in commit quorums, but stores no application data and never becomes leader.
+///
+/// What does this test do?
+///
+/// - bring a cluster with 1 voter and 1 learner up, add witness-2, then promote learner-1 and witness-2 to voters.
+/// - write some logs, assert the witness stores only log ids and membership configs, and applies no application data.
+/// - transfer the leadership to the witness, expect `IsWitness`.
+/// - isolate the leader, assert node-1 is elected with the vote of the witness, which never campaigns.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn witness() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    tracing::info!("--- add witness-2");
+    {
+        router.new_raft_node(2).await;
+
+        router.change_membership(0, ChangeMembers::AddWitnesses(btreemap! {2 => None})).await?;
+        log_index += 1;
+
+        router.wait_for_state(&btreeset! {2}, State::Witness, timeout(), "node-2 becomes witness").await?;
+    }
+
+    tracing::info!("--- promote learner-1 and witness-2 to voters");
+    {
+        router.change_membership(0, ChangeMembers::AddVoters(btreemap! {1 => None, 2 => None})).await?;
+        log_index += 2;
+
+        router.wait_for_log(&btreeset! {0,1}, Some(log_index), timeout(), "promote to voters").await?;
+        router
+            .wait(&2, timeout())
+            .await?
+            .metrics(|x| x.last_log_index == Some(log_index), "witness receives logs")
+            .await?;
+
+        let m = router.get_metrics(&2).await?;
+        assert_eq!(State::Witness, m.state);
+        assert_eq!(&vec![btreeset! {0,1,2}], m.membership_config.membership.get_configs());
+        assert_eq!(&btreeset! {2}, m.membership_config.membership.witnesses());
+    }
+
+    tracing::info!("--- witness stores no application data");
+    {
+        router.client_request_many(0, "foo", 10).await;
+        log_index += 10;
+
+        router.wait_for_log(&btreeset! {0,1}, Some(log_index), timeout(), "write logs").await?;
+        router
+            .wait(&2, timeout())
+            .await?
+            .metrics(
+                |x| x.last_log_index == Some(log_index) && x.last_applied.index() == Some(log_index),
+                "witness receives and applies logs",
+            )
+            .await?;
+
+        let sto = router.get_storage_handle(&2).await?;
+        let logs = sto.try_get_log_entries(..).await?;
+        assert_eq!(Some(log_index), logs.last().map(|ent| ent.log_id.index));
+        assert!(logs.iter().all(|ent| !matches!(ent.payload, EntryPayload::Normal(_))));
+
+        let sm = sto.get_state_machine().await;
+        assert!(sm.client_status.is_empty());
+        assert_eq!(Some(log_index), sm.last_applied_log.index());
+
+        let sto = router.get_storage_handle(&1).await?;
+        let logs = sto.try_get_log_entries(..).await?;
+        assert_eq!(
+            10,
+            logs.iter().filter(|ent| matches!(ent.payload, EntryPayload::Normal(_))).count()
+        );
+    }
+
+    tracing::info!("--- transfer leadership to witness");
+    {
+        let res = router.transfer_leader(0, 2).await;
+        assert_eq!(Err(TransferLeaderError::IsWitness(IsWitness { node_id: 2 })), res);
+    }
+
+    tracing::info!("--- isolate leader, node-1 is elected with the vote of witness-2");
+    {
+        router.isolate_node(0).await;
+
+        router
+            .wait_for_state(&btreeset! {1}, State::Leader, election_timeout(), "node-1 is elected")
+            .await?;
+
+        let m = router.wait(&2, timeout()).await?.current_leader(1, "witness follows node-1").await?;
+        assert_eq!(State::Witness, m.state);
+    }
+
+    Ok(())
+}
+
+/// A witness lagging behind the purged logs of the leader is sent only the snapshot meta, without the snapshot data.
+///
+/// What does this test do?
+///
+/// - build a single node cluster, write enough logs to build a snapshot and purge the logs.
+/// - add witness-1, assert it records the snapshot as applied, purges the logs included in it, and receives and applies
+///   the following logs.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn witness_snapshot() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            max_applied_log_to_keep: 0,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- send just enough logs to trigger snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - 1 - log_index) as usize).await;
+        log_index = snapshot_threshold - 1;
+
+        router.wait_for_log(&btreeset! {0}, Some(log_index), timeout(), "write logs").await?;
+        router
+            .wait_for_snapshot(
+                &btreeset! {0},
+                LogId::new(LeaderId::new(1, 0), log_index),
+                timeout(),
+                "snapshot",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- add witness-1");
+    {
+        router.new_raft_node(1).await;
+
+        router.change_membership(0, ChangeMembers::AddWitnesses(btreemap! {1 => None})).await?;
+        log_index += 1;
+
+        router.wait_for_state(&btreeset! {1}, State::Witness, timeout(), "node-1 becomes witness").await?;
+        let m = router
+            .wait(&1, timeout())
+            .await?
+            .metrics(
+                |x| {
+                    x.snapshot.index() >= Some(snapshot_threshold - 1)
+                        && x.last_log_index == Some(log_index)
+                        && x.last_applied.index() == Some(log_index)
+                },
+                "witness receives snapshot meta and logs",
+            )
+            .await?;
+        assert_eq!(&btreeset! {1}, m.membership_config.membership.witnesses());
+
+        // The logs included in the snapshot are recorded as applied and purged, but the snapshot data is not
+        // installed. The following logs are applied and purged too, since no applied log is kept.
+        let sto = router.get_storage_handle(&1).await?;
+        assert!(sto.get_log_state().await?.last_purged_log_id >= m.snapshot);
+        assert!(sto.try_get_log_entries(..).await?.iter().all(|ent| Some(ent.log_id.index) > m.snapshot.index()));
+        assert!(sto.get_current_snapshot().await?.is_none());
+
+        let sm = sto.get_state_machine().await;
+        assert!(sm.client_status.is_empty());
+        assert_eq!(Some(log_index), sm.last_applied_log.index());
+        assert_eq!(
+            Some(&btreeset! {1}),
+            sm.last_membership.as_ref().map(|m| m.membership.witnesses())
+        );
+    }
+
+    Ok(())
+}
+
+/// A witness that is never sent a snapshot purges the applied logs, thus its log does not grow without limit.
+///
+/// What does this test do?
+///
+/// - bring a cluster with 1 voter and witness-1 up, with a snapshot policy that never fires and keeping 5 logs.
+/// - write 100 logs, assert the witness applies them and keeps only the last 5 applied logs.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn witness_purges_committed_logs() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let max_keep: u64 = 5;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::Never,
+            max_applied_log_to_keep: max_keep,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- add witness-1");
+    {
+        router.new_raft_node(1).await;
+
+        router.change_membership(0, ChangeMembers::AddWitnesses(btreemap! {1 => None})).await?;
+        log_index += 1;
+
+        router.wait_for_state(&btreeset! {1}, State::Witness, timeout(), "node-1 becomes witness").await?;
+    }
+
+    tracing::info!("--- write 100 logs, the witness purges the applied logs");
+    {
+        router.client_request_many(0, "foo", 100).await;
+        log_index += 100;
+
+        router.wait_for_log(&btreeset! {0}, Some(log_index), timeout(), "write logs").await?;
+
+        let sto = router.get_storage_handle(&1).await?;
+        let want_purged = log_index - max_keep;
+
+        // Logs are purged right after being applied.
+        router
+            .wait(&1, timeout())
+            .await?
+            .metrics(
+                |x| x.last_log_index == Some(log_index) && x.last_applied.index() == Some(log_index),
+                "witness receives and applies logs",
+            )
+            .await?;
+
+        let purged = sto.get_log_state().await?.last_purged_log_id.index();
+        assert_eq!(Some(want_purged), purged);
+
+        let logs = sto.try_get_log_entries(..).await?;
+        assert_eq!(max_keep, logs.len() as u64, "witness keeps {} logs", logs.len());
+        assert!(sto.get_state_machine().await.client_status.is_empty());
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(1000))
+}
+
+fn election_timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
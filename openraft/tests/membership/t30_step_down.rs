@@ -13,6 +13,7 @@ use crate::fixtures::RaftRouter;
 /// Change membership from {0,1} to {1,2,3}.
 ///
 /// - Then the leader should step down after joint log is committed.
+/// - The leader hands over the leadership to a voter in the new cluster.
 /// - Check logs on other node.
 #[tokio::test(flavor = "multi_thread", worker_threads = 5)]
 async fn step_down() -> Result<()> {
@@ -54,6 +55,18 @@ async fn step_down() -> Result<()> {
             .await?;
     }
 
+    tracing::info!("--- the old leader hands over the leadership without waiting for an election timeout");
+    {
+        router
+            .wait_for_metrics(
+                &1,
+                |x| x.current_leader.is_some() && x.current_leader != Some(orig_leader),
+                Some(Duration::from_millis(config.election_timeout_min)),
+                "a new leader is elected at once",
+            )
+            .await?;
+    }
+
     // Another node(e.g. node-1) in the old cluster may not commit the second membership change log.
     // Because to commit the 2nd log it only need a quorum of the new cluster.
 
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use openraft::error::ClientWriteError;
+use openraft::error::NotVoter;
+use openraft::error::TransferLeaderError;
+use openraft::error::TransferLeaderTimeout;
+use openraft::Config;
+use openraft::State;
+
+use crate::fixtures::RaftRouter;
+
+/// Transfer leadership from node 0 to node 1 in a cluster of {0,1,2}.
+///
+/// - Transferring to a learner or an unknown node is rejected.
+/// - Node 1 becomes the leader and node 0 becomes a follower.
+/// - The cluster keeps accepting writes through the new leader.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn transfer_leader() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3}).await?;
+
+    tracing::info!("--- transfer to a non-voter is rejected");
+    {
+        let res = router.transfer_leader(0, 3).await;
+        assert_eq!(Err(TransferLeaderError::NotVoter(NotVoter { node_id: 3 })), res);
+
+        let res = router.transfer_leader(0, 10).await;
+        assert_eq!(Err(TransferLeaderError::NotVoter(NotVoter { node_id: 10 })), res);
+    }
+
+    tracing::info!("--- transfer to the leader itself does nothing");
+    {
+        router.transfer_leader(0, 0).await?;
+        assert_eq!(Some(0), router.leader().await);
+    }
+
+    tracing::info!("--- transfer leadership to node 1");
+    {
+        router.transfer_leader(0, 1).await?;
+
+        router.wait_for_state(&btreeset! {1}, State::Leader, timeout(), "node 1 becomes leader").await?;
+        router
+            .wait_for_state(&btreeset! {0,2}, State::Follower, timeout(), "others become follower")
+            .await?;
+
+        let leader = router.current_leader(0).await;
+        assert_eq!(Some(1), leader, "node 0 follows node 1");
+
+        // The new leader commits a blank log.
+        log_index += 1;
+        router.wait_for_log(&btreeset! {0,1,2}, Some(log_index), timeout(), "new leader blank log").await?;
+    }
+
+    tracing::info!("--- write through the new leader");
+    {
+        router.client_request_many(1, "client", 10).await;
+        log_index += 10;
+
+        router.wait_for_log(&btreeset! {0,1,2}, Some(log_index), timeout(), "write to new leader").await?;
+    }
+
+    Ok(())
+}
+
+/// A transfer to a voter that does not catch up in time is aborted, and the leader resumes accepting writes.
+///
+/// - Isolate node 1 and write a log, thus node 1 lags behind.
+/// - Transfer leadership to node 1, writes are rejected during the transfer.
+/// - The transfer fails with `Timeout`, node 0 is still the leader and accepts writes again.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn transfer_leader_timeout() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    // Setup test dependencies.
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {}).await?;
+
+    tracing::info!("--- isolate node 1 and write a log, node 1 lags behind");
+    {
+        router.isolate_node(1).await;
+
+        router.client_request_many(0, "client", 1).await;
+        log_index += 1;
+
+        router.wait_for_log(&btreeset! {0,2}, Some(log_index), timeout(), "write to node 0,2").await?;
+    }
+
+    tracing::info!("--- transfer to the lagging node 1 times out");
+    {
+        let r = router.clone();
+        let handle = tokio::spawn(async move { r.transfer_leader(0, 1).await });
+
+        // Wait for node 0 to start the transfer.
+        tokio::time::sleep(Duration::from_millis(config.election_timeout_min / 2)).await;
+
+        let req = ClientRequest {
+            client: "client".into(),
+            serial: 100,
+            status: "during transfer".into(),
+        };
+        let res = router.send_client_request(0, req).await;
+        match res {
+            Err(ClientWriteError::ForwardToLeader(e)) => assert_eq!(Some(1), e.leader_id),
+            _ => panic!("expect ForwardToLeader during the transfer, got: {:?}", res),
+        }
+
+        let res = handle.await?;
+        assert_eq!(
+            Err(TransferLeaderError::Timeout(TransferLeaderTimeout {
+                target: 1,
+                timeout: Duration::from_millis(config.election_timeout_max),
+            })),
+            res
+        );
+
+        assert_eq!(Some(0), router.leader().await);
+    }
+
+    tracing::info!("--- node 0 accepts writes again");
+    {
+        router.client_request_many(0, "client", 10).await;
+        log_index += 10;
+
+        router
+            .wait_for_log(&btreeset! {0,2}, Some(log_index), timeout(), "write after the transfer")
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(2000))
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::Node;
+use openraft::State;
+
+use crate::fixtures::RaftRouter;
+
+/// The voter with the highest election priority ends up leader.
+///
+/// What does this test do?
+///
+/// - bring a cluster with 1 voter and 2 learners up.
+/// - change the voters to {0,1,2} with priorities 0, 5 and 10, assert the leadership is handed over to node 2.
+/// - isolate node 2, assert node 1, which has a higher priority than node 0, becomes leader.
+/// - restore node 2, assert the leadership is handed back to node 2 once it catches up.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn election_priority() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {1,2}).await?;
+
+    tracing::info!("--- set priorities, leadership is handed over to node 2");
+    {
+        router
+            .change_membership(0, btreemap! {
+                0 => Node::new("0"),
+                1 => Node::new("1").with_priority(5),
+                2 => Node::new("2").with_priority(10),
+            })
+            .await?;
+        log_index += 2;
+
+        router.wait_for_state(&btreeset! {2}, State::Leader, timeout(), "node 2 becomes leader").await?;
+        router.wait_for_state(&btreeset! {0,1}, State::Follower, timeout(), "others follow node 2").await?;
+
+        router.wait(&0, timeout()).await?.current_leader(2, "node 0 follows node 2").await?;
+
+        // Node 1 can not be elected without the blank log of node 2.
+        log_index += 1;
+        router
+            .wait_for_log(
+                &btreeset! {0,1,2},
+                Some(log_index),
+                timeout(),
+                "replicate logs of node 2",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- isolate node 2, node 1 is elected before node 0");
+    {
+        router.isolate_node(2).await;
+
+        router.wait_for_state(&btreeset! {1}, State::Leader, timeout(), "node 1 becomes leader").await?;
+        assert_eq!(State::Follower, router.get_metrics(&0).await?.state);
+    }
+
+    tracing::info!("--- restore node 2, leadership is handed back to it");
+    {
+        router.restore_node(2).await;
+
+        router
+            .wait_for_state(&btreeset! {2}, State::Leader, timeout(), "node 2 becomes leader again")
+            .await?;
+        router.wait_for_state(&btreeset! {0,1}, State::Follower, timeout(), "others follow node 2").await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(3000))
+}
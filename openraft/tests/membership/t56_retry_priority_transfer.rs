@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreemap;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::Node;
+use openraft::State;
+
+use crate::fixtures::RaftRouter;
+
+/// A failed leadership transfer to a voter with a higher priority is retried, even if the cluster is idle.
+///
+/// What does this test do?
+///
+/// - bring a cluster with 1 voter and 2 learners up.
+/// - make the first TimeoutNow RPC fail.
+/// - change the voters to {0,1,2} with node 2 having the highest priority, and write nothing after it.
+/// - assert the leadership is handed over to node 2 with a second TimeoutNow.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn retry_priority_transfer() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    router.new_nodes_from_single(btreeset! {0}, btreeset! {1,2}).await?;
+
+    tracing::info!("--- drop the first TimeoutNow, set priorities");
+    {
+        router.drop_timeout_now(1);
+
+        router
+            .change_membership(0, btreemap! {
+                0 => Node::new("0"),
+                1 => Node::new("1"),
+                2 => Node::new("2").with_priority(10),
+            })
+            .await?;
+    }
+
+    tracing::info!("--- the transfer is retried, node 2 becomes leader");
+    {
+        router.wait_for_state(&btreeset! {2}, State::Leader, timeout(), "node 2 becomes leader").await?;
+        router.wait_for_state(&btreeset! {0,1}, State::Follower, timeout(), "others follow node 2").await?;
+
+        assert!(
+            router.timeout_now_count(2) >= 2,
+            "the failed transfer is retried, TimeoutNow count: {}",
+            router.timeout_now_count(2)
+        );
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(3000))
+}
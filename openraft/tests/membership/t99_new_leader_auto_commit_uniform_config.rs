@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use maplit::btreeset;
@@ -14,12 +15,10 @@ use openraft::RaftStorage;
 use crate::fixtures::RaftRouter;
 
 /// Cluster members_leader_fix_partial test.
-/// TODO(xp): in discussion: whether a leader should auto commit a uniform membership config:
-/// https://github.com/datafuselabs/openraft/discussions/17
 ///
 /// - brings up 1 leader.
 /// - manually append a joint config log.
-/// - shutdown and restart, it should NOT add another final config log to complete the partial
+/// - shutdown and restart, it should add another final config log to complete the partial
 /// membership changing
 #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
 async fn new_leader_auto_commit_uniform_config() -> Result<()> {
@@ -32,8 +31,8 @@ async fn new_leader_auto_commit_uniform_config() -> Result<()> {
 
     let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
 
-    let sto = router.get_storage_handle(&0).await?;
-    router.remove_node(0).await;
+    let (r0, sto) = router.remove_node(0).await.unwrap();
+    r0.shutdown().await?;
 
     {
         sto.append_to_log(&[&Entry {
@@ -43,10 +42,8 @@ async fn new_leader_auto_commit_uniform_config() -> Result<()> {
         .await?;
     }
 
-    // A joint log and the leader should add a new final config log.
-    log_index += 2;
-
-    let _ = log_index;
+    // A joint log, the blank log of the new leader, and the final config log the leader adds.
+    log_index += 3;
 
     // To let tne router not panic
     router.new_raft_node(1).await;
@@ -54,31 +51,23 @@ async fn new_leader_auto_commit_uniform_config() -> Result<()> {
 
     let node = Raft::new(0, config.clone(), router.clone(), sto.clone());
 
-    let _ = node;
+    node.wait(Some(Duration::from_millis(2000)))
+        .metrics(
+            |x| x.last_log_index == Some(log_index),
+            "wait for leader to complete the final config log",
+        )
+        .await?;
+
+    let final_log = sto.get_log_entries(log_index..=log_index).await?[0].clone();
+
+    let m = match final_log.payload {
+        EntryPayload::Membership(ref m) => m.clone(),
+        _ => {
+            panic!("expect membership config log")
+        }
+    };
 
-    // node.wait(Some(Duration::from_millis(500)))
-    //     .metrics(
-    //         |x| x.last_log_index == want,
-    //         "wait for leader to complete the final config log",
-    //     )
-    //     .await?;
-    //
-    // let final_log = sto.get_log_entries(want..=want).await?[0].clone();
-    //
-    // let m = match final_log.payload {
-    //     EntryPayload::Membership(ref m) => m.membership.clone(),
-    //     _ => {
-    //         panic!("expect membership config log")
-    //     }
-    // };
-    //
-    // assert_eq!(
-    //     MembershipConfig {
-    //         members: btreeset! {0,1,2},
-    //         members_after_consensus: None,
-    //     },
-    //     m
-    // );
+    assert_eq!(&vec![btreeset! {0,1,2}], m.get_configs());
 
     Ok(())
 }
@@ -9,3 +9,4 @@ mod t10_current_leader;
 mod t20_metrics_state_machine_consistency;
 mod t30_leader_metrics;
 mod t40_metrics_wait;
+mod t50_replication_failures;
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -5,13 +6,14 @@ use anyhow::Result;
 use futures::stream::StreamExt;
 use maplit::btreeset;
 use maplit::hashmap;
+use openraft::metrics::LeaderMetrics;
 use openraft::raft::VoteRequest;
 use openraft::Config;
 use openraft::LeaderId;
 use openraft::LogId;
 use openraft::RaftNetwork;
-use openraft::ReplicationMetrics;
 use openraft::State;
+use openraft::TargetReplState;
 use openraft::Vote;
 #[allow(unused_imports)]
 use pretty_assertions::assert_eq;
@@ -105,16 +107,14 @@ async fn leader_metrics() -> Result<()> {
 
     router.assert_stable_cluster(Some(1), Some(log_index)).await; // Still in term 1, so leader is still node 0.
 
-    let ww = ReplicationMetrics {
-        matched: Some(LogId::new(LeaderId::new(1, 0), log_index)),
-    };
-    let want_repl = hashmap! { 1=>ww.clone(), 2=>ww.clone(), 3=>ww.clone(), 4=>ww.clone(), };
+    let ww = Some(LogId::new(LeaderId::new(1, 0), log_index));
+    let want_repl = hashmap! { 1=>ww, 2=>ww, 3=>ww, 4=>ww, };
     router
         .wait_for_metrics(
             &0,
             |x| {
                 if let Some(ref q) = x.leader_metrics {
-                    q.replication == want_repl
+                    matched_of(q) == want_repl
                 } else {
                     false
                 }
@@ -155,16 +155,14 @@ async fn leader_metrics() -> Result<()> {
 
     tracing::info!("--- replication metrics should reflect the replication state");
     {
-        let ww = ReplicationMetrics {
-            matched: Some(LogId::new(LeaderId::new(1, 0), log_index)),
-        };
-        let want_repl = hashmap! { 1=>ww.clone(), 2=>ww.clone(), 3=>ww.clone()};
+        let ww = Some(LogId::new(LeaderId::new(1, 0), log_index));
+        let want_repl = hashmap! { 1=>ww, 2=>ww, 3=>ww};
         router
             .wait_for_metrics(
                 &0,
                 |x| {
                     if let Some(ref q) = x.leader_metrics {
-                        q.replication == want_repl
+                        matched_of(q) == want_repl
                     } else {
                         false
                     }
@@ -173,6 +171,16 @@ async fn leader_metrics() -> Result<()> {
                 "replication metrics to 3 nodes",
             )
             .await?;
+
+        let metrics = router.get_metrics(&0).await?.leader_metrics.unwrap();
+        for id in [1, 2, 3] {
+            let m = &metrics.replication[&id];
+            assert_eq!(TargetReplState::LineRate, m.state, "node {}", id);
+            assert!(m.last_success_at.is_some(), "node {}", id);
+            assert!(m.last_rpc_latency.is_some(), "node {}", id);
+            assert!(m.bytes_sent > 0, "node {}", id);
+            assert_eq!(None, m.snapshot_transfer, "node {}", id);
+        }
     }
 
     let leader = router.current_leader(0).await.unwrap();
@@ -180,10 +188,11 @@ async fn leader_metrics() -> Result<()> {
     tracing::info!("--- take leadership of node {}", leader);
     {
         router
-            .send_vote(leader, VoteRequest {
-                vote: Vote::new(100, 100),
-                last_log_id: Some(LogId::new(LeaderId::new(10, 0), 100)),
-            })
+            .send_vote(
+                leader,
+                None,
+                VoteRequest::new(Vote::new(100, 100), Some(LogId::new(LeaderId::new(10, 0), 100))),
+            )
             .await?;
 
         router
@@ -228,6 +237,11 @@ async fn leader_metrics() -> Result<()> {
     Ok(())
 }
 
+/// The matched log id of every replication target.
+fn matched_of(m: &LeaderMetrics) -> HashMap<u64, Option<LogId<u64>>> {
+    m.replication.iter().map(|(id, r)| (*id, r.matched)).collect()
+}
+
 fn timeout() -> Option<Duration> {
     Some(Duration::from_millis(1000))
 }
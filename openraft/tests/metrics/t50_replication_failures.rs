@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+
+use crate::fixtures::RaftRouter;
+
+/// Replication to an unreachable target backs off, and the failures are reported in metrics.
+///
+/// What does this test do?
+///
+/// - bring a cluster with 1 voter and 1 learner up.
+/// - isolate the learner and write logs, assert the failures to it are counted, but not more than the backoff allows.
+/// - restore the learner, assert it receives the logs and the failures are cleared.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_failures() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            replication_backoff_min: 20,
+            replication_backoff_max: 200,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    tracing::info!("--- isolate the learner, replication to it fails");
+    {
+        router.isolate_node(1).await;
+
+        router.client_request_many(0, "foo", 10).await;
+        log_index += 10;
+
+        router
+            .wait_for_metrics(
+                &0,
+                |x| {
+                    let repl = x.leader_metrics.as_ref().and_then(|l| l.replication.get(&1));
+                    repl.map(|r| r.failures >= 3 && r.last_error.is_some()).unwrap_or(false)
+                },
+                timeout(),
+                "failures to node 1 are counted",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- retries back off");
+    {
+        tokio::time::sleep(Duration::from_millis(1_000)).await;
+
+        let metrics = router.get_metrics(&0).await?;
+        let failures = metrics.leader_metrics.unwrap().replication[&1].failures;
+
+        // Without backoff, an unreachable target is retried at once, thousands of times a second.
+        assert!(failures < 100, "failures: {}", failures);
+    }
+
+    tracing::info!("--- restore the learner, replication recovers");
+    {
+        router.restore_node(1).await;
+
+        router.wait_for_log(&btreeset! {0,1}, Some(log_index), timeout(), "node 1 catches up").await?;
+
+        router
+            .wait_for_metrics(
+                &0,
+                |x| {
+                    let repl = x.leader_metrics.as_ref().and_then(|l| l.replication.get(&1));
+                    repl.map(|r| r.failures == 0 && r.last_error.is_none()).unwrap_or(false)
+                },
+                timeout(),
+                "failures to node 1 are cleared",
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(3000))
+}
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use fixtures::RaftRouter;
+use futures::stream::FuturesUnordered;
+use futures::stream::StreamExt;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LeaderId;
+use openraft::LogId;
+
+#[macro_use]
+mod fixtures;
+
+/// Replicate logs with pipelined AppendEntries RPCs.
+///
+/// What does this test do?
+///
+/// - Setup a network with <=20 ms random delay of messages, thus pipelined RPCs may arrive out of order.
+/// - bring on a cluster of 3 voters and 1 learner, with small payloads and up to 8 RPCs in flight.
+/// - write logs concurrently, assert every node receives all of them, with more than one RPC in flight to a follower at
+///   a time, and the matched log id of every follower never goes backward, even when RPCs are reordered.
+/// - add a new learner, which has to find the matching log before pipelining, assert it catches up.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn replication_pipeline() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            max_payload_entries: 5,
+            max_in_flight_append_entries: 8,
+            heartbeat_interval: 100,
+            election_timeout_min: 500,
+            election_timeout_max: 1000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::builder(config).send_delay(20).build());
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0,1,2}, btreeset! {3}).await?;
+
+    tracing::info!("--- write logs concurrently");
+    {
+        let watch_matched = tokio::spawn(assert_matched_monotonic(router.clone()));
+
+        let mut clients = FuturesUnordered::new();
+        for i in 0..4 {
+            let router = router.clone();
+            clients.push(async move { router.client_request_many(0, &i.to_string(), 100).await });
+        }
+        while clients.next().await.is_some() {}
+        log_index += 400;
+
+        router.wait_for_log(&btreeset! {0,1,2,3}, Some(log_index), timeout(), "replicate 400 logs").await?;
+
+        watch_matched.abort();
+        if let Err(e) = watch_matched.await {
+            assert!(e.is_cancelled(), "matched log id goes backward: {}", e);
+        }
+
+        for id in [1, 2, 3] {
+            let max = router.max_append_entries_in_flight(id);
+            assert!(max > 1, "node {}: max AppendEntries in flight: {}", id, max);
+            assert!(max <= 8, "node {}: max AppendEntries in flight: {}", id, max);
+        }
+    }
+
+    tracing::info!("--- add learner 4, it catches up");
+    {
+        router.new_raft_node(4).await;
+        router.add_learner(0, 4).await?;
+        log_index += 1;
+
+        router
+            .wait_for_log(
+                &btreeset! {0,1,2,3,4},
+                Some(log_index),
+                timeout(),
+                "learner 4 catches up",
+            )
+            .await?;
+    }
+
+    router
+        .assert_storage_state(1, log_index, Some(0), LogId::new(LeaderId::new(1, 0), log_index), None)
+        .await?;
+
+    Ok(())
+}
+
+/// Watch the metrics of the leader and panic if the matched log id of a target goes backward.
+async fn assert_matched_monotonic(router: Arc<RaftRouter>) {
+    let mut last = BTreeMap::new();
+
+    loop {
+        let metrics = router.get_metrics(&0).await.unwrap();
+
+        if let Some(leader) = metrics.leader_metrics {
+            for (id, repl) in leader.replication.iter() {
+                let prev = last.insert(*id, repl.matched);
+                assert!(
+                    prev <= Some(repl.matched),
+                    "node {}: {:?} -> {:?}",
+                    id,
+                    prev,
+                    repl.matched
+                );
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(10_000))
+}
@@ -3,8 +3,12 @@
 mod fixtures;
 
 mod after_snapshot_add_learner_and_request_a_log;
+mod snapshot_bytes_lag_new_learner;
 mod snapshot_chunk_size;
 mod snapshot_ge_half_threshold;
 mod snapshot_line_rate_to_snapshot;
+mod snapshot_not_sent_to_idle_follower;
 mod snapshot_overrides_membership;
+mod snapshot_resume_after_lost_reply;
+mod snapshot_transfer_options;
 mod snapshot_uses_prev_snap_membership;
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::SnapshotPolicy;
+
+use crate::fixtures::RaftRouter;
+
+/// A new learner far behind is sent a snapshot under a bytes-only snapshot policy, even before any log is sent to it.
+///
+/// What does this test do?
+///
+/// - bring a cluster of 1 voter up with `SnapshotPolicy::BytesSinceLast`, and write more logs than the threshold in
+///   bytes. The logs are not purged.
+/// - add an isolated learner, thus the leader decides how to replicate to it before sending any log.
+/// - restore the learner, assert it catches up with a snapshot instead of the logs.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_bytes_lag_new_learner() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::BytesSinceLast(1024),
+            max_applied_log_to_keep: 1000,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- write logs of more than 1024 bytes");
+    {
+        router.client_request_many(0, "0", 100).await;
+        log_index += 100;
+
+        router.wait_for_log(&btreeset![0], Some(log_index), timeout(), "write logs").await?;
+    }
+
+    tracing::info!("--- add isolated learner-1");
+    {
+        router.new_raft_node(1).await;
+        router.isolate_node(1).await;
+
+        router.add_learner_with_blocking(0, 1, false).await?;
+        log_index += 1;
+
+        // Let the first AppendEntries to learner-1 fail.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    tracing::info!("--- restore learner-1, it catches up with a snapshot");
+    {
+        router.restore_node(1).await;
+
+        router.wait_for_log(&btreeset![1], Some(log_index), timeout(), "learner-1 catches up").await?;
+        assert!(router.install_snapshot_count(1) > 0, "a snapshot is sent to learner-1");
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
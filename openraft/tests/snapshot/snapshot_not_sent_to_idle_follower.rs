@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::SnapshotPolicy;
+
+use crate::fixtures::RaftRouter;
+
+/// An `IntervalSinceLast` policy builds snapshots, but does not make the leader send a snapshot to a target that was
+/// idle for longer than the interval and then lags behind by a few logs.
+///
+/// What does this test do?
+///
+/// - bring a cluster with 1 voter and 1 learner up, with a policy of either 1000 logs or 100 ms since the last
+///   snapshot.
+/// - stay idle for much longer than the interval.
+/// - isolate the learner and write a log, so that replication to it fails and it lags behind by 1 log.
+/// - restore the learner, assert it catches up with logs, without any InstallSnapshot RPC.
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn snapshot_not_sent_to_idle_follower() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::Any(vec![
+                SnapshotPolicy::LogsSinceLast(1000),
+                SnapshotPolicy::IntervalSinceLast(100),
+            ]),
+            replication_backoff_min: 20,
+            replication_backoff_max: 100,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {1}).await?;
+
+    router.client_request_many(0, "0", 5).await;
+    log_index += 5;
+    router.wait_for_log(&btreeset! {0, 1}, Some(log_index), timeout(), "write 5 logs").await?;
+
+    tracing::info!("--- stay idle for longer than the snapshot interval");
+    {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    tracing::info!("--- isolate the learner and write 1 log, replication to it fails");
+    {
+        router.isolate_node(1).await;
+
+        router.client_request_many(0, "0", 1).await;
+        log_index += 1;
+
+        router
+            .wait_for_metrics(
+                &0,
+                |x| {
+                    let repl = x.leader_metrics.as_ref().and_then(|l| l.replication.get(&1));
+                    repl.map(|r| r.failures >= 1).unwrap_or(false)
+                },
+                timeout(),
+                "replication to node 1 fails",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- restore the learner, it catches up with logs");
+    {
+        router.restore_node(1).await;
+        router.wait_for_log(&btreeset! {0, 1}, Some(log_index), timeout(), "node 1 catches up").await?;
+
+        assert_eq!(0, router.install_snapshot_count(1), "no snapshot is sent to node 1");
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(2000))
+}
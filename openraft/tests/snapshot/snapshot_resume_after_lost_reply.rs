@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LeaderId;
+use openraft::LogId;
+use openraft::SnapshotPolicy;
+
+use crate::fixtures::RaftRouter;
+
+/// A snapshot whose chunk is received by the target but whose reply is lost, is resumed from the offset the target
+/// tells, instead of from the beginning.
+///
+/// What does this test do?
+///
+/// - build a single node cluster with a snapshot sent in small chunks.
+/// - lose the reply to the third chunk.
+/// - add a learner, assert it receives the snapshot and the chunk after the lost reply is sent at the offset the
+///   learner has received up to.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_resume_after_lost_reply() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let snapshot_threshold: u64 = 10;
+    let chunk_size: u64 = 10;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            snapshot_max_chunk_size: chunk_size,
+            ..Default::default()
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- send just enough logs to trigger snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - 1 - log_index) as usize).await;
+        log_index = snapshot_threshold - 1;
+
+        router
+            .wait_for_log(
+                &btreeset![0],
+                Some(log_index),
+                timeout(),
+                "send log to trigger snapshot",
+            )
+            .await?;
+        router
+            .wait_for_snapshot(
+                &btreeset![0],
+                LogId::new(LeaderId::new(1, 0), log_index),
+                timeout(),
+                "snapshot",
+            )
+            .await?;
+    }
+
+    tracing::info!("--- lose the reply to the third chunk, add learner to receive snapshot");
+    {
+        let lost = 2 * chunk_size;
+        router.drop_install_snapshot_reply(lost);
+
+        router.new_raft_node(1).await;
+        router.add_learner(0, 1).await?;
+        log_index += 1;
+
+        router.wait_for_log(&btreeset![0, 1], Some(log_index), timeout(), "add learner").await?;
+
+        let offsets = router.install_snapshot_offsets(1);
+        tracing::info!("InstallSnapshot offsets: {:?}", offsets);
+
+        assert_eq!(
+            1,
+            offsets.iter().filter(|o| **o == 0).count(),
+            "not sent from the beginning again"
+        );
+
+        // The chunk is resent after the lost reply, rejected by the learner, then the next one is sent.
+        let i = offsets.iter().position(|o| *o == lost).unwrap();
+        assert_eq!(&[lost, lost, lost + chunk_size], &offsets[i..i + 3]);
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(3000))
+}
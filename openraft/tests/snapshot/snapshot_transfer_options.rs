@@ -0,0 +1,150 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use maplit::btreeset;
+use openraft::Config;
+use openraft::LeaderId;
+use openraft::LogId;
+use openraft::RaftStorage;
+use openraft::RaftStorageDebug;
+use openraft::SnapshotPolicy;
+use tokio::time::Instant;
+
+use crate::fixtures::RaftRouter;
+
+/// The rate to send a snapshot is limited by `snapshot_max_bytes_per_sec`.
+///
+/// What does this test do?
+///
+/// - build a single node cluster and write enough logs to build a snapshot.
+/// - add a learner, assert it takes at least as long as the rate limit requires to receive the snapshot.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_max_bytes_per_sec() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let max_bytes_per_sec = 1024;
+
+    let config = Config {
+        snapshot_max_chunk_size: 64,
+        snapshot_max_bytes_per_sec: max_bytes_per_sec,
+        ..Default::default()
+    };
+
+    let (size, elapsed) = send_snapshot_to_learner(config).await?;
+
+    let want = Duration::from_secs_f64((size - 64) as f64 / max_bytes_per_sec as f64);
+    assert!(
+        elapsed >= want,
+        "sending {} bytes takes {:?}, expect at least {:?}",
+        size,
+        elapsed,
+        want
+    );
+
+    Ok(())
+}
+
+/// Snapshot chunks are compressed with `snapshot_compression`.
+///
+/// What does this test do?
+///
+/// - build a single node cluster with lz4 compression enabled, and write enough logs to build a snapshot.
+/// - add a learner, assert it decompresses and installs the snapshot.
+#[cfg(feature = "lz4")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_compression_lz4() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Config {
+        snapshot_max_chunk_size: 64,
+        snapshot_compression: openraft::SnapshotCompression::Lz4,
+        ..Default::default()
+    };
+
+    send_snapshot_to_learner(config).await?;
+
+    Ok(())
+}
+
+/// Snapshot chunks are compressed with zstd.
+#[cfg(feature = "zstd")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn snapshot_compression_zstd() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let config = Config {
+        snapshot_max_chunk_size: 64,
+        snapshot_compression: openraft::SnapshotCompression::Zstd,
+        ..Default::default()
+    };
+
+    send_snapshot_to_learner(config).await?;
+
+    Ok(())
+}
+
+/// Build a snapshot on node-0 and add node-1 as a learner, which receives the snapshot.
+///
+/// Returns the size of the snapshot and the time it takes to add the learner.
+async fn send_snapshot_to_learner(config: Config) -> Result<(u64, Duration)> {
+    let snapshot_threshold: u64 = 20;
+
+    let config = Arc::new(
+        Config {
+            snapshot_policy: SnapshotPolicy::LogsSinceLast(snapshot_threshold),
+            max_applied_log_to_keep: 0,
+            ..config
+        }
+        .validate()?,
+    );
+    let router = Arc::new(RaftRouter::new(config.clone()));
+
+    let mut log_index = router.new_nodes_from_single(btreeset! {0}, btreeset! {}).await?;
+
+    tracing::info!("--- send just enough logs to trigger snapshot");
+    {
+        router.client_request_many(0, "0", (snapshot_threshold - 1 - log_index) as usize).await;
+        log_index = snapshot_threshold - 1;
+
+        router.wait_for_log(&btreeset! {0}, Some(log_index), timeout(), "write logs").await?;
+        router
+            .wait_for_snapshot(
+                &btreeset! {0},
+                LogId::new(LeaderId::new(1, 0), log_index),
+                timeout(),
+                "snapshot",
+            )
+            .await?;
+    }
+
+    let sto = router.get_storage_handle(&0).await?;
+    let snapshot = sto.get_current_snapshot().await?.expect("snapshot is built");
+    let size = snapshot.snapshot.get_ref().len() as u64;
+
+    tracing::info!("--- add learner to receive snapshot");
+    let start = Instant::now();
+    {
+        router.new_raft_node(1).await;
+        router.add_learner(0, 1).await?;
+        log_index += 1;
+
+        router.wait_for_log(&btreeset! {0, 1}, Some(log_index), timeout(), "add learner").await?;
+
+        let m = router.get_metrics(&1).await?;
+        assert!(m.snapshot.map(|x| x.index) >= Some(snapshot_threshold - 1));
+    }
+
+    let sm0 = router.get_storage_handle(&0).await?.get_state_machine().await;
+    let sm1 = router.get_storage_handle(&1).await?.get_state_machine().await;
+    assert_eq!(sm0.client_status, sm1.client_status);
+
+    Ok((size, start.elapsed()))
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(5000))
+}
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use anyerror::AnyError;
+use anyhow::Result;
+use maplit::btreemap;
+use maplit::btreeset;
+use memstore::ClientRequest;
+use memstore::ClientResponse;
+use memstore::MemStore;
+use openraft::async_trait::async_trait;
+use openraft::error::AppendEntriesError;
+use openraft::error::ClientReadError;
+use openraft::error::InstallSnapshotError;
+use openraft::error::NetworkError;
+use openraft::error::RPCError;
+use openraft::error::RemoteError;
+use openraft::error::TimeoutNowError;
+use openraft::error::VoteError;
+use openraft::raft::AppendEntriesRequest;
+use openraft::raft::AppendEntriesResponse;
+use openraft::raft::ClientWriteRequest;
+use openraft::raft::EntryPayload;
+use openraft::raft::InstallSnapshotRequest;
+use openraft::raft::InstallSnapshotResponse;
+use openraft::raft::ReadIndexRequest;
+use openraft::raft::ReadIndexResponse;
+use openraft::raft::TimeoutNowRequest;
+use openraft::raft::TimeoutNowResponse;
+use openraft::raft::VoteRequest;
+use openraft::raft::VoteResponse;
+use openraft::Config;
+use openraft::NodeInfo;
+use openraft::Raft;
+use openraft::RaftNetwork;
+use openraft::RaftStorage;
+use openraft::State;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[macro_use]
+mod fixtures;
+
+type StrRaft = Raft<ClientRequest, ClientResponse, StrRouter, MemStore<String, StrNode>, String, StrNode>;
+
+/// An application defined node type: the address a node is registered with in the `StrRouter`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct StrNode {
+    addr: String,
+}
+
+impl StrNode {
+    fn new(addr: &str) -> Self {
+        Self { addr: addr.to_string() }
+    }
+}
+
+impl Display for StrNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.addr)
+    }
+}
+
+impl NodeInfo for StrNode {}
+
+/// A network of nodes identified by `String` ids, e.g., UUIDs, and reached by the address in their node info.
+#[derive(Default)]
+struct StrRouter {
+    /// Nodes by address.
+    nodes: RwLock<BTreeMap<String, StrRaft>>,
+}
+
+impl StrRouter {
+    fn get(&self, addr: &str) -> StrRaft {
+        let nodes = self.nodes.read().unwrap();
+        nodes.get(addr).cloned().unwrap_or_else(|| panic!("node {} not found", addr))
+    }
+
+    /// Find the node to send an RPC to by the address in the target node info.
+    fn route(&self, target_node: Option<&StrNode>) -> std::result::Result<StrRaft, NetworkError> {
+        match target_node {
+            Some(n) => Ok(self.get(&n.addr)),
+            None => Err(NetworkError::new(&AnyError::error("no address of the target node"))),
+        }
+    }
+}
+
+#[async_trait]
+impl RaftNetwork<ClientRequest, String, StrNode> for StrRouter {
+    async fn send_append_entries(
+        &self,
+        target: String,
+        target_node: Option<&StrNode>,
+        rpc: AppendEntriesRequest<ClientRequest, String, StrNode>,
+    ) -> std::result::Result<AppendEntriesResponse<String>, RPCError<AppendEntriesError<String>, String>> {
+        let resp = self.route(target_node)?.append_entries(rpc).await;
+        Ok(resp.map_err(|e| RemoteError::new(target, e))?)
+    }
+
+    async fn send_install_snapshot(
+        &self,
+        target: String,
+        target_node: Option<&StrNode>,
+        rpc: InstallSnapshotRequest<String, StrNode>,
+    ) -> std::result::Result<InstallSnapshotResponse<String>, RPCError<InstallSnapshotError<String>, String>> {
+        let resp = self.route(target_node)?.install_snapshot(rpc).await;
+        Ok(resp.map_err(|e| RemoteError::new(target, e))?)
+    }
+
+    async fn send_vote(
+        &self,
+        target: String,
+        target_node: Option<&StrNode>,
+        rpc: VoteRequest<String>,
+    ) -> std::result::Result<VoteResponse<String>, RPCError<VoteError<String>, String>> {
+        let resp = self.route(target_node)?.vote(rpc).await;
+        Ok(resp.map_err(|e| RemoteError::new(target, e))?)
+    }
+
+    async fn send_timeout_now(
+        &self,
+        target: String,
+        target_node: Option<&StrNode>,
+        rpc: TimeoutNowRequest<String>,
+    ) -> std::result::Result<TimeoutNowResponse<String>, RPCError<TimeoutNowError<String>, String>> {
+        let resp = self.route(target_node)?.timeout_now(rpc).await;
+        Ok(resp.map_err(|e| RemoteError::new(target, e))?)
+    }
+
+    async fn send_read_index(
+        &self,
+        target: String,
+        target_node: Option<&StrNode>,
+        rpc: ReadIndexRequest<String>,
+    ) -> std::result::Result<ReadIndexResponse<String>, RPCError<ClientReadError<String, StrNode>, String>> {
+        let resp = self.route(target_node)?.read_index(rpc).await;
+        Ok(resp.map_err(|e| RemoteError::new(target, e))?)
+    }
+}
+
+/// A cluster works with node ids of a type other than `u64` and with an application defined node type.
+///
+/// What does this test do?
+///
+/// - bring 3 nodes up with `String` ids and `MemStore<String, StrNode>`, initialize a cluster of one of them.
+/// - add the other two as learners and then voters, write a log, assert every node applies it.
+/// - transfer the leadership, assert the new leader is elected by its `String` id.
+///
+/// Every RPC is routed by the address in the `StrNode` of the target, which is stored in the membership config.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn string_node_id() -> Result<()> {
+    let (_log_guard, ut_span) = init_ut!();
+    let _ent = ut_span.enter();
+
+    let s = |x: &str| x.to_string();
+
+    let config = Arc::new(Config::default().validate()?);
+    let router = Arc::new(StrRouter::default());
+
+    let addr = |id: &str| format!("addr-{}", id);
+
+    let mut stores = BTreeMap::new();
+    for id in ["alice", "bob", "carol"] {
+        let sto = Arc::new(MemStore::new().await);
+        let raft = Raft::new(s(id), config.clone(), router.clone(), sto.clone());
+        router.nodes.write().unwrap().insert(addr(id), raft);
+        stores.insert(id, sto);
+    }
+
+    let alice = router.get(&addr("alice"));
+    let mut log_index = 0;
+
+    tracing::info!("--- initialize a cluster of alice");
+    {
+        alice.initialize(btreemap! {s("alice") => StrNode::new(&addr("alice"))}).await?;
+        log_index += 1;
+
+        alice.wait(timeout()).state(State::Leader, "alice becomes leader").await?;
+        alice.wait(timeout()).log(Some(log_index), "alice commits blank log").await?;
+    }
+
+    tracing::info!("--- add bob and carol as voters");
+    {
+        alice.add_learner(s("bob"), Some(StrNode::new(&addr("bob"))), true).await?;
+        alice.add_learner(s("carol"), Some(StrNode::new(&addr("carol"))), true).await?;
+        log_index += 2;
+
+        alice.change_membership(btreeset! {s("alice"), s("bob"), s("carol")}, true, false).await?;
+        log_index += 2;
+    }
+
+    tracing::info!("--- write a log, every node applies it");
+    {
+        let req = ClientRequest {
+            client: s("foo"),
+            serial: 0,
+            status: s("bar"),
+        };
+        alice.client_write(ClientWriteRequest::new(EntryPayload::Normal(req))).await?;
+        log_index += 1;
+
+        for id in ["alice", "bob", "carol"] {
+            let m = router.get(&addr(id)).wait(timeout()).log(Some(log_index), "write a log").await?;
+            assert_eq!(Some(s("alice")), m.current_leader);
+            assert_eq!(
+                &vec![btreeset! {s("alice"), s("bob"), s("carol")}],
+                m.membership_config.membership.get_configs()
+            );
+            assert_eq!(
+                Some(&StrNode::new(&addr("carol"))),
+                m.membership_config.membership.get_node(&s("carol"))
+            );
+        }
+
+        let vote = stores["bob"].read_vote().await?.unwrap();
+        assert_eq!(s("alice"), vote.node_id);
+
+        let logs = stores["carol"].get_log_entries(..).await?;
+        assert_eq!(Some(log_index), logs.last().map(|ent| ent.log_id.index));
+        assert_eq!(s("alice"), logs.last().unwrap().log_id.leader_id.node_id);
+    }
+
+    tracing::info!("--- transfer the leadership to bob");
+    {
+        alice.transfer_leader(s("bob")).await?;
+
+        for id in ["alice", "bob", "carol"] {
+            router.get(&addr(id)).wait(timeout()).current_leader(s("bob"), "bob becomes leader").await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn timeout() -> Option<Duration> {
+    Some(Duration::from_millis(2000))
+}